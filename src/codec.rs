@@ -18,6 +18,7 @@ use crate::{
 };
 use std::fmt::{Debug, Display};
 use tremor_script::Value;
+pub(crate) mod avro;
 pub(crate) mod binary;
 pub(crate) mod binflux;
 pub(crate) mod csv;
@@ -25,6 +26,8 @@ pub(crate) mod influx;
 pub(crate) mod json;
 pub(crate) mod msgpack;
 pub(crate) mod null;
+pub(crate) mod parquet;
+pub(crate) mod protobuf;
 pub(crate) mod statsd;
 pub(crate) mod string;
 pub(crate) mod syslog;
@@ -80,6 +83,27 @@ pub trait Codec: Send + Sync {
         Ok(())
     }
 
+    /// Encodes a Value as the next record of a stream
+    ///
+    /// Codecs for file formats that can only be written once all records are known, like parquet,
+    /// buffer the record and return `None` until they have something to write.
+    /// Defaults to `encode`.
+    ///
+    /// # Errors
+    ///  * If the encoding fails
+    fn encode_record(&mut self, data: &Value) -> Result<Option<Vec<u8>>> {
+        self.encode(data).map(Some)
+    }
+
+    /// Finishes the stream encoded with `encode_record`, returning what is still buffered,
+    /// e.g. the footer of a file. The next record starts a new stream.
+    ///
+    /// # Errors
+    ///  * If the encoding of the buffered records fails
+    fn finish(&mut self) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
     /// special clone method for getting clone functionality
     /// into a this trait referenced as trait object
     /// otherwise we cannot use this type inside structs that need to be `Clone`.
@@ -117,6 +141,13 @@ pub fn resolve(config: &config::Codec) -> Result<Box<dyn Codec>> {
         "binary" => Ok(Box::new(binary::Binary {})),
         "syslog" => Ok(Box::new(syslog::Syslog::utcnow())),
         "csv" => Ok(Box::new(csv::Csv {})),
+        "avro" => Ok(Box::new(avro::Avro::from_config(config.config.as_ref())?)),
+        "parquet" => Ok(Box::new(parquet::Parquet::from_config(
+            config.config.as_ref(),
+        )?)),
+        "protobuf" => Ok(Box::new(protobuf::Protobuf::from_config(
+            config.config.as_ref(),
+        )?)),
         s => Err(ErrorKind::CodecNotFound(s.into()).into()),
     }
 }
@@ -136,6 +167,9 @@ mod test {
         assert!(super::resolve(&"statsd".into()).is_ok());
         assert!(super::resolve(&"yaml".into()).is_ok());
         assert!(super::resolve(&"syslog".into()).is_ok());
+        assert!(super::resolve(&"avro".into()).is_ok());
+        // parquet needs a schema
+        assert!(super::resolve(&"parquet".into()).is_err());
         assert_eq!(
             super::resolve(&"snot".into()).err().unwrap().to_string(),
             "Codec \"snot\" not found."
@@ -0,0 +1,514 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Avro codec
+//!
+//! Supports two formats, configured via `format`:
+//!
+//! * `container` (default) - avro object container files with the schema embedded,
+//!   an event is either a single record or an array of records, decoding yields an array of records.
+//!   A sink writes a single container per stream: the header precedes its first block, records are
+//!   written out in blocks of about 16 KiB, the last one once the stream ends, i.e. when a `file` sink
+//!   stops or an `s3_writer` moves on to the next object key.
+//! * `datum` - a single avro datum without any schema information, a `schema` is required to decode.
+//!
+//! The `schema` is given either as a JSON string or as a record and is required to encode.
+
+use super::prelude::*;
+use apache_avro::{
+    from_avro_datum, to_avro_datum, types::Value as AvroValue, Codec as Compression, Reader,
+    Schema, Writer,
+};
+use std::collections::HashMap;
+
+const NAME: &str = "avro";
+/// the magic bytes starting an object container file
+const MAGIC: &[u8] = b"Obj\x01";
+/// size of the serialized records from which on a block is written out
+const BLOCK_SIZE: usize = 16_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Container,
+    Datum,
+}
+
+pub struct Avro {
+    schema: Option<Schema>,
+    format: Format,
+    compression: Compression,
+    /// the sync marker of the container being written, once its header is
+    marker: Option<[u8; 16]>,
+    /// serialized records of the block being built
+    block: Vec<u8>,
+    block_records: usize,
+}
+
+impl Avro {
+    pub(crate) fn from_config(config: Option<&Value>) -> Result<Self> {
+        let schema = match config.get("schema") {
+            Some(Value::String(s)) => Some(Schema::parse_str(s)?),
+            Some(schema) => Some(Schema::parse_str(&schema.encode())?),
+            None => None,
+        };
+        let format = match config.get_str("format") {
+            Some("container") | None => Format::Container,
+            Some("datum") if schema.is_some() => Format::Datum,
+            Some("datum") => return Err(invalid_config("format `datum` requires a `schema`")),
+            Some(other) => return Err(invalid_config(&format!("Unknown format: {other}"))),
+        };
+        let compression = match config.get_str("compression") {
+            Some("null") | None => Compression::Null,
+            Some("deflate") => Compression::Deflate,
+            Some(other) => return Err(invalid_config(&format!("Unknown compression: {other}"))),
+        };
+        Ok(Self::new(schema, format, compression))
+    }
+
+    fn new(schema: Option<Schema>, format: Format, compression: Compression) -> Self {
+        Self {
+            schema,
+            format,
+            compression,
+            marker: None,
+            block: Vec::new(),
+            block_records: 0,
+        }
+    }
+
+    fn schema(&self) -> Result<&Schema> {
+        self.schema
+            .as_ref()
+            .ok_or_else(|| invalid_config("a `schema` is required to encode"))
+    }
+
+    /// the header of a container with the sync `marker`
+    fn header(&self, marker: &[u8; 16]) -> Result<Vec<u8>> {
+        let codec = match self.compression {
+            Compression::Deflate => "deflate",
+            _ => "null",
+        };
+        let metadata: HashMap<String, AvroValue> = [
+            (
+                "avro.schema".to_string(),
+                AvroValue::Bytes(simd_json::to_vec(self.schema()?)?),
+            ),
+            (
+                "avro.codec".to_string(),
+                AvroValue::Bytes(codec.as_bytes().to_vec()),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let mut header = MAGIC.to_vec();
+        header.append(&mut to_avro_datum(
+            &Schema::Map(Box::new(Schema::Bytes)),
+            AvroValue::Map(metadata),
+        )?);
+        header.extend_from_slice(marker);
+        Ok(header)
+    }
+
+    /// writes out the block being built, preceded by the header if it is the first one
+    fn flush_block(&mut self) -> Result<Vec<u8>> {
+        let mut res = Vec::new();
+        let marker = if let Some(marker) = self.marker {
+            marker
+        } else {
+            let marker = rand::random();
+            res = self.header(&marker)?;
+            self.marker = Some(marker);
+            marker
+        };
+        let records = std::mem::take(&mut self.block_records);
+        let mut block = std::mem::take(&mut self.block);
+        self.compression.compress(&mut block)?;
+        res.append(&mut to_avro_datum(
+            &Schema::Long,
+            AvroValue::Long(i64::try_from(records)?),
+        )?);
+        res.append(&mut to_avro_datum(
+            &Schema::Long,
+            AvroValue::Long(i64::try_from(block.len())?),
+        )?);
+        res.append(&mut block);
+        res.extend_from_slice(&marker);
+        Ok(res)
+    }
+}
+
+fn invalid_config(msg: &str) -> Error {
+    ErrorKind::InvalidConfiguration(format!("{NAME} codec"), msg.to_string()).into()
+}
+
+fn mismatch(expected: &str, value: &Value) -> Error {
+    format!(
+        "Value of type {:?} can not be encoded as avro {expected}",
+        value.value_type()
+    )
+    .into()
+}
+
+/// converts a tremor value into an avro value guided by `schema`
+fn to_avro(value: &Value, schema: &Schema) -> Result<AvroValue> {
+    Ok(match schema {
+        Schema::Null if value.is_null() => AvroValue::Null,
+        Schema::Null => return Err(mismatch("null", value)),
+        Schema::Boolean => {
+            AvroValue::Boolean(value.as_bool().ok_or_else(|| mismatch("boolean", value))?)
+        }
+        Schema::Int => AvroValue::Int(value.as_i32().ok_or_else(|| mismatch("int", value))?),
+        Schema::Long => AvroValue::Long(value.as_i64().ok_or_else(|| mismatch("long", value))?),
+        Schema::Float => AvroValue::Float(value.as_f32().ok_or_else(|| mismatch("float", value))?),
+        Schema::Double => {
+            AvroValue::Double(value.cast_f64().ok_or_else(|| mismatch("double", value))?)
+        }
+        Schema::Bytes => AvroValue::Bytes(
+            value
+                .as_bytes()
+                .ok_or_else(|| mismatch("bytes", value))?
+                .to_vec(),
+        ),
+        Schema::String => AvroValue::String(
+            value
+                .as_str()
+                .ok_or_else(|| mismatch("string", value))?
+                .to_string(),
+        ),
+        Schema::Array(items) => AvroValue::Array(
+            value
+                .as_array()
+                .ok_or_else(|| mismatch("array", value))?
+                .iter()
+                .map(|v| to_avro(v, items))
+                .collect::<Result<_>>()?,
+        ),
+        Schema::Map(values) => AvroValue::Map(
+            value
+                .as_object()
+                .ok_or_else(|| mismatch("map", value))?
+                .iter()
+                .map(|(k, v)| Ok((k.to_string(), to_avro(v, values)?)))
+                .collect::<Result<HashMap<_, _>>>()?,
+        ),
+        Schema::Union(union) => {
+            // the first variant the value can be encoded as wins
+            let (idx, v) = union
+                .variants()
+                .iter()
+                .enumerate()
+                .find_map(|(idx, s)| Some((idx, to_avro(value, s).ok()?)))
+                .ok_or_else(|| mismatch("union", value))?;
+            AvroValue::Union(u32::try_from(idx)?, Box::new(v))
+        }
+        Schema::Record { fields, .. } => {
+            if !value.is_object() {
+                return Err(mismatch("record", value));
+            }
+            let mut record = Vec::with_capacity(fields.len());
+            for field in fields {
+                let v = match (value.get(field.name.as_str()), &field.default) {
+                    (Some(v), _) => to_avro(v, &field.schema)?,
+                    (None, Some(default)) => {
+                        AvroValue::from(default.clone()).resolve(&field.schema)?
+                    }
+                    (None, None) => to_avro(&NULL, &field.schema)?,
+                };
+                record.push((field.name.clone(), v));
+            }
+            AvroValue::Record(record)
+        }
+        Schema::Enum { symbols, .. } => {
+            let symbol = value.as_str().ok_or_else(|| mismatch("enum", value))?;
+            let idx = symbols
+                .iter()
+                .position(|s| s == symbol)
+                .ok_or_else(|| mismatch("enum", value))?;
+            AvroValue::Enum(u32::try_from(idx)?, symbol.to_string())
+        }
+        Schema::Fixed { size, .. } => {
+            let bytes = value.as_bytes().ok_or_else(|| mismatch("fixed", value))?;
+            if bytes.len() != *size {
+                return Err(mismatch("fixed", value));
+            }
+            AvroValue::Fixed(*size, bytes.to_vec())
+        }
+        // logical types are resolved from their underlying type
+        Schema::Date | Schema::TimeMillis => {
+            AvroValue::Int(value.as_i32().ok_or_else(|| mismatch("int", value))?).resolve(schema)?
+        }
+        Schema::TimeMicros | Schema::TimestampMillis | Schema::TimestampMicros => {
+            AvroValue::Long(value.as_i64().ok_or_else(|| mismatch("long", value))?)
+                .resolve(schema)?
+        }
+        Schema::Uuid => AvroValue::String(
+            value
+                .as_str()
+                .ok_or_else(|| mismatch("uuid", value))?
+                .to_string(),
+        )
+        .resolve(schema)?,
+        Schema::Decimal { .. } => AvroValue::Bytes(
+            value
+                .as_bytes()
+                .ok_or_else(|| mismatch("decimal", value))?
+                .to_vec(),
+        )
+        .resolve(schema)?,
+        other => return Err(format!("Unsupported avro schema: {:?}", other).into()),
+    })
+}
+
+/// converts an avro value into a tremor value
+fn from_avro(value: AvroValue) -> Result<Value<'static>> {
+    Ok(match value {
+        AvroValue::Null => Value::null(),
+        AvroValue::Boolean(b) => Value::from(b),
+        AvroValue::Int(i) | AvroValue::Date(i) | AvroValue::TimeMillis(i) => Value::from(i),
+        AvroValue::Long(i)
+        | AvroValue::TimeMicros(i)
+        | AvroValue::TimestampMillis(i)
+        | AvroValue::TimestampMicros(i) => Value::from(i),
+        AvroValue::Float(f) => Value::from(f64::from(f)),
+        AvroValue::Double(f) => Value::from(f),
+        AvroValue::Bytes(b) | AvroValue::Fixed(_, b) => Value::Bytes(b.into()),
+        AvroValue::String(s) | AvroValue::Enum(_, s) => Value::from(s),
+        AvroValue::Uuid(u) => Value::from(u.to_string()),
+        AvroValue::Union(_, v) => from_avro(*v)?,
+        AvroValue::Array(a) => {
+            Value::from(a.into_iter().map(from_avro).collect::<Result<Vec<_>>>()?)
+        }
+        AvroValue::Map(m) => {
+            let mut o = Value::object_with_capacity(m.len());
+            for (k, v) in m {
+                o.try_insert(k, from_avro(v)?);
+            }
+            o
+        }
+        AvroValue::Record(fields) => {
+            let mut o = Value::object_with_capacity(fields.len());
+            for (k, v) in fields {
+                o.try_insert(k, from_avro(v)?);
+            }
+            o
+        }
+        other => return Err(format!("Unsupported avro value: {:?}", other).into()),
+    })
+}
+
+impl Codec for Avro {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn mime_types(&self) -> Vec<&'static str> {
+        vec!["application/avro", "avro/binary"]
+    }
+
+    fn decode<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        _ingest_ns: u64,
+    ) -> Result<Option<Value<'input>>> {
+        let mut data: &[u8] = data;
+        match self.format {
+            Format::Container => {
+                let reader = if let Some(schema) = &self.schema {
+                    Reader::with_schema(schema, data)?
+                } else {
+                    Reader::new(data)?
+                };
+                let records = reader.map(|r| from_avro(r?)).collect::<Result<Vec<_>>>()?;
+                Ok(Some(Value::from(records)))
+            }
+            Format::Datum => {
+                let value = from_avro_datum(self.schema()?, &mut data, None)?;
+                Ok(Some(from_avro(value)?))
+            }
+        }
+    }
+
+    fn encode(&self, data: &Value) -> Result<Vec<u8>> {
+        let schema = self.schema()?;
+        match self.format {
+            Format::Container => {
+                let mut writer = Writer::with_codec(schema, Vec::new(), self.compression);
+                if let Some(records) = data.as_array() {
+                    for record in records {
+                        writer.append(to_avro(record, schema)?)?;
+                    }
+                } else {
+                    writer.append(to_avro(data, schema)?)?;
+                }
+                Ok(writer.into_inner()?)
+            }
+            Format::Datum => Ok(to_avro_datum(schema, to_avro(data, schema)?)?),
+        }
+    }
+
+    fn encode_record(&mut self, data: &Value) -> Result<Option<Vec<u8>>> {
+        if self.format == Format::Datum {
+            return self.encode(data).map(Some);
+        }
+        let schema = self.schema()?;
+        let records = data
+            .as_array()
+            .map_or_else(|| std::slice::from_ref(data), Vec::as_slice);
+        // serialized up front, so a bad record doesn't leave half a batch in the block
+        let mut serialized = Vec::new();
+        for record in records {
+            serialized.append(&mut to_avro_datum(schema, to_avro(record, schema)?)?);
+        }
+        self.block.append(&mut serialized);
+        self.block_records += records.len();
+        if self.block.len() < BLOCK_SIZE {
+            Ok(None)
+        } else {
+            self.flush_block().map(Some)
+        }
+    }
+
+    fn finish(&mut self) -> Result<Option<Vec<u8>>> {
+        let res = if self.block_records > 0 {
+            Some(self.flush_block()?)
+        } else {
+            None
+        };
+        self.marker = None;
+        Ok(res)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Codec> {
+        // a clone starts out with a stream of its own
+        Box::new(Self::new(
+            self.schema.clone(),
+            self.format,
+            self.compression,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tremor_value::literal;
+
+    fn config(format: &str) -> Value<'static> {
+        literal!({
+            "format": format,
+            "schema": {
+                "type": "record",
+                "name": "snot",
+                "fields": [
+                    {"name": "name", "type": "string"},
+                    {"name": "count", "type": "long"},
+                    {"name": "ratio", "type": ["null", "double"], "default": null},
+                    {"name": "tags", "type": {"type": "array", "items": "string"}},
+                    {"name": "kind", "type": {"type": "enum", "name": "kind", "symbols": ["badger", "snot"]}},
+                    {"name": "labels", "type": {"type": "map", "values": "long"}}
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn container_roundtrip() -> Result<()> {
+        let config = config("container");
+        let mut codec = Avro::from_config(Some(&config))?;
+        let records = literal!([
+            {"name": "a", "count": 1, "ratio": 0.5, "tags": ["x"], "kind": "snot", "labels": {"l": 1}},
+            {"name": "b", "count": 2, "tags": [], "kind": "badger", "labels": {}}
+        ]);
+        let mut data = codec.encode(&records)?;
+        let decoded = codec.decode(&mut data, 0)?;
+        let expected = literal!([
+            {"name": "a", "count": 1, "ratio": 0.5, "tags": ["x"], "kind": "snot", "labels": {"l": 1}},
+            {"name": "b", "count": 2, "ratio": null, "tags": [], "kind": "badger", "labels": {}}
+        ]);
+        assert_eq!(Some(expected), decoded);
+
+        // the schema is embedded, decoding works without one
+        let mut codec = Avro::from_config(None)?;
+        let decoded = codec.decode(&mut data, 0)?;
+        assert_eq!(2, decoded.as_array().map(Vec::len).unwrap_or_default());
+        Ok(())
+    }
+
+    #[test]
+    fn container_stream() -> Result<()> {
+        let config = config("container");
+        let mut codec = Avro::from_config(Some(&config))?;
+        let record = |name: &str| literal!({"name": name, "count": 1, "tags": [], "kind": "snot", "labels": {}});
+        // buffered until the block is full
+        assert_eq!(None, codec.encode_record(&record("a"))?);
+        assert_eq!(
+            None,
+            codec.encode_record(&Value::from(vec![record("b"), record("c")]))?
+        );
+        assert!(codec.encode_record(&literal!({"name": 1})).is_err());
+        let mut data = codec.finish()?.expect("no container written");
+        assert_eq!(None, codec.finish()?);
+        let decoded = codec.decode(&mut data, 0)?;
+        let names: Vec<&str> = decoded
+            .as_array()
+            .map(|records| records.iter().filter_map(|r| r.get_str("name")).collect())
+            .unwrap_or_default();
+        assert_eq!(vec!["a", "b", "c"], names);
+
+        // large streams are written in several blocks of the same container
+        let mut data = Vec::new();
+        for _ in 0..2000 {
+            if let Some(mut block) = codec.encode_record(&record("snotbadger"))? {
+                data.append(&mut block);
+            }
+        }
+        assert!(!data.is_empty());
+        data.append(&mut codec.finish()?.expect("no block written"));
+        let decoded = codec.decode(&mut data, 0)?;
+        assert_eq!(2000, decoded.as_array().map(Vec::len).unwrap_or_default());
+        Ok(())
+    }
+
+    #[test]
+    fn datum_roundtrip() -> Result<()> {
+        let config = config("datum");
+        let mut codec = Avro::from_config(Some(&config))?;
+        let record = literal!({"name": "a", "count": 1, "ratio": null, "tags": ["x", "y"], "kind": "badger", "labels": {}});
+        let mut data = codec.encode(&record)?;
+        assert_eq!(Some(record), codec.decode(&mut data, 0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn bad_values() -> Result<()> {
+        let config = config("datum");
+        let codec = Avro::from_config(Some(&config))?;
+        assert!(codec.encode(&literal!({"name": 1})).is_err());
+        assert!(codec
+            .encode(
+                &literal!({"name": "a", "count": 1, "tags": [], "kind": "unknown", "labels": {}})
+            )
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bad_config() -> Result<()> {
+        assert!(Avro::from_config(Some(&literal!({"format": "datum"}))).is_err());
+        assert!(Avro::from_config(Some(&literal!({"format": "snot"}))).is_err());
+        assert!(Avro::from_config(Some(&literal!({"schema": "snot"}))).is_err());
+        assert!(Avro::from_config(None)?.encode(&literal!({})).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,571 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parquet codec
+//!
+//! Encodes records, objects following the configured `schema`, into a parquet file.
+//! The `schema` is a parquet message type like:
+//!
+//! ```text
+//! message event {
+//!   required int64 id;
+//!   optional binary name (UTF8);
+//! }
+//! ```
+//!
+//! A sink writes a single file per stream: records, single ones or batches, are buffered into
+//! row groups of `row_group_size` rows, which are written out once they are full. The rest and the
+//! footer are written once the stream ends, i.e. when a `file` sink stops or an `s3_writer` moves
+//! on to the next object key. Encoding a value on its own yields a complete file.
+//! Only flat schemas of `required` and `optional` primitive columns are supported for encoding.
+//! Decoding turns a complete parquet file into an array of records.
+
+use super::prelude::*;
+use parquet::{
+    basic::{Compression, Repetition, Type as PhysicalType},
+    column::writer::ColumnWriter,
+    data_type::ByteArray,
+    file::{
+        properties::{WriterProperties, WriterPropertiesPtr},
+        reader::{FileReader, SerializedFileReader},
+        writer::SerializedFileWriter,
+    },
+    record::Field,
+    schema::{parser::parse_message_type, types::Type},
+};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+const NAME: &str = "parquet";
+const DEFAULT_ROW_GROUP_SIZE: usize = 1024;
+
+#[derive(Clone)]
+struct Column {
+    name: String,
+    required: bool,
+    physical_type: PhysicalType,
+}
+
+/// Collects what a file writer writes, to hand it out once a row group is done
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn take(&self) -> Result<Vec<u8>> {
+        let mut data = self
+            .0
+            .lock()
+            .map_err(|_| Error::from("Parquet output lock poisoned"))?;
+        Ok(std::mem::take(&mut *data))
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Parquet output lock poisoned"))?
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct Parquet {
+    schema: Arc<Type>,
+    columns: Vec<Column>,
+    row_group_size: usize,
+    compression: Compression,
+    /// records of the row group being built
+    pending: Vec<Value<'static>>,
+    /// the file being written, once its first row group is done
+    writer: Option<SerializedFileWriter<Output>>,
+    output: Output,
+}
+
+fn invalid_config(msg: &str) -> Error {
+    ErrorKind::InvalidConfiguration(format!("{NAME} codec"), msg.to_string()).into()
+}
+
+impl Parquet {
+    pub(crate) fn from_config(config: Option<&Value>) -> Result<Self> {
+        let schema = config
+            .get_str("schema")
+            .ok_or_else(|| invalid_config("a `schema` is required"))?;
+        let schema = Arc::new(parse_message_type(schema)?);
+        let mut columns = Vec::with_capacity(schema.get_fields().len());
+        for field in schema.get_fields() {
+            let required = match field.get_basic_info().repetition() {
+                Repetition::REQUIRED => true,
+                Repetition::OPTIONAL => false,
+                Repetition::REPEATED => {
+                    return Err(invalid_config(&format!(
+                        "repeated column `{}` is not supported",
+                        field.name()
+                    )))
+                }
+            };
+            if !field.is_primitive() {
+                return Err(invalid_config(&format!(
+                    "nested column `{}` is not supported",
+                    field.name()
+                )));
+            }
+            let physical_type = field.get_physical_type();
+            match physical_type {
+                PhysicalType::BOOLEAN
+                | PhysicalType::INT32
+                | PhysicalType::INT64
+                | PhysicalType::FLOAT
+                | PhysicalType::DOUBLE
+                | PhysicalType::BYTE_ARRAY => (),
+                other => {
+                    return Err(invalid_config(&format!(
+                        "column `{}` has the unsupported type {other}",
+                        field.name()
+                    )))
+                }
+            }
+            columns.push(Column {
+                name: field.name().to_string(),
+                required,
+                physical_type,
+            });
+        }
+        let row_group_size = match config.get("row_group_size").and_then(ValueAccess::as_usize) {
+            Some(0) => return Err(invalid_config("`row_group_size` must be greater than 0")),
+            Some(size) => size,
+            None => DEFAULT_ROW_GROUP_SIZE,
+        };
+        let compression = match config.get_str("compression") {
+            Some("uncompressed") | None => Compression::UNCOMPRESSED,
+            Some("snappy") => Compression::SNAPPY,
+            Some("zstd") => Compression::ZSTD,
+            Some(other) => return Err(invalid_config(&format!("Unknown compression: {other}"))),
+        };
+        Ok(Self::new(schema, columns, row_group_size, compression))
+    }
+
+    fn new(
+        schema: Arc<Type>,
+        columns: Vec<Column>,
+        row_group_size: usize,
+        compression: Compression,
+    ) -> Self {
+        Self {
+            schema,
+            columns,
+            row_group_size,
+            compression,
+            pending: Vec::new(),
+            writer: None,
+            output: Output::default(),
+        }
+    }
+
+    fn props(&self) -> WriterPropertiesPtr {
+        Arc::new(
+            WriterProperties::builder()
+                .set_compression(self.compression)
+                .set_max_row_group_size(self.row_group_size)
+                .build(),
+        )
+    }
+
+    /// the records of `data`, a single record or a batch of them
+    fn records<'value>(data: &'value Value<'value>) -> &'value [Value<'value>] {
+        data.as_array()
+            .map_or_else(|| std::slice::from_ref(data), Vec::as_slice)
+    }
+
+    /// checks that `record` fits the schema, so it doesn't fail the row group it ends up in
+    fn check(&self, record: &Value) -> Result<()> {
+        if !record.is_object() {
+            return Err(format!(
+                "The {NAME} codec only encodes records, got a {:?}",
+                record.value_type()
+            )
+            .into());
+        }
+        for column in &self.columns {
+            match record.get(column.name.as_str()).filter(|v| !v.is_null()) {
+                Some(v) if !matches_type(v, column.physical_type) => {
+                    return Err(mismatch(v, column));
+                }
+                None if column.required => {
+                    return Err(
+                        format!("Missing value for required column `{}`", column.name).into(),
+                    );
+                }
+                Some(_) | None => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// writes the next row group of the file of the current stream
+    fn write_pending(&mut self, len: usize) -> Result<()> {
+        if self.writer.is_none() {
+            self.writer = Some(SerializedFileWriter::new(
+                self.output.clone(),
+                self.schema.clone(),
+                self.props(),
+            )?);
+        }
+        let records: Vec<Value> = self.pending.drain(..len).collect();
+        if let Some(writer) = self.writer.as_mut() {
+            write_row_group(writer, &self.columns, &records)?;
+        }
+        Ok(())
+    }
+}
+
+fn matches_type(value: &Value, physical_type: PhysicalType) -> bool {
+    match physical_type {
+        PhysicalType::BOOLEAN => value.as_bool().is_some(),
+        PhysicalType::INT32 => value.as_i32().is_some(),
+        PhysicalType::INT64 => value.as_i64().is_some(),
+        PhysicalType::FLOAT => value.as_f32().is_some(),
+        PhysicalType::DOUBLE => value.cast_f64().is_some(),
+        PhysicalType::BYTE_ARRAY => value.as_bytes().is_some(),
+        _ => false,
+    }
+}
+
+fn mismatch(value: &Value, column: &Column) -> Error {
+    format!(
+        "Value of type {:?} does not match the type of column `{}`",
+        value.value_type(),
+        column.name
+    )
+    .into()
+}
+
+/// collects the non null values of a column, converted with `f`
+fn column_values<'value, T, F>(
+    values: &[Option<&'value Value<'value>>],
+    column: &Column,
+    f: F,
+) -> Result<Vec<T>>
+where
+    F: Fn(&'value Value<'value>) -> Option<T>,
+{
+    values
+        .iter()
+        .flatten()
+        .map(|v| f(v).ok_or_else(|| mismatch(v, column)))
+        .collect()
+}
+
+fn write_column(writer: &mut ColumnWriter, column: &Column, records: &[Value]) -> Result<()> {
+    let values: Vec<Option<&Value>> = records
+        .iter()
+        .map(|r| r.get(column.name.as_str()).filter(|v| !v.is_null()))
+        .collect();
+    let def_levels: Vec<i16> = values.iter().map(|v| i16::from(v.is_some())).collect();
+    let def_levels = if column.required {
+        if values.iter().any(Option::is_none) {
+            return Err(format!("Missing value for required column `{}`", column.name).into());
+        }
+        None
+    } else {
+        Some(def_levels.as_slice())
+    };
+    match writer {
+        ColumnWriter::BoolColumnWriter(w) => w.write_batch(
+            &column_values(&values, column, Value::as_bool)?,
+            def_levels,
+            None,
+        )?,
+        ColumnWriter::Int32ColumnWriter(w) => w.write_batch(
+            &column_values(&values, column, Value::as_i32)?,
+            def_levels,
+            None,
+        )?,
+        ColumnWriter::Int64ColumnWriter(w) => w.write_batch(
+            &column_values(&values, column, Value::as_i64)?,
+            def_levels,
+            None,
+        )?,
+        ColumnWriter::FloatColumnWriter(w) => w.write_batch(
+            &column_values(&values, column, Value::as_f32)?,
+            def_levels,
+            None,
+        )?,
+        ColumnWriter::DoubleColumnWriter(w) => w.write_batch(
+            &column_values(&values, column, Value::cast_f64)?,
+            def_levels,
+            None,
+        )?,
+        ColumnWriter::ByteArrayColumnWriter(w) => {
+            let data = column_values(&values, column, |v| {
+                v.as_bytes().map(|b| ByteArray::from(b.to_vec()))
+            })?;
+            w.write_batch(&data, def_levels, None)?
+        }
+        _ => return Err(format!("Unsupported type for column `{}`", column.name).into()),
+    };
+    Ok(())
+}
+
+fn write_row_group<W: Write>(
+    writer: &mut SerializedFileWriter<W>,
+    columns: &[Column],
+    records: &[Value],
+) -> Result<()> {
+    let mut row_group = writer.next_row_group()?;
+    let mut columns = columns.iter();
+    while let Some(mut column_writer) = row_group.next_column()? {
+        let column = columns
+            .next()
+            .ok_or_else(|| Error::from("Parquet schema and columns diverged"))?;
+        write_column(column_writer.untyped(), column, records)?;
+        column_writer.close()?;
+    }
+    row_group.close()?;
+    Ok(())
+}
+
+/// converts a parquet field into a tremor value
+fn from_field(field: &Field) -> Value<'static> {
+    match field {
+        Field::Null => Value::null(),
+        Field::Bool(b) => Value::from(*b),
+        Field::Byte(i) => Value::from(i64::from(*i)),
+        Field::Short(i) => Value::from(i64::from(*i)),
+        Field::Int(i) => Value::from(i64::from(*i)),
+        Field::Long(i) => Value::from(*i),
+        Field::UByte(u) => Value::from(u64::from(*u)),
+        Field::UShort(u) => Value::from(u64::from(*u)),
+        Field::UInt(u) => Value::from(u64::from(*u)),
+        Field::ULong(u) => Value::from(*u),
+        Field::Float(f) => Value::from(f64::from(*f)),
+        Field::Double(f) => Value::from(*f),
+        Field::Str(s) => Value::from(s.clone()),
+        Field::Bytes(b) => Value::Bytes(b.data().to_vec().into()),
+        Field::Group(row) => {
+            let mut o = Value::object_with_capacity(row.len());
+            for (name, field) in row.get_column_iter() {
+                o.try_insert(name.clone(), from_field(field));
+            }
+            o
+        }
+        Field::ListInternal(list) => {
+            Value::from(list.elements().iter().map(from_field).collect::<Vec<_>>())
+        }
+        // dates, timestamps, decimals and maps use their string representation
+        other => Value::from(other.to_string()),
+    }
+}
+
+impl Codec for Parquet {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn mime_types(&self) -> Vec<&'static str> {
+        vec!["application/vnd.apache.parquet"]
+    }
+
+    fn decode<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        _ingest_ns: u64,
+    ) -> Result<Option<Value<'input>>> {
+        let reader = SerializedFileReader::new(bytes::Bytes::copy_from_slice(data))?;
+        let records: Vec<Value> = reader
+            .get_row_iter(None)?
+            .map(|row| from_field(&Field::Group(row)))
+            .collect();
+        Ok(Some(Value::from(records)))
+    }
+
+    fn encode(&self, data: &Value) -> Result<Vec<u8>> {
+        let records = Self::records(data);
+        for record in records {
+            self.check(record)?;
+        }
+        let mut res = Vec::new();
+        let mut writer = SerializedFileWriter::new(&mut res, self.schema.clone(), self.props())?;
+        for chunk in records.chunks(self.row_group_size) {
+            write_row_group(&mut writer, &self.columns, chunk)?;
+        }
+        writer.close()?;
+        Ok(res)
+    }
+
+    fn encode_record(&mut self, data: &Value) -> Result<Option<Vec<u8>>> {
+        let records = Self::records(data);
+        for record in records {
+            self.check(record)?;
+        }
+        self.pending
+            .extend(records.iter().map(|r| r.clone_static()));
+        if self.pending.len() < self.row_group_size {
+            return Ok(None);
+        }
+        while self.pending.len() >= self.row_group_size {
+            self.write_pending(self.row_group_size)?;
+        }
+        Ok(Some(self.output.take()?))
+    }
+
+    fn finish(&mut self) -> Result<Option<Vec<u8>>> {
+        if !self.pending.is_empty() {
+            self.write_pending(self.pending.len())?;
+        }
+        if let Some(writer) = self.writer.take() {
+            writer.close()?;
+            Ok(Some(self.output.take()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Codec> {
+        // a clone starts out with a stream of its own
+        Box::new(Self::new(
+            self.schema.clone(),
+            self.columns.clone(),
+            self.row_group_size,
+            self.compression,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tremor_value::literal;
+
+    const SCHEMA: &str = r#"
+        message event {
+          required int64 id;
+          optional binary name (UTF8);
+          optional double ratio;
+          required boolean flag;
+        }
+    "#;
+
+    #[test]
+    fn roundtrip() -> Result<()> {
+        let config = literal!({
+            "schema": SCHEMA,
+            "row_group_size": 2,
+            "compression": "snappy"
+        });
+        let mut codec = Parquet::from_config(Some(&config))?;
+        let records = literal!([
+            {"id": 1, "name": "snot", "ratio": 0.5, "flag": true},
+            {"id": 2, "name": null, "flag": false},
+            {"id": 3, "name": "badger", "ratio": 1.5, "flag": true, "ignored": [1, 2]}
+        ]);
+        let mut data = codec.encode(&records)?;
+        let decoded = codec.decode(&mut data, 0)?;
+        let expected = literal!([
+            {"id": 1, "name": "snot", "ratio": 0.5, "flag": true},
+            {"id": 2, "name": null, "ratio": null, "flag": false},
+            {"id": 3, "name": "badger", "ratio": 1.5, "flag": true}
+        ]);
+        assert_eq!(Some(expected), decoded);
+
+        // a batch of a single record is encoded as a file with a single row
+        let mut data = codec.encode(&literal!([{"id": 4, "flag": false}]))?;
+        let decoded = codec.decode(&mut data, 0)?;
+        assert_eq!(
+            Some(literal!([{"id": 4, "name": null, "ratio": null, "flag": false}])),
+            decoded
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn bad_records() -> Result<()> {
+        let codec = Parquet::from_config(Some(&literal!({ "schema": SCHEMA })))?;
+        // missing required column
+        assert!(codec.encode(&literal!([{"id": 1}])).is_err());
+        // wrong type
+        assert!(codec
+            .encode(&literal!([{"id": "1", "flag": true}]))
+            .is_err());
+        // not a record
+        assert!(codec.encode(&literal!("snot")).is_err());
+        assert!(codec
+            .encode(&literal!([{"id": 1, "flag": true}, 2]))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn stream() -> Result<()> {
+        let config = literal!({
+            "schema": SCHEMA,
+            "row_group_size": 2
+        });
+        let mut codec = Parquet::from_config(Some(&config))?;
+        let mut data = Vec::new();
+        // buffered until the row group is full
+        assert_eq!(
+            None,
+            codec.encode_record(&literal!({"id": 1, "flag": true}))?
+        );
+        data.append(
+            &mut codec
+                .encode_record(&literal!([{"id": 2, "flag": false}, {"id": 3, "flag": true}]))?
+                .expect("no row group written"),
+        );
+        // a bad record doesn't end up in the file
+        assert!(codec.encode_record(&literal!({"id": 4})).is_err());
+        assert_eq!(
+            None,
+            codec.encode_record(&literal!({"id": 5, "name": "snot", "flag": true}))?
+        );
+        data.append(&mut codec.finish()?.expect("no footer written"));
+        assert_eq!(None, codec.finish()?);
+
+        let decoded = codec.decode(&mut data, 0)?;
+        let ids: Vec<i64> = decoded
+            .as_array()
+            .map(|records| records.iter().filter_map(|r| r.get_i64("id")).collect())
+            .unwrap_or_default();
+        assert_eq!(vec![1, 2, 3, 5], ids);
+
+        // the next record starts a new file
+        codec.encode_record(&literal!({"id": 6, "flag": true}))?;
+        let mut data = codec.finish()?.expect("no file written");
+        assert_eq!(
+            Some(literal!([{"id": 6, "name": null, "ratio": null, "flag": true}])),
+            codec.decode(&mut data, 0)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn bad_config() {
+        assert!(Parquet::from_config(None).is_err());
+        assert!(Parquet::from_config(Some(&literal!({"schema": "snot"}))).is_err());
+        assert!(Parquet::from_config(Some(&literal!({
+            "schema": "message event { repeated int64 ids; }"
+        })))
+        .is_err());
+        assert!(Parquet::from_config(Some(&literal!({
+            "schema": SCHEMA,
+            "compression": "snot"
+        })))
+        .is_err());
+    }
+}
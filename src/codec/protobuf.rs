@@ -0,0 +1,349 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Protobuf codec
+//!
+//! Converts between protobuf messages and tremor values using a descriptor set,
+//! as created by `protoc --include_imports --descriptor_set_out=<file>`.
+//!
+//! Configuration:
+//!
+//! * `descriptor` - path to the descriptor set file
+//! * `message` - the fully qualified name of the message type, e.g. `my.package.Event`
+//! * `include_defaults` - include fields that are not set with their default value, defaults to `false`
+//!
+//! Messages become records keyed by field name, enums are represented by the name of their value
+//! and maps by records. When encoding, enums can also be given by their number.
+
+use super::prelude::*;
+use prost::Message;
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, FieldDescriptor, Kind, MapKey, MessageDescriptor,
+    Value as PbValue,
+};
+use std::collections::HashMap;
+
+const NAME: &str = "protobuf";
+
+#[derive(Clone)]
+pub struct Protobuf {
+    message: MessageDescriptor,
+    include_defaults: bool,
+}
+
+fn invalid_config(msg: &str) -> Error {
+    ErrorKind::InvalidConfiguration(format!("{NAME} codec"), msg.to_string()).into()
+}
+
+impl Protobuf {
+    pub(crate) fn from_config(config: Option<&Value>) -> Result<Self> {
+        let descriptor = config
+            .get_str("descriptor")
+            .ok_or_else(|| invalid_config("a `descriptor` file is required"))?;
+        let message = config
+            .get_str("message")
+            .ok_or_else(|| invalid_config("a `message` type is required"))?;
+        let pool = DescriptorPool::decode(std::fs::read(descriptor)?.as_slice())?;
+        let message = pool.get_message_by_name(message).ok_or_else(|| {
+            invalid_config(&format!("Unknown message type `{message}` in {descriptor}"))
+        })?;
+        Ok(Self {
+            message,
+            include_defaults: config.get_bool("include_defaults").unwrap_or_default(),
+        })
+    }
+}
+
+fn mismatch(field: &FieldDescriptor, value: &Value) -> Error {
+    format!(
+        "Value of type {:?} does not match the type of field `{}`",
+        value.value_type(),
+        field.full_name()
+    )
+    .into()
+}
+
+/// converts a record into a message of type `desc`
+fn to_message(value: &Value, desc: &MessageDescriptor) -> Result<DynamicMessage> {
+    let record = value
+        .as_object()
+        .ok_or_else(|| format!("Expected a record for message `{}`", desc.full_name()))?;
+    let mut msg = DynamicMessage::new(desc.clone());
+    for (name, v) in record {
+        let field = desc
+            .get_field_by_name(name)
+            .ok_or_else(|| format!("Unknown field `{name}` for message `{}`", desc.full_name()))?;
+        if !v.is_null() {
+            msg.set_field(&field, field_to_pb(v, &field)?);
+        }
+    }
+    Ok(msg)
+}
+
+fn field_to_pb(value: &Value, field: &FieldDescriptor) -> Result<PbValue> {
+    if field.is_map() {
+        let entry = match field.kind() {
+            Kind::Message(entry) => entry,
+            _ => return Err(mismatch(field, value)),
+        };
+        let key_field = entry.map_entry_key_field();
+        let value_field = entry.map_entry_value_field();
+        let map = value
+            .as_object()
+            .ok_or_else(|| mismatch(field, value))?
+            .iter()
+            .map(|(k, v)| {
+                Ok((
+                    to_map_key(k, &key_field)?,
+                    to_pb(v, &value_field.kind(), &value_field)?,
+                ))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        Ok(PbValue::Map(map))
+    } else if field.is_list() {
+        let kind = field.kind();
+        let list = value
+            .as_array()
+            .ok_or_else(|| mismatch(field, value))?
+            .iter()
+            .map(|v| to_pb(v, &kind, field))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(PbValue::List(list))
+    } else {
+        to_pb(value, &field.kind(), field)
+    }
+}
+
+fn to_map_key(key: &str, field: &FieldDescriptor) -> Result<MapKey> {
+    Ok(match field.kind() {
+        Kind::String => MapKey::String(key.to_string()),
+        Kind::Bool => MapKey::Bool(parse_key(key, field)?),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => MapKey::I32(parse_key(key, field)?),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => MapKey::I64(parse_key(key, field)?),
+        Kind::Uint32 | Kind::Fixed32 => MapKey::U32(parse_key(key, field)?),
+        Kind::Uint64 | Kind::Fixed64 => MapKey::U64(parse_key(key, field)?),
+        _ => return Err(format!("Invalid key type for map `{}`", field.full_name()).into()),
+    })
+}
+
+fn parse_key<T: std::str::FromStr>(key: &str, field: &FieldDescriptor) -> Result<T> {
+    key.parse()
+        .map_err(|_| format!("Invalid key `{key}` for map `{}`", field.full_name()).into())
+}
+
+/// converts a single value of `kind`, `field` is used for error reporting
+fn to_pb(value: &Value, kind: &Kind, field: &FieldDescriptor) -> Result<PbValue> {
+    let err = || mismatch(field, value);
+    Ok(match kind {
+        Kind::Double => PbValue::F64(value.cast_f64().ok_or_else(err)?),
+        Kind::Float => PbValue::F32(value.as_f32().ok_or_else(err)?),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            PbValue::I32(value.as_i32().ok_or_else(err)?)
+        }
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+            PbValue::I64(value.as_i64().ok_or_else(err)?)
+        }
+        Kind::Uint32 | Kind::Fixed32 => PbValue::U32(value.as_u32().ok_or_else(err)?),
+        Kind::Uint64 | Kind::Fixed64 => PbValue::U64(value.as_u64().ok_or_else(err)?),
+        Kind::Bool => PbValue::Bool(value.as_bool().ok_or_else(err)?),
+        Kind::String => PbValue::String(value.as_str().ok_or_else(err)?.to_string()),
+        Kind::Bytes => PbValue::Bytes(bytes::Bytes::copy_from_slice(
+            value.as_bytes().ok_or_else(err)?,
+        )),
+        Kind::Enum(e) => {
+            let number = if let Some(name) = value.as_str() {
+                e.get_value_by_name(name).ok_or_else(err)?.number()
+            } else {
+                value.as_i32().ok_or_else(err)?
+            };
+            PbValue::EnumNumber(number)
+        }
+        Kind::Message(m) => PbValue::Message(to_message(value, m)?),
+    })
+}
+
+/// converts a message into a record
+fn from_message(msg: &DynamicMessage, include_defaults: bool) -> Value<'static> {
+    let desc = msg.descriptor();
+    let mut record = Value::object_with_capacity(desc.fields().len());
+    for field in desc.fields() {
+        if include_defaults || msg.has_field(&field) {
+            let value = from_pb(&msg.get_field(&field), &field.kind(), include_defaults);
+            record.try_insert(field.name().to_string(), value);
+        }
+    }
+    record
+}
+
+fn map_key_to_string(key: &MapKey) -> String {
+    match key {
+        MapKey::Bool(b) => b.to_string(),
+        MapKey::I32(i) => i.to_string(),
+        MapKey::I64(i) => i.to_string(),
+        MapKey::U32(u) => u.to_string(),
+        MapKey::U64(u) => u.to_string(),
+        MapKey::String(s) => s.clone(),
+    }
+}
+
+/// converts a value of `kind`, for lists and maps `kind` is the kind of the field
+fn from_pb(value: &PbValue, kind: &Kind, include_defaults: bool) -> Value<'static> {
+    match value {
+        PbValue::Bool(b) => Value::from(*b),
+        PbValue::I32(i) => Value::from(*i),
+        PbValue::I64(i) => Value::from(*i),
+        PbValue::U32(u) => Value::from(*u),
+        PbValue::U64(u) => Value::from(*u),
+        PbValue::F32(f) => Value::from(f64::from(*f)),
+        PbValue::F64(f) => Value::from(*f),
+        PbValue::String(s) => Value::from(s.clone()),
+        PbValue::Bytes(b) => Value::Bytes(b.to_vec().into()),
+        PbValue::EnumNumber(n) => match kind {
+            Kind::Enum(e) => e
+                .get_value(*n)
+                .map_or_else(|| Value::from(*n), |v| Value::from(v.name().to_string())),
+            _ => Value::from(*n),
+        },
+        PbValue::Message(m) => from_message(m, include_defaults),
+        PbValue::List(l) => Value::from(
+            l.iter()
+                .map(|v| from_pb(v, kind, include_defaults))
+                .collect::<Vec<_>>(),
+        ),
+        PbValue::Map(m) => {
+            let value_kind = match kind {
+                Kind::Message(entry) => entry.map_entry_value_field().kind(),
+                _ => kind.clone(),
+            };
+            let mut record = Value::object_with_capacity(m.len());
+            for (k, v) in m {
+                record.try_insert(
+                    map_key_to_string(k),
+                    from_pb(v, &value_kind, include_defaults),
+                );
+            }
+            record
+        }
+    }
+}
+
+impl Codec for Protobuf {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn mime_types(&self) -> Vec<&'static str> {
+        vec!["application/x-protobuf", "application/protobuf"]
+    }
+
+    fn decode<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        _ingest_ns: u64,
+    ) -> Result<Option<Value<'input>>> {
+        let data: &[u8] = data;
+        let msg = DynamicMessage::decode(self.message.clone(), data)?;
+        Ok(Some(from_message(&msg, self.include_defaults)))
+    }
+
+    fn encode(&self, data: &Value) -> Result<Vec<u8>> {
+        Ok(to_message(data, &self.message)?.encode_to_vec())
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Codec> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tremor_value::literal;
+
+    const DESCRIPTOR: &str = "tests/data/protobuf/event.desc";
+
+    fn codec(include_defaults: bool) -> Result<Protobuf> {
+        Protobuf::from_config(Some(&literal!({
+            "descriptor": DESCRIPTOR,
+            "message": "tremor.test.Event",
+            "include_defaults": include_defaults
+        })))
+    }
+
+    #[test]
+    fn roundtrip() -> Result<()> {
+        let mut codec = codec(false)?;
+        let event = literal!({
+            "id": "snot",
+            "count": 42,
+            "ratio": 0.5,
+            "flag": true,
+            "kind": "BADGER",
+            "inner": {"name": "badger", "values": [1, -2, 3]},
+            "inners": [{"name": "a"}, {"values": [4]}],
+            "labels": {"snot": 1, "badger": 2},
+            "data": Value::Bytes(b"bytes".to_vec().into()),
+            "delta": -7
+        });
+        let mut data = codec.encode(&event)?;
+        assert_eq!(Some(event), codec.decode(&mut data, 0)?);
+
+        // enums can be given by number
+        let mut data = codec.encode(&literal!({"kind": 1}))?;
+        assert_eq!(
+            Some(literal!({"kind": "SNOT"})),
+            codec.decode(&mut data, 0)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn include_defaults() -> Result<()> {
+        let mut codec = codec(true)?;
+        let mut data = codec.encode(&literal!({"id": "snot"}))?;
+        let decoded = codec.decode(&mut data, 0)?;
+        assert_eq!(Some("snot"), decoded.get_str("id"));
+        assert_eq!(Some(0), decoded.get_i64("count"));
+        assert_eq!(Some("UNKNOWN"), decoded.get_str("kind"));
+        assert_eq!(Some(&literal!([])), decoded.get("inners"));
+        assert_eq!(Some(&literal!({})), decoded.get("labels"));
+        Ok(())
+    }
+
+    #[test]
+    fn bad_values() -> Result<()> {
+        let codec = codec(false)?;
+        assert!(codec.encode(&literal!("snot")).is_err());
+        assert!(codec.encode(&literal!({"unknown": 1})).is_err());
+        assert!(codec.encode(&literal!({"count": "1"})).is_err());
+        assert!(codec.encode(&literal!({"kind": "SNOTBADGER"})).is_err());
+        assert!(codec.encode(&literal!({"inners": {"name": "a"}})).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bad_config() {
+        assert!(Protobuf::from_config(None).is_err());
+        assert!(Protobuf::from_config(Some(&literal!({
+            "descriptor": DESCRIPTOR,
+            "message": "tremor.test.Snot"
+        })))
+        .is_err());
+        assert!(Protobuf::from_config(Some(&literal!({
+            "descriptor": "tests/data/protobuf/missing.desc",
+            "message": "tremor.test.Event"
+        })))
+        .is_err());
+    }
+}
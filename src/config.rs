@@ -48,6 +48,23 @@ fn default_growth_rate() -> f64 {
     1.5
 }
 
+/// What a source does with data that failed preprocessing or decoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum DeadLetter {
+    /// Send an event with the error to the `err` port
+    Error,
+    /// Send an event with the error, the raw data and its origin to the `err` port,
+    /// so it can be stored and replayed later
+    Raw,
+}
+
+impl Default for DeadLetter {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
 impl Default for Reconnect {
     fn default() -> Self {
         Self::None
@@ -149,6 +166,9 @@ pub(crate) struct Connector {
 
     //pub(crate) on_pause: PauseBehaviour,
     pub(crate) metrics_interval_s: Option<u64>,
+
+    /// What sources do with data failing preprocessing or decoding
+    pub(crate) dead_letter: DeadLetter,
 }
 
 impl Connector {
@@ -212,6 +232,12 @@ impl Connector {
             ValueType::Array,
             connector_id,
         )?;
+        validate_type(
+            connector_config,
+            ConnectorDefinition::DEAD_LETTER,
+            ValueType::String,
+            connector_id,
+        )?;
         validate_type(
             connector_config,
             ConnectorDefinition::POSTPROCESSORS,
@@ -251,6 +277,12 @@ impl Connector {
                 .transpose()?
                 .unwrap_or_default(),
             metrics_interval_s: connector_config.get_u64(ConnectorDefinition::METRICS_INTERVAL_S),
+            dead_letter: connector_config
+                .get(ConnectorDefinition::DEAD_LETTER)
+                .cloned()
+                .map(tremor_value::structurize)
+                .transpose()?
+                .unwrap_or_default(),
             codec: connector_config
                 .get(ConnectorDefinition::CODEC)
                 .map(Codec::try_from)
@@ -342,4 +374,21 @@ mod tests {
         assert_eq!(String::from("Invalid Definition for connector \"my_id\": Expected type I64 for key metrics_interval_s but got String"), res.err().unwrap().to_string());
         Ok(())
     }
+
+    #[test]
+    fn test_connector_config_dead_letter() -> Result<()> {
+        let c = Connector::from_config("my_id", "file".into(), &literal!({}))?;
+        assert_eq!(DeadLetter::Error, c.dead_letter);
+        let c = Connector::from_config("my_id", "file".into(), &literal!({"dead_letter": "raw"}))?;
+        assert_eq!(DeadLetter::Raw, c.dead_letter);
+        assert!(
+            Connector::from_config("my_id", "file".into(), &literal!({"dead_letter": "snot"}))
+                .is_err()
+        );
+        assert!(
+            Connector::from_config("my_id", "file".into(), &literal!({"dead_letter": true}))
+                .is_err()
+        );
+        Ok(())
+    }
 }
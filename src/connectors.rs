@@ -13,6 +13,8 @@
 // limitations under the License.
 
 pub(crate) mod impls;
+/// connector types provided by plugins
+pub(crate) mod plugin;
 /// prelude with commonly needed stuff imported
 pub(crate) mod prelude;
 
@@ -36,6 +38,7 @@ pub(crate) use crate::config::Connector as ConnectorConfig;
 use crate::instance::State;
 use crate::pipeline;
 use crate::system::World;
+use crate::tap::Tap;
 use crate::{
     errors::{Error, Kind as ErrorKind, Result},
     log_error,
@@ -146,7 +149,16 @@ impl Addr {
     /// # Errors
     ///   * if sending failed
     pub async fn pause(&self) -> Result<()> {
-        self.send(Msg::Pause).await
+        self.send(Msg::Pause(None)).await
+    }
+    /// pauses the connector and waits until its source part doesn't send out any more events
+    ///
+    /// # Errors
+    ///   * if sending or receiving failed
+    pub(crate) async fn pause_and_wait(&self) -> Result<()> {
+        let (tx, rx) = bounded(1);
+        self.send(Msg::Pause(Some(tx))).await?;
+        rx.recv().await?
     }
     /// resumes the connector
     ///
@@ -165,6 +177,24 @@ impl Addr {
         self.send(Msg::Report(tx)).await?;
         Ok(rx.recv().await?)
     }
+
+    /// attach a tap to the output `port` of the source part of this connector
+    ///
+    /// # Errors
+    ///   * if the connector has no source part or no such output port
+    ///   * if sending failed
+    pub async fn tap(&self, port: &str, tap: Tap) -> Result<()> {
+        let is_output_port =
+            port.eq_ignore_ascii_case(OUT.as_ref()) || port.eq_ignore_ascii_case(ERR.as_ref());
+        if !self.has_source() || !is_output_port {
+            return Err(ErrorKind::InvalidTap(self.alias.clone(), port.to_string()).into());
+        }
+        self.send_source(SourceMsg::Tap {
+            port: port.to_string().into(),
+            tap,
+        })
+        .await
+    }
 }
 
 /// Messages a Connector instance receives and acts upon
@@ -200,7 +230,9 @@ pub(crate) enum Msg {
     /// source part is not polling for new data
     /// sink part issues a CB trigger
     /// until Resume is called, sink is restoring the CB again
-    Pause,
+    ///
+    /// bears an optional sender for replying once the source part doesn't send out any more events
+    Pause(Option<Sender<Result<()>>>),
     /// resume the connector after a pause
     Resume,
     /// Drain events from this connector
@@ -395,14 +427,16 @@ pub(crate) type Known =
 /// # Errors
 /// if the connector can not be built or the config is invalid
 pub(crate) async fn spawn(
+    flow_alias: &str,
     alias: &str,
     connector_id_gen: &mut ConnectorIdGen,
     builder: &dyn ConnectorBuilder,
     config: ConnectorConfig,
 ) -> Result<Addr> {
     // instantiate connector
-    let connector = builder.build(alias, &config).await?;
+    let connector = builder.build_in_flow(flow_alias, alias, &config).await?;
     let r = connector_task(
+        flow_alias,
         alias.to_string(),
         connector,
         config,
@@ -416,6 +450,7 @@ pub(crate) async fn spawn(
 #[allow(clippy::too_many_lines)]
 // instantiates the connector and starts listening for control plane messages
 async fn connector_task(
+    flow_alias: &str,
     alias: String,
     mut connector: Box<dyn Connector>,
     config: ConnectorConfig,
@@ -430,6 +465,7 @@ async fn connector_task(
     let notifier = ConnectionLostNotifier::new(msg_tx.clone());
 
     let source_metrics_reporter = SourceReporter::new(
+        flow_alias,
         alias.clone(),
         METRICS_CHANNEL.tx(),
         config.metrics_interval_s,
@@ -459,6 +495,7 @@ async fn connector_task(
     };
 
     let sink_metrics_reporter = SinkReporter::new(
+        flow_alias,
         alias.clone(),
         METRICS_CHANNEL.tx(),
         config.metrics_interval_s,
@@ -732,7 +769,7 @@ async fn connector_task(
                     }
                 }
 
-                Msg::Pause if connector_state == State::Running => {
+                Msg::Pause(paused_tx) if connector_state == State::Running => {
                     info!("{ctx} Pausing...");
 
                     // TODO: in implementations that don't really support pausing
@@ -741,16 +778,26 @@ async fn connector_task(
                     //
                     ctx.swallow_err(connector.on_pause(&ctx).await, "Error during on_pause");
 
-                    connector_addr.send_source(SourceMsg::Pause).await?;
                     connector_addr.send_sink(SinkMsg::Pause).await?;
+                    if connector_addr.has_source() {
+                        // the source replies once it stopped sending events
+                        connector_addr
+                            .send_source(SourceMsg::Pause(paused_tx))
+                            .await?;
+                    } else if let Some(paused_tx) = paused_tx {
+                        ctx.swallow_err(paused_tx.send(Ok(())).await, "Error sending Pause reply");
+                    }
 
                     connector_state = State::Paused;
                     quiescence_beacon.pause();
 
                     info!("{ctx} Paused.");
                 }
-                Msg::Pause => {
+                Msg::Pause(paused_tx) => {
                     info!("{ctx} Ignoring Pause Msg. Current state: {connector_state}",);
+                    if let Some(paused_tx) = paused_tx {
+                        ctx.swallow_err(paused_tx.send(Ok(())).await, "Error sending Pause reply");
+                    }
                 }
                 Msg::Resume if connector_state == State::Paused => {
                     info!("{ctx} Resuming...");
@@ -1140,6 +1187,22 @@ pub(crate) trait ConnectorBuilder: Sync + Send + std::fmt::Debug {
     /// # Errors
     ///  * If the config is invalid for the connector
     async fn build(&self, alias: &str, config: &ConnectorConfig) -> Result<Box<dyn Connector>>;
+
+    /// create a connector from the given `alias` and `config` within the flow `flow_alias`
+    ///
+    /// Connectors sharing state with other connectors of their flow override this,
+    /// it defaults to `build`.
+    ///
+    /// # Errors
+    ///  * If the config is invalid for the connector
+    async fn build_in_flow(
+        &self,
+        _flow_alias: &str,
+        alias: &str,
+        config: &ConnectorConfig,
+    ) -> Result<Box<dyn Connector>> {
+        self.build(alias, config).await
+    }
 }
 
 /// builtin connector types
@@ -1167,6 +1230,7 @@ pub(crate) fn builtin_connector_types() -> Vec<Box<dyn ConnectorBuilder + 'stati
         Box::new(impls::s3::reader::Builder::default()),
         Box::new(impls::kafka::consumer::Builder::default()),
         Box::new(impls::kafka::producer::Builder::default()),
+        Box::new(impls::mqtt::client::Builder::default()),
         #[cfg(unix)]
         Box::new(impls::unix_socket::server::Builder::default()),
         #[cfg(unix)]
@@ -40,6 +40,8 @@ pub(crate) mod kv;
 pub(crate) mod metrics;
 /// Metronome
 pub(crate) mod metronome;
+/// MQTT client
+pub(crate) mod mqtt;
 /// Never send any events and swallow all events it receives into the void.
 pub(crate) mod null;
 /// `OpenTelemetry`
@@ -315,6 +315,27 @@ impl Sink for FileSink {
         false
     }
 
+    async fn on_finish(
+        &mut self,
+        ctx: &SinkContext,
+        serializer: &mut EventSerializer,
+    ) -> Result<()> {
+        // e.g. the footer of a parquet file
+        let data = serializer.finish_stream(DEFAULT_STREAM_ID)?;
+        if let Some(file) = self.file.as_mut() {
+            for chunk in data {
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+        } else if !data.is_empty() {
+            error!(
+                "{} No file available to write the end of the stream to.",
+                &ctx
+            );
+        }
+        Ok(())
+    }
+
     async fn on_stop(&mut self, ctx: &SinkContext) -> Result<()> {
         if let Some(file) = self.file.take() {
             if let Err(e) = file.sync_all().await {
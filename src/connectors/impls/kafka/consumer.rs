@@ -14,6 +14,8 @@
 
 use async_std::sync::Arc;
 use beef::Cow;
+use std::collections::BTreeMap;
+use std::sync::{atomic::AtomicUsize, Mutex, RwLock, Weak};
 use std::time::Duration;
 
 use super::SmolRuntime;
@@ -28,13 +30,125 @@ use halfbrown::HashMap;
 use indexmap::IndexMap;
 use log::Level::Debug;
 use rdkafka::config::ClientConfig;
-use rdkafka::consumer::{CommitMode, Consumer, ConsumerContext, Rebalance, StreamConsumer};
+use rdkafka::consumer::{
+    CommitMode, Consumer, ConsumerContext, ConsumerGroupMetadata, Rebalance, StreamConsumer,
+};
 use rdkafka::error::KafkaError;
 use rdkafka::message::{BorrowedMessage, Headers, Message};
 use rdkafka::{ClientContext, Offset, TopicPartitionList};
 use rdkafka_sys::RDKafkaErrorCode;
 
-const KAFKA_CONSUMER_META_KEY: &str = "kafka_consumer";
+pub(crate) const KAFKA_CONSUMER_META_KEY: &str = "kafka_consumer";
+
+lazy_static! {
+    /// the links between consumers and transactional producers by flow and consumer alias
+    static ref LINKS: Mutex<std::collections::HashMap<(String, String), Weak<ConsumerLink>>> =
+        Mutex::new(std::collections::HashMap::new());
+}
+
+/// The link between a `kafka_consumer` and the transactional `kafka_producer`s of the same flow
+/// that commit its offsets within their transactions.
+///
+/// While a producer is linked the consumer doesn't commit offsets on its own.
+#[derive(Default)]
+pub(crate) struct ConsumerLink {
+    /// the consumer, while it is connected
+    consumer: RwLock<Option<Weak<TremorConsumer>>>,
+    /// the number of linked producers
+    producers: AtomicUsize,
+}
+
+impl ConsumerLink {
+    /// The link to the consumer `alias` of the flow `flow_alias`, shared by all connectors of that flow.
+    pub(crate) fn get(flow_alias: &str, alias: &str) -> Result<Arc<Self>> {
+        let mut links = LINKS.lock()?;
+        // forget the links of undeployed flows
+        links.retain(|_, link| link.strong_count() > 0);
+        let key = (flow_alias.to_string(), alias.to_string());
+        if let Some(link) = links.get(&key).and_then(Weak::upgrade) {
+            Ok(link)
+        } else {
+            let link = Arc::new(Self::default());
+            links.insert(key, Arc::downgrade(&link));
+            Ok(link)
+        }
+    }
+
+    fn is_linked(&self) -> bool {
+        self.producers.load(Ordering::Acquire) > 0
+    }
+
+    fn register(&self, consumer: &Arc<TremorConsumer>) -> Result<()> {
+        *self.consumer.write()? = Some(Arc::downgrade(consumer));
+        Ok(())
+    }
+
+    fn unregister(&self) -> Result<()> {
+        *self.consumer.write()? = None;
+        Ok(())
+    }
+
+    fn running_consumer(&self) -> Option<Arc<TremorConsumer>> {
+        self.consumer.read().ok()?.as_ref()?.upgrade()
+    }
+
+    /// The group metadata of the running consumer.
+    ///
+    /// A transactional producer needs it to commit the offsets of the consumed messages
+    /// within its transaction.
+    pub(crate) fn group_metadata(&self) -> Option<ConsumerGroupMetadata> {
+        self.running_consumer()?.group_metadata()
+    }
+
+    /// The offsets a transactional producer commits on behalf of the consumer
+    /// for the consumed `messages`, by topic and partition, its transaction is derived from.
+    ///
+    /// Per partition this is the end of the acknowledged prefix of the messages in flight,
+    /// counting `messages` as acknowledged, so messages still in flight before them are never
+    /// skipped. Partitions that can't move past such messages are left out.
+    pub(crate) fn transaction_offsets(
+        &self,
+        messages: &HashMap<(&str, i32), Vec<i64>>,
+    ) -> Result<TopicPartitionList> {
+        let consumer = self
+            .running_consumer()
+            .ok_or_else(|| Error::from("No kafka_consumer running"))?;
+        let offsets = consumer.context().offsets.lock()?;
+        let mut tpl = TopicPartitionList::with_capacity(messages.len());
+        for ((topic, partition), acked) in messages {
+            if let Some(offset) = offsets.commit_with(topic, *partition, acked) {
+                tpl.add_partition_offset(topic, *partition, Offset::Offset(offset))?;
+            }
+        }
+        Ok(tpl)
+    }
+}
+
+/// A transactional producer linked to a consumer, unlinked once dropped with its connector
+pub(crate) struct ProducerLink(Arc<ConsumerLink>);
+
+impl ProducerLink {
+    /// Links a producer to the consumer of `link`, which doesn't commit offsets on its own from
+    /// its next connect on.
+    pub(crate) fn new(link: Arc<ConsumerLink>) -> Self {
+        link.producers.fetch_add(1, Ordering::AcqRel);
+        Self(link)
+    }
+}
+
+impl std::ops::Deref for ProducerLink {
+    type Target = ConsumerLink;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Drop for ProducerLink {
+    fn drop(&mut self) {
+        self.0.producers.fetch_sub(1, Ordering::AcqRel);
+    }
+}
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
@@ -54,28 +168,44 @@ pub struct Config {
     ///
     /// If set to `false` this source will only commit the consumer offset
     /// if the message has been successfully acknowledged.
+    /// Failed messages are not retried and do not hold back commits of later messages.
     ///
     /// This might lead to events being sent multiple times.
     /// This should not be used when persistent errors are expected (e.g. if the message content is malformed and will lead to repeated errors)
     #[serde(default = "default_false")]
     pub retry_failed_events: bool,
 
+    /// Interval in milliseconds in which acknowledged offsets are committed
+    /// if `enable.auto.commit` is set to false in `rdkafka_options`.
+    ///
+    /// Only the offsets up to which all messages of a partition are acknowledged are committed,
+    /// so a message acknowledged out of order never causes an earlier one to be skipped.
+    /// If set to `0` offsets are committed on every acknowledgement.
+    #[serde(default = "default_commit_interval_ms")]
+    pub commit_interval_ms: u64,
+
     /// Optional rdkafka configuration
+    ///
+    /// If a transactional `kafka_producer` commits the offsets of this consumer,
+    /// `enable.auto.commit` is always set to `false`.
     pub rdkafka_options: Option<HashMap<String, String>>,
 }
 
 impl ConfigImpl for Config {}
 
+fn default_commit_interval_ms() -> u64 {
+    1000
+}
+
 #[derive(Default, Debug)]
 pub(crate) struct Builder {}
 
-#[async_trait::async_trait()]
-impl ConnectorBuilder for Builder {
-    fn connector_type(&self) -> ConnectorType {
-        "kafka_consumer".into()
-    }
-
-    async fn build(&self, alias: &str, config: &ConnectorConfig) -> Result<Box<dyn Connector>> {
+impl Builder {
+    fn build_linked(
+        alias: &str,
+        config: &ConnectorConfig,
+        link: Arc<ConsumerLink>,
+    ) -> Result<Box<dyn Connector>> {
         let metrics_interval_s = config.metrics_interval_s;
         if let Some(raw_config) = &config.config {
             let config = Config::new(raw_config)?;
@@ -125,6 +255,7 @@ impl ConnectorBuilder for Builder {
                 config,
                 client_config,
                 origin_uri,
+                link,
             }))
         } else {
             Err(ErrorKind::MissingConfiguration(alias.to_string()).into())
@@ -132,13 +263,35 @@ impl ConnectorBuilder for Builder {
     }
 }
 
+#[async_trait::async_trait()]
+impl ConnectorBuilder for Builder {
+    fn connector_type(&self) -> ConnectorType {
+        "kafka_consumer".into()
+    }
+
+    async fn build(&self, alias: &str, config: &ConnectorConfig) -> Result<Box<dyn Connector>> {
+        // outside of a flow no producer can link to this consumer
+        Self::build_linked(alias, config, Arc::new(ConsumerLink::default()))
+    }
+
+    async fn build_in_flow(
+        &self,
+        flow_alias: &str,
+        alias: &str,
+        config: &ConnectorConfig,
+    ) -> Result<Box<dyn Connector>> {
+        Self::build_linked(alias, config, ConsumerLink::get(flow_alias, alias)?)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct KafkaStats {}
 
 struct TremorConsumerContext {
     ctx: SourceContext,
     connect_tx: Sender<Result<bool>>,
-    metrics_tx: BroadcastSender<EventPayload>,
+    metrics_tx: BroadcastSender<Vec<EventPayload>>,
+    offsets: Arc<Mutex<OffsetTracker>>,
 }
 
 impl ClientContext for TremorConsumerContext {
@@ -175,10 +328,55 @@ impl ClientContext for TremorConsumerContext {
                 Cow::const_str("connector"),
                 Value::from(self.ctx.alias.clone()),
             );
-            let metrics_payload =
-                make_metrics_payload("kafka_consumer_stats", fields, tags, timestamp);
+            let mut payloads = vec![make_metrics_payload(
+                "kafka_consumer_stats",
+                fields,
+                tags,
+                timestamp,
+            )];
+
+            // per partition stats
+            let offsets = self.offsets.lock().ok();
+            for (topic_name, topic) in &stats.topics {
+                for partition in topic.partitions.values() {
+                    // partition -1 is the internal unassigned partition
+                    if !partition.desired || partition.unknown || partition.partition < 0 {
+                        continue;
+                    }
+                    let in_flight = offsets
+                        .as_ref()
+                        .map(|o| o.in_flight(topic_name, partition.partition))
+                        .unwrap_or_default();
+                    let mut fields = HashMap::with_capacity(3);
+                    fields.insert(
+                        Cow::const_str("consumer_lag"),
+                        Value::from(partition.consumer_lag),
+                    );
+                    fields.insert(
+                        Cow::const_str("committed_offset"),
+                        Value::from(partition.committed_offset),
+                    );
+                    fields.insert(Cow::const_str("in_flight"), Value::from(in_flight));
+                    let mut tags = HashMap::with_capacity(3);
+                    tags.insert(
+                        Cow::const_str("connector"),
+                        Value::from(self.ctx.alias.clone()),
+                    );
+                    tags.insert(Cow::const_str("topic"), Value::from(topic_name.clone()));
+                    tags.insert(
+                        Cow::const_str("partition"),
+                        Value::from(partition.partition),
+                    );
+                    payloads.push(make_metrics_payload(
+                        "kafka_consumer_partition_stats",
+                        fields,
+                        tags,
+                        timestamp,
+                    ));
+                }
+            }
 
-            if let Err(e) = self.metrics_tx.try_broadcast(metrics_payload) {
+            if let Err(e) = self.metrics_tx.try_broadcast(payloads) {
                 warn!("{} Error sending kafka statistics: {}", &self.ctx, e);
             }
         }
@@ -258,6 +456,16 @@ impl ConsumerContext for TremorConsumerContext {
                     &self.ctx,
                     offset_strings.join(" ")
                 );
+                // messages in flight of revoked partitions will be consumed again
+                // by their new owner, acks for them must not be committed anymore
+                match self.offsets.lock() {
+                    Ok(mut offsets) => {
+                        for elem in tpl.elements() {
+                            offsets.revoke(elem.topic(), elem.partition());
+                        }
+                    }
+                    Err(e) => error!("{} Error dropping revoked offsets: {e}", &self.ctx),
+                }
             }
             Rebalance::Error(err_info) => {
                 warn!("{} Post Rebalance error {}", &self.ctx, err_info);
@@ -302,12 +510,14 @@ impl TremorConsumerContext {
     fn new(
         source_ctx: &SourceContext,
         connect_tx: Sender<Result<bool>>,
-        metrics_tx: BroadcastSender<EventPayload>,
+        metrics_tx: BroadcastSender<Vec<EventPayload>>,
+        offsets: Arc<Mutex<OffsetTracker>>,
     ) -> Self {
         Self {
             ctx: source_ctx.clone(),
             connect_tx,
             metrics_tx,
+            offsets,
         }
     }
 }
@@ -318,6 +528,7 @@ struct KafkaConsumerConnector {
     config: Config,
     client_config: ClientConfig,
     origin_uri: EventOriginUri,
+    link: Arc<ConsumerLink>,
 }
 
 #[async_trait::async_trait()]
@@ -331,6 +542,7 @@ impl Connector for KafkaConsumerConnector {
             self.config.clone(),
             self.client_config.clone(),
             self.origin_uri.clone(),
+            self.link.clone(),
         );
         builder.spawn(source, source_context).map(Some)
     }
@@ -370,22 +582,33 @@ struct KafkaConsumerSource {
     topics: Vec<String>,
     topic_resolver: TopicResolver,
     transactional: bool,
+    /// while a transactional producer is linked it commits the offsets, so we must never commit them ourselves
+    link: Arc<ConsumerLink>,
     retry_failed_events: bool,
     seek_timeout: Duration,
+    commit_interval: Duration,
+    offsets: Arc<Mutex<OffsetTracker>>,
     source_tx: Sender<(SourceReply, Option<u64>)>,
     source_rx: Receiver<(SourceReply, Option<u64>)>,
     consumer: Option<Arc<TremorConsumer>>,
     consumer_task: Option<JoinHandle<()>>,
-    metrics_rx: Option<BroadcastReceiver<EventPayload>>,
+    commit_task: Option<JoinHandle<()>>,
+    metrics_rx: Option<BroadcastReceiver<Vec<EventPayload>>>,
 }
 
 impl KafkaConsumerSource {
     const DEFAULT_SEEK_TIMEOUT: Duration = Duration::from_millis(500);
 
-    fn new(config: Config, client_config: ClientConfig, origin_uri: EventOriginUri) -> Self {
+    fn new(
+        config: Config,
+        client_config: ClientConfig,
+        origin_uri: EventOriginUri,
+        link: Arc<ConsumerLink>,
+    ) -> Self {
         let Config {
             topics,
             retry_failed_events,
+            commit_interval_ms,
             ..
         } = config;
         let topic_resolver = TopicResolver::new(topics.clone());
@@ -409,27 +632,79 @@ impl KafkaConsumerSource {
             topics,
             topic_resolver,
             transactional: !auto_commit,
+            link,
             retry_failed_events,
             seek_timeout,
+            commit_interval: Duration::from_millis(commit_interval_ms),
+            offsets: Arc::new(Mutex::new(OffsetTracker::default())),
             source_tx,
             source_rx,
             consumer: None,
             consumer_task: None,
+            commit_task: None,
             metrics_rx: None,
         }
     }
+
+    /// if we commit acknowledged offsets ourselves
+    fn commits(&self) -> bool {
+        self.transactional && !self.link.is_linked()
+    }
+
+    /// stops the consumer and commit tasks and drops the consumer
+    async fn stop_consumer(&mut self) {
+        if let Some(commit_task) = self.commit_task.take() {
+            commit_task.cancel().await;
+        }
+        if let Some(consumer) = self.consumer.take() {
+            consumer.unsubscribe();
+            drop(consumer);
+        }
+        if let Some(consumer_task) = self.consumer_task.take() {
+            consumer_task.cancel().await;
+        }
+    }
+}
+
+/// commits the offsets up to which all messages were acknowledged
+fn commit(
+    consumer: &TremorConsumer,
+    offsets: &Mutex<OffsetTracker>,
+    mode: CommitMode,
+) -> Result<()> {
+    let commits = offsets.lock()?.take_commits();
+    if !commits.is_empty() {
+        let mut tpl = TopicPartitionList::with_capacity(commits.len());
+        for (topic, partition, offset) in &commits {
+            tpl.add_partition_offset(topic, *partition, Offset::Offset(*offset))?;
+        }
+        consumer.commit(&tpl, mode)?;
+    }
+    Ok(())
+}
+
+/// Periodically commits acknowledged offsets
+async fn commit_task(
+    consumer: Arc<TremorConsumer>,
+    offsets: Arc<Mutex<OffsetTracker>>,
+    interval: Duration,
+    ctx: SourceContext,
+) {
+    loop {
+        task::sleep(interval).await;
+        ctx.swallow_err(
+            commit(&consumer, &offsets, CommitMode::Async),
+            "Error committing offsets",
+        );
+    }
 }
 
 #[async_trait::async_trait()]
 impl Source for KafkaConsumerSource {
     async fn connect(&mut self, ctx: &SourceContext, _attempt: &Attempt) -> Result<bool> {
-        if let Some(consumer_task) = self.consumer_task.take() {
-            if let Some(consumer) = self.consumer.take() {
-                consumer.unsubscribe();
-                drop(consumer);
-            }
-            consumer_task.cancel().await;
-        }
+        self.stop_consumer().await;
+        // the new consumer gets its partitions assigned anew
+        *self.offsets.lock()? = OffsetTracker::default();
         let (version_n, version_s) = rdkafka::util::get_rdkafka_version();
         info!(
             "{} Connecting using rdkafka 0x{:08x}, {}",
@@ -439,9 +714,19 @@ impl Source for KafkaConsumerSource {
         let (mut metrics_tx, metrics_rx) = broadcast(1);
         metrics_tx.set_overflow(true);
         self.metrics_rx = Some(metrics_rx);
-        let consumer_context =
-            TremorConsumerContext::new(ctx, connect_result_tx.clone(), metrics_tx);
-        let consumer: TremorConsumer = self.client_config.create_with_context(consumer_context)?;
+        let consumer_context = TremorConsumerContext::new(
+            ctx,
+            connect_result_tx.clone(),
+            metrics_tx,
+            self.offsets.clone(),
+        );
+        let consumer: TremorConsumer = if self.link.is_linked() {
+            let mut client_config = self.client_config.clone();
+            client_config.set("enable.auto.commit", "false");
+            client_config.create_with_context(consumer_context)?
+        } else {
+            self.client_config.create_with_context(consumer_context)?
+        };
 
         let topics: Vec<&str> = self
             .topics
@@ -458,18 +743,29 @@ impl Source for KafkaConsumerSource {
             }
         };
         let arc_consumer = Arc::new(consumer);
+        self.link.register(&arc_consumer)?;
         let task_consumer = arc_consumer.clone();
-        self.consumer = Some(arc_consumer);
+        self.consumer = Some(arc_consumer.clone());
 
+        let offsets = self.is_transactional().then(|| self.offsets.clone());
         let handle = task::spawn(consumer_task(
             task_consumer,
             self.topic_resolver.clone(),
             self.origin_uri.clone(),
             connect_result_tx,
             self.source_tx.clone(),
+            offsets,
             ctx.clone(),
         ));
         self.consumer_task = Some(handle);
+        if self.commits() && !self.commit_interval.is_zero() {
+            self.commit_task = Some(task::spawn(commit_task(
+                arc_consumer,
+                self.offsets.clone(),
+                self.commit_interval,
+                ctx.clone(),
+            )));
+        }
 
         let res = connect_result_rx
             .recv()
@@ -497,36 +793,51 @@ impl Source for KafkaConsumerSource {
     }
 
     async fn ack(&mut self, stream_id: u64, pull_id: u64, ctx: &SourceContext) -> Result<()> {
-        if self.transactional {
-            if let Some(consumer) = self.consumer.as_ref() {
-                if let Some((topic, partition, offset)) =
-                    self.topic_resolver.resolve_topic(stream_id, pull_id)
-                {
-                    let mut tpl: TopicPartitionList = TopicPartitionList::with_capacity(1);
-                    tpl.add_partition_offset(topic, partition, offset)?;
-                    consumer.commit(&tpl, CommitMode::Async)?;
-                } else {
-                    error!("{} Could not ack event with stream={}, pull_id={}. Unable to detect topic from internal state.", &ctx, stream_id, pull_id);
+        if self.is_transactional() {
+            if let Some((topic, partition, Offset::Offset(offset))) =
+                self.topic_resolver.resolve_topic(stream_id, pull_id)
+            {
+                self.offsets.lock()?.ack(topic, partition, offset);
+                if self.commits() && self.commit_interval.is_zero() {
+                    if let Some(consumer) = self.consumer.as_ref() {
+                        commit(consumer, &self.offsets, CommitMode::Async)?;
+                    }
                 }
+            } else {
+                error!("{} Could not ack event with stream={}, pull_id={}. Unable to detect topic from internal state.", &ctx, stream_id, pull_id);
             }
         }
         Ok(())
     }
 
     async fn fail(&mut self, stream_id: u64, pull_id: u64, ctx: &SourceContext) -> Result<()> {
-        if self.transactional && self.retry_failed_events {
-            if let Some(consumer) = self.consumer.as_ref() {
-                if let Some((topic, partition, offset)) =
-                    self.topic_resolver.resolve_topic(stream_id, pull_id)
+        if self.is_transactional() {
+            if let Some((topic, partition, offset)) =
+                self.topic_resolver.resolve_topic(stream_id, pull_id)
+            {
+                if let (true, Some(consumer), Offset::Offset(o)) =
+                    (self.retry_failed_events, self.consumer.as_ref(), offset)
                 {
                     debug!(
                         "{} Failing: [topic={}, partition={} offset={:?}]",
                         &ctx, topic, partition, offset
                     );
                     consumer.seek(topic, partition, offset, self.seek_timeout)?;
-                } else {
-                    error!("{} Could not seek back to failed event with stream={}, pull_id={}. Unable to detect topic from internal state.", &ctx, stream_id, pull_id);
+                    // everything from the failed offset on will be consumed again
+                    self.offsets.lock()?.rewind(topic, partition, o);
+                } else if self.link.is_linked() {
+                    // the transaction of the event was aborted, the offset stays in flight,
+                    // so no later transaction commits an offset past it
+                    warn!(
+                        "{} Failed message is not retried and holds back offsets: [topic={}, partition={} offset={:?}]",
+                        &ctx, topic, partition, offset
+                    );
+                } else if let Offset::Offset(o) = offset {
+                    // not retried, so it must not hold back the commits of later offsets
+                    self.offsets.lock()?.ack(topic, partition, o);
                 }
+            } else {
+                error!("{} Could not seek back to failed event with stream={}, pull_id={}. Unable to detect topic from internal state.", &ctx, stream_id, pull_id);
             }
         }
         Ok(())
@@ -568,21 +879,22 @@ impl Source for KafkaConsumerSource {
     }
 
     async fn on_stop(&mut self, ctx: &SourceContext) -> Result<()> {
-        // clear out the consumer
-        if let Some(consumer) = self.consumer.take() {
-            consumer.unsubscribe();
-            drop(consumer);
-        }
-        // stop the consumer task
-        if let Some(consumer_task) = self.consumer_task.take() {
-            consumer_task.cancel().await;
-            info!("{} Consumer stopped.", &ctx);
+        // commit what has been acknowledged so far
+        if let (true, Some(consumer)) = (self.commits(), self.consumer.as_ref()) {
+            ctx.swallow_err(
+                commit(consumer, &self.offsets, CommitMode::Sync),
+                "Error committing offsets",
+            );
         }
+        // clear out the consumer and stop the consumer task
+        self.link.unregister()?;
+        self.stop_consumer().await;
+        info!("{} Consumer stopped.", &ctx);
         Ok(())
     }
 
     fn is_transactional(&self) -> bool {
-        self.transactional
+        self.transactional || self.link.is_linked()
     }
 
     fn asynchronous(&self) -> bool {
@@ -592,8 +904,8 @@ impl Source for KafkaConsumerSource {
     fn metrics(&mut self, _timestamp: u64, _ctx: &SourceContext) -> Vec<EventPayload> {
         if let Some(metrics_rx) = self.metrics_rx.as_mut() {
             let mut vec = Vec::with_capacity(metrics_rx.len());
-            while let Ok(payload) = metrics_rx.try_recv() {
-                vec.push(payload);
+            while let Ok(payloads) = metrics_rx.try_recv() {
+                vec.extend(payloads);
             }
             vec
         } else {
@@ -609,6 +921,7 @@ async fn consumer_task(
     consumer_origin_uri: EventOriginUri,
     connect_result_tx: Sender<Result<bool>>,
     source_tx: Sender<(SourceReply, Option<u64>)>,
+    offsets: Option<Arc<Mutex<OffsetTracker>>>,
     source_ctx: SourceContext,
 ) {
     info!("{} Consumer started.", &source_ctx);
@@ -635,6 +948,16 @@ async fn consumer_task(
                 ];
                 let data: Vec<u8> = kafka_msg.payload().map(<[u8]>::to_vec).unwrap_or_default();
 
+                if let Some(offsets) = offsets.as_ref() {
+                    match offsets.lock() {
+                        Ok(mut offsets) => offsets.track(
+                            kafka_msg.topic(),
+                            kafka_msg.partition(),
+                            kafka_msg.offset(),
+                        ),
+                        Err(e) => error!("{source_ctx} Error tracking offset: {e}"),
+                    }
+                }
                 let meta = kafka_meta(&kafka_msg);
                 let reply = SourceReply::Data {
                     origin_uri,
@@ -713,6 +1036,119 @@ async fn consumer_task(
     }
 }
 
+/// Offsets of the messages in flight of a single partition
+#[derive(Debug, Default)]
+struct PartitionOffsets {
+    /// offsets of the messages sent out, with whether they are acknowledged
+    in_flight: BTreeMap<i64, bool>,
+    /// the offset to commit, if it changed since the last commit
+    commit: Option<i64>,
+}
+
+impl PartitionOffsets {
+    /// moves the offset to commit past the acknowledged prefix of messages in flight
+    fn advance(&mut self) {
+        while let Some((&offset, &acked)) = self.in_flight.iter().next() {
+            if !acked {
+                break;
+            }
+            self.in_flight.remove(&offset);
+            // kafka expects the offset of the next message to consume
+            self.commit = Some(offset + 1);
+        }
+    }
+}
+
+/// Tracks the messages in flight per topic partition, so only offsets
+/// up to which all messages are acknowledged are ever committed
+#[derive(Debug, Default)]
+struct OffsetTracker {
+    topics: HashMap<String, HashMap<i32, PartitionOffsets>>,
+}
+
+impl OffsetTracker {
+    fn partition_mut(&mut self, topic: &str, partition: i32) -> Option<&mut PartitionOffsets> {
+        self.topics
+            .get_mut(topic)
+            .and_then(|partitions| partitions.get_mut(&partition))
+    }
+
+    /// track a message sent out
+    fn track(&mut self, topic: &str, partition: i32, offset: i64) {
+        if !self.topics.contains_key(topic) {
+            self.topics.insert(topic.to_string(), HashMap::new());
+        }
+        if let Some(partitions) = self.topics.get_mut(topic) {
+            partitions
+                .entry(partition)
+                .or_default()
+                .in_flight
+                .insert(offset, false);
+        }
+    }
+
+    /// acknowledge a message, acks for messages not in flight are stale and ignored
+    fn ack(&mut self, topic: &str, partition: i32, offset: i64) {
+        if let Some(p) = self.partition_mut(topic, partition) {
+            if let Some(acked) = p.in_flight.get_mut(&offset) {
+                *acked = true;
+                p.advance();
+            }
+        }
+    }
+
+    /// the offset to commit for a partition if the messages at `acked` were acknowledged as well,
+    /// `None` if the messages in flight before them aren't acknowledged yet
+    fn commit_with(&self, topic: &str, partition: i32, acked: &[i64]) -> Option<i64> {
+        let p = self.topics.get(topic)?.get(&partition)?;
+        let mut commit = None;
+        for (&offset, &is_acked) in &p.in_flight {
+            if !is_acked && !acked.contains(&offset) {
+                break;
+            }
+            // kafka expects the offset of the next message to consume
+            commit = Some(offset + 1);
+        }
+        commit
+    }
+
+    /// forget all messages from `offset` on, as they will be consumed again
+    fn rewind(&mut self, topic: &str, partition: i32, offset: i64) {
+        if let Some(p) = self.partition_mut(topic, partition) {
+            p.in_flight.split_off(&offset);
+            p.advance();
+        }
+    }
+
+    /// forget about a partition that is no longer assigned to us
+    fn revoke(&mut self, topic: &str, partition: i32) {
+        if let Some(partitions) = self.topics.get_mut(topic) {
+            partitions.remove(&partition);
+        }
+    }
+
+    /// number of messages in flight for a partition
+    fn in_flight(&self, topic: &str, partition: i32) -> usize {
+        self.topics
+            .get(topic)
+            .and_then(|partitions| partitions.get(&partition))
+            .map_or(0, |p| p.in_flight.len())
+    }
+
+    /// takes the offsets to commit for all partitions that progressed since the last commit
+    fn take_commits(&mut self) -> Vec<(String, i32, i64)> {
+        let mut commits = Vec::new();
+        for (topic, partitions) in &mut self.topics {
+            for (partition, p) in partitions.iter_mut() {
+                if let Some(offset) = p.commit.take() {
+                    commits.push((topic.clone(), *partition, offset));
+                }
+            }
+        }
+        commits
+    }
+}
+
 #[derive(Clone)]
 struct TopicResolver(IndexMap<String, u64>);
 impl TopicResolver {
@@ -778,9 +1214,75 @@ impl TopicResolver {
 #[cfg(test)]
 mod test {
 
-    use super::{Offset, TopicResolver};
+    use super::{ConsumerLink, Offset, OffsetTracker, ProducerLink, TopicResolver};
+    use crate::errors::Result;
     use proptest::prelude::*;
 
+    #[test]
+    fn consumer_link() -> Result<()> {
+        let link = ConsumerLink::get("flow1", "consumer")?;
+        assert!(!link.is_linked());
+        let producer = ProducerLink::new(ConsumerLink::get("flow1", "consumer")?);
+        assert!(link.is_linked());
+        // the same alias in another flow is a different consumer
+        assert!(!ConsumerLink::get("flow2", "consumer")?.is_linked());
+        drop(producer);
+        assert!(!link.is_linked());
+        Ok(())
+    }
+
+    #[test]
+    fn offset_tracker() {
+        let mut o = OffsetTracker::default();
+        for offset in 0..5 {
+            o.track("snot", 0, offset);
+        }
+        o.track("badger", 1, 10);
+        assert_eq!(5, o.in_flight("snot", 0));
+        assert_eq!(1, o.in_flight("badger", 1));
+        assert_eq!(0, o.in_flight("badger", 0));
+
+        // acking out of order does not commit past the gap
+        o.ack("snot", 0, 1);
+        o.ack("snot", 0, 2);
+        assert!(o.take_commits().is_empty());
+        o.ack("snot", 0, 0);
+        assert_eq!(vec![("snot".to_string(), 0, 3)], o.take_commits());
+        assert!(o.take_commits().is_empty());
+        assert_eq!(2, o.in_flight("snot", 0));
+
+        // rewinding drops everything from the failed offset on
+        o.ack("snot", 0, 4);
+        o.rewind("snot", 0, 3);
+        assert_eq!(0, o.in_flight("snot", 0));
+        assert!(o.take_commits().is_empty());
+        o.track("snot", 0, 3);
+        o.track("snot", 0, 4);
+        o.ack("snot", 0, 3);
+        assert_eq!(vec![("snot".to_string(), 0, 4)], o.take_commits());
+
+        // a transaction only commits past the acknowledged prefix
+        o.track("snot", 0, 5);
+        o.track("snot", 0, 6);
+        o.track("snot", 0, 7);
+        o.ack("snot", 0, 6);
+        assert_eq!(None, o.commit_with("snot", 0, &[7]));
+        assert_eq!(None, o.commit_with("snot", 0, &[5]));
+        assert_eq!(Some(7), o.commit_with("snot", 0, &[4, 5]));
+        assert_eq!(Some(8), o.commit_with("snot", 0, &[4, 5, 7]));
+        assert_eq!(None, o.commit_with("snot", 1, &[4]));
+        assert_eq!(None, o.commit_with("badger", 0, &[4]));
+        // without changing what the consumer commits itself
+        assert!(o.take_commits().is_empty());
+        assert_eq!(4, o.in_flight("snot", 0));
+
+        // stale acks of revoked partitions are ignored
+        o.revoke("badger", 1);
+        o.ack("badger", 1, 10);
+        assert_eq!(0, o.in_flight("badger", 1));
+        assert!(o.take_commits().is_empty());
+    }
+
     fn topics_and_index() -> BoxedStrategy<(Vec<String>, usize)> {
         proptest::collection::hash_set(proptest::string::string_regex(".+").unwrap(), 1..100_usize)
             .prop_flat_map(|topics| {
@@ -14,9 +14,27 @@
 
 //! Kafka Producer Connector
 //! Sending events from tremor to a kafka topic
-
+//!
+//! If `transactional.id` is set in `rdkafka_options`, every tremor event is written
+//! within its own kafka transaction, which is committed before the event is acknowledged
+//! or aborted before it is failed. Records of a batched event are thus written all or nothing.
+//!
+//! Transactions are not pipelined: the sink waits for each commit, a round trip to the
+//! transaction coordinator, before it handles the next event. To amortize that cost over
+//! many records, batch events upstream, e.g. with the `batch` operator, so a single
+//! transaction spans the whole batch.
+//!
+//! With `consumer` set additionally, the offsets of the consumed messages an event
+//! is derived from are committed within its transaction on behalf of the `kafka_consumer`
+//! with that alias in the same flow (consume-transform-produce). Offsets are only
+//! committed up to the first message that is still in flight in that consumer, so messages
+//! of events that are not yet written are never skipped. The consumer doesn't commit any
+//! offsets on its own then, and the messages of aborted transactions are never committed.
+
+use std::sync::Arc;
 use std::time::Duration;
 
+use super::consumer::{ConsumerLink, ProducerLink};
 use super::SmolRuntime;
 use crate::connectors::impls::kafka::{is_failed_connect_error, KAFKA_CONNECT_TIMEOUT};
 use crate::connectors::metrics::make_metrics_payload;
@@ -39,6 +57,10 @@ use rdkafka_sys::RDKafkaErrorCode;
 use tremor_common::time::nanotime;
 
 const KAFKA_PRODUCER_META_KEY: &str = "kafka_producer";
+/// timeout for the blocking transaction operations
+const KAFKA_TRANSACTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+type TremorProducer = FutureProducer<TremorProducerContext, SmolRuntime>;
 
 #[derive(Deserialize, Clone)]
 pub struct Config {
@@ -60,6 +82,13 @@ pub struct Config {
     /// * `queue.buffering.max.ms` - `"0"` - don't buffer for lower latency (high)
     #[serde(default = "Default::default")]
     pub rdkafka_options: Option<HashMap<String, String>>,
+    /// alias of a `kafka_consumer` in the same flow whose offsets are committed
+    /// within the transaction of each event derived from its messages, up to the first
+    /// message still in flight.
+    ///
+    /// Requires `transactional.id` to be set in `rdkafka_options`.
+    #[serde(default = "Default::default")]
+    pub consumer: Option<String>,
 }
 
 impl ConfigImpl for Config {}
@@ -74,6 +103,25 @@ impl ConnectorBuilder for Builder {
     }
 
     async fn build(&self, alias: &str, config: &ConnectorConfig) -> Result<Box<dyn Connector>> {
+        Self::build_linked(None, alias, config)
+    }
+
+    async fn build_in_flow(
+        &self,
+        flow_alias: &str,
+        alias: &str,
+        config: &ConnectorConfig,
+    ) -> Result<Box<dyn Connector>> {
+        Self::build_linked(Some(flow_alias), alias, config)
+    }
+}
+
+impl Builder {
+    fn build_linked(
+        flow_alias: Option<&str>,
+        alias: &str,
+        config: &ConnectorConfig,
+    ) -> Result<Box<dyn Connector>> {
         let metrics_interval_s = config.metrics_interval_s;
         if let Some(raw_config) = &config.config {
             let config = Config::new(raw_config)?;
@@ -105,9 +153,29 @@ impl ConnectorBuilder for Builder {
                 .for_each(|(k, v)| {
                     producer_config.set(k, v);
                 });
+            let link = if let Some(consumer) = config.consumer.as_ref() {
+                if producer_config.get("transactional.id").is_none() {
+                    return Err(ErrorKind::InvalidConfiguration(
+                        alias.to_string(),
+                        "`consumer` requires `transactional.id` in `rdkafka_options`".to_string(),
+                    )
+                    .into());
+                }
+                let flow_alias = flow_alias.ok_or_else(|| {
+                    ErrorKind::InvalidConfiguration(
+                        alias.to_string(),
+                        "`consumer` is only supported within a flow".to_string(),
+                    )
+                })?;
+                let link = ConsumerLink::get(flow_alias, consumer)?;
+                Some(Arc::new(ProducerLink::new(link)))
+            } else {
+                None
+            };
             Ok(Box::new(KafkaProducerConnector {
                 config,
                 producer_config,
+                link,
             }))
         } else {
             Err(ErrorKind::MissingConfiguration(alias.to_string()).into())
@@ -181,6 +249,8 @@ impl ClientContext for TremorProducerContext {
 struct KafkaProducerConnector {
     config: Config,
     producer_config: ClientConfig,
+    /// the link to the consumer whose offsets we commit, undone once this connector is dropped
+    link: Option<Arc<ProducerLink>>,
 }
 
 #[async_trait::async_trait()]
@@ -193,6 +263,7 @@ impl Connector for KafkaProducerConnector {
         let sink = KafkaProducerSink::new(
             self.config.clone(),
             self.producer_config.clone(),
+            self.link.clone(),
             builder.reply_tx(),
         );
         builder.spawn(sink, sink_context).map(Some)
@@ -206,7 +277,10 @@ impl Connector for KafkaProducerConnector {
 struct KafkaProducerSink {
     config: Config,
     producer_config: ClientConfig,
-    producer: Option<FutureProducer<TremorProducerContext, SmolRuntime>>,
+    producer: Option<TremorProducer>,
+    /// if true, each event is written within its own kafka transaction
+    transactional: bool,
+    link: Option<Arc<ProducerLink>>,
     reply_tx: Sender<AsyncSinkReply>,
     metrics_rx: Option<BroadcastReceiver<EventPayload>>,
 }
@@ -215,39 +289,30 @@ impl KafkaProducerSink {
     fn new(
         config: Config,
         producer_config: ClientConfig,
+        link: Option<Arc<ProducerLink>>,
         reply_tx: Sender<AsyncSinkReply>,
     ) -> Self {
+        let transactional = producer_config.get("transactional.id").is_some();
         Self {
             config,
             producer_config,
             producer: None,
+            transactional,
+            link,
             reply_tx,
             metrics_rx: None,
         }
     }
-}
 
-#[async_trait::async_trait()]
-impl Sink for KafkaProducerSink {
-    async fn on_event(
-        &mut self,
-        _input: &str,
-        event: Event,
+    /// enqueues all records of `event`, returning their delivery futures
+    async fn send_records(
+        &self,
+        producer: &TremorProducer,
+        event: &Event,
         ctx: &SinkContext,
         serializer: &mut EventSerializer,
-        start: u64,
-    ) -> Result<SinkReply> {
-        let producer = self
-            .producer
-            .as_ref()
-            .ok_or_else(|| ErrorKind::ProducerNotAvailable(ctx.alias().to_string()))?;
-        let transactional = event.transactional;
-        let mut delivery_futures: Vec<DeliveryFuture> = if transactional {
-            Vec::with_capacity(event.len())
-        } else {
-            // no need to wait for anything, fire and forget
-            vec![]
-        };
+    ) -> Result<Vec<DeliveryFuture>> {
+        let mut delivery_futures = Vec::with_capacity(event.len());
         let ingest_ns = event.ingest_ns;
         for (value, meta) in event.value_meta_iter() {
             let kafka_meta = meta.get(KAFKA_PRODUCER_META_KEY);
@@ -294,6 +359,128 @@ impl Sink for KafkaProducerSink {
                 }
             }
         }
+        Ok(delivery_futures)
+    }
+
+    /// writes `event` within a kafka transaction, acking it upon commit and failing it upon abort
+    async fn send_transaction(
+        &self,
+        producer: &TremorProducer,
+        event: &Event,
+        ctx: &SinkContext,
+        serializer: &mut EventSerializer,
+    ) -> Result<SinkReply> {
+        let p = producer.clone();
+        task::spawn_blocking(move || p.begin_transaction()).await?;
+        match self
+            .write_transaction(producer, event, ctx, serializer)
+            .await
+        {
+            Ok(()) => Ok(SinkReply::ack_or_none(event.transactional)),
+            Err(e) => {
+                error!("{ctx} Error writing kafka transaction: {e}");
+                let fatal = matches!(e.kind(), ErrorKind::KafkaError(ke) if is_fatal(ke));
+                if fatal {
+                    // we might have been fenced by another producer with our `transactional.id`,
+                    // a new producer will abort whatever is left open
+                    error!("{ctx} Fatal Kafka Error: {e}. Attempting a reconnect.");
+                    ctx.notifier.connection_lost().await?;
+                } else {
+                    let p = producer.clone();
+                    if let Err(e) =
+                        task::spawn_blocking(move || p.abort_transaction(KAFKA_TRANSACTION_TIMEOUT))
+                            .await
+                    {
+                        error!(
+                            "{ctx} Error aborting kafka transaction: {e}. Attempting a reconnect."
+                        );
+                        ctx.notifier.connection_lost().await?;
+                    }
+                }
+                Ok(SinkReply::fail_or_none(event.transactional))
+            }
+        }
+    }
+
+    /// writes the records and consumer offsets of `event` within the current transaction and commits it
+    async fn write_transaction(
+        &self,
+        producer: &TremorProducer,
+        event: &Event,
+        ctx: &SinkContext,
+        serializer: &mut EventSerializer,
+    ) -> Result<()> {
+        let delivery_futures = self.send_records(producer, event, ctx, serializer).await?;
+        if let Some(link) = self.link.as_ref() {
+            let offsets = link.transaction_offsets(&consumed_messages(event))?;
+            if offsets.count() > 0 {
+                let group_metadata = link
+                    .group_metadata()
+                    .ok_or_else(|| Error::from("No kafka_consumer running"))?;
+                let p = producer.clone();
+                task::spawn_blocking(move || {
+                    p.send_offsets_to_transaction(
+                        &offsets,
+                        &group_metadata,
+                        KAFKA_TRANSACTION_TIMEOUT,
+                    )
+                })
+                .await?;
+            }
+        }
+        let results = futures::future::try_join_all(delivery_futures)
+            .await
+            .map_err(|e| Error::from(format!("Kafka record delivery cancelled: {e}")))?;
+        for result in results {
+            result.map_err(|(e, _)| e)?;
+        }
+        let p = producer.clone();
+        task::spawn_blocking(move || p.commit_transaction(KAFKA_TRANSACTION_TIMEOUT)).await?;
+        Ok(())
+    }
+}
+
+/// The offsets, by topic and partition, of the consumed messages `event` is derived from,
+/// as found in their `kafka_consumer` metadata
+fn consumed_messages(event: &Event) -> HashMap<(&str, i32), Vec<i64>> {
+    let mut messages: HashMap<(&str, i32), Vec<i64>> = HashMap::new();
+    for (_, meta) in event.value_meta_iter() {
+        let consumer_meta = meta.get(super::consumer::KAFKA_CONSUMER_META_KEY);
+        if let (Some(topic), Some(partition), Some(offset)) = (
+            consumer_meta.get_str("topic"),
+            consumer_meta.get_i32("partition"),
+            consumer_meta.get_i64("offset"),
+        ) {
+            messages
+                .entry((topic, partition))
+                .or_insert_with(Vec::new)
+                .push(offset);
+        }
+    }
+    messages
+}
+
+#[async_trait::async_trait()]
+impl Sink for KafkaProducerSink {
+    async fn on_event(
+        &mut self,
+        _input: &str,
+        event: Event,
+        ctx: &SinkContext,
+        serializer: &mut EventSerializer,
+        start: u64,
+    ) -> Result<SinkReply> {
+        let producer = self
+            .producer
+            .as_ref()
+            .ok_or_else(|| ErrorKind::ProducerNotAvailable(ctx.alias().to_string()))?;
+        if self.transactional {
+            return self
+                .send_transaction(producer, &event, ctx, serializer)
+                .await;
+        }
+        let transactional = event.transactional;
+        let delivery_futures = self.send_records(producer, &event, ctx, serializer).await?;
         if !delivery_futures.is_empty() {
             let cf_data = if transactional {
                 Some(ContraflowData::from(&event))
@@ -335,12 +522,19 @@ impl Sink for KafkaProducerSink {
         info!("{ctx} Connecting kafka producer with rdkafka 0x{version_n:08x} {version_s}");
 
         let producer_config = self.producer_config.clone();
-        let producer: FutureProducer<TremorProducerContext, SmolRuntime> =
+        let producer: TremorProducer =
             FutureProducer::from_config_and_context(&producer_config, context)?;
         // check if we receive any error callbacks
         match rx.recv().timeout(KAFKA_CONNECT_TIMEOUT).await {
             Err(_timeout) => {
                 // timeout error, everything is ok, no error
+                if self.transactional {
+                    // this fences off any previous producer with the same `transactional.id`,
+                    // e.g. our own before the reconnect, and aborts its open transaction
+                    let p = producer.clone();
+                    task::spawn_blocking(move || p.init_transactions(KAFKA_TRANSACTION_TIMEOUT))
+                        .await?;
+                }
                 self.producer = Some(producer);
                 Ok(true)
             }
@@ -435,3 +629,49 @@ fn is_fatal(e: &KafkaError) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tremor_value::literal;
+
+    #[test]
+    fn consumed_messages_of_batch() {
+        let data = literal!([{
+            "data": {
+                "value": 1,
+                "meta": {"kafka_consumer": {"topic": "snot", "partition": 0, "offset": 42}}
+            }
+        }, {
+            "data": {
+                "value": 2,
+                "meta": {"kafka_consumer": {"topic": "snot", "partition": 0, "offset": 41}}
+            }
+        }, {
+            "data": {
+                "value": 3,
+                "meta": {"kafka_consumer": {"topic": "badger", "partition": 2, "offset": 0}}
+            }
+        }, {
+            "data": {
+                "value": 4,
+                "meta": {}
+            }
+        }]);
+        let event = Event {
+            data: (data, literal!({})).into(),
+            is_batch: true,
+            ..Event::default()
+        };
+        let messages = consumed_messages(&event);
+        assert_eq!(2, messages.len());
+        assert_eq!(Some(&vec![42, 41]), messages.get(&("snot", 0)));
+        assert_eq!(Some(&vec![0]), messages.get(&("badger", 2)));
+
+        let event = Event {
+            data: (literal!("snot"), literal!({})).into(),
+            ..Event::default()
+        };
+        assert!(consumed_messages(&event).is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! MQTT connectors
+//!
+//! `mqtt_client` connects to a broker speaking MQTT 3.1.1 or MQTT 5.
+
+pub(crate) mod client;
+
+use crate::connectors::prelude::*;
+use rumqttc::QoS;
+
+pub(crate) struct MqttDefaults;
+impl Defaults for MqttDefaults {
+    const SCHEME: &'static str = "mqtt";
+    const HOST: &'static str = "localhost";
+    const PORT: u16 = 1883;
+}
+
+/// MQTT protocol version
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Protocol {
+    #[serde(rename = "3.1.1")]
+    V311,
+    #[serde(rename = "5")]
+    V5,
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Self::V311
+    }
+}
+
+fn default_qos() -> u8 {
+    1
+}
+
+fn qos(level: u8) -> Result<QoS> {
+    match level {
+        0 => Ok(QoS::AtMostOnce),
+        1 => Ok(QoS::AtLeastOnce),
+        2 => Ok(QoS::ExactlyOnce),
+        other => Err(format!("Invalid MQTT qos {other}, expected 0, 1 or 2").into()),
+    }
+}
+
+fn qos_level(qos: QoS) -> u8 {
+    match qos {
+        QoS::AtMostOnce => 0,
+        QoS::AtLeastOnce => 1,
+        QoS::ExactlyOnce => 2,
+    }
+}
+
+fn qos_v5(qos: QoS) -> rumqttc::v5::mqttbytes::QoS {
+    match qos {
+        QoS::AtMostOnce => rumqttc::v5::mqttbytes::QoS::AtMostOnce,
+        QoS::AtLeastOnce => rumqttc::v5::mqttbytes::QoS::AtLeastOnce,
+        QoS::ExactlyOnce => rumqttc::v5::mqttbytes::QoS::ExactlyOnce,
+    }
+}
+
+fn qos_v5_level(qos: rumqttc::v5::mqttbytes::QoS) -> u8 {
+    match qos {
+        rumqttc::v5::mqttbytes::QoS::AtMostOnce => 0,
+        rumqttc::v5::mqttbytes::QoS::AtLeastOnce => 1,
+        rumqttc::v5::mqttbytes::QoS::ExactlyOnce => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qos_levels() -> Result<()> {
+        for level in 0..=2 {
+            assert_eq!(level, qos_level(qos(level)?));
+            assert_eq!(level, qos_v5_level(qos_v5(qos(level)?)));
+        }
+        assert!(qos(3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn protocol() -> Result<()> {
+        assert_eq!(
+            Protocol::V5,
+            tremor_value::structurize::<Protocol>(literal!("5"))?
+        );
+        assert_eq!(
+            Protocol::V311,
+            tremor_value::structurize::<Protocol>(literal!("3.1.1"))?
+        );
+        assert!(tremor_value::structurize::<Protocol>(literal!("4")).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,817 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! MQTT client connector
+//!
+//! Subscribes to the configured topic filters at the broker and emits every message
+//! received as an event. Events sent to this connector are published, the topic, `qos`
+//! and `retain` flag of a message can be set per event via the `$mqtt_client` metadata.
+//!
+//! Received messages with qos 1 or 2 are only acknowledged to the broker once their event
+//! has been acked. Acknowledgements are sent in the order the messages were received, as the
+//! protocol requires, so a message is only acknowledged once all messages received before it
+//! are acked, failed or produced no events. Failed events are not acknowledged, the broker
+//! delivers them again once we reconnect with a persistent session
+//! (`clean_session` set to `false`).
+//!
+//! The connection is served without waiting for the source, so pings and acknowledgements
+//! keep flowing while the pipeline is busy. Received qos 1 and 2 messages are bounded by the
+//! broker, as it only sends so many unacknowledged messages at a time. With MQTT 5 we ask the
+//! broker for at most as many as the source queue holds. Received qos 0 messages are dropped
+//! while the source queue is full.
+//!
+//! An event sent to the sink is acked once the broker confirmed all of its messages, with a
+//! PUBACK for qos 1 or a PUBCOMP for qos 2, and failed if the connection breaks before.
+//! qos 0 messages are acked as soon as they are written to the connection.
+//! The sink waits for these confirmations before publishing the next event,
+//! so with qos 1 and 2 there is only ever a single event in flight.
+//!
+//! Speaks MQTT 3.1.1 by default, MQTT 5 if `protocol` is set to `"5"`.
+#![allow(clippy::module_name_repetitions)]
+
+use super::{default_qos, qos, qos_level, qos_v5, qos_v5_level, MqttDefaults, Protocol};
+use crate::connectors::prelude::*;
+use crate::connectors::utils::tls::{ca_certs_pem, TLSClientConfig};
+use async_std::channel::{bounded, unbounded, Receiver, Sender};
+use async_std::prelude::FutureExt;
+use async_std::task::{self, JoinHandle};
+use either::Either;
+use halfbrown::HashMap;
+use rumqttc::{v5, Outgoing, QoS, TlsConfiguration, Transport};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+const CONNECTOR_TYPE: &str = "mqtt_client";
+const URL_SCHEME: &str = "tremor-mqtt-client";
+/// how long to wait for the broker to accept our connection
+const MQTT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// how long to wait for the broker to confirm a published message
+const MQTT_CONFIRM_TIMEOUT: Duration = Duration::from_secs(10);
+/// capacity of the channel for requests to the mqtt event loop
+const MQTT_REQUEST_CAP: usize = 64;
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    /// the broker to connect to
+    url: Url<MqttDefaults>,
+    /// protocol version to speak, `"3.1.1"` or `"5"`
+    #[serde(default = "Default::default")]
+    protocol: Protocol,
+    /// client id, defaults to `tremor-<hostname>-<alias>`
+    #[serde(default = "Default::default")]
+    client_id: Option<String>,
+    /// topic filters to subscribe to
+    #[serde(default = "Default::default")]
+    topics: Vec<String>,
+    /// topic to publish to, if not set via `$mqtt_client.topic`
+    #[serde(default = "Default::default")]
+    topic: Option<String>,
+    /// quality of service of the subscriptions and of published messages, if not set via `$mqtt_client.qos`
+    #[serde(default = "default_qos")]
+    qos: u8,
+    /// retain flag of published messages, if not set via `$mqtt_client.retain`
+    #[serde(default = "default_false")]
+    retain: bool,
+    /// start without any state from previous sessions of this client id
+    #[serde(default = "default_true")]
+    clean_session: bool,
+    #[serde(default = "Default::default")]
+    username: Option<String>,
+    #[serde(default = "Default::default")]
+    password: Option<String>,
+    #[serde(with = "either::serde_untagged_optional", default = "Default::default")]
+    tls: Option<Either<TLSClientConfig, bool>>,
+}
+
+impl ConfigImpl for Config {}
+
+#[derive(Debug, Default)]
+pub(crate) struct Builder {}
+
+#[async_trait::async_trait]
+impl ConnectorBuilder for Builder {
+    fn connector_type(&self) -> ConnectorType {
+        CONNECTOR_TYPE.into()
+    }
+
+    async fn build(&self, id: &str, config: &ConnectorConfig) -> Result<Box<dyn Connector>> {
+        if let Some(raw_config) = &config.config {
+            let config = Config::new(raw_config)?;
+            let qos = qos(config.qos).map_err(|e| {
+                Error::from(ErrorKind::InvalidConnectorDefinition(
+                    id.to_string(),
+                    e.to_string(),
+                ))
+            })?;
+            let client_id = config
+                .client_id
+                .clone()
+                .unwrap_or_else(|| format!("tremor-{}-{id}", hostname()));
+            // rumqttc brings its own rustls version, so we hand it the certificates
+            // instead of a rustls `ClientConfig`, the broker is verified against the host of the `url`
+            let tls_ca = match config.tls.as_ref() {
+                Some(Either::Right(true)) => Some(ca_certs_pem(&TLSClientConfig::default()).await?),
+                Some(Either::Left(tls_config)) => Some(ca_certs_pem(tls_config).await?),
+                Some(Either::Right(false)) | None => None,
+            };
+            // never full, the event loop bounds what it queues on its own
+            let (source_tx, source_rx) = unbounded();
+            Ok(Box::new(MqttClient {
+                config,
+                client_id,
+                qos,
+                tls_ca,
+                source_tx,
+                source_rx: Some(source_rx),
+                sink_runtime: None,
+                client: None,
+                event_loop: None,
+            }))
+        } else {
+            Err(ErrorKind::MissingConfiguration(id.to_string()).into())
+        }
+    }
+}
+
+/// A connection to the broker, of either protocol version
+#[derive(Clone)]
+enum Client {
+    V311(rumqttc::AsyncClient),
+    V5(v5::AsyncClient),
+}
+
+impl Client {
+    async fn subscribe(&self, topic: &str, qos: QoS) -> Result<()> {
+        match self {
+            Self::V311(client) => client.subscribe(topic, qos).await?,
+            Self::V5(client) => client.subscribe(topic, qos_v5(qos)).await?,
+        }
+        Ok(())
+    }
+
+    async fn publish(&self, topic: &str, qos: QoS, retain: bool, payload: Vec<u8>) -> Result<()> {
+        match self {
+            Self::V311(client) => client.publish(topic, qos, retain, payload).await?,
+            Self::V5(client) => client.publish(topic, qos_v5(qos), retain, payload).await?,
+        }
+        Ok(())
+    }
+
+    fn try_disconnect(&self) {
+        // the event loop might be gone already, nothing to worry about
+        let _ = match self {
+            Self::V311(client) => client.try_disconnect().map_err(Error::from),
+            Self::V5(client) => client.try_disconnect().map_err(Error::from),
+        };
+    }
+}
+
+/// A received message waiting to be acknowledged, with the connection it was received on
+enum Ack {
+    V311(rumqttc::Publish, rumqttc::AsyncClient),
+    V5(v5::mqttbytes::v5::Publish, v5::AsyncClient),
+}
+
+impl Ack {
+    async fn send(&self) -> Result<()> {
+        match self {
+            Self::V311(publish, client) => client.ack(publish).await?,
+            Self::V5(publish, client) => client.ack(publish).await?,
+        }
+        Ok(())
+    }
+}
+
+/// Received qos 1 and 2 messages waiting for their events to be acked, by pull id
+///
+/// Pull ids grow with every message, so they keep the order the messages were received in.
+/// The broker expects acknowledgements in that order, so they are only handed out for the
+/// leading run of messages whose events are done.
+struct Unacked<A> {
+    /// the acknowledgement of every message, `None` once it is not to be sent
+    pending: BTreeMap<u64, (Option<A>, bool)>,
+}
+
+impl<A> Default for Unacked<A> {
+    fn default() -> Self {
+        Self {
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+impl<A> Unacked<A> {
+    fn track(&mut self, pull_id: u64, ack: A) {
+        self.pending.insert(pull_id, (Some(ack), false));
+    }
+
+    /// marks the message of `pull_id` done, returning the acknowledgements to send in order
+    fn ack(&mut self, pull_id: u64) -> Vec<A> {
+        self.done(pull_id, true)
+    }
+
+    /// marks the message of `pull_id` done without acknowledging it,
+    /// returning the acknowledgements of later messages that can be sent now in order
+    fn fail(&mut self, pull_id: u64) -> Vec<A> {
+        self.done(pull_id, false)
+    }
+
+    fn done(&mut self, pull_id: u64, acknowledge: bool) -> Vec<A> {
+        if let Some((ack, done)) = self.pending.get_mut(&pull_id) {
+            if !acknowledge {
+                *ack = None;
+            }
+            *done = true;
+        }
+        let mut res = Vec::new();
+        while let Some((&pull_id, &(_, true))) = self.pending.iter().next() {
+            if let Some((Some(ack), _)) = self.pending.remove(&pull_id) {
+                res.push(ack);
+            }
+        }
+        res
+    }
+
+    fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// A message received from the broker
+struct Message {
+    topic: String,
+    payload: Vec<u8>,
+    qos: u8,
+    retain: bool,
+    dup: bool,
+    /// only set for qos 1 and 2
+    ack: Option<Ack>,
+}
+
+/// What happened on the connection to the broker
+enum Polled {
+    ConnAck,
+    Publish(Message),
+    /// a message with this packet id was written to the connection
+    Sent(u16),
+    /// the message for this packet id collided with one still in flight
+    Collided(u16),
+    /// the broker confirmed the message with this packet id
+    Confirmed(u16),
+    Other,
+}
+
+enum EventLoop {
+    V311(rumqttc::EventLoop),
+    V5(v5::EventLoop),
+}
+
+impl EventLoop {
+    async fn poll(&mut self, client: &Client) -> Result<Polled> {
+        use rumqttc::{Event, Packet};
+        use v5::mqttbytes::v5::Packet as PacketV5;
+        let outgoing = match (self, client) {
+            (Self::V311(event_loop), Client::V311(client)) => match event_loop.poll().await? {
+                Event::Incoming(Packet::ConnAck(_)) => return Ok(Polled::ConnAck),
+                Event::Incoming(Packet::Publish(publish)) => {
+                    return Ok(Polled::Publish(Message {
+                        topic: publish.topic.clone(),
+                        payload: publish.payload.to_vec(),
+                        qos: qos_level(publish.qos),
+                        retain: publish.retain,
+                        dup: publish.dup,
+                        ack: (publish.qos != QoS::AtMostOnce)
+                            .then(|| Ack::V311(publish, client.clone())),
+                    }))
+                }
+                Event::Incoming(Packet::PubAck(ack)) => return Ok(Polled::Confirmed(ack.pkid)),
+                Event::Incoming(Packet::PubComp(comp)) => return Ok(Polled::Confirmed(comp.pkid)),
+                Event::Incoming(_) => return Ok(Polled::Other),
+                Event::Outgoing(outgoing) => outgoing,
+            },
+            (Self::V5(event_loop), Client::V5(client)) => match event_loop.poll().await? {
+                v5::Event::Incoming(PacketV5::ConnAck(_)) => return Ok(Polled::ConnAck),
+                v5::Event::Incoming(PacketV5::Publish(publish)) => {
+                    return Ok(Polled::Publish(Message {
+                        topic: String::from_utf8_lossy(&publish.topic).to_string(),
+                        payload: publish.payload.to_vec(),
+                        qos: qos_v5_level(publish.qos),
+                        retain: publish.retain,
+                        dup: publish.dup,
+                        ack: (publish.qos != v5::mqttbytes::QoS::AtMostOnce)
+                            .then(|| Ack::V5(publish, client.clone())),
+                    }))
+                }
+                v5::Event::Incoming(PacketV5::PubAck(ack)) => {
+                    return Ok(Polled::Confirmed(ack.pkid))
+                }
+                v5::Event::Incoming(PacketV5::PubComp(comp)) => {
+                    return Ok(Polled::Confirmed(comp.pkid))
+                }
+                v5::Event::Incoming(_) => return Ok(Polled::Other),
+                v5::Event::Outgoing(outgoing) => outgoing,
+            },
+            _ => return Err("MQTT client and event loop speak different protocols".into()),
+        };
+        Ok(match outgoing {
+            Outgoing::Publish(pkid) => Polled::Sent(pkid),
+            Outgoing::AwaitAck(pkid) => Polled::Collided(pkid),
+            _ => Polled::Other,
+        })
+    }
+}
+
+pub(crate) struct MqttClient {
+    config: Config,
+    client_id: String,
+    qos: QoS,
+    tls_ca: Option<Vec<u8>>,
+    source_tx: Sender<Message>,
+    source_rx: Option<Receiver<Message>>,
+    sink_runtime: Option<SingleStreamSinkRuntime>,
+    client: Option<Client>,
+    event_loop: Option<JoinHandle<()>>,
+}
+
+impl MqttClient {
+    fn transport(&self) -> Option<Transport> {
+        self.tls_ca.as_ref().map(|ca| {
+            Transport::tls_with_config(TlsConfiguration::Simple {
+                ca: ca.clone(),
+                alpn: None,
+                client_auth: None,
+            })
+        })
+    }
+
+    fn client(&self) -> (Client, EventLoop) {
+        let host = self.config.url.host_or_local().to_string();
+        let port = self.config.url.port_or_dflt();
+        let credentials = self.config.username.as_ref().map(|username| {
+            (
+                username.clone(),
+                self.config.password.clone().unwrap_or_default(),
+            )
+        });
+        match self.config.protocol {
+            Protocol::V311 => {
+                let mut options = rumqttc::MqttOptions::new(self.client_id.clone(), host, port);
+                options.set_clean_session(self.config.clean_session);
+                // received messages are acknowledged once their events are acked
+                options.set_manual_acks(true);
+                if let Some((username, password)) = credentials {
+                    options.set_credentials(username, password);
+                }
+                if let Some(transport) = self.transport() {
+                    options.set_transport(transport);
+                }
+                let (client, event_loop) = rumqttc::AsyncClient::new(options, MQTT_REQUEST_CAP);
+                (Client::V311(client), EventLoop::V311(event_loop))
+            }
+            Protocol::V5 => {
+                let mut options = v5::MqttOptions::new(self.client_id.clone(), host, port);
+                options.set_clean_start(self.config.clean_session);
+                // received messages are acknowledged once their events are acked
+                options.set_manual_acks(true);
+                // the broker doesn't send more unacknowledged messages than the source queue holds
+                let receive_maximum = u16::try_from(QSIZE.load(Ordering::Relaxed))
+                    .unwrap_or(u16::MAX)
+                    .max(1);
+                options.set_receive_maximum(Some(receive_maximum));
+                if let Some((username, password)) = credentials {
+                    options.set_credentials(username, password);
+                }
+                if let Some(transport) = self.transport() {
+                    options.set_transport(transport);
+                }
+                let (client, event_loop) = v5::AsyncClient::new(options, MQTT_REQUEST_CAP);
+                (Client::V5(client), EventLoop::V5(event_loop))
+            }
+        }
+    }
+
+    /// disconnect from the broker and stop the event loop
+    async fn disconnect(&mut self) {
+        if let Some(client) = self.client.take() {
+            client.try_disconnect();
+        }
+        if let Some(event_loop) = self.event_loop.take() {
+            event_loop.cancel().await;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Connector for MqttClient {
+    async fn create_source(
+        &mut self,
+        source_context: SourceContext,
+        builder: SourceManagerBuilder,
+    ) -> Result<Option<SourceAddr>> {
+        let source = MqttSource {
+            rx: self.source_rx.take().ok_or("Source already created")?,
+            unacked: Unacked::default(),
+            origin_uri: EventOriginUri {
+                scheme: URL_SCHEME.to_string(),
+                host: self.config.url.host_or_local().to_string(),
+                port: Some(self.config.url.port_or_dflt()),
+                path: vec![],
+            },
+        };
+        let addr = builder.spawn(source, source_context)?;
+        Ok(Some(addr))
+    }
+
+    async fn create_sink(
+        &mut self,
+        sink_context: SinkContext,
+        builder: SinkManagerBuilder,
+    ) -> Result<Option<SinkAddr>> {
+        let sink = SingleStreamSink::new_with_meta(builder.qsize(), builder.reply_tx());
+        self.sink_runtime = Some(sink.runtime());
+        let addr = builder.spawn(sink, sink_context)?;
+        Ok(Some(addr))
+    }
+
+    async fn connect(&mut self, ctx: &ConnectorContext, _attempt: &Attempt) -> Result<bool> {
+        let sink_runtime = self
+            .sink_runtime
+            .clone()
+            .ok_or("Sink runtime not initialized")?;
+        // get rid of the previous connection, if any
+        self.disconnect().await;
+
+        let (client, mut event_loop) = self.client();
+        let connack = async {
+            loop {
+                if let Polled::ConnAck = event_loop.poll(&client).await? {
+                    return Ok::<(), Error>(());
+                }
+            }
+        };
+        match connack.timeout(MQTT_CONNECT_TIMEOUT).await {
+            Ok(res) => res?,
+            Err(_timeout) => {
+                return Err(
+                    format!("Timeout connecting to MQTT broker at {}", self.config.url).into(),
+                )
+            }
+        }
+        info!("{ctx} Connected to MQTT broker at {}", self.config.url);
+        // subscriptions are sent out by the event loop
+        for topic in &self.config.topics {
+            client.subscribe(topic, self.qos).await?;
+        }
+
+        let (confirm_tx, confirm_rx) = unbounded();
+        self.event_loop = Some(task::spawn(run_event_loop(
+            event_loop,
+            client.clone(),
+            self.source_tx.clone(),
+            confirm_rx,
+            ctx.clone(),
+        )));
+
+        let writer = MqttWriter {
+            client: client.clone(),
+            confirm_tx,
+            topic: self.config.topic.clone(),
+            qos: self.qos,
+            retain: self.config.retain,
+        };
+        sink_runtime.register_stream_writer(DEFAULT_STREAM_ID, ctx, writer);
+        self.client = Some(client);
+        Ok(true)
+    }
+
+    async fn on_stop(&mut self, _ctx: &ConnectorContext) -> Result<()> {
+        self.disconnect().await;
+        Ok(())
+    }
+
+    fn codec_requirements(&self) -> CodecReq {
+        CodecReq::Required
+    }
+}
+
+/// resolved once the broker confirmed the delivery of a published message,
+/// dropped if the connection breaks before
+type Confirmation = Sender<()>;
+
+/// Pending confirmations of the messages published on a connection
+#[derive(Default)]
+struct Confirmations {
+    /// confirmations of messages in the order they were handed to the event loop
+    queued: VecDeque<Confirmation>,
+    /// confirmation of a message waiting for its packet id to be acknowledged
+    collision: Option<(u16, Confirmation)>,
+    /// confirmations of messages sent out, by packet id
+    ///
+    /// the broker might acknowledge a packet id that has been reused for a collided message
+    /// after its previous message was sent out, so there can be more than one
+    in_flight: HashMap<u16, VecDeque<Confirmation>>,
+}
+
+impl Confirmations {
+    /// a message with `pkid` was written to the connection
+    fn sent(&mut self, pkid: u16) {
+        let confirmation = match self.collision.take() {
+            Some((collided, confirmation)) if collided == pkid => Some(confirmation),
+            other => {
+                self.collision = other;
+                self.queued.pop_front()
+            }
+        };
+        if let Some(confirmation) = confirmation {
+            if pkid == 0 {
+                // qos 0, there is nothing more to wait for
+                let _ = confirmation.try_send(());
+            } else {
+                self.in_flight
+                    .entry(pkid)
+                    .or_default()
+                    .push_back(confirmation);
+            }
+        }
+    }
+
+    /// the message for `pkid` collided with one still in flight
+    fn collided(&mut self, pkid: u16) {
+        self.collision = self.queued.pop_front().map(|c| (pkid, c));
+    }
+
+    /// the broker confirmed the message with `pkid`
+    fn confirmed(&mut self, pkid: u16) {
+        if let Some(confirmations) = self.in_flight.get_mut(&pkid) {
+            if let Some(confirmation) = confirmations.pop_front() {
+                let _ = confirmation.try_send(());
+            }
+            if confirmations.is_empty() {
+                self.in_flight.remove(&pkid);
+            }
+        }
+    }
+}
+
+/// drives the connection to the broker
+///
+/// Forwards received messages to `tx` and resolves the confirmations of published messages.
+/// It never waits for the source, so it keeps answering the broker while the source is busy.
+async fn run_event_loop(
+    mut event_loop: EventLoop,
+    client: Client,
+    tx: Sender<Message>,
+    confirm_rx: Receiver<Confirmation>,
+    ctx: ConnectorContext,
+) {
+    let qsize = QSIZE.load(Ordering::Relaxed);
+    let mut confirmations = Confirmations::default();
+    loop {
+        let polled = event_loop.poll(&client).await;
+        while let Ok(confirmation) = confirm_rx.try_recv() {
+            confirmations.queued.push_back(confirmation);
+        }
+        match polled {
+            Ok(Polled::Publish(message)) => {
+                // qos 1 and 2 messages are bounded by the broker, it waits for our acknowledgements
+                if message.ack.is_none() && tx.len() >= qsize {
+                    debug!(
+                        "{ctx} Source is busy, dropping qos 0 message on {}",
+                        message.topic
+                    );
+                } else if tx.try_send(message).is_err() {
+                    break;
+                }
+            }
+            Ok(Polled::Sent(pkid)) => confirmations.sent(pkid),
+            Ok(Polled::Collided(pkid)) => confirmations.collided(pkid),
+            Ok(Polled::Confirmed(pkid)) => confirmations.confirmed(pkid),
+            Ok(Polled::ConnAck | Polled::Other) => (),
+            Err(e) => {
+                // we leave reconnecting to the runtime
+                error!("{ctx} MQTT connection error: {e}");
+                ctx.swallow_err(
+                    ctx.notifier().connection_lost().await,
+                    "Failed to notify connector",
+                );
+                break;
+            }
+        }
+    }
+    // pending confirmations are dropped here, failing their events
+}
+
+struct MqttSource {
+    rx: Receiver<Message>,
+    unacked: Unacked<Ack>,
+    origin_uri: EventOriginUri,
+}
+
+#[async_trait::async_trait]
+impl Source for MqttSource {
+    async fn pull_data(&mut self, pull_id: &mut u64, ctx: &SourceContext) -> Result<SourceReply> {
+        let message = self.rx.recv().await?;
+        let meta = ctx.meta(literal!({
+            "topic": message.topic,
+            "qos": message.qos,
+            "retain": message.retain,
+            "dup": message.dup
+        }));
+        if let Some(ack) = message.ack {
+            self.unacked.track(*pull_id, ack);
+        }
+        Ok(SourceReply::Data {
+            origin_uri: self.origin_uri.clone(),
+            data: message.payload,
+            meta: Some(meta),
+            stream: None,
+            port: None,
+            codec_overwrite: None,
+        })
+    }
+
+    async fn ack(&mut self, _stream_id: u64, pull_id: u64, ctx: &SourceContext) -> Result<()> {
+        let acks = self.unacked.ack(pull_id);
+        send_acks(acks, ctx).await;
+        Ok(())
+    }
+
+    async fn fail(&mut self, _stream_id: u64, pull_id: u64, ctx: &SourceContext) -> Result<()> {
+        debug!("{ctx} Not acknowledging failed MQTT message of event {pull_id}");
+        let acks = self.unacked.fail(pull_id);
+        send_acks(acks, ctx).await;
+        Ok(())
+    }
+
+    async fn on_no_events(
+        &mut self,
+        pull_id: u64,
+        _stream: u64,
+        ctx: &SourceContext,
+    ) -> Result<()> {
+        // there is nothing left to process for the message
+        let acks = self.unacked.ack(pull_id);
+        send_acks(acks, ctx).await;
+        Ok(())
+    }
+
+    async fn on_connection_lost(&mut self, _ctx: &SourceContext) -> Result<()> {
+        // the broker delivers unacknowledged messages again on the next connection
+        self.unacked.clear();
+        Ok(())
+    }
+
+    fn is_transactional(&self) -> bool {
+        true
+    }
+
+    fn asynchronous(&self) -> bool {
+        true
+    }
+}
+
+async fn send_acks(acks: Vec<Ack>, ctx: &SourceContext) {
+    for ack in acks {
+        // the connection the message was received on might be gone already,
+        // then the broker delivers it again
+        ctx.swallow_err(ack.send().await, "Error acknowledging MQTT message");
+    }
+}
+
+struct MqttWriter {
+    client: Client,
+    /// hands the confirmations of published messages to the event loop
+    confirm_tx: Sender<Confirmation>,
+    topic: Option<String>,
+    qos: QoS,
+    retain: bool,
+}
+
+#[async_trait::async_trait]
+impl StreamWriter for MqttWriter {
+    async fn write(&mut self, data: Vec<Vec<u8>>, meta: Option<SinkMeta>) -> Result<()> {
+        let meta = meta.as_ref().get(CONNECTOR_TYPE);
+        let topic = meta
+            .get_str("topic")
+            .or(self.topic.as_deref())
+            .ok_or("No topic to publish to, set `$mqtt_client.topic` or `topic` in the config")?;
+        let qos = meta.get_u8("qos").map(qos).transpose()?.unwrap_or(self.qos);
+        let retain = meta.get_bool("retain").unwrap_or(self.retain);
+        let mut confirmations = Vec::with_capacity(data.len());
+        for payload in data {
+            let (tx, rx) = bounded(1);
+            // queued before the publish request so the event loop finds it when sending it out
+            self.confirm_tx.send(tx).await?;
+            self.client.publish(topic, qos, retain, payload).await?;
+            confirmations.push(rx);
+        }
+        for confirmation in confirmations {
+            confirmation
+                .recv()
+                .timeout(MQTT_CONFIRM_TIMEOUT)
+                .await
+                .map_err(|_| "Timeout waiting for the MQTT broker to confirm a message")?
+                .map_err(|_| "MQTT connection lost before the broker confirmed a message")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirmations() {
+        let mut confirmations = Confirmations::default();
+        let (qos0, qos0_rx) = bounded(1);
+        let (first, first_rx) = bounded(1);
+        let (collided, collided_rx) = bounded(1);
+        confirmations.queued.extend([qos0, first, collided]);
+
+        confirmations.sent(0);
+        assert!(qos0_rx.try_recv().is_ok());
+        confirmations.sent(1);
+        confirmations.collided(1);
+        assert!(first_rx.try_recv().is_err());
+        // the collided message goes out before the broker acknowledges the first one
+        confirmations.sent(1);
+        confirmations.confirmed(1);
+        assert!(first_rx.try_recv().is_ok());
+        assert!(collided_rx.try_recv().is_err());
+        confirmations.confirmed(1);
+        assert!(collided_rx.try_recv().is_ok());
+        assert!(confirmations.in_flight.is_empty());
+    }
+
+    #[test]
+    fn unacked_in_order() {
+        let mut unacked = Unacked::default();
+        for pull_id in 1..=5 {
+            unacked.track(pull_id, pull_id);
+        }
+        // acks are held back until all earlier messages are done
+        assert!(unacked.ack(3).is_empty());
+        assert!(unacked.ack(2).is_empty());
+        assert_eq!(vec![1, 2, 3], unacked.ack(1));
+        // failed messages are not acknowledged, but don't hold back later ones
+        assert!(unacked.ack(5).is_empty());
+        assert_eq!(vec![5], unacked.fail(4));
+        assert!(unacked.pending.is_empty());
+        // acks for unknown messages are ignored
+        assert!(unacked.ack(6).is_empty());
+
+        unacked.track(7, 7);
+        unacked.track(8, 8);
+        assert!(unacked.ack(8).is_empty());
+        unacked.clear();
+        assert!(unacked.ack(7).is_empty());
+    }
+
+    #[async_std::test]
+    async fn missing_config() {
+        let config = ConnectorConfig {
+            connector_type: CONNECTOR_TYPE.into(),
+            ..ConnectorConfig::default()
+        };
+        assert!(Builder::default().build("snot", &config).await.is_err());
+    }
+
+    #[async_std::test]
+    async fn invalid_qos() {
+        let config = ConnectorConfig {
+            connector_type: CONNECTOR_TYPE.into(),
+            config: Some(literal!({
+                "url": "mqtt://localhost:1883",
+                "qos": 3
+            })),
+            ..ConnectorConfig::default()
+        };
+        assert!(Builder::default().build("snot", &config).await.is_err());
+    }
+
+    #[async_std::test]
+    async fn invalid_protocol() {
+        let config = ConnectorConfig {
+            connector_type: CONNECTOR_TYPE.into(),
+            config: Some(literal!({
+                "url": "mqtt://localhost:1883",
+                "protocol": "4"
+            })),
+            ..ConnectorConfig::default()
+        };
+        assert!(Builder::default().build("snot", &config).await.is_err());
+    }
+}
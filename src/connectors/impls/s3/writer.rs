@@ -168,6 +168,8 @@ impl Sink for S3Sink {
             let object_key = if let Some(key) = s3_meta.get_object_key().map(ToString::to_string) {
                 key
             } else {
+                // the current upload is abandoned, and so is what the codec buffered for it
+                serializer.finish_stream(DEFAULT_STREAM_ID)?;
                 self.current_key.clear();
                 error!("{ctx}: missing '$s3_writer.key' meta data in event");
                 return Ok(SinkReply::FAIL);
@@ -177,7 +179,8 @@ impl Sink for S3Sink {
                 // we switched keys:
                 // 1. finish the current upload, if any
                 // 2. initiate a new upload
-                self.prepare_new_multipart(object_key, ctx).await?;
+                self.prepare_new_multipart(object_key, ctx, serializer)
+                    .await?;
             }
 
             // Handle the aggregation.
@@ -201,6 +204,17 @@ impl Sink for S3Sink {
         Ok(SinkReply::default())
     }
 
+    async fn on_finish(
+        &mut self,
+        _ctx: &SinkContext,
+        serializer: &mut EventSerializer,
+    ) -> Result<()> {
+        if !self.current_key.is_empty() {
+            self.finish_object(serializer)?;
+        }
+        Ok(())
+    }
+
     async fn on_stop(&mut self, ctx: &SinkContext) -> Result<()> {
         // Commit the final upload.
         self.complete_multipart(ctx).await?;
@@ -225,9 +239,23 @@ impl S3Sink {
             .ok_or_else(|| ErrorKind::S3Error("no s3 client available".to_string()).into())
     }
 
-    async fn prepare_new_multipart(&mut self, key: String, ctx: &SinkContext) -> Result<()> {
+    /// appends what the codec still buffers for the current object, e.g. a parquet footer
+    fn finish_object(&mut self, serializer: &mut EventSerializer) -> Result<()> {
+        for data in serializer.finish_stream(DEFAULT_STREAM_ID)? {
+            self.buffer.extend(data);
+        }
+        Ok(())
+    }
+
+    async fn prepare_new_multipart(
+        &mut self,
+        key: String,
+        ctx: &SinkContext,
+        serializer: &mut EventSerializer,
+    ) -> Result<()> {
         // Finish the previous multipart upload if any.
         if !self.current_key.is_empty() {
+            self.finish_object(serializer)?;
             self.complete_multipart(ctx).await?;
         }
 
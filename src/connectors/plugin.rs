@@ -0,0 +1,291 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Connector types provided by plugins built against `tremor-pdk`
+//!
+//! Plugins exchange raw bytes, the configured codecs and pre- and postprocessors are applied
+//! by the runtime. Calls into plugins are blocking, so they are issued from the blocking thread pool.
+#![allow(clippy::module_name_repetitions)]
+
+use crate::connectors::prelude::*;
+use abi_stable::std_types::{RNone, RResult, RSome, RString};
+use async_std::channel::{bounded, Receiver, Sender};
+use async_std::task::{self, JoinHandle};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use tremor_pdk::{
+    BoxedRawConnector, BoxedRawSink, BoxedRawSource, ConnectorPluginRef, RawConnector, RawSink,
+    RawSource,
+};
+
+const URL_SCHEME: &str = "tremor-plugin";
+/// how long a single read from a plugin source blocks at most
+const READ_TIMEOUT_MS: u64 = 100;
+
+/// Builds connectors of a type provided by a plugin
+pub(crate) struct Builder {
+    connector_type: ConnectorType,
+    path: PathBuf,
+    plugin: ConnectorPluginRef,
+}
+
+impl fmt::Debug for Builder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Plugin({}, {})",
+            self.connector_type,
+            self.path.display()
+        )
+    }
+}
+
+impl Builder {
+    /// creates a builder for the connector type provided by the plugin loaded from `path`
+    pub(crate) fn new(path: &Path, plugin: ConnectorPluginRef) -> Self {
+        let connector_type = (plugin.connector_type())().into_string();
+        Self {
+            connector_type: connector_type.into(),
+            path: path.to_path_buf(),
+            plugin,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ConnectorBuilder for Builder {
+    fn connector_type(&self) -> ConnectorType {
+        self.connector_type.clone()
+    }
+
+    async fn build(&self, alias: &str, config: &ConnectorConfig) -> Result<Box<dyn Connector>> {
+        let config = config
+            .config
+            .as_ref()
+            .map_or_else(|| "null".to_string(), Value::encode);
+        let build = self.plugin.build();
+        let id = alias.to_string();
+        let mut raw =
+            task::spawn_blocking(move || build(id.as_str().into(), config.as_str().into()))
+                .await
+                .into_result()
+                .map_err(|e| ErrorKind::InvalidConfiguration(alias.to_string(), e.into_string()))?;
+        let source = raw.source().into_option().map(|s| Arc::new(Mutex::new(s)));
+        let sink = raw.sink().into_option().map(|s| Arc::new(Mutex::new(s)));
+        let (source_tx, source_rx) = bounded(QSIZE.load(Ordering::Relaxed));
+        Ok(Box::new(PluginConnector {
+            connector_type: self.connector_type.clone(),
+            raw: Arc::new(Mutex::new(raw)),
+            source,
+            sink,
+            source_runtime: None,
+            sink_runtime: None,
+            source_tx,
+            source_rx: Some(source_rx),
+            read_loop: None,
+        }))
+    }
+}
+
+/// runs a blocking call into a plugin on the blocking thread pool
+async fn blocking<T, R, F>(target: &Arc<Mutex<T>>, f: F) -> Result<R>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: FnOnce(&mut T) -> RResult<R, RString> + Send + 'static,
+{
+    let target = target.clone();
+    task::spawn_blocking(move || {
+        let mut target = target.lock()?;
+        f(&mut target)
+            .into_result()
+            .map_err(|e| Error::from(e.into_string()))
+    })
+    .await
+}
+
+pub(crate) struct PluginConnector {
+    connector_type: ConnectorType,
+    raw: Arc<Mutex<BoxedRawConnector>>,
+    source: Option<Arc<Mutex<BoxedRawSource>>>,
+    sink: Option<Arc<Mutex<BoxedRawSink>>>,
+    source_runtime: Option<ChannelSourceRuntime>,
+    sink_runtime: Option<SingleStreamSinkRuntime>,
+    /// data read from the plugin source, shared by the read loops of all connections
+    source_tx: Sender<Vec<u8>>,
+    /// taken by the stream reader registered on the first connect
+    source_rx: Option<Receiver<Vec<u8>>>,
+    /// the read loop of the current connection and the flag to stop it
+    read_loop: Option<(Arc<AtomicBool>, JoinHandle<()>)>,
+}
+
+impl PluginConnector {
+    /// stops the read loop of the current connection and waits for it to finish,
+    /// data it read in the meantime is still handed to the stream reader
+    async fn stop_reading(&mut self) {
+        if let Some((reading, handle)) = self.read_loop.take() {
+            reading.store(false, Ordering::Release);
+            handle.await;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Connector for PluginConnector {
+    async fn create_source(
+        &mut self,
+        source_context: SourceContext,
+        builder: SourceManagerBuilder,
+    ) -> Result<Option<SourceAddr>> {
+        if self.source.is_none() {
+            return Ok(None);
+        }
+        let source = ChannelSource::new(builder.qsize());
+        self.source_runtime = Some(source.runtime());
+        let addr = builder.spawn(source, source_context)?;
+        Ok(Some(addr))
+    }
+
+    async fn create_sink(
+        &mut self,
+        sink_context: SinkContext,
+        builder: SinkManagerBuilder,
+    ) -> Result<Option<SinkAddr>> {
+        if self.sink.is_none() {
+            return Ok(None);
+        }
+        let sink = SingleStreamSink::new_with_meta(builder.qsize(), builder.reply_tx());
+        self.sink_runtime = Some(sink.runtime());
+        let addr = builder.spawn(sink, sink_context)?;
+        Ok(Some(addr))
+    }
+
+    async fn connect(&mut self, ctx: &ConnectorContext, _attempt: &Attempt) -> Result<bool> {
+        // the read loop of a previous connection must not compete with the new one
+        self.stop_reading().await;
+        if !blocking(&self.raw, |raw| raw.connect()).await? {
+            return Ok(false);
+        }
+
+        if let (Some(source), Some(source_runtime)) = (&self.source, &self.source_runtime) {
+            // a single reader takes the data of all connections
+            if let Some(rx) = self.source_rx.take() {
+                let reader = PluginReader {
+                    rx,
+                    origin_uri: EventOriginUri {
+                        scheme: URL_SCHEME.to_string(),
+                        host: hostname(),
+                        port: None,
+                        path: vec![self.connector_type.to_string()],
+                    },
+                };
+                source_runtime.register_stream_reader(DEFAULT_STREAM_ID, ctx, reader);
+            }
+            let reading = Arc::new(AtomicBool::new(true));
+            let handle = task::spawn(read_loop(
+                source.clone(),
+                self.source_tx.clone(),
+                reading.clone(),
+                ctx.clone(),
+            ));
+            self.read_loop = Some((reading, handle));
+        }
+        if let (Some(sink), Some(sink_runtime)) = (&self.sink, &self.sink_runtime) {
+            let writer = PluginWriter { sink: sink.clone() };
+            sink_runtime.register_stream_writer(DEFAULT_STREAM_ID, ctx, writer);
+        }
+        Ok(true)
+    }
+
+    async fn on_stop(&mut self, _ctx: &ConnectorContext) -> Result<()> {
+        self.stop_reading().await;
+        blocking(&self.raw, |raw| raw.stop()).await
+    }
+
+    fn codec_requirements(&self) -> CodecReq {
+        CodecReq::Required
+    }
+}
+
+/// reads from the plugin source until the connection is replaced, stopped or fails
+async fn read_loop(
+    source: Arc<Mutex<BoxedRawSource>>,
+    tx: Sender<Vec<u8>>,
+    reading: Arc<AtomicBool>,
+    ctx: ConnectorContext,
+) {
+    while reading.load(Ordering::Acquire) {
+        match blocking(&source, |source| source.read(READ_TIMEOUT_MS)).await {
+            Ok(RSome(data)) => {
+                if tx.send(data.into_vec()).await.is_err() {
+                    break;
+                }
+            }
+            Ok(RNone) => (),
+            Err(e) => {
+                // we leave reconnecting to the runtime
+                error!("{ctx} Error reading from plugin: {e}");
+                ctx.swallow_err(
+                    ctx.notifier().connection_lost().await,
+                    "Failed to notify connector",
+                );
+                break;
+            }
+        }
+    }
+}
+
+struct PluginReader {
+    rx: Receiver<Vec<u8>>,
+    origin_uri: EventOriginUri,
+}
+
+#[async_trait::async_trait]
+impl StreamReader for PluginReader {
+    async fn read(&mut self, stream: u64) -> Result<SourceReply> {
+        let data = self.rx.recv().await?;
+        Ok(SourceReply::Data {
+            origin_uri: self.origin_uri.clone(),
+            data,
+            meta: None,
+            stream: Some(stream),
+            port: None,
+            codec_overwrite: None,
+        })
+    }
+
+    async fn quiesce(&mut self, stream: u64) -> Option<SourceReply> {
+        Some(SourceReply::EndStream {
+            origin_uri: self.origin_uri.clone(),
+            stream,
+            meta: None,
+        })
+    }
+}
+
+struct PluginWriter {
+    sink: Arc<Mutex<BoxedRawSink>>,
+}
+
+#[async_trait::async_trait]
+impl StreamWriter for PluginWriter {
+    async fn write(&mut self, data: Vec<Vec<u8>>, _meta: Option<SinkMeta>) -> Result<()> {
+        for chunk in data {
+            blocking(&self.sink, move |sink| sink.write(chunk.as_slice().into())).await?;
+        }
+        Ok(())
+    }
+}
@@ -31,7 +31,9 @@ use crate::connectors::utils::reconnect::{Attempt, ConnectionLostNotifier};
 use crate::connectors::{ConnectorType, Context, Msg, QuiescenceBeacon, StreamDone};
 use crate::errors::Result;
 use crate::pipeline;
-use crate::postprocessor::{finish, make_postprocessors, postprocess, Postprocessors};
+use crate::postprocessor::{
+    finish, make_postprocessors, postprocess, Postprocessor, Postprocessors,
+};
 use crate::primerge::PriorityMerge;
 use async_std::channel::{bounded, unbounded, Receiver, Sender};
 use async_std::stream::StreamExt; // for .next() on PriorityMerge
@@ -211,6 +213,14 @@ pub(crate) trait Sink: Send {
     async fn on_resume(&mut self, _ctx: &SinkContext) -> Result<()> {
         Ok(())
     }
+    /// called when stopped, right before `on_stop`, to write out what the `serializer` still buffers
+    async fn on_finish(
+        &mut self,
+        _ctx: &SinkContext,
+        _serializer: &mut EventSerializer,
+    ) -> Result<()> {
+        Ok(())
+    }
     /// called when stopped
     async fn on_stop(&mut self, _ctx: &SinkContext) -> Result<()> {
         Ok(())
@@ -522,17 +532,18 @@ impl EventSerializer {
     ) -> Result<Vec<Vec<u8>>> {
         if stream_id == DEFAULT_STREAM_ID {
             // no codec_overwrite for the default stream
-            postprocess(
+            encode_record(
+                self.codec.as_mut(),
                 &mut self.postprocessors,
+                value,
                 ingest_ns,
-                self.codec.encode(value)?,
                 &self.alias,
             )
         } else {
             match self.streams.entry(stream_id) {
                 Entry::Occupied(mut entry) => {
                     let (codec, pps) = entry.get_mut();
-                    postprocess(pps, ingest_ns, codec.encode(value)?, &self.alias)
+                    encode_record(codec.as_mut(), pps, value, ingest_ns, &self.alias)
                 }
                 Entry::Vacant(entry) => {
                     // codec overwrite only considered for new streams
@@ -543,22 +554,57 @@ impl EventSerializer {
                     let pps = make_postprocessors(self.postprocessor_configs.as_slice())?;
                     // insert data for a new stream
                     let (c, pps2) = entry.insert((codec, pps));
-                    postprocess(pps2, ingest_ns, c.encode(value)?, &self.alias)
+                    encode_record(c.as_mut(), pps2, value, ingest_ns, &self.alias)
                 }
             }
         }
     }
 
     /// remove and flush out any pending data from the stream identified by the given `stream_id`
+    ///
+    /// The default stream is kept, its next event starts over with a fresh codec output,
+    /// e.g. a new parquet file.
     pub(crate) fn finish_stream(&mut self, stream_id: u64) -> Result<Vec<Vec<u8>>> {
-        if let Some((mut _codec, mut postprocessors)) = self.streams.remove(&stream_id) {
-            finish(&mut postprocessors, &self.alias)
+        if stream_id == DEFAULT_STREAM_ID {
+            finish_codec(self.codec.as_mut(), &mut self.postprocessors, &self.alias)
+        } else if let Some((mut codec, mut postprocessors)) = self.streams.remove(&stream_id) {
+            finish_codec(codec.as_mut(), &mut postprocessors, &self.alias)
         } else {
             Ok(vec![])
         }
     }
 }
 
+/// encodes `value` as the next record of a stream, nothing if the codec buffered it
+fn encode_record(
+    codec: &mut dyn Codec,
+    postprocessors: &mut [Box<dyn Postprocessor>],
+    value: &Value,
+    ingest_ns: u64,
+    alias: &str,
+) -> Result<Vec<Vec<u8>>> {
+    if let Some(data) = codec.encode_record(value)? {
+        postprocess(postprocessors, ingest_ns, data, alias)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// flushes out what the codec and the postprocessors of a stream still buffer
+fn finish_codec(
+    codec: &mut dyn Codec,
+    postprocessors: &mut [Box<dyn Postprocessor>],
+    alias: &str,
+) -> Result<Vec<Vec<u8>>> {
+    let mut data = if let Some(data) = codec.finish()? {
+        postprocess(postprocessors, nanotime(), data, alias)?
+    } else {
+        vec![]
+    };
+    data.append(&mut finish(postprocessors, alias)?);
+    Ok(data)
+}
+
 #[derive(Debug, PartialEq)]
 enum SinkState {
     Initialized,
@@ -678,8 +724,11 @@ where
                         SinkMsg::Stop(sender) => {
                             info!("{} Stopping...", &self.ctx);
                             self.state = Stopped;
+                            let finished =
+                                self.sink.on_finish(&self.ctx, &mut self.serializer).await;
+                            let stopped = self.sink.on_stop(&self.ctx).await;
                             self.ctx.swallow_err(
-                                sender.send(self.sink.on_stop(&self.ctx).await).await,
+                                sender.send(finished.and(stopped)).await,
                                 "Error sending Stop reply",
                             );
                             // exit control plane
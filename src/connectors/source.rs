@@ -40,13 +40,14 @@ use crate::connectors::{
 use crate::errors::{Error, Result};
 use crate::pipeline;
 use crate::preprocessor::{finish, make_preprocessors, preprocess, Preprocessors};
+use crate::tap::{Tap, Taps};
 use crate::{
     codec::{self, Codec},
     pipeline::InputTarget,
 };
 use crate::{
     config::{
-        self, Codec as CodecConfig, Connector as ConnectorConfig,
+        self, Codec as CodecConfig, Connector as ConnectorConfig, DeadLetter,
         Preprocessor as PreprocessorConfig,
     },
     log_error,
@@ -82,14 +83,21 @@ pub(crate) enum SourceMsg {
     Cb(CbAction, EventId),
     /// start the source
     Start,
-    /// pause the source
-    Pause,
+    /// pause the source, bears a sender for replying once no more events are sent out
+    Pause(Option<Sender<Result<()>>>),
     /// resume the source
     Resume,
     /// stop the source
     Stop(Sender<Result<()>>),
     /// drain the source - bears a sender for sending out a SourceDrained status notification
     Drain(Sender<Msg>),
+    /// attach a tap to an output port
+    Tap {
+        /// the tapped port
+        port: Cow<'static, str>,
+        /// the tap
+        tap: Tap,
+    },
     #[cfg(test)]
     Ping(Sender<()>),
 }
@@ -428,7 +436,12 @@ pub(crate) fn builder(
             .clone()
             .unwrap_or_else(|| CodecConfig::from(opt)),
     };
-    let streams = Streams::new(source_uid, codec_config, preprocessor_configs)?;
+    let streams = Streams::new(
+        source_uid,
+        codec_config,
+        preprocessor_configs,
+        config.dead_letter,
+    )?;
 
     Ok(SourceManagerBuilder {
         qsize,
@@ -443,6 +456,7 @@ struct Streams {
     uid: SourceId,
     codec_config: CodecConfig,
     preprocessor_configs: Vec<PreprocessorConfig>,
+    dead_letter: DeadLetter,
     states: BTreeMap<u64, StreamState>,
 }
 
@@ -455,6 +469,7 @@ impl Streams {
         uid: SourceId,
         codec_config: config::Codec,
         preprocessor_configs: Vec<PreprocessorConfig>,
+        dead_letter: DeadLetter,
     ) -> Result<Self> {
         let default = Self::build_stream(
             uid,
@@ -462,6 +477,7 @@ impl Streams {
             &codec_config,
             None,
             preprocessor_configs.as_slice(),
+            dead_letter,
         )?;
         let mut states = BTreeMap::new();
         states.insert(DEFAULT_STREAM_ID, default);
@@ -469,6 +485,7 @@ impl Streams {
             uid,
             codec_config,
             preprocessor_configs,
+            dead_letter,
             states,
         })
     }
@@ -494,6 +511,7 @@ impl Streams {
                     &self.codec_config,
                     None,
                     &self.preprocessor_configs,
+                    self.dead_letter,
                 )?;
                 e.insert(state)
             }
@@ -507,6 +525,7 @@ impl Streams {
             &self.codec_config,
             codec_overwrite,
             &self.preprocessor_configs,
+            self.dead_letter,
         )
     }
 
@@ -517,6 +536,7 @@ impl Streams {
         codec_config: &CodecConfig,
         codec_overwrite: Option<String>,
         preprocessor_configs: &[PreprocessorConfig],
+        dead_letter: DeadLetter,
     ) -> Result<StreamState> {
         let codec = if let Some(codec_overwrite) = codec_overwrite {
             codec::resolve(&codec_overwrite.as_str().into())?
@@ -530,6 +550,8 @@ impl Streams {
             idgen,
             codec,
             preprocessors,
+            dead_letter,
+            raw: Vec::new(),
         })
     }
 }
@@ -540,6 +562,31 @@ struct StreamState {
     idgen: EventIdGenerator,
     codec: Box<dyn Codec>,
     preprocessors: Preprocessors,
+    dead_letter: DeadLetter,
+    /// the raw chunk being decoded, kept for dead letters
+    raw: Vec<u8>,
+}
+
+impl StreamState {
+    /// a copy of the raw `data` to send along with errors, if configured
+    fn dead_letter_data(&self, data: &[u8]) -> Option<Vec<u8>> {
+        (self.dead_letter == DeadLetter::Raw).then(|| data.to_vec())
+    }
+
+    /// keeps the raw `chunk` about to be decoded for dead letters, if configured
+    ///
+    /// Decoding might mutate the chunk, so it is kept in a buffer reused for every chunk.
+    fn keep_raw(&mut self, chunk: &[u8]) {
+        if self.dead_letter == DeadLetter::Raw {
+            self.raw.clear();
+            self.raw.extend_from_slice(chunk);
+        }
+    }
+
+    /// a copy of the raw chunk that failed to decode, if configured
+    fn dead_letter_raw(&self) -> Option<Vec<u8>> {
+        self.dead_letter_data(&self.raw)
+    }
 }
 
 /// possible states of a source implementation
@@ -572,6 +619,7 @@ where
     pipelines_err: Vec<(DeployEndpoint, pipeline::Addr)>,
     streams: Streams,
     metrics_reporter: SourceReporter,
+    taps: Taps,
     // `Paused` is used for both explicitly pausing and CB close/open
     // this way we can explicitly resume a Cb triggered source if need be
     // but also an explicitly paused source might receive a Cb open and continue sending data :scream:
@@ -618,6 +666,7 @@ where
             addr,
             streams,
             metrics_reporter: source_metrics_reporter,
+            taps: Taps::default(),
             pipelines_out: Vec::with_capacity(1),
             pipelines_err: Vec::with_capacity(1),
             state: SourceState::Initialized,
@@ -669,12 +718,13 @@ where
                 Control::Continue
             }
 
-            SourceMsg::Pause if self.state == Running => {
+            SourceMsg::Pause(paused_tx) if self.state == Running => {
                 // TODO: execute pause strategy chosen by source / connector / configured by user
                 info!("{} Paused.", self.ctx);
                 self.state = Paused;
                 let res = self.source.on_pause(&self.ctx).await;
                 self.ctx.swallow_err(res, "on_pause failed");
+                self.reply_paused(paused_tx).await;
                 Control::Continue
             }
             SourceMsg::Stop(sender) => {
@@ -699,19 +749,37 @@ where
                 Control::Continue
             }
             SourceMsg::Cb(cb, id) => self.handle_cb(cb, id).await,
+            SourceMsg::Tap { port, tap } => {
+                info!("{} Tapping port {port}.", self.ctx);
+                self.taps.add(port, tap);
+                Control::Continue
+            }
             #[cfg(test)]
             SourceMsg::Ping(sender) => {
                 self.ctx
                     .swallow_err(sender.send(()).await, "Error sending Pong");
                 Control::Continue
             }
-            m @ (SourceMsg::Start | SourceMsg::Resume | SourceMsg::Pause) => {
+            SourceMsg::Pause(paused_tx) => {
+                info!("{} Ignoring Pause msg in {state} state", self.ctx);
+                self.reply_paused(paused_tx).await;
+                Control::Continue
+            }
+            m @ (SourceMsg::Start | SourceMsg::Resume) => {
                 info!("{} Ignoring {m:?} msg in {state} state", self.ctx);
                 Control::Continue
             }
         }
     }
 
+    /// tells whoever waits for the pause that no more events are sent out
+    async fn reply_paused(&self, paused_tx: Option<Sender<Result<()>>>) {
+        if let Some(paused_tx) = paused_tx {
+            self.ctx
+                .swallow_err(paused_tx.send(Ok(())).await, "Error sending Pause reply");
+        }
+    }
+
     async fn handle_drain(&mut self, drained_sender: Sender<Msg>) -> Control {
         let state = self.state;
         match self.state {
@@ -846,6 +914,8 @@ where
             return Control::Continue;
         };
         // We can not move this to the system flow since we need to know about transactionality
+        // all connectors of a flow are built by now, which might have made the source transactional
+        self.is_transactional = self.source.is_transactional();
         for (pipeline_url, p) in &pipelines {
             self.ctx.swallow_err(
                 p.send_mgmt(pipeline::MgmtMsg::ConnectInput {
@@ -886,6 +956,7 @@ where
                 error!("{ctx} Trying to send event to invalid port: {port}");
                 continue;
             };
+            self.taps.observe(&port, &event);
 
             // flush metrics reporter or similar
             if let Some(t) = self.metrics_reporter.periodic_flush(event.ingest_ns) {
@@ -1295,59 +1366,80 @@ fn build_events(
     meta: &Value<'static>,
     is_transactional: bool,
 ) -> Vec<(Cow<'static, str>, Event)> {
-    match preprocess(
-        stream_state.preprocessors.as_mut_slice(),
-        ingest_ns,
-        data,
-        alias,
-    ) {
-        Ok(processed) => {
-            let mut res = Vec::with_capacity(processed.len());
-            for chunk in processed {
-                let line_value = EventPayload::try_new::<Option<Error>, _>(chunk, |mut_data| {
-                    match stream_state.codec.decode(mut_data, *ingest_ns) {
-                        Ok(None) => Err(None),
-                        Err(e) => Err(Some(e)),
-                        Ok(Some(decoded)) => {
-                            Ok(ValueAndMeta::from_parts(decoded, meta.clone()))
-                            // TODO: avoid clone on last iterator element
-                        }
-                    }
-                });
-                let (port, payload) = match line_value {
-                    Ok(decoded) => (port.unwrap_or(&OUT).clone(), decoded),
-                    Err(None) => continue,
-                    Err(Some(e)) => (
-                        ERR,
-                        make_error(alias, &e, stream_state.stream_id, pull_id, meta.clone()),
-                    ),
-                };
+    // without preprocessors the data is decoded as is
+    let processed = if stream_state.preprocessors.is_empty() {
+        vec![data]
+    } else {
+        match preprocess(
+            stream_state.preprocessors.as_mut_slice(),
+            ingest_ns,
+            &data,
+            alias,
+        ) {
+            Ok(processed) => processed,
+            Err(e) => {
+                // preprocessor error
+                let err_payload = make_error(
+                    alias,
+                    &e,
+                    stream_state.stream_id,
+                    pull_id,
+                    origin_uri,
+                    stream_state.dead_letter_data(&data),
+                    meta.clone(),
+                );
                 let event = build_event(
                     stream_state,
                     pull_id,
                     *ingest_ns,
-                    payload,
-                    origin_uri.clone(), // TODO: use split_last to avoid this clone for the last item
+                    err_payload,
+                    origin_uri.clone(),
                     is_transactional,
                 );
-                res.push((port, event));
+                return vec![(ERR, event)];
             }
-            res
-        }
-        Err(e) => {
-            // preprocessor error
-            let err_payload = make_error(alias, &e, stream_state.stream_id, pull_id, meta.clone());
-            let event = build_event(
-                stream_state,
-                pull_id,
-                *ingest_ns,
-                err_payload,
-                origin_uri.clone(),
-                is_transactional,
-            );
-            vec![(ERR, event)]
         }
+    };
+    let mut res = Vec::with_capacity(processed.len());
+    for chunk in processed {
+        stream_state.keep_raw(&chunk);
+        let line_value = EventPayload::try_new::<Option<Error>, _>(chunk, |mut_data| {
+            match stream_state.codec.decode(mut_data, *ingest_ns) {
+                Ok(None) => Err(None),
+                Err(e) => Err(Some(e)),
+                Ok(Some(decoded)) => {
+                    Ok(ValueAndMeta::from_parts(decoded, meta.clone()))
+                    // TODO: avoid clone on last iterator element
+                }
+            }
+        });
+        let (port, payload) = match line_value {
+            Ok(decoded) => (port.unwrap_or(&OUT).clone(), decoded),
+            Err(None) => continue,
+            Err(Some(e)) => (
+                ERR,
+                make_error(
+                    alias,
+                    &e,
+                    stream_state.stream_id,
+                    pull_id,
+                    origin_uri,
+                    stream_state.dead_letter_raw(),
+                    meta.clone(),
+                ),
+            ),
+        };
+        let event = build_event(
+            stream_state,
+            pull_id,
+            *ingest_ns,
+            payload,
+            origin_uri.clone(), // TODO: use split_last to avoid this clone for the last item
+            is_transactional,
+        );
+        res.push((port, event));
     }
+    res
 }
 
 /// build any number of `Event`s from a given Source Transport Unit (`data`)
@@ -1367,6 +1459,7 @@ fn build_last_events(
         Ok(processed) => {
             let mut res = Vec::with_capacity(processed.len());
             for chunk in processed {
+                stream_state.keep_raw(&chunk);
                 let line_value = EventPayload::try_new::<Option<Error>, _>(chunk, |mut_data| {
                     match stream_state.codec.decode(mut_data, *ingest_ns) {
                         Ok(None) => Err(None),
@@ -1382,7 +1475,15 @@ fn build_last_events(
                     Err(None) => continue,
                     Err(Some(e)) => (
                         ERR,
-                        make_error(alias, &e, stream_state.stream_id, pull_id, meta.clone()),
+                        make_error(
+                            alias,
+                            &e,
+                            stream_state.stream_id,
+                            pull_id,
+                            origin_uri,
+                            stream_state.dead_letter_raw(),
+                            meta.clone(),
+                        ),
                     ),
                 };
                 let event = build_event(
@@ -1398,8 +1499,16 @@ fn build_last_events(
             res
         }
         Err(e) => {
-            // preprocessor error
-            let err_payload = make_error(alias, &e, stream_state.stream_id, pull_id, meta.clone());
+            // preprocessor error, the data is buffered within the preprocessors
+            let err_payload = make_error(
+                alias,
+                &e,
+                stream_state.stream_id,
+                pull_id,
+                origin_uri,
+                None,
+                meta.clone(),
+            );
             let event = build_event(
                 stream_state,
                 pull_id,
@@ -1414,20 +1523,29 @@ fn build_last_events(
 }
 
 /// create an error payload
+///
+/// With `raw` data, it carries everything needed to replay the data later on.
 fn make_error(
     connector_alias: &str,
     error: &Error,
     stream_id: u64,
     pull_id: u64,
+    origin_uri: &EventOriginUri,
+    raw: Option<Vec<u8>>,
     mut meta: Value<'static>,
 ) -> EventPayload {
     let e_string = error.to_string();
-    let data = literal!({
+    let mut data = literal!({
         "error": e_string.clone(),
         "source": connector_alias.to_string(),
         "stream_id": stream_id,
         "pull_id": pull_id
     });
+    if let Some(raw) = raw {
+        data.try_insert("data", Value::Bytes(raw.into()));
+        data.try_insert("origin_uri", origin_uri.to_string());
+        data.try_insert("meta", meta.clone());
+    }
     meta.try_insert("error", e_string);
     EventPayload::from(ValueAndMeta::from_parts(data, meta))
 }
@@ -13,9 +13,11 @@
 // limitations under the License.
 
 use super::ConnectorHarness;
-use crate::{connectors::impls::file, errors::Result};
+use crate::{codec, config, connectors::impls::file, errors::Result};
 use async_std::path::Path;
-use tremor_value::literal;
+use tremor_common::ports::IN;
+use tremor_pipeline::{CbAction, Event};
+use tremor_value::{literal, Value};
 use value_trait::ValueAccess;
 
 #[async_std::test]
@@ -81,3 +83,154 @@ async fn file_connector() -> Result<()> {
 
     Ok(())
 }
+
+#[async_std::test]
+async fn file_connector_dead_letter() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let input_path = Path::new(file!())
+        .parent()
+        .unwrap()
+        .join("../../..")
+        .join("tests")
+        .join("data")
+        .join("input.txt");
+    let defn = literal!({
+        "codec": "json",
+        "preprocessors": ["separate"],
+        "dead_letter": "raw",
+        "config": {
+            "path": input_path.display().to_string(),
+            "mode": "read"
+        }
+    });
+
+    let harness = ConnectorHarness::new(function_name!(), &file::Builder::default(), &defn).await?;
+    let err = harness.err().expect("No err pipeline");
+    harness.start().await?;
+
+    harness.wait_for_connected().await?;
+
+    // the lines are no valid json, so they end up on the err port with their raw data
+    for line in ["snot", "badger"] {
+        let event = err.get_event().await?;
+        let value = event.data.suffix().value();
+        assert_eq!(Some(line.as_bytes()), value.get_bytes("data"));
+        assert!(value.get_str("error").is_some());
+        assert_eq!(
+            Some("src/connectors/tests/../../../tests/data/input.txt"),
+            value.get("meta").get("file").get_str("path")
+        );
+        assert!(value
+            .get_str("origin_uri")
+            .map_or(false, |uri| uri.starts_with("tremor-file://")));
+    }
+
+    let (out_events, err_events) = harness.stop().await?;
+    assert!(
+        out_events.is_empty(),
+        "got some events on OUT port: {:?}",
+        out_events
+    );
+    assert!(
+        err_events.is_empty(),
+        "got some events on ERR port: {:?}",
+        err_events
+    );
+
+    Ok(())
+}
+
+/// writes a batch of two records and a single record with `codec` to a file and returns its content
+async fn write_records(name: &str, codec: Value<'static>) -> Result<Vec<u8>> {
+    let temp_file = tempfile::Builder::new().tempfile()?;
+    let defn = literal!({
+        "codec": codec,
+        "config": {
+            "path": temp_file.path().display().to_string(),
+            "mode": "truncate"
+        }
+    });
+    let harness = ConnectorHarness::new(name, &file::Builder::default(), &defn).await?;
+    harness.start().await?;
+    harness.wait_for_connected().await?;
+    harness.consume_initial_sink_contraflow().await?;
+    let in_pipe = harness.get_pipe(IN).expect("No pipeline connected to IN");
+
+    let batch = Event {
+        data: (
+            literal!([
+                {"data": {"value": {"id": 1, "name": "snot"}, "meta": {}}},
+                {"data": {"value": {"id": 2, "name": "badger"}, "meta": {}}}
+            ]),
+            literal!({}),
+        )
+            .into(),
+        is_batch: true,
+        transactional: true,
+        ..Event::default()
+    };
+    let event = Event {
+        data: (literal!({"id": 3, "name": "snotbadger"}), literal!({})).into(),
+        transactional: true,
+        ..Event::default()
+    };
+    for event in [batch, event] {
+        harness.send_to_sink(event, IN).await?;
+        assert_eq!(CbAction::Ack, in_pipe.get_contraflow().await?.cb);
+    }
+
+    // the file is only complete once the sink stopped
+    let (out_events, err_events) = harness.stop().await?;
+    assert!(out_events.is_empty(), "{out_events:?}");
+    assert!(err_events.is_empty(), "{err_events:?}");
+    Ok(async_std::fs::read(temp_file.path()).await?)
+}
+
+fn expected_records() -> Value<'static> {
+    literal!([
+        {"id": 1, "name": "snot"},
+        {"id": 2, "name": "badger"},
+        {"id": 3, "name": "snotbadger"}
+    ])
+}
+
+#[async_std::test]
+async fn file_connector_parquet() -> Result<()> {
+    let _ = env_logger::try_init();
+    let codec_config = literal!({
+        "name": "parquet",
+        "config": {
+            "schema": "message event { required int64 id; required binary name (UTF8); }",
+            "row_group_size": 2
+        }
+    });
+    let mut data = write_records(function_name!(), codec_config.clone()).await?;
+    // a single file with two row groups
+    let mut codec = codec::resolve(&config::Codec::try_from(&codec_config)?)?;
+    assert_eq!(Some(expected_records()), codec.decode(&mut data, 0)?);
+    Ok(())
+}
+
+#[async_std::test]
+async fn file_connector_avro() -> Result<()> {
+    let _ = env_logger::try_init();
+    let codec_config = literal!({
+        "name": "avro",
+        "config": {
+            "schema": {
+                "type": "record",
+                "name": "event",
+                "fields": [
+                    {"name": "id", "type": "long"},
+                    {"name": "name", "type": "string"}
+                ]
+            }
+        }
+    });
+    let mut data = write_records(function_name!(), codec_config).await?;
+    // a single container, readable without a schema
+    let mut codec = codec::resolve(&"avro".into())?;
+    assert_eq!(Some(expected_records()), codec.decode(&mut data, 0)?);
+    Ok(())
+}
@@ -37,6 +37,11 @@ async fn redpanda_container<'d>(docker: &'d DockerCli) -> Result<Container<'d, G
         "--reserve-memory=0M",
         "--node-id=0",
         "--check=false",
+        // required for transactional producers
+        "--set",
+        "redpanda.enable_idempotence=true",
+        "--set",
+        "redpanda.enable_transactions=true",
         "--kafka-addr=0.0.0.0:9092",
         &format!("--advertise-kafka-addr=127.0.0.1:{kafka_port}"),
     ]
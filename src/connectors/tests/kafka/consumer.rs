@@ -886,3 +886,100 @@ async fn connector_kafka_consumer_pause_resume() -> Result<()> {
     drop(container);
     Ok(())
 }
+
+#[async_std::test]
+#[serial(kafka)]
+async fn connector_kafka_consumer_shared_group() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let docker = DockerCli::default();
+    let container = redpanda_container(&docker).await?;
+
+    let port = container.get_host_port(9092);
+    let mut admin_config = ClientConfig::new();
+
+    let broker = format!("127.0.0.1:{}", port);
+    let topic = "tremor_test_shared_group";
+    let group_id = "group_shared";
+
+    admin_config
+        .set("client.id", "test-admin")
+        .set("bootstrap.servers", &broker);
+    let admin_client = AdminClient::from_config(&admin_config)?;
+    let options = AdminOptions::default();
+    let res = admin_client
+        .create_topics(
+            vec![&NewTopic::new(topic, 2, TopicReplication::Fixed(1))],
+            &options,
+        )
+        .await?;
+    for r in res {
+        match r {
+            Err((topic, err)) => {
+                error!("Error creating topic {}: {}", &topic, err);
+            }
+            Ok(topic) => {
+                info!("Created topic {}", topic);
+            }
+        }
+    }
+
+    let connector_config = literal!({
+        "codec": "json-sorted",
+        "config": {
+            "brokers": [
+                broker
+            ],
+            "group_id": group_id,
+            "topics": [
+                topic
+            ],
+            "rdkafka_options": {
+                "enable.auto.commit": "false"
+            }
+        }
+    });
+    let alias1 = "connector_kafka_consumer_shared_group_1";
+    let alias2 = "connector_kafka_consumer_shared_group_2";
+    let harness1 = ConnectorHarness::new(
+        alias1,
+        &kafka::consumer::Builder::default(),
+        &connector_config,
+    )
+    .await?;
+    let harness2 = ConnectorHarness::new(
+        alias2,
+        &kafka::consumer::Builder::default(),
+        &connector_config,
+    )
+    .await?;
+    harness1.start().await?;
+    harness2.start().await?;
+    harness1.wait_for_connected().await?;
+    harness2.wait_for_connected().await?;
+
+    // both consumers of the group are registered on their own, within the harness flow
+    let link1 = kafka::consumer::ConsumerLink::get("test", alias1)?;
+    let link2 = kafka::consumer::ConsumerLink::get("test", alias2)?;
+    assert!(link1.group_metadata().is_some());
+    assert!(link2.group_metadata().is_some());
+    // but not within any other flow
+    let other = kafka::consumer::ConsumerLink::get("other", alias1)?;
+    assert!(other.group_metadata().is_none());
+
+    let (out_events, err_events) = harness1.stop().await?;
+    assert!(out_events.is_empty());
+    assert!(err_events.is_empty());
+
+    // stopping one of them keeps the other one available to producers
+    assert!(link1.group_metadata().is_none());
+    assert!(link2.group_metadata().is_some());
+
+    let (out_events, err_events) = harness2.stop().await?;
+    assert!(out_events.is_empty());
+    assert!(err_events.is_empty());
+
+    // cleanup
+    drop(container);
+    Ok(())
+}
@@ -28,7 +28,7 @@ use serial_test::serial;
 use std::time::Duration;
 use testcontainers::clients::Cli as DockerCli;
 use tremor_common::ports::IN;
-use tremor_pipeline::EventId;
+use tremor_pipeline::{CbAction, EventId};
 use tremor_value::literal;
 
 #[async_std::test]
@@ -272,3 +272,142 @@ async fn connector_kafka_producer() -> Result<()> {
     drop(container);
     Ok(())
 }
+
+#[async_std::test]
+#[serial(kafka)]
+async fn connector_kafka_producer_transactional() -> Result<()> {
+    let _ = env_logger::try_init();
+    let docker = DockerCli::default();
+    let container = redpanda_container(&docker).await?;
+
+    let port = container.get_host_port(9092);
+    let mut admin_config = ClientConfig::new();
+    let broker = format!("127.0.0.1:{}", port);
+    let topic = "tremor_test_transactional";
+    admin_config
+        .set("client.id", "test-admin")
+        .set("bootstrap.servers", &broker);
+    let admin_client = AdminClient::from_config(&admin_config)?;
+    let options = AdminOptions::default();
+    let res = admin_client
+        .create_topics(
+            vec![&NewTopic::new(topic, 1, TopicReplication::Fixed(1))],
+            &options,
+        )
+        .await?;
+    for r in res {
+        match r {
+            Err((topic, err)) => {
+                error!("Error creating topic {}: {}", &topic, err);
+            }
+            Ok(topic) => {
+                info!("Created topic {}", topic);
+            }
+        }
+    }
+
+    let connector_config = literal!({
+        "reconnect": {
+            "retry": {
+                "interval_ms": 1000_u64,
+                "max_retries": 10_u64
+            }
+        },
+        "codec": "json-sorted",
+        "config": {
+            "brokers": [
+                broker.clone()
+            ],
+            "topic": topic,
+            "rdkafka_options": {
+                "transactional.id": "connector_kafka_producer_transactional"
+            }
+        }
+    });
+    let harness = ConnectorHarness::new(
+        function_name!(),
+        &kafka::producer::Builder::default(),
+        &connector_config,
+    )
+    .await?;
+    let in_pipe = harness.get_pipe(IN).expect("No pipe connected to port IN");
+    harness.start().await?;
+    harness.wait_for_connected().await?;
+    harness.consume_initial_sink_contraflow().await?;
+
+    let consumer = ClientConfig::new()
+        .set("bootstrap.servers", &broker)
+        .set("group.id", "connector_kafka_producer_transactional")
+        .set("session.timeout.ms", "6000")
+        .set("auto.offset.reset", "earliest")
+        .set("enable.auto.commit", "false")
+        // only see committed transactions
+        .set("isolation.level", "read_committed")
+        .create::<StreamConsumer>()
+        .expect("Consumer creation error");
+    consumer.subscribe(&[topic]).unwrap();
+    let mut message_stream = consumer.stream();
+
+    // batched event, written within a single transaction
+    let batched_data = literal!([{
+        "data": {
+            "value": {"snot": 1},
+            "meta": {}
+        }
+    }, {
+        "data": {
+            "value": {"badger": 2},
+            "meta": {}
+        }
+    }]);
+    let batched_event = Event {
+        id: EventId::from_id(0, 0, 1),
+        data: (batched_data, literal!({})).into(),
+        transactional: true,
+        is_batch: true,
+        ..Event::default()
+    };
+    harness.send_to_sink(batched_event, IN).await?;
+
+    // the event is only acked once the transaction is committed
+    let cf = in_pipe.get_contraflow().await?;
+    assert_eq!(CbAction::Ack, cf.cb);
+
+    let mut payloads = Vec::with_capacity(2);
+    for _ in 0..2 {
+        match message_stream
+            .next()
+            .timeout(Duration::from_secs(30))
+            .await?
+        {
+            Some(Ok(msg)) => {
+                payloads.push(msg.payload().map(<[u8]>::to_vec));
+            }
+            Some(Err(e)) => {
+                return Err(e.into());
+            }
+            None => {
+                return Err("EOF on kafka topic".into());
+            }
+        }
+    }
+    assert_eq!(
+        vec![
+            Some(b"{\"snot\":1}".to_vec()),
+            Some(b"{\"badger\":2}".to_vec())
+        ],
+        payloads
+    );
+
+    consumer.unsubscribe();
+    drop(message_stream);
+    drop(consumer);
+
+    // shutdown
+    let (out_events, err_events) = harness.stop().await?;
+    assert!(out_events.is_empty());
+    assert!(err_events.is_empty());
+    // cleanup
+    drop(container);
+    Ok(())
+}
@@ -32,6 +32,8 @@ mod http;
 mod kafka;
 #[cfg(feature = "metronome-integration")]
 mod metronome;
+#[cfg(feature = "mqtt-integration")]
+mod mqtt;
 mod pause_resume;
 #[cfg(feature = "s3-integration")]
 mod s3;
@@ -94,7 +96,7 @@ impl ConnectorHarness {
         }
         let raw_config = config::Connector::from_config(id, builder.connector_type(), defn)?;
         let connector_addr =
-            connectors::spawn(id, &mut connector_id_gen, builder, raw_config).await?;
+            connectors::spawn("test", id, &mut connector_id_gen, builder, raw_config).await?;
         let mut pipes = HashMap::new();
 
         let (link_tx, link_rx) = async_std::channel::unbounded();
@@ -173,7 +175,7 @@ impl ConnectorHarness {
     }
 
     pub(crate) async fn pause(&self) -> Result<()> {
-        Ok(self.addr.send(connectors::Msg::Pause).await?)
+        Ok(self.addr.send(connectors::Msg::Pause(None)).await?)
     }
 
     pub(crate) async fn resume(&self) -> Result<()> {
@@ -272,7 +274,11 @@ impl ConnectorHarness {
         self.get_pipe(OUT)
     }
 
-    #[cfg(any(feature = "kafka-integration", feature = "es-integration",))]
+    #[cfg(any(
+        feature = "kafka-integration",
+        feature = "es-integration",
+        feature = "file-integration",
+    ))]
 
     /// get the err pipeline - if any
     pub(crate) fn err(&self) -> Option<&TestPipeline> {
@@ -281,6 +287,7 @@ impl ConnectorHarness {
     #[cfg(any(
         feature = "http-integration",
         feature = "es-integration",
+        feature = "mqtt-integration",
         feature = "socket-integration",
         feature = "tcp-integration",
         feature = "ws-integration"
@@ -304,7 +311,11 @@ impl ConnectorHarness {
             .await
     }
 
-    #[cfg(any(feature = "kafka-integration", feature = "wal-integration"))]
+    #[cfg(any(
+        feature = "kafka-integration",
+        feature = "mqtt-integration",
+        feature = "wal-integration"
+    ))]
     pub(crate) async fn send_contraflow(&self, cb: CbAction, id: EventId) -> Result<()> {
         self.addr.send_source(SourceMsg::Cb(cb, id)).await
     }
@@ -350,6 +361,7 @@ impl TestPipeline {
     #[cfg(any(
         feature = "kafka-integration",
         feature = "es-integration",
+        feature = "mqtt-integration",
         feature = "s3-integration",
         feature = "tcp-integration",
     ))]
@@ -0,0 +1,378 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::ConnectorHarness;
+use crate::connectors::impls::mqtt;
+use crate::errors::Result;
+use async_std::{
+    channel::{bounded, Receiver, Sender},
+    net::{TcpListener, TcpStream},
+    prelude::*,
+    sync::{Arc, Mutex},
+    task,
+};
+use std::time::Duration;
+use tremor_common::ports::IN;
+use tremor_pipeline::{CbAction, Event, EventId};
+use tremor_value::{literal, prelude::*};
+
+/// a message published to the broker
+#[derive(Debug, PartialEq)]
+struct Published {
+    topic: String,
+    qos: u8,
+    retain: bool,
+    payload: Vec<u8>,
+}
+
+/// topic filters with their qos and the connections subscribed to them, and whether those speak MQTT 5
+type Subscriptions = Arc<Mutex<Vec<(String, u8, bool, Sender<Vec<u8>>)>>>;
+
+/// A minimal embedded MQTT 3.1.1 and MQTT 5 broker
+///
+/// It forwards every published message to all matching subscriptions with at most qos 1
+/// and reports it on `published`. Acknowledgements of forwarded messages are reported on `acked`.
+struct TestBroker {
+    port: u16,
+    published: Receiver<Published>,
+    acked: Receiver<u16>,
+}
+
+impl TestBroker {
+    async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let (tx, published) = bounded(128);
+        let (acked_tx, acked) = bounded(128);
+        let subscriptions = Subscriptions::default();
+        task::spawn(async move {
+            let mut incoming = listener.incoming();
+            while let Some(Ok(stream)) = incoming.next().await {
+                task::spawn(handle_connection(
+                    stream,
+                    subscriptions.clone(),
+                    tx.clone(),
+                    acked_tx.clone(),
+                ));
+            }
+        });
+        Ok(Self {
+            port,
+            published,
+            acked,
+        })
+    }
+
+    async fn next_published(&self) -> Result<Published> {
+        Ok(self
+            .published
+            .recv()
+            .timeout(Duration::from_secs(5))
+            .await??)
+    }
+
+    async fn next_acked(&self) -> Result<u16> {
+        Ok(self.acked.recv().timeout(Duration::from_secs(5)).await??)
+    }
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut res = vec![header];
+    let mut len = body.len();
+    loop {
+        let mut byte = u8::try_from(len % 128).unwrap_or_default();
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        res.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+    res.extend_from_slice(body);
+    res
+}
+
+async fn read_packet(stream: &mut TcpStream) -> Result<(u8, Vec<u8>)> {
+    let mut header = [0_u8; 1];
+    stream.read_exact(&mut header).await?;
+    let mut len = 0_usize;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0_u8; 1];
+        stream.read_exact(&mut byte).await?;
+        len += usize::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    let mut body = vec![0_u8; len];
+    stream.read_exact(&mut body).await?;
+    Ok((header[0], body))
+}
+
+/// reads a length prefixed string at `at`, returning it and the offset after it
+fn read_str(body: &[u8], at: usize) -> (String, usize) {
+    let len = usize::from(u16::from_be_bytes([body[at], body[at + 1]]));
+    let end = at + 2 + len;
+    (String::from_utf8_lossy(&body[at + 2..end]).to_string(), end)
+}
+
+/// skips the MQTT 5 properties at `at`, returning the offset after them
+fn skip_properties(body: &[u8], at: usize) -> usize {
+    let mut at = at;
+    let mut len = 0_usize;
+    let mut shift = 0;
+    loop {
+        let byte = body[at];
+        at += 1;
+        len += usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    at + len
+}
+
+fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut filter = filter.split('/');
+    let mut topic = topic.split('/');
+    loop {
+        match (filter.next(), topic.next()) {
+            (Some("#"), _) | (None, None) => return true,
+            (Some("+"), Some(_)) => (),
+            (Some(f), Some(t)) if f == t => (),
+            _ => return false,
+        }
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    subscriptions: Subscriptions,
+    published: Sender<Published>,
+    acked: Sender<u16>,
+) -> Result<()> {
+    let (out_tx, out_rx) = bounded::<Vec<u8>>(128);
+    let mut writer = stream.clone();
+    task::spawn(async move {
+        while let Ok(packet) = out_rx.recv().await {
+            if writer.write_all(&packet).await.is_err() {
+                break;
+            }
+        }
+    });
+    let mut reader = stream;
+    let mut next_pkid = 0_u16;
+    let mut v5 = false;
+    loop {
+        let (header, body) = read_packet(&mut reader).await?;
+        match header >> 4 {
+            // CONNECT, the protocol level follows the protocol name
+            1 => {
+                v5 = body[6] == 5;
+                // MQTT 5 adds empty properties
+                let connack: &[u8] = if v5 { &[0, 0, 0] } else { &[0, 0] };
+                out_tx.send(packet(0x20, connack)).await?;
+            }
+            // PUBLISH
+            3 => {
+                let qos = (header >> 1) & 0x03;
+                let retain = header & 0x01 == 1;
+                let (topic, mut at) = read_str(&body, 0);
+                if qos > 0 {
+                    let pkid = [body[at], body[at + 1]];
+                    at += 2;
+                    // PUBACK or PUBREC
+                    let ack = if qos == 1 { 0x40 } else { 0x50 };
+                    out_tx.send(packet(ack, &pkid)).await?;
+                }
+                if v5 {
+                    at = skip_properties(&body, at);
+                }
+                let payload = body[at..].to_vec();
+                for (filter, max_qos, sub_v5, tx) in subscriptions.lock().await.iter() {
+                    if topic_matches(filter, &topic) {
+                        let qos = qos.min(*max_qos).min(1);
+                        let mut forward = body[..2].to_vec();
+                        forward.extend_from_slice(topic.as_bytes());
+                        if qos > 0 {
+                            next_pkid += 1;
+                            forward.extend_from_slice(&next_pkid.to_be_bytes());
+                        }
+                        if *sub_v5 {
+                            forward.push(0);
+                        }
+                        forward.extend_from_slice(&payload);
+                        // the subscriber might be gone already
+                        let _ = tx.send(packet(0x30 | (qos << 1), &forward)).await;
+                    }
+                }
+                published
+                    .send(Published {
+                        topic,
+                        qos,
+                        retain,
+                        payload,
+                    })
+                    .await?;
+            }
+            // PUBACK
+            4 => acked.send(u16::from_be_bytes([body[0], body[1]])).await?,
+            // PUBREL
+            6 => out_tx.send(packet(0x70, &body[..2])).await?,
+            // SUBSCRIBE
+            8 => {
+                let mut suback = body[..2].to_vec();
+                let mut at = 2;
+                if v5 {
+                    at = skip_properties(&body, at);
+                    suback.push(0);
+                }
+                while at < body.len() {
+                    let (filter, next) = read_str(&body, at);
+                    // the lowest bits of the subscription options are the qos,
+                    // we forward with at most qos 1
+                    let qos = (body[next] & 0x03).min(1);
+                    suback.push(qos);
+                    at = next + 1;
+                    subscriptions
+                        .lock()
+                        .await
+                        .push((filter, qos, v5, out_tx.clone()));
+                }
+                out_tx.send(packet(0x90, &suback)).await?;
+            }
+            // PINGREQ
+            12 => out_tx.send(packet(0xd0, &[])).await?,
+            // DISCONNECT
+            14 => return Ok(()),
+            _ => (),
+        }
+    }
+}
+
+/// publishes via and receives from an `mqtt_client` speaking `protocol`
+async fn client_roundtrip(name: &str, protocol: &str) -> Result<()> {
+    let _ = env_logger::try_init();
+    let broker = TestBroker::start().await?;
+
+    let defn = literal!({
+        "codec": "string",
+        "config": {
+            "url": format!("mqtt://127.0.0.1:{}", broker.port),
+            "topics": ["snot/#"],
+            "topic": "badger",
+            "qos": 1,
+            "protocol": protocol
+        }
+    });
+    let harness = ConnectorHarness::new(name, &mqtt::client::Builder::default(), &defn).await?;
+    let out = harness.out().expect("No pipeline connected to port OUT");
+    harness.start().await?;
+    harness.wait_for_connected().await?;
+    harness.consume_initial_sink_contraflow().await?;
+
+    // publish to the configured topic
+    let event = Event {
+        id: EventId::default(),
+        data: (Value::from("hello"), literal!({})).into(),
+        transactional: true,
+        ..Event::default()
+    };
+    harness.send_to_sink(event, IN).await?;
+    assert_eq!(
+        Published {
+            topic: "badger".to_string(),
+            qos: 1,
+            retain: false,
+            payload: b"hello".to_vec()
+        },
+        broker.next_published().await?
+    );
+    // acked once the broker sent the PUBACK
+    let cf = harness.get_contraflow().await?;
+    assert_eq!(CbAction::Ack, cf.cb);
+
+    // publish to a topic we are subscribed to, set via metadata
+    let event = Event {
+        id: EventId::default(),
+        data: (
+            Value::from("world"),
+            literal!({
+                "mqtt_client": {
+                    "topic": "snot/1",
+                    "qos": 2,
+                    "retain": true
+                }
+            }),
+        )
+            .into(),
+        transactional: true,
+        ..Event::default()
+    };
+    harness.send_to_sink(event, IN).await?;
+    assert_eq!(
+        Published {
+            topic: "snot/1".to_string(),
+            qos: 2,
+            retain: true,
+            payload: b"world".to_vec()
+        },
+        broker.next_published().await?
+    );
+    // acked once the broker sent the PUBCOMP
+    let cf = harness.get_contraflow().await?;
+    assert_eq!(CbAction::Ack, cf.cb);
+
+    // and receive it back
+    let event = out.get_event().await?;
+    let (value, meta) = event.data.parts();
+    assert_eq!(Some("world"), value.as_str());
+    assert_eq!(Some("snot/1"), meta.get("mqtt_client").get_str("topic"));
+    assert_eq!(Some(1), meta.get("mqtt_client").get_u8("qos"));
+
+    // the message is only acknowledged to the broker once the event is acked
+    task::sleep(Duration::from_millis(200)).await;
+    assert!(broker.acked.is_empty());
+    harness.send_contraflow(CbAction::Ack, event.id).await?;
+    assert_eq!(1, broker.next_acked().await?);
+
+    let (out_events, err_events) = harness.stop().await?;
+    assert!(out_events.is_empty(), "{out_events:?}");
+    assert!(err_events.is_empty(), "{err_events:?}");
+    Ok(())
+}
+
+#[async_std::test]
+async fn mqtt_client() -> Result<()> {
+    client_roundtrip(function_name!(), "3.1.1").await
+}
+
+#[async_std::test]
+async fn mqtt_client_v5() -> Result<()> {
+    client_roundtrip(function_name!(), "5").await
+}
+
+#[test]
+fn topic_filters() {
+    assert!(topic_matches("snot/#", "snot/badger/1"));
+    assert!(topic_matches("snot/+/1", "snot/badger/1"));
+    assert!(topic_matches("snot", "snot"));
+    assert!(!topic_matches("snot/+", "snot/badger/1"));
+    assert!(!topic_matches("badger", "snot"));
+}
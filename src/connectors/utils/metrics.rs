@@ -22,6 +22,7 @@ use tremor_value::prelude::*;
 
 /// metrics reporter for connector sources
 pub struct SourceReporter {
+    flow_alias: String,
     alias: String,
     metrics_out: u64,
     metrics_err: u64,
@@ -31,8 +32,14 @@ pub struct SourceReporter {
 }
 
 impl SourceReporter {
-    pub(crate) fn new(alias: String, tx: MetricsSender, flush_interval_s: Option<u64>) -> Self {
+    pub(crate) fn new(
+        flow_alias: &str,
+        alias: String,
+        tx: MetricsSender,
+        flush_interval_s: Option<u64>,
+    ) -> Self {
         Self {
+            flow_alias: flow_alias.to_string(),
             alias,
             metrics_out: 0,
             metrics_err: 0,
@@ -55,10 +62,20 @@ impl SourceReporter {
     pub(crate) fn periodic_flush(&mut self, timestamp: u64) -> Option<u64> {
         if let Some(interval) = self.flush_interval_ns {
             if timestamp >= self.last_flush_ns + interval {
-                let payload_out =
-                    make_event_count_metrics_payload(timestamp, OUT, self.metrics_out, &self.alias);
-                let payload_err =
-                    make_event_count_metrics_payload(timestamp, ERR, self.metrics_err, &self.alias);
+                let payload_out = make_event_count_metrics_payload(
+                    timestamp,
+                    OUT,
+                    self.metrics_out,
+                    &self.flow_alias,
+                    &self.alias,
+                );
+                let payload_err = make_event_count_metrics_payload(
+                    timestamp,
+                    ERR,
+                    self.metrics_err,
+                    &self.flow_alias,
+                    &self.alias,
+                );
                 send(&self.tx, payload_out, &self.alias);
                 send(&self.tx, payload_err, &self.alias);
                 self.last_flush_ns = timestamp;
@@ -78,6 +95,7 @@ impl SourceReporter {
 
 /// metrics reporter for connector sinks
 pub(crate) struct SinkReporter {
+    flow_alias: String,
     alias: String,
     metrics_in: u64,
     tx: MetricsSender,
@@ -86,8 +104,14 @@ pub(crate) struct SinkReporter {
 }
 
 impl SinkReporter {
-    pub(crate) fn new(alias: String, tx: MetricsSender, flush_interval_s: Option<u64>) -> Self {
+    pub(crate) fn new(
+        flow_alias: &str,
+        alias: String,
+        tx: MetricsSender,
+        flush_interval_s: Option<u64>,
+    ) -> Self {
         Self {
+            flow_alias: flow_alias.to_string(),
             alias,
             metrics_in: 0,
             tx,
@@ -103,8 +127,13 @@ impl SinkReporter {
     pub(crate) fn periodic_flush(&mut self, timestamp: u64) -> Option<u64> {
         if let Some(interval) = self.flush_interval_ns {
             if timestamp >= self.last_flush_ns + interval {
-                let payload =
-                    make_event_count_metrics_payload(timestamp, IN, self.metrics_in, &self.alias);
+                let payload = make_event_count_metrics_payload(
+                    timestamp,
+                    IN,
+                    self.metrics_in,
+                    &self.flow_alias,
+                    &self.alias,
+                );
                 send(&self.tx, payload, &self.alias);
                 self.last_flush_ns = timestamp;
                 return Some(timestamp);
@@ -139,9 +168,11 @@ pub(crate) fn make_event_count_metrics_payload(
     timestamp: u64,
     port: Cow<'static, str>,
     count: u64,
+    flow_alias: &str,
     artefact_id: &str,
 ) -> EventPayload {
-    let mut tags: HashMap<Cow<'static, str>, Value<'static>> = HashMap::with_capacity(2);
+    let mut tags: HashMap<Cow<'static, str>, Value<'static>> = HashMap::with_capacity(3);
+    tags.insert_nocheck(Cow::const_str("flow"), flow_alias.to_string().into());
     tags.insert_nocheck(Cow::const_str("connector"), artefact_id.to_string().into());
     tags.insert_nocheck(Cow::const_str("port"), port.into());
 
@@ -14,7 +14,7 @@
 
 use halfbrown::HashMap;
 
-const MIME_TYPES: [(&str, &str); 9] = [
+const MIME_TYPES: [(&str, &str); 10] = [
     ("application/json", "json"),
     ("application/yaml", "yaml"),
     ("text/csv", "csv"),
@@ -24,10 +24,11 @@ const MIME_TYPES: [(&str, &str); 9] = [
     ("application/x-msgpack", "msgpack"),
     ("application/vnd.msgpack", "msgpack"),
     ("application/octet-stream", "binary"),
+    ("application/avro", "avro"),
 ];
 
 /// additional mapping from codec to mime-types
-const CODEC_TO_MIME_TYPES: [(&str, &str); 11] = [
+const CODEC_TO_MIME_TYPES: [(&str, &str); 14] = [
     ("json-sorted", "application/json"),
     ("json", "application/json"),
     ("csv", "text/csv"),
@@ -39,6 +40,9 @@ const CODEC_TO_MIME_TYPES: [(&str, &str); 11] = [
     ("influx", "text/plain"),
     ("binflux", "application/octet-stream"),
     ("statsd", "text/plain"),
+    ("avro", "application/avro"),
+    ("parquet", "application/vnd.apache.parquet"),
+    ("protobuf", "application/x-protobuf"),
 ];
 
 /// Map from mime-type / content-type to codec name
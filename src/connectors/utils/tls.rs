@@ -121,6 +121,27 @@ pub(crate) async fn tls_client_connector(config: &TLSClientConfig) -> Result<Tls
     Ok(TlsConnector::from(tls_config))
 }
 
+/// PEM encoded certificates to verify servers with, for clients that bring their own TLS stack
+///
+/// Like `tls_client_config` this only loads the `cafile` if one is configured,
+/// and the system certificates otherwise.
+pub(crate) async fn ca_certs_pem(config: &TLSClientConfig) -> Result<Vec<u8>> {
+    if let Some(cafile) = config.cafile.as_ref() {
+        Ok(async_std::fs::read(cafile).await?)
+    } else {
+        let mut pem = Vec::new();
+        for cert in load_native_certs()? {
+            pem.extend_from_slice(b"-----BEGIN CERTIFICATE-----\n");
+            for line in base64::encode(&cert.0).as_bytes().chunks(64) {
+                pem.extend_from_slice(line);
+                pem.push(b'\n');
+            }
+            pem.extend_from_slice(b"-----END CERTIFICATE-----\n");
+        }
+        Ok(pem)
+    }
+}
+
 pub(crate) async fn tls_client_config(tremor_config: &TLSClientConfig) -> Result<ClientConfig> {
     let mut tls_config = ClientConfig::new();
     if let Some(cafile) = tremor_config.cafile.as_ref() {
@@ -139,6 +139,7 @@ error_chain! {
         AnyhowError(anyhow::Error);
         AsyncChannelRecvError(async_std::channel::RecvError);
         AsyncChannelTryRecvError(async_std::channel::TryRecvError);
+        AvroError(apache_avro::Error);
         Base64Error(base64::DecodeError);
         ChannelReceiveError(std::sync::mpsc::RecvError);
         Common(tremor_common::Error);
@@ -159,10 +160,17 @@ error_chain! {
         JsonError(simd_json::Error);
         KafkaError(rdkafka::error::KafkaError);
         ModeParseError(file_mode::ModeParseError);
+        MqttClientError(rumqttc::ClientError);
+        MqttConnectionError(rumqttc::ConnectionError);
+        MqttV5ClientError(rumqttc::v5::ClientError);
+        MqttV5ConnectionError(rumqttc::v5::ConnectionError);
         MsgPackDecoderError(rmp_serde::decode::Error);
         MsgPackEncoderError(rmp_serde::encode::Error);
         ParseIntError(std::num::ParseIntError);
         ParseFloatError(std::num::ParseFloatError);
+        ParquetError(parquet::errors::ParquetError);
+        ProtobufDecodeError(prost::DecodeError);
+        ProtobufDescriptorError(prost_reflect::DescriptorError);
         //Postgres(postgres::Error);
         RegexError(regex::Error);
         ReqwestError(reqwest::Error);
@@ -302,6 +310,10 @@ error_chain! {
             description("Duplicate Flow")
                 display("Flow with id \"{}\" is already deployed.", flow)
         }
+        FlowStopping(flow: String) {
+            description("Flow is stopping")
+                display("Flow with id \"{}\" is still stopping.", flow)
+        }
         ProducerNotAvailable(alias: String) {
             description("Producer not available")
                 display("Kafka Producer not available for Connector {}", alias)
@@ -314,6 +326,26 @@ error_chain! {
             description("Connector not found")
                 display("Connector \"{}\" not found in Flow \"{}\"", alias, flow_id)
         }
+        PipelineNotFound(flow_id: String, alias: String) {
+            description("Pipeline not found")
+                display("Pipeline \"{}\" not found in Flow \"{}\"", alias, flow_id)
+        }
+        PipelineSwapError(alias: String, err: String) {
+            description("Error swapping the query of a Pipeline")
+                display("Error swapping the query of Pipeline {}: {}", alias, err)
+        }
+        InvalidTap(alias: String, port: String) {
+            description("Invalid port to tap")
+                display("\"{}\" has no output port \"{}\" to tap", alias, port)
+        }
+        InvalidTapFilter(err: String) {
+            description("Invalid tap filter")
+                display("Invalid tap filter: {}", err)
+        }
+        PluginLoadError(path: String, err: String) {
+            description("Error loading a plugin")
+                display("Error loading plugin {}: {}", path, err)
+        }
         InvalidInputData(msg: &'static str) {
             description("Invalid Input data")
                 display("Invalid Input data: {}", msg)
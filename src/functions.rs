@@ -12,6 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+/// functions provided by plugins
+pub(crate) mod plugin;
+
 use crate::connectors::impls::otel;
 use crate::errors::Result;
 use crate::version::VERSION;
@@ -0,0 +1,205 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! tremor-script functions provided by plugins built against `tremor-pdk`
+
+use std::ops::RangeInclusive;
+use tremor_pdk::{FunctionPluginRef, PluginFunction};
+use tremor_script::prelude::*;
+use tremor_script::registry::{
+    mfa, to_runtime_error, FResult, Registry, TremorFn, TremorFnWrapper,
+};
+
+/// Installs the functions of a plugin into a registry, returning the module they are registered in
+pub(crate) fn install(reg: &mut Registry, plugin: FunctionPluginRef) -> String {
+    let module = (plugin.module())().into_string();
+    register(reg, &module, (plugin.functions())().into_vec());
+    module
+}
+
+fn register(reg: &mut Registry, module: &str, functions: Vec<PluginFunction>) {
+    for function in functions {
+        let name = function.name.to_string();
+        let fun = PluginFn {
+            module: module.to_string(),
+            function,
+        };
+        reg.insert(TremorFnWrapper::new(
+            module.to_string(),
+            name,
+            Box::new(fun),
+        ));
+    }
+}
+
+/// A function provided by a plugin, exchanging arguments and results as JSON
+#[derive(Clone, Debug)]
+struct PluginFn {
+    module: String,
+    function: PluginFunction,
+}
+
+impl TremorFn for PluginFn {
+    fn invoke<'event>(
+        &self,
+        _ctx: &EventContext,
+        args: &[&Value<'event>],
+    ) -> FResult<Value<'event>> {
+        let this_mfa = || mfa(&self.module, self.function.name.as_str(), args.len());
+        let mut encoded = String::from("[");
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                encoded.push(',');
+            }
+            encoded.push_str(&arg.encode());
+        }
+        encoded.push(']');
+        let mut result = (self.function.invoke)(encoded.as_str().into())
+            .into_result()
+            .map_err(|e| to_runtime_error(this_mfa(), e))?
+            .into_string()
+            .into_bytes();
+        tremor_value::parse_to_value(&mut result)
+            .map(Value::into_static)
+            .map_err(|e| to_runtime_error(this_mfa(), format!("Invalid result: {e}")))
+    }
+
+    fn boxed_clone(&self) -> Box<dyn TremorFn> {
+        Box::new(self.clone())
+    }
+
+    fn arity(&self) -> RangeInclusive<usize> {
+        self.function.min_arity..=self.function.max_arity
+    }
+
+    fn is_const(&self) -> bool {
+        self.function.pure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi_stable::{
+        sabi_extern_fn,
+        std_types::{RErr, ROk, RResult, RStr, RString},
+    };
+    use tremor_script::{registry::FunctionError, Script};
+
+    #[sabi_extern_fn]
+    fn sum(args: RStr<'_>) -> RResult<RString, RString> {
+        let mut args = args.as_str().as_bytes().to_vec();
+        match tremor_value::parse_to_value(&mut args) {
+            Ok(args) => {
+                let sum: i64 = args
+                    .as_array()
+                    .map(|args| args.iter().filter_map(|arg| arg.as_i64()).sum())
+                    .unwrap_or_default();
+                ROk(RString::from(sum.to_string()))
+            }
+            Err(e) => RErr(RString::from(e.to_string())),
+        }
+    }
+
+    #[sabi_extern_fn]
+    fn fail(_args: RStr<'_>) -> RResult<RString, RString> {
+        RErr(RString::from("snot"))
+    }
+
+    #[sabi_extern_fn]
+    fn garbage(_args: RStr<'_>) -> RResult<RString, RString> {
+        ROk(RString::from("{badger"))
+    }
+
+    fn function(
+        name: &str,
+        pure: bool,
+        invoke: extern "C" fn(RStr<'_>) -> RResult<RString, RString>,
+    ) -> PluginFunction {
+        PluginFunction {
+            name: RString::from(name),
+            min_arity: 1,
+            max_arity: 3,
+            pure,
+            invoke,
+        }
+    }
+
+    fn registry() -> Registry {
+        let mut reg = tremor_script::registry();
+        register(
+            &mut reg,
+            "test_plugin",
+            vec![
+                function("sum", true, sum),
+                function("fail", false, fail),
+                function("garbage", false, garbage),
+            ],
+        );
+        reg
+    }
+
+    #[test]
+    fn plugin_functions() -> FResult<()> {
+        let reg = registry();
+        let sum = reg.find("test_plugin", "sum")?;
+        assert!(sum.is_const());
+        assert!(sum.valid_arity(3));
+        assert!(!sum.valid_arity(0));
+        assert_eq!(
+            Value::from(6),
+            sum.invoke(
+                &EventContext::new(0, None),
+                &[&Value::from(1), &Value::from(2), &Value::from(3)]
+            )?
+        );
+
+        let fail = reg.find("test_plugin", "fail")?;
+        assert!(!fail.is_const());
+        assert!(matches!(
+            fail.invoke(&EventContext::new(0, None), &[&Value::null()]),
+            Err(FunctionError::RuntimeError { error, .. }) if error == "snot"
+        ));
+        let garbage = reg.find("test_plugin", "garbage")?;
+        assert!(matches!(
+            garbage.invoke(&EventContext::new(0, None), &[&Value::null()]),
+            Err(FunctionError::RuntimeError { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn plugin_functions_in_scripts() -> crate::errors::Result<()> {
+        let reg = registry();
+        let script = Script::parse("core::test_plugin::sum(event, 2)", &reg)?;
+        let mut event = Value::from(40);
+        let mut state = Value::null();
+        let mut meta = Value::object();
+        let res = script.run(
+            &EventContext::new(0, None),
+            AggrType::Tick,
+            &mut event,
+            &mut state,
+            &mut meta,
+        )?;
+        assert_eq!(
+            Return::Emit {
+                value: Value::from(42),
+                port: None
+            },
+            res
+        );
+        Ok(())
+    }
+}
@@ -55,6 +55,9 @@ pub mod functions;
 
 pub(crate) mod primerge;
 
+/// Plugins loaded from shared libraries
+pub(crate) mod plugin;
+
 /// pipelines
 pub mod pipeline;
 /// Onramp Preprocessors
@@ -66,6 +69,8 @@ pub mod preprocessor;
 pub mod connectors;
 /// Tremor runtime system
 pub mod system;
+/// Live taps on pipeline and connector ports
+pub mod tap;
 /// Utility functions
 pub mod utils;
 /// Tremor runtime version tools
@@ -13,9 +13,10 @@
 // limitations under the License.
 use crate::{
     connectors::{self, sink::SinkMsg, source::SourceMsg},
-    errors::Result,
+    errors::{ErrorKind, Result},
     instance::State,
     primerge::PriorityMerge,
+    tap::{Tap, Taps},
 };
 use async_std::{
     channel::{bounded, unbounded, Receiver, Sender},
@@ -23,11 +24,16 @@ use async_std::{
     task::{self, JoinHandle},
 };
 use beef::Cow;
-use std::{fmt, sync::atomic::Ordering, time::Duration};
+use std::{
+    fmt,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
 use tremor_common::{ids::OperatorIdGen, time::nanotime};
 use tremor_pipeline::{
-    errors::ErrorKind as PipelineErrorKind, CbAction, Event, ExecutableGraph, GraphReturns,
-    SignalKind,
+    checkpoint::{SledBackend, StateBackend},
+    errors::ErrorKind as PipelineErrorKind,
+    CbAction, Event, ExecutableGraph, GraphReport, GraphReturns, SignalKind,
 };
 use tremor_script::{ast::DeployEndpoint, highlighter::Dumb, prelude::BaseExpr};
 
@@ -84,9 +90,44 @@ impl Addr {
     pub(crate) async fn pause(&self) -> Result<()> {
         self.send_mgmt(MgmtMsg::Pause).await
     }
+    /// swap the query of this pipeline and wait until it is done
+    pub(crate) async fn swap(&self, query: NewQuery) -> Result<()> {
+        let (tx, rx) = bounded(1);
+        self.send_mgmt(MgmtMsg::Swap {
+            query: Box::new(query),
+            tx,
+        })
+        .await?;
+        rx.recv().await?
+    }
     pub(crate) async fn resume(&self) -> Result<()> {
         self.send_mgmt(MgmtMsg::Resume).await
     }
+
+    /// report the structure of the running graph with the events counted per node
+    ///
+    /// # Errors
+    /// if the pipeline is not running anymore and can't be reached
+    pub async fn report_graph(&self) -> Result<GraphReport> {
+        let (tx, rx) = bounded(1);
+        self.send_mgmt(MgmtMsg::ReportGraph(tx)).await?;
+        Ok(rx.recv().await?)
+    }
+
+    /// attach a tap to the output `port` of this pipeline
+    ///
+    /// # Errors
+    /// if the pipeline has no such output port or can't be reached
+    pub async fn tap(&self, port: &str, tap: Tap) -> Result<()> {
+        let (tx, rx) = bounded(1);
+        self.send_mgmt(MgmtMsg::Tap {
+            port: port.to_string().into(),
+            tap,
+            tx,
+        })
+        .await?;
+        rx.recv().await?
+    }
 }
 
 impl fmt::Debug for Addr {
@@ -143,6 +184,7 @@ impl TryFrom<connectors::Addr> for OutputTarget {
 }
 
 pub(crate) fn spawn(
+    flow_alias: &str,
     alias: &str,
     config: &tremor_pipeline::query::Query,
     operator_id_gen: &mut OperatorIdGen,
@@ -150,6 +192,30 @@ pub(crate) fn spawn(
     let qsize = crate::QSIZE.load(Ordering::Relaxed);
     let mut pipeline = config.to_pipe(operator_id_gen)?;
     pipeline.optimize();
+    pipeline.flow_id = Some(flow_alias.to_string());
+
+    let checkpointing = if let Some(dir) = config.state_dir() {
+        let checkpointing = Checkpointing::open(
+            dir,
+            format!("{flow_alias}::{alias}"),
+            config.checkpoint_interval()?,
+        )?;
+        if let Some(checkpoint) = checkpointing.backend.load(&checkpointing.key)? {
+            info!("[Pipeline::{alias}] Restoring operator state from {dir}.");
+            if let Err(e) = pipeline.restore(&checkpoint) {
+                warn!("[Pipeline::{alias}] Discarding the checkpoint in {dir}, it doesn't fit the query: {e}");
+                // the checkpoint might be partially restored already
+                pipeline = config.to_pipe(operator_id_gen)?;
+                pipeline.optimize();
+                pipeline.flow_id = Some(flow_alias.to_string());
+                checkpointing.backend.remove(&checkpointing.key)?;
+            }
+        }
+        pipeline.enable_checkpoints();
+        Some(checkpointing)
+    } else {
+        None
+    };
 
     let (tx, rx) = bounded::<Box<Msg>>(qsize);
     // We use a unbounded channel for counterflow, while an unbounded channel seems dangerous
@@ -184,10 +250,92 @@ pub(crate) fn spawn(
             cf_rx,
             mgmt_rx,
             tick_handler,
+            checkpointing,
         ))?;
     Ok(addr)
 }
 
+/// A query to replace the current one of a pipeline with
+#[derive(Debug)]
+pub(crate) struct NewQuery {
+    pipeline: ExecutableGraph,
+    /// the `state_dir` of the query
+    state_dir: Option<String>,
+    /// the `checkpoint_interval_s` of the query in nanoseconds
+    checkpoint_interval: u64,
+}
+
+impl NewQuery {
+    /// builds the graph of `query` along with its checkpoint settings
+    ///
+    /// # Errors
+    /// if the graph can't be built or the checkpoint interval is invalid
+    pub(crate) fn new(
+        query: &tremor_pipeline::query::Query,
+        operator_id_gen: &mut OperatorIdGen,
+    ) -> Result<Self> {
+        let mut pipeline = query.to_pipe(operator_id_gen)?;
+        pipeline.optimize();
+        Ok(Self {
+            pipeline,
+            state_dir: query.state_dir().map(ToString::to_string),
+            checkpoint_interval: query.checkpoint_interval()?,
+        })
+    }
+}
+
+/// Persists the operator state of a pipeline
+#[derive(Debug)]
+pub(crate) struct Checkpointing {
+    backend: Arc<dyn StateBackend>,
+    /// key of the pipeline in the backend, `<flow>::<pipeline>`
+    key: String,
+    /// interval in nanoseconds between two checkpoints
+    interval: u64,
+    last: u64,
+}
+
+impl Checkpointing {
+    /// checkpoints into the state backend in `dir`
+    fn open(dir: &str, key: String, interval: u64) -> Result<Self> {
+        Ok(Self {
+            backend: Arc::new(SledBackend::open(dir)?),
+            key,
+            interval,
+            last: nanotime(),
+        })
+    }
+
+    /// stages a new checkpoint if the interval passed since the last one
+    fn on_tick(&mut self, pipeline: &mut ExecutableGraph, now: u64) -> Result<()> {
+        if now.saturating_sub(self.last) >= self.interval {
+            self.last = now;
+            pipeline.checkpoint()?;
+        }
+        Ok(())
+    }
+
+    /// stores the latest checkpoint that was acknowledged by all downstream sinks
+    async fn store(&self, pipeline: &mut ExecutableGraph) -> Result<()> {
+        if let Some(checkpoint) = pipeline.take_checkpoint() {
+            let backend = self.backend.clone();
+            let key = self.key.clone();
+            // the backend blocks until the checkpoint is on disk
+            task::spawn_blocking(move || backend.store(&key, &checkpoint)).await?;
+        }
+        Ok(())
+    }
+
+    /// forgets the stored checkpoint, so the state of a swapped out query is never restored
+    async fn reset(&mut self) -> Result<()> {
+        let backend = self.backend.clone();
+        let key = self.key.clone();
+        task::spawn_blocking(move || backend.remove(&key)).await?;
+        self.last = nanotime();
+        Ok(())
+    }
+}
+
 /// control plane message
 #[derive(Debug)]
 pub(crate) enum MgmtMsg {
@@ -217,6 +365,25 @@ pub(crate) enum MgmtMsg {
     Resume,
     /// stop the pipeline
     Stop,
+    /// replace the graph of this pipeline with a new one having the same input and output ports,
+    /// after all messages sent before have been handled by the current graph
+    Swap {
+        /// the new query
+        query: Box<NewQuery>,
+        /// result sender
+        tx: Sender<Result<()>>,
+    },
+    /// report the nodes and edges of the graph with their current event counts
+    ReportGraph(Sender<GraphReport>),
+    /// attach a tap to an output port
+    Tap {
+        /// the tapped output port
+        port: Cow<'static, str>,
+        /// the tap
+        tap: Tap,
+        /// result sender
+        tx: Sender<Result<()>>,
+    },
     #[cfg(test)]
     Inspect(Sender<report::StatusReport>),
 }
@@ -342,8 +509,13 @@ async fn send_events(
     dests: &mut Dests,
     pipeline: &mut ExecutableGraph,
     inputs: &Inputs,
+    taps: &mut Taps,
 ) -> Result<()> {
     for (output, event) in eventset.output.drain(..) {
+        taps.observe(&output, &event);
+        // every destination acknowledges the event, if there is none we do it once below
+        let destinations = dests.get(&output).map_or(0, Vec::len);
+        pipeline.sent(&event, destinations.max(1));
         if let Some(destinations) = dests.get_mut(&output) {
             if let Some((last, rest)) = destinations.split_last_mut() {
                 for (id, dest) in rest {
@@ -456,6 +628,60 @@ fn maybe_send(r: Result<()>) {
     }
 }
 
+/// handle an event or signal with the current graph
+#[allow(clippy::too_many_arguments)]
+async fn handle_flow_msg(
+    msg: Msg,
+    alias: &str,
+    pipeline: &mut ExecutableGraph,
+    eventset: &mut GraphReturns,
+    dests: &mut Dests,
+    inputs: &Inputs,
+    taps: &mut Taps,
+    checkpointing: Option<&mut Checkpointing>,
+) -> Result<()> {
+    match msg {
+        Msg::Event { input, event } => match pipeline.enqueue(&input, event, eventset).await {
+            Ok(()) => {
+                handle_insights(pipeline, inputs).await;
+                maybe_send(send_events(eventset, dests, pipeline, inputs, taps).await);
+            }
+            Err(e) => {
+                let err_str = if let PipelineErrorKind::Script(script_kind) = e.0 {
+                    let script_error = tremor_script::errors::Error(script_kind, e.1);
+
+                    Dumb::error_to_string(&script_error)
+                        .unwrap_or_else(|e| format!(" {script_error}: {e}"))
+                } else {
+                    format!(" {e}")
+                };
+                error!("[Pipeline::{alias}] Error handling event:{err_str}");
+            }
+        },
+        Msg::Signal(signal) => {
+            if let (Some(checkpointing), Some(SignalKind::Tick)) = (checkpointing, &signal.kind) {
+                if let Err(e) = checkpointing.on_tick(pipeline, signal.ingest_ns) {
+                    error!("[Pipeline::{alias}] Error taking a checkpoint: {e}");
+                }
+            }
+            if let Err(e) = pipeline.enqueue_signal(signal.clone(), eventset) {
+                let err_str = if let PipelineErrorKind::Script(script_kind) = e.0 {
+                    let script_error = tremor_script::errors::Error(script_kind, e.1);
+                    Dumb::error_to_string(&script_error)?
+                } else {
+                    format!(" {:?}", e)
+                };
+                error!("[Pipeline::{}] Error handling signal:{}", alias, err_str);
+            } else {
+                maybe_send(send_signal(alias, signal, dests).await);
+                handle_insights(pipeline, inputs).await;
+                maybe_send(send_events(eventset, dests, pipeline, inputs, taps).await);
+            }
+        }
+    }
+    Ok(())
+}
+
 #[allow(clippy::too_many_lines)]
 pub(crate) async fn pipeline_task(
     alias: String,
@@ -465,17 +691,21 @@ pub(crate) async fn pipeline_task(
     cf_rx: Receiver<CfMsg>,
     mgmt_rx: Receiver<MgmtMsg>,
     tick_handler: JoinHandle<()>,
+    mut checkpointing: Option<Checkpointing>,
 ) -> Result<()> {
     pipeline.id = alias.clone();
 
     let mut dests: Dests = halfbrown::HashMap::new();
     let mut inputs: Inputs = halfbrown::HashMap::new();
     let mut eventset = GraphReturns::default();
+    let mut taps = Taps::default();
 
     let mut state: State = State::Initializing;
 
     info!("[Pipeline::{alias}] Starting Pipeline.");
 
+    // used for draining the forward event flow before swapping the graph
+    let flow_rx = rx.clone();
     let ff = rx.map(|e| AnyMsg::Flow(*e));
     let cf = cf_rx.map(AnyMsg::Contraflow);
     let mf = mgmt_rx.map(AnyMsg::Mgmt);
@@ -487,43 +717,18 @@ pub(crate) async fn pipeline_task(
             AnyMsg::Contraflow(msg) => {
                 handle_cf_msg(msg, &mut pipeline, &inputs).await?;
             }
-            AnyMsg::Flow(Msg::Event { input, event }) => {
-                match pipeline.enqueue(&input, event, &mut eventset).await {
-                    Ok(()) => {
-                        handle_insights(&mut pipeline, &inputs).await;
-                        maybe_send(
-                            send_events(&mut eventset, &mut dests, &mut pipeline, &inputs).await,
-                        );
-                    }
-                    Err(e) => {
-                        let err_str = if let PipelineErrorKind::Script(script_kind) = e.0 {
-                            let script_error = tremor_script::errors::Error(script_kind, e.1);
-
-                            Dumb::error_to_string(&script_error)
-                                .unwrap_or_else(|e| format!(" {script_error}: {e}"))
-                        } else {
-                            format!(" {e}")
-                        };
-                        error!("[Pipeline::{alias}] Error handling event:{err_str}");
-                    }
-                }
-            }
-            AnyMsg::Flow(Msg::Signal(signal)) => {
-                if let Err(e) = pipeline.enqueue_signal(signal.clone(), &mut eventset) {
-                    let err_str = if let PipelineErrorKind::Script(script_kind) = e.0 {
-                        let script_error = tremor_script::errors::Error(script_kind, e.1);
-                        Dumb::error_to_string(&script_error)?
-                    } else {
-                        format!(" {:?}", e)
-                    };
-                    error!("[Pipeline::{}] Error handling signal:{}", alias, err_str);
-                } else {
-                    maybe_send(send_signal(&alias, signal, &mut dests).await);
-                    handle_insights(&mut pipeline, &inputs).await;
-                    maybe_send(
-                        send_events(&mut eventset, &mut dests, &mut pipeline, &inputs).await,
-                    );
-                }
+            AnyMsg::Flow(msg) => {
+                handle_flow_msg(
+                    msg,
+                    &alias,
+                    &mut pipeline,
+                    &mut eventset,
+                    &mut dests,
+                    &inputs,
+                    &mut taps,
+                    checkpointing.as_mut(),
+                )
+                .await?;
             }
             AnyMsg::Mgmt(MgmtMsg::ConnectInput {
                 endpoint,
@@ -605,6 +810,106 @@ pub(crate) async fn pipeline_task(
                 info!("[Pipeline::{}] Stopping...", alias);
                 break;
             }
+            AnyMsg::Mgmt(MgmtMsg::Swap { query, tx }) => {
+                let NewQuery {
+                    pipeline: mut new_pipeline,
+                    state_dir,
+                    checkpoint_interval,
+                } = *query;
+                // the new query checkpoints with its own settings
+                let key = format!(
+                    "{}::{alias}",
+                    pipeline.flow_id.as_deref().unwrap_or_default()
+                );
+                let mut new_checkpointing = state_dir
+                    .as_deref()
+                    .map(|dir| Checkpointing::open(dir, key, checkpoint_interval))
+                    .transpose();
+                let res: Result<()> = if pipeline.input_ports() != new_pipeline.input_ports() {
+                    Err(format!(
+                        "New query has input ports {:?}, expected {:?}",
+                        new_pipeline.input_ports(),
+                        pipeline.input_ports()
+                    )
+                    .into())
+                } else if pipeline.output_ports() != new_pipeline.output_ports() {
+                    Err(format!(
+                        "New query has output ports {:?}, expected {:?}",
+                        new_pipeline.output_ports(),
+                        pipeline.output_ports()
+                    )
+                    .into())
+                } else if let Err(e) = new_checkpointing {
+                    Err(e)
+                } else {
+                    info!("[Pipeline::{alias}] Draining before swapping the query...");
+                    // management messages are handled first, so handle everything sent
+                    // before the swap with the current graph
+                    while let Ok(msg) = flow_rx.try_recv() {
+                        handle_flow_msg(
+                            *msg,
+                            &alias,
+                            &mut pipeline,
+                            &mut eventset,
+                            &mut dests,
+                            &inputs,
+                            &mut taps,
+                            checkpointing.as_mut(),
+                        )
+                        .await?;
+                    }
+                    // the new graph starts out empty, so the current one emits
+                    // what it still holds, e.g. open windows, before it is dropped
+                    if let Err(e) = pipeline.enqueue_signal(Event::signal_shutdown(), &mut eventset)
+                    {
+                        error!("[Pipeline::{alias}] Error flushing the query before the swap: {e}");
+                    }
+                    handle_insights(&mut pipeline, &inputs).await;
+                    maybe_send(
+                        send_events(&mut eventset, &mut dests, &mut pipeline, &inputs, &mut taps)
+                            .await,
+                    );
+                    new_pipeline.id = alias.clone();
+                    new_pipeline.flow_id = pipeline.flow_id.take();
+                    // checkpoints of the current query still waiting for acks are dropped with it,
+                    // a checkpoint stored for the new one is never restored into it
+                    for checkpointing in checkpointing
+                        .iter_mut()
+                        .chain(new_checkpointing.iter_mut().flatten())
+                    {
+                        if let Err(e) = checkpointing.reset().await {
+                            error!("[Pipeline::{alias}] Error removing the checkpoint of the swapped query: {e}");
+                        }
+                    }
+                    checkpointing = new_checkpointing.ok().flatten();
+                    if checkpointing.is_some() {
+                        new_pipeline.enable_checkpoints();
+                    }
+                    pipeline = new_pipeline;
+                    info!("[Pipeline::{alias}] Query swapped.");
+                    Ok(())
+                };
+                if tx.send(res).await.is_err() {
+                    error!("[Pipeline::{alias}] Error sending swap result.");
+                }
+            }
+            AnyMsg::Mgmt(MgmtMsg::ReportGraph(tx)) => {
+                if tx.send(pipeline.report()).await.is_err() {
+                    error!("[Pipeline::{alias}] Error sending graph report.");
+                }
+            }
+            AnyMsg::Mgmt(MgmtMsg::Tap { port, tap, tx }) => {
+                let res: Result<()> = if pipeline.output_ports().contains(port.as_ref()) {
+                    info!("[Pipeline::{alias}] Tapping port {port}.");
+                    taps.add(port, tap);
+                    Ok(())
+                } else {
+                    Err(ErrorKind::InvalidTap(alias.clone(), port.to_string()).into())
+                };
+                if tx.send(res).await.is_err() {
+                    error!("[Pipeline::{alias}] Error sending tap result.");
+                }
+            }
             #[cfg(test)]
             AnyMsg::Mgmt(MgmtMsg::Inspect(tx)) => {
                 use report::*;
@@ -632,6 +937,11 @@ pub(crate) async fn pipeline_task(
                 }
             }
         }
+        if let Some(checkpointing) = checkpointing.as_ref() {
+            if let Err(e) = checkpointing.store(&mut pipeline).await {
+                error!("[Pipeline::{alias}] Error storing checkpoint: {e}");
+            }
+        }
     }
     // stop ticks
     tick_handler.cancel().await;
@@ -645,6 +955,7 @@ mod tests {
 
     use super::*;
     use crate::connectors::{prelude::SinkAddr, source::SourceAddr};
+    use async_std::prelude::FutureExt;
     use std::time::Instant;
     use tremor_common::{
         ids::{Id, SourceId},
@@ -652,7 +963,7 @@ mod tests {
     };
     use tremor_pipeline::{EventId, OpMeta};
     use tremor_script::{aggr_registry, lexer::Location, NodeMeta, FN_REGISTRY};
-    use tremor_value::Value;
+    use tremor_value::prelude::*;
 
     #[async_std::test]
     async fn pipeline_spawn() -> Result<()> {
@@ -662,7 +973,7 @@ mod tests {
         let aggr_reg = aggr_registry();
         let query =
             tremor_pipeline::query::Query::parse(trickle, &*FN_REGISTRY.read()?, &aggr_reg)?;
-        let addr = spawn("test-pipe", &query, &mut operator_id_gen)?;
+        let addr = spawn("test-flow", "test-pipe", &query, &mut operator_id_gen)?;
 
         let (tx, rx) = unbounded();
         addr.send_mgmt(MgmtMsg::Inspect(tx.clone())).await?;
@@ -808,4 +1119,198 @@ mod tests {
         }
         Ok(())
     }
+
+    async fn next_sink_event(sink_rx: &Receiver<SinkMsg>) -> Result<Event> {
+        loop {
+            match sink_rx.recv().timeout(Duration::from_secs(4)).await?? {
+                SinkMsg::Event { event, .. } => return Ok(event),
+                SinkMsg::Signal { .. } => continue,
+                other => return Err(format!("Expected Event, got: {other:?}").into()),
+            }
+        }
+    }
+
+    #[async_std::test]
+    async fn pipeline_swap() -> Result<()> {
+        let _ = env_logger::try_init();
+        let mut operator_id_gen = OperatorIdGen::new();
+        let aggr_reg = aggr_registry();
+        let query = tremor_pipeline::query::Query::parse(
+            "select event from in into out;",
+            &*FN_REGISTRY.read()?,
+            &aggr_reg,
+        )?;
+        let addr = spawn("test-flow", "test-pipe", &query, &mut operator_id_gen)?;
+        addr.start().await?;
+
+        let (sink_tx, sink_rx) = unbounded();
+        let mid = NodeMeta::new(Location::yolo(), Location::yolo());
+        addr.send_mgmt(MgmtMsg::ConnectOutput {
+            endpoint: DeployEndpoint::new(&"sink_01", &IN, &mid),
+            port: OUT,
+            target: OutputTarget::Sink(SinkAddr { addr: sink_tx }),
+        })
+        .await?;
+
+        // the first event is sent before the swap, so it is handled by the old query
+        let event = Event {
+            data: (Value::from(1_u64), Value::object()).into(),
+            ..Event::default()
+        };
+        addr.send(Box::new(Msg::Event { event, input: IN })).await?;
+        // the new query checkpoints with its own settings, the old one didn't
+        let dir = tempfile::Builder::new()
+            .prefix("tremor-pipeline-swap")
+            .tempdir()?;
+        let query = tremor_pipeline::query::Query::parse(
+            &format!(
+                "#!config state_dir = \"{}\"\n#!config checkpoint_interval_s = 0\nselect {{\"swapped\": event}} from in into out;",
+                dir.path().display()
+            ),
+            &*FN_REGISTRY.read()?,
+            &aggr_reg,
+        )?;
+        addr.swap(NewQuery::new(&query, &mut operator_id_gen)?)
+            .await?;
+        let event = Event {
+            data: (Value::from(2_u64), Value::object()).into(),
+            ..Event::default()
+        };
+        addr.send(Box::new(Msg::Event { event, input: IN })).await?;
+
+        let event = next_sink_event(&sink_rx).await?;
+        assert_eq!(Value::from(1_u64), event.data.suffix().value());
+        let event = next_sink_event(&sink_rx).await?;
+        assert_eq!(Some(2_u64), event.data.suffix().value().get_u64("swapped"));
+
+        // a query with different output ports is rejected
+        let mut query = tremor_pipeline::query::Query::parse(
+            "select event from in into out;",
+            &*FN_REGISTRY.read()?,
+            &aggr_reg,
+        )?;
+        query.0.query.into.retain(|port| port.id.as_ref() != "err");
+        assert!(addr
+            .swap(NewQuery::new(&query, &mut operator_id_gen)?)
+            .await
+            .is_err());
+
+        // and the pipeline keeps using the current one
+        let event = Event {
+            data: (Value::from(3_u64), Value::object()).into(),
+            ..Event::default()
+        };
+        addr.send(Box::new(Msg::Event { event, input: IN })).await?;
+        let event = next_sink_event(&sink_rx).await?;
+        assert_eq!(Some(3_u64), event.data.suffix().value().get_u64("swapped"));
+
+        let backend = SledBackend::open(dir.path())?;
+        let start = Instant::now();
+        while backend.load("test-flow::test-pipe")?.is_none() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "No checkpoint stored for the swapped query"
+            );
+            task::sleep(Duration::from_millis(TICK_MS)).await;
+        }
+
+        addr.stop().await?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn pipeline_swap_flushes_windows() -> Result<()> {
+        let _ = env_logger::try_init();
+        let mut operator_id_gen = OperatorIdGen::new();
+        let aggr_reg = aggr_registry();
+        let query = tremor_pipeline::query::Query::parse(
+            r#"
+            define window three from tumbling with size = 3 end;
+            select aggr::win::collect_flattened(event) from in[three] into out;
+            "#,
+            &*FN_REGISTRY.read()?,
+            &aggr_reg,
+        )?;
+        let addr = spawn("test-flow", "test-pipe", &query, &mut operator_id_gen)?;
+        addr.start().await?;
+
+        let (sink_tx, sink_rx) = unbounded();
+        let mid = NodeMeta::new(Location::yolo(), Location::yolo());
+        addr.send_mgmt(MgmtMsg::ConnectOutput {
+            endpoint: DeployEndpoint::new(&"sink_01", &IN, &mid),
+            port: OUT,
+            target: OutputTarget::Sink(SinkAddr { addr: sink_tx }),
+        })
+        .await?;
+
+        for i in 1..=2_u64 {
+            let event = Event {
+                data: (Value::from(i), Value::object()).into(),
+                ..Event::default()
+            };
+            addr.send(Box::new(Msg::Event { event, input: IN })).await?;
+        }
+        let query = tremor_pipeline::query::Query::parse(
+            "select event from in into out;",
+            &*FN_REGISTRY.read()?,
+            &aggr_reg,
+        )?;
+        addr.swap(NewQuery::new(&query, &mut operator_id_gen)?)
+            .await?;
+        let event = Event {
+            data: (Value::from(3_u64), Value::object()).into(),
+            ..Event::default()
+        };
+        addr.send(Box::new(Msg::Event { event, input: IN })).await?;
+
+        // the open window of the old query is emitted before the swap
+        let event = next_sink_event(&sink_rx).await?;
+        assert_eq!(&literal!([1, 2]), event.data.suffix().value());
+        let event = next_sink_event(&sink_rx).await?;
+        assert_eq!(Value::from(3_u64), event.data.suffix().value());
+
+        addr.stop().await?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn pipeline_tap() -> Result<()> {
+        let _ = env_logger::try_init();
+        let mut operator_id_gen = OperatorIdGen::new();
+        let aggr_reg = aggr_registry();
+        let query = tremor_pipeline::query::Query::parse(
+            "select event from in into out;",
+            &*FN_REGISTRY.read()?,
+            &aggr_reg,
+        )?;
+        let addr = spawn("test-flow", "test-pipe", &query, &mut operator_id_gen)?;
+        addr.start().await?;
+
+        let (tap, _rx) = Tap::new(1, None)?;
+        assert!(addr.tap("snot", tap).await.is_err());
+        let (tap, tap_rx) = Tap::new(1, Some("event > 1"))?;
+        addr.tap("out", tap).await?;
+
+        // the output port doesn't need to be connected to be tapped
+        for i in 1..=3_u64 {
+            let event = Event {
+                data: (Value::from(i), Value::object()).into(),
+                ..Event::default()
+            };
+            addr.send(Box::new(Msg::Event { event, input: IN })).await?;
+        }
+        let tapped = tap_rx.recv().timeout(Duration::from_secs(4)).await??;
+        assert_eq!(
+            r#"{"port":"out","ingest_ns":0,"value":2,"meta":{}}"#,
+            tapped
+        );
+        let tapped = tap_rx.recv().timeout(Duration::from_secs(4)).await??;
+        assert_eq!(
+            r#"{"port":"out","ingest_ns":0,"value":3,"meta":{}}"#,
+            tapped
+        );
+
+        addr.stop().await?;
+        Ok(())
+    }
 }
@@ -0,0 +1,172 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Plugins loaded from shared libraries built against `tremor-pdk`
+//!
+//! Every shared library in the plugin directory is checked for being built against a
+//! compatible `tremor-pdk` and, depending on its root module, registered as a connector type
+//! or as a module of tremor-script functions at boot.
+
+use crate::connectors::{plugin::Builder, ConnectorBuilder};
+use crate::errors::{Error, ErrorKind, Result};
+use crate::functions;
+use crate::system::World;
+use abi_stable::library::lib_header_from_path;
+use std::path::{Path, PathBuf};
+use tremor_pdk::{ConnectorPluginRef, FunctionPluginRef};
+use tremor_script::FN_REGISTRY;
+
+/// A loaded plugin
+pub(crate) enum Plugin {
+    /// provides a connector type
+    Connector(Builder),
+    /// provides a module of tremor-script functions
+    Functions(FunctionPluginRef),
+}
+
+/// Loads all plugins in `dir` and registers their connector types and functions
+///
+/// # Errors
+///  * if the directory can't be read
+///  * if a plugin can't be loaded or was built against an incompatible `tremor-pdk`
+pub(crate) async fn register_plugins(world: &World, dir: &Path) -> Result<()> {
+    for path in plugin_paths(dir)? {
+        match load(&path)? {
+            Plugin::Connector(builder) => {
+                info!(
+                    "Registering connector type {} from plugin {}",
+                    builder.connector_type(),
+                    path.display()
+                );
+                world
+                    .register_builtin_connector_type(Box::new(builder))
+                    .await?;
+            }
+            Plugin::Functions(plugin) => {
+                let module = functions::plugin::install(&mut *FN_REGISTRY.write()?, plugin);
+                info!(
+                    "Registering function module {module} from plugin {}",
+                    path.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// the shared libraries in `dir`, in a stable order
+fn plugin_paths(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path.extension().and_then(std::ffi::OsStr::to_str)
+                == Some(std::env::consts::DLL_EXTENSION)
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// loads the plugin at `path`, checking it was built against a compatible `tremor-pdk`
+fn load(path: &Path) -> Result<Plugin> {
+    let load_error =
+        |e: String| Error::from(ErrorKind::PluginLoadError(path.display().to_string(), e));
+    let header = lib_header_from_path(path).map_err(|e| load_error(e.to_string()))?;
+    // a plugin exports a single root module, which is checked against the layout of each kind of plugin
+    match header.init_root_module::<ConnectorPluginRef>() {
+        Ok(plugin) => Ok(Plugin::Connector(Builder::new(path, plugin))),
+        Err(connector_err) => header
+            .init_root_module::<FunctionPluginRef>()
+            .map(Plugin::Functions)
+            .map_err(|function_err| {
+                load_error(format!(
+                    "neither a connector plugin ({connector_err}) nor a function plugin ({function_err})"
+                ))
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_paths_skip_other_files() -> Result<()> {
+        let dir = tempfile::Builder::new()
+            .prefix("tremor-plugins")
+            .tempdir()?;
+        std::fs::write(dir.path().join("README.md"), "not a plugin")?;
+        std::fs::create_dir(dir.path().join("subdir"))?;
+        assert!(plugin_paths(dir.path())?.is_empty());
+
+        let lib = dir
+            .path()
+            .join(format!("snot.{}", std::env::consts::DLL_EXTENSION));
+        std::fs::write(&lib, "badger")?;
+        assert_eq!(vec![lib], plugin_paths(dir.path())?);
+        Ok(())
+    }
+
+    #[test]
+    fn load_invalid_plugin() -> Result<()> {
+        let dir = tempfile::Builder::new()
+            .prefix("tremor-plugins")
+            .tempdir()?;
+        let path = dir
+            .path()
+            .join(format!("snot.{}", std::env::consts::DLL_EXTENSION));
+        std::fs::write(&path, "badger")?;
+        let err = load(&path).err().expect("loaded an invalid plugin");
+        assert!(
+            matches!(&err.0, ErrorKind::PluginLoadError(p, _) if p == &path.display().to_string())
+        );
+        Ok(())
+    }
+
+    /// needs the example plugin built for the same target and profile as the tests, run it with
+    /// `cargo build --package tremor-echo-plugin && cargo test --lib plugin -- --ignored`
+    #[test]
+    #[ignore]
+    fn load_example_plugin() -> Result<()> {
+        // the test binary lives in `<target dir>/<profile>/deps`, next to which cargo puts the plugin
+        let exe = std::env::current_exe()?;
+        let dir = exe
+            .parent()
+            .and_then(Path::parent)
+            .ok_or_else(|| Error::from(format!("no target dir for {}", exe.display())))?;
+        let path = dir.join(format!(
+            "{}tremor_echo_plugin.{}",
+            std::env::consts::DLL_PREFIX,
+            std::env::consts::DLL_EXTENSION
+        ));
+        assert!(
+            path.exists(),
+            "{} not found, build it with `cargo build --package tremor-echo-plugin`",
+            path.display()
+        );
+        match load(&path)? {
+            Plugin::Connector(builder) => assert_eq!("echo", builder.connector_type().to_string()),
+            Plugin::Functions(_) => panic!("loaded the connector plugin as a function plugin"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn plugin_paths_missing_dir() {
+        assert!(plugin_paths(Path::new("/i/do/not/exist")).is_err());
+    }
+}
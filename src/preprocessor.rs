@@ -101,27 +101,40 @@ pub fn make_preprocessors(preprocessors: &[PreprocessorConfig]) -> Result<Prepro
 pub fn preprocess(
     preprocessors: &mut [Box<dyn Preprocessor>],
     ingest_ns: &mut u64,
-    data: Vec<u8>,
+    data: &[u8],
     alias: &str,
 ) -> Result<Vec<Vec<u8>>> {
-    let mut data = vec![data];
+    let (first, rest) = if let Some(split) = preprocessors.split_first_mut() {
+        split
+    } else {
+        return Ok(vec![data.to_vec()]);
+    };
+    // the first preprocessor reads the borrowed data, so it is never copied up front
+    let mut data = process_chunk(first.as_mut(), ingest_ns, 0, data, alias)?;
     let mut data1 = Vec::new();
-    for pp in preprocessors {
+    for pp in rest {
         data1.clear();
         for (i, d) in data.iter().enumerate() {
-            match pp.process(ingest_ns, d) {
-                Ok(mut r) => data1.append(&mut r),
-                Err(e) => {
-                    error!("[{}] Preprocessor [{}] error: {}", alias, i, e);
-                    return Err(e);
-                }
-            }
+            data1.append(&mut process_chunk(pp.as_mut(), ingest_ns, i, d, alias)?);
         }
         std::mem::swap(&mut data, &mut data1);
     }
     Ok(data)
 }
 
+fn process_chunk(
+    pp: &mut dyn Preprocessor,
+    ingest_ns: &mut u64,
+    i: usize,
+    data: &[u8],
+    alias: &str,
+) -> Result<Vec<Vec<u8>>> {
+    pp.process(ingest_ns, data).map_err(|e| {
+        error!("[{}] Preprocessor [{}] error: {}", alias, i, e);
+        e
+    })
+}
+
 /// Canonical way to finish preprocessors up
 ///
 /// # Errors
@@ -455,13 +468,13 @@ mod test {
         let (start, end) = wire[0].split_at(7);
         let id = String::from("test");
         let mut pps: Vec<Box<dyn Preprocessor>> = vec![Box::new(pre_p)];
-        let recv = preprocess(pps.as_mut_slice(), &mut it, start.to_vec(), &id)?;
+        let recv = preprocess(pps.as_mut_slice(), &mut it, start, &id)?;
         assert!(recv.is_empty());
-        let recv = preprocess(pps.as_mut_slice(), &mut it, end.to_vec(), &id)?;
+        let recv = preprocess(pps.as_mut_slice(), &mut it, end, &id)?;
         assert_eq!(recv[0], data);
 
         // incomplete data
-        let processed = preprocess(pps.as_mut_slice(), &mut it, start.to_vec(), &id)?;
+        let processed = preprocess(pps.as_mut_slice(), &mut it, start, &id)?;
         assert!(processed.is_empty());
         // not emitted upon finish
         let finished = finish(pps.as_mut_slice(), &id)?;
@@ -15,6 +15,7 @@
 /// contains Flow definition, control plane task and lifecycle management
 pub mod flow;
 mod flow_supervisor;
+mod metrics;
 
 use self::flow::Flow;
 use crate::errors::{Error, Kind as ErrorKind, Result};
@@ -22,6 +23,7 @@ use crate::{connectors, QSIZE};
 use async_std::channel::bounded;
 use async_std::prelude::*;
 use async_std::task::JoinHandle;
+use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
 use tremor_script::{ast, highlighter::Highlighter};
@@ -32,12 +34,18 @@ pub struct WorldConfig {
     pub qsize: usize,
     /// if debug connectors should be loaded
     pub debug_connectors: bool,
+    /// if runtime metrics should be collected for rendering them in the Prometheus text format
+    pub metrics_endpoint: bool,
+    /// directory to load connector and function plugins from
+    pub plugin_dir: Option<PathBuf>,
 }
 impl Default for WorldConfig {
     fn default() -> Self {
         Self {
             qsize: QSIZE.load(Ordering::Relaxed),
             debug_connectors: false,
+            metrics_endpoint: false,
+            plugin_dir: None,
         }
     }
 }
@@ -60,6 +68,7 @@ pub enum ShutdownMode {
 #[derive(Clone, Debug)]
 pub struct World {
     pub(crate) system: flow_supervisor::Channel,
+    metrics: Option<metrics::Metrics>,
 }
 
 impl World {
@@ -67,6 +76,10 @@ impl World {
     /// # Errors
     /// If the flow can't be started
     pub async fn start_flow(&self, flow: &ast::DeployFlow<'static>) -> Result<()> {
+        // a flow with the same alias might have been stopped before
+        if let Some(metrics) = &self.metrics {
+            metrics.write().await.add_flow(&flow.instance_alias);
+        }
         let (tx, rx) = bounded(1);
         self.system
             .send(flow_supervisor::Msg::StartDeploy {
@@ -76,6 +89,9 @@ impl World {
             .await?;
         if let Err(e) = rx.recv().await? {
             let err_str = match e {
+                e @ Error(ErrorKind::DuplicateFlow(_) | ErrorKind::FlowStopping(_), _) => {
+                    return Err(e)
+                }
                 Error(
                     ErrorKind::Script(e)
                     | ErrorKind::Pipeline(tremor_pipeline::errors::ErrorKind::Script(e)),
@@ -130,6 +146,85 @@ impl World {
         flow_rx.recv().await?
     }
 
+    /// Drain and stop the flow identified by `flow_id` and all of its connectors and pipelines.
+    ///
+    /// Draining and stopping are each given `timeout` to complete.
+    /// The metrics of the flow are no longer rendered afterwards.
+    ///
+    /// # Errors
+    ///  * if the flow is not deployed
+    ///  * if we fail to send the request or fail to receive it
+    ///  * if stopping the flow failed or timed out
+    pub async fn stop_flow(&self, flow_id: String, timeout: Duration) -> Result<()> {
+        let (tx, rx) = bounded(1);
+        self.system
+            .send(flow_supervisor::Msg::StopDeploy {
+                id: flow::Id(flow_id.clone()),
+                timeout,
+                sender: tx,
+            })
+            .await?;
+        let res = rx.recv().await?;
+        // the flow is removed even if stopping it failed, so are its metrics,
+        // including those still queued on the metrics channel
+        if let Some(metrics) = &self.metrics {
+            metrics.write().await.remove_flow(&flow_id);
+        }
+        res
+    }
+
+    /// Replace the query of the pipeline `pipeline_alias` in the flow `flow_id` with the trickle query `src`.
+    ///
+    /// All connectors sending to pipelines in the flow are paused while the pipeline handles all
+    /// events it already received with its current query, then the query is swapped and the connectors resumed.
+    /// Connections to connectors and other pipelines are kept, operator state is not carried over.
+    /// Checkpoints of the operator state are taken as configured by the new query.
+    ///
+    /// # Errors
+    ///  * if the flow or pipeline doesn't exist
+    ///  * if the query is invalid or doesn't have the same input and output ports as the current one
+    ///  * if we fail to send the request or fail to receive it
+    pub async fn swap_pipeline(
+        &self,
+        flow_id: String,
+        pipeline_alias: String,
+        src: &str,
+    ) -> Result<()> {
+        let query = {
+            let aggr_reg = tremor_script::aggr_registry();
+            let reg = tremor_script::FN_REGISTRY.read()?;
+            tremor_script::query::Query::parse(src, &reg, &aggr_reg)
+        };
+        let query = match query {
+            Ok(query) => tremor_pipeline::query::Query(query),
+            Err(e) => {
+                let err_str = tremor_script::highlighter::Dumb::error_to_string(&e)?;
+                return Err(ErrorKind::PipelineSwapError(pipeline_alias, err_str).into());
+            }
+        };
+        let (tx, rx) = bounded(1);
+        self.system
+            .send(flow_supervisor::Msg::SwapPipeline {
+                id: flow::Id(flow_id),
+                alias: pipeline_alias,
+                query: Box::new(query),
+                sender: tx,
+            })
+            .await?;
+        rx.recv().await?
+    }
+
+    /// Renders the latest metrics of all pipelines and connectors in the Prometheus text format
+    ///
+    /// Returns `None` if metrics collection is disabled via `WorldConfig::metrics_endpoint`
+    pub async fn render_metrics(&self) -> Option<String> {
+        if let Some(metrics) = &self.metrics {
+            Some(metrics.read().await.render())
+        } else {
+            None
+        }
+    }
+
     /// list the currently deployed flows
     ///
     /// # Errors
@@ -149,9 +244,13 @@ impl World {
     pub async fn start(config: WorldConfig) -> Result<(Self, JoinHandle<Result<()>>)> {
         let (system_h, system) = flow_supervisor::FlowSupervisor::new(config.qsize).start();
 
-        let world = Self { system };
+        let metrics = config.metrics_endpoint.then(metrics::spawn_collector);
+        let world = Self { system, metrics };
 
         connectors::register_builtin_connector_types(&world, config.debug_connectors).await?;
+        if let Some(plugin_dir) = &config.plugin_dir {
+            crate::plugin::register_plugins(&world, plugin_dir).await?;
+        }
         Ok((world, system_h))
     }
 
@@ -118,6 +118,17 @@ pub(crate) enum Msg {
     GetConnector(ConnectorAlias, Sender<Result<connectors::Addr>>),
     /// Get the addresses for all connectors of this flow
     GetConnectors(Sender<Result<Vec<connectors::Addr>>>),
+    /// Get the addr for a single pipeline
+    GetPipeline(PipelineId, Sender<Result<pipeline::Addr>>),
+    /// Replace the graph of a pipeline, while all connectors sending to pipelines are paused
+    SwapPipeline {
+        /// alias of the pipeline
+        alias: PipelineId,
+        /// the new query
+        query: Box<pipeline::NewQuery>,
+        /// result sender
+        sender: Sender<Result<()>>,
+    },
 }
 type Addr = Sender<Msg>;
 
@@ -181,6 +192,17 @@ impl Flow {
         rx.recv().await?
     }
 
+    /// get the Address used to send messages of a pipeline within this flow, identified by `pipeline_id`
+    ///
+    /// # Errors
+    /// if the flow is not running anymore and can't be reached or if the pipeline is not part of the flow
+    pub async fn get_pipeline(&self, pipeline_id: String) -> Result<pipeline::Addr> {
+        let pipeline_id = PipelineId(pipeline_id);
+        let (tx, rx) = bounded(1);
+        self.addr.send(Msg::GetPipeline(pipeline_id, tx)).await?;
+        rx.recv().await?
+    }
+
     /// Pause this flow and all connectors in it.
     ///
     /// # Errors
@@ -199,6 +221,23 @@ impl Flow {
         self.addr.send(Msg::Resume).await.map_err(Error::from)
     }
 
+    /// Replace the query of the pipeline `alias` in this flow with `query`, keeping all connections
+    pub(crate) async fn swap_pipeline(
+        &self,
+        alias: String,
+        query: pipeline::NewQuery,
+    ) -> Result<()> {
+        let (tx, rx) = bounded(1);
+        self.addr
+            .send(Msg::SwapPipeline {
+                alias: PipelineId(alias),
+                query: Box::new(query),
+                sender: tx,
+            })
+            .await?;
+        rx.recv().await?
+    }
+
     pub(crate) async fn start(
         flow: ast::DeployFlow<'static>,
         operator_id_gen: &mut OperatorIdGen,
@@ -223,8 +262,14 @@ impl Flow {
                             })?;
                     connectors.insert(
                         ConnectorAlias::from(alias),
-                        connectors::spawn(alias, connector_id_gen, builder.as_ref(), config)
-                            .await?,
+                        connectors::spawn(
+                            &flow.instance_alias,
+                            alias,
+                            connector_id_gen,
+                            builder.as_ref(),
+                            config,
+                        )
+                        .await?,
                     );
                 }
                 ast::CreateTargetDefinition::Pipeline(defn) => {
@@ -238,7 +283,8 @@ impl Flow {
                     let pipeline = tremor_pipeline::query::Query(
                         tremor_script::query::Query::from_query(query),
                     );
-                    let addr = pipeline::spawn(alias, &pipeline, operator_id_gen)?;
+                    let addr =
+                        pipeline::spawn(&flow.instance_alias, alias, &pipeline, operator_id_gen)?;
                     pipelines.insert(PipelineId::from(alias), addr);
                 }
             }
@@ -450,8 +496,6 @@ async fn spawn_task(
         })
         .collect();
 
-    let pipelines: Vec<_> = pipelines.values().cloned().collect();
-
     let start_points: Vec<_> = source_connectors
         .difference(&sink_connectors)
         .filter_map(|p| connectors.get(p))
@@ -485,7 +529,7 @@ async fn spawn_task(
                 MsgWrapper::Msg(Msg::Start) if state == State::Initializing => {
                     info!("{prefix} Starting...");
                     // start all pipelines first - order doesnt matter as connectors aren't started yet
-                    for pipe in &pipelines {
+                    for pipe in pipelines.values() {
                         pipe.start().await?;
                     }
 
@@ -512,7 +556,7 @@ async fn spawn_task(
                     for source in start_points.iter().chain(&mixed_pickles).chain(&end_points) {
                         source.pause().await?;
                     }
-                    for pipeline in &pipelines {
+                    for pipeline in pipelines.values() {
                         pipeline.pause().await?;
                     }
                     state = State::Paused;
@@ -524,7 +568,7 @@ async fn spawn_task(
                 MsgWrapper::Msg(Msg::Resume) if state == State::Paused => {
                     info!("{prefix} Resuming...");
 
-                    for pipeline in &pipelines {
+                    for pipeline in pipelines.values() {
                         pipeline.resume().await?;
                     }
                     for sink in end_points.iter().chain(&mixed_pickles).chain(&start_points) {
@@ -592,7 +636,7 @@ async fn spawn_task(
                         }
                     }
 
-                    for pipeline in &pipelines {
+                    for pipeline in pipelines.values() {
                         if let Err(e) = pipeline.stop().await {
                             error!("{prefix} Error stopping pipeline {pipeline:?}: {e}");
                         }
@@ -630,7 +674,75 @@ async fn spawn_task(
                         "{prefix} Error sending GetConnectors response: {e}"
                     );
                 }
+                MsgWrapper::Msg(Msg::GetPipeline(pipeline_id, reply_tx)) => {
+                    log_error!(
+                        reply_tx
+                            .send(pipelines.get(&pipeline_id).cloned().ok_or_else(|| {
+                                ErrorKind::PipelineNotFound(alias.clone(), pipeline_id.0).into()
+                            }))
+                            .await,
+                        "{prefix} Error sending GetPipeline response: {e}"
+                    );
+                }
 
+                MsgWrapper::Msg(Msg::SwapPipeline {
+                    alias: pipeline_alias,
+                    query,
+                    sender,
+                }) => {
+                    let res = match pipelines.get(&pipeline_alias) {
+                        None => Err(ErrorKind::PipelineNotFound(
+                            alias.clone(),
+                            pipeline_alias.to_string(),
+                        )
+                        .into()),
+                        Some(_) if !matches!(state, State::Running | State::Paused) => {
+                            Err(ErrorKind::PipelineSwapError(
+                                pipeline_alias.to_string(),
+                                format!("Flow {alias} is {state}"),
+                            )
+                            .into())
+                        }
+                        Some(addr) => {
+                            info!("{prefix} Swapping the query of Pipeline {pipeline_alias}...");
+                            // no new events should arrive while the pipeline drains its queue,
+                            // so the swap waits for all sources to stop sending events
+                            let paused = state == State::Running;
+                            let mut res = Ok(());
+                            if paused {
+                                for source in start_points.iter().chain(&mixed_pickles) {
+                                    if let Err(e) = source.pause_and_wait().await {
+                                        error!(
+                                            "{prefix} Error pausing Connector {}: {e}",
+                                            source.alias
+                                        );
+                                        res = res.and(Err(e));
+                                    }
+                                }
+                            }
+                            if res.is_ok() {
+                                res = addr.swap(*query).await;
+                            }
+                            // resume every source, also if pausing or swapping failed
+                            if paused {
+                                for source in start_points.iter().chain(&mixed_pickles) {
+                                    if let Err(e) = source.resume().await {
+                                        error!(
+                                            "{prefix} Error resuming Connector {}: {e}",
+                                            source.alias
+                                        );
+                                        res = res.and(Err(e));
+                                    }
+                                }
+                            }
+                            res
+                        }
+                    };
+                    log_error!(
+                        sender.send(res).await,
+                        "{prefix} Error sending SwapPipeline result: {e}"
+                    );
+                }
                 MsgWrapper::DrainResult(conn_res) => {
                     info!("[Flow::{}] Connector {} drained.", &alias, &conn_res.alias);
 
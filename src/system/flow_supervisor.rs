@@ -17,12 +17,17 @@ use crate::errors::{Kind as ErrorKind, Result};
 use crate::system::DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT;
 use crate::{
     connectors::{self, ConnectorBuilder, ConnectorType},
-    log_error,
+    log_error, pipeline,
 };
 use async_std::channel::{bounded, Sender};
 use async_std::prelude::*;
 use async_std::task::{self, JoinHandle};
 use hashbrown::{hash_map::Entry, HashMap};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
 use tremor_common::ids::{ConnectorIdGen, OperatorIdGen};
 use tremor_script::ast::DeployFlow;
 
@@ -37,6 +42,26 @@ pub(crate) enum Msg {
         /// result sender
         sender: Sender<Result<()>>,
     },
+    /// drain and stop a single Flow
+    StopDeploy {
+        /// id of the flow to stop
+        id: Id,
+        /// timeout for each of draining and stopping the flow
+        timeout: Duration,
+        /// result sender
+        sender: Sender<Result<()>>,
+    },
+    /// replace the query of a pipeline in a running flow
+    SwapPipeline {
+        /// id of the flow
+        id: Id,
+        /// alias of the pipeline within the flow
+        alias: String,
+        /// the new query
+        query: Box<tremor_pipeline::query::Query>,
+        /// result sender
+        sender: Sender<Result<()>>,
+    },
     RegisterConnectorType {
         /// the type of connector
         connector_type: ConnectorType,
@@ -54,6 +79,9 @@ pub(crate) enum Msg {
 #[derive(Debug)]
 pub(crate) struct FlowSupervisor {
     flows: HashMap<Id, Flow>,
+    /// flows that were stopped, with whether they are done draining and stopping,
+    /// their ids can only be reused once they are done
+    stopping: HashMap<Id, Arc<AtomicBool>>,
     operator_id_gen: OperatorIdGen,
     connector_id_gen: ConnectorIdGen,
     known_connectors: connectors::Known,
@@ -64,6 +92,7 @@ impl FlowSupervisor {
     pub fn new(qsize: usize) -> Self {
         Self {
             flows: HashMap::new(),
+            stopping: HashMap::new(),
             known_connectors: connectors::Known::new(),
             operator_id_gen: OperatorIdGen::new(),
             connector_id_gen: ConnectorIdGen::new(),
@@ -83,8 +112,14 @@ impl FlowSupervisor {
 
     async fn handle_start_deploy(&mut self, flow: DeployFlow<'static>, sender: Sender<Result<()>>) {
         let id = Id::from(&flow);
+        self.stopping
+            .retain(|_, done| !done.load(Ordering::Acquire));
         let res = match self.flows.entry(id.clone()) {
             Entry::Occupied(_occupied) => Err(ErrorKind::DuplicateFlow(id.0.clone()).into()),
+            // the connectors of the stopping flow might still hold on to ports and the like
+            Entry::Vacant(_vacant) if self.stopping.contains_key(&id) => {
+                Err(ErrorKind::FlowStopping(id.0.clone()).into())
+            }
             Entry::Vacant(vacant) => Flow::start(
                 flow,
                 &mut self.operator_id_gen,
@@ -101,6 +136,65 @@ impl FlowSupervisor {
             "Error sending StartDeploy Err Result: {e}"
         );
     }
+
+    /// removes the flow from the supervisor right away and reports back once it has been
+    /// drained and stopped, only then its id can be reused
+    async fn handle_stop_deploy(&mut self, id: Id, timeout: Duration, sender: Sender<Result<()>>) {
+        if let Some(flow) = self.flows.remove(&id) {
+            let done = Arc::new(AtomicBool::new(false));
+            self.stopping.insert(id, done.clone());
+            task::spawn(async move {
+                let res = drain_and_stop(&flow, timeout).await;
+                done.store(true, Ordering::Release);
+                log_error!(
+                    sender.send(res).await,
+                    "Error sending StopDeploy result: {e}"
+                );
+            });
+        } else {
+            log_error!(
+                sender.send(Err(ErrorKind::FlowNotFound(id.0).into())).await,
+                "Error sending StopDeploy Err Result: {e}"
+            );
+        }
+    }
+
+    async fn handle_swap_pipeline(
+        &mut self,
+        id: Id,
+        alias: String,
+        query: &tremor_pipeline::query::Query,
+        sender: Sender<Result<()>>,
+    ) {
+        let flow: Result<Flow> = self
+            .flows
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| ErrorKind::FlowNotFound(id.0).into());
+        let res = flow.and_then(|flow| {
+            let query = pipeline::NewQuery::new(query, &mut self.operator_id_gen)?;
+            Ok((flow, query))
+        });
+        match res {
+            Ok((flow, query)) => {
+                // do not block the supervisor while the pipeline drains
+                task::spawn(async move {
+                    let res = flow.swap_pipeline(alias, query).await;
+                    log_error!(
+                        sender.send(res).await,
+                        "Error sending SwapPipeline result: {e}"
+                    );
+                });
+            }
+            Err(e) => {
+                log_error!(
+                    sender.send(Err(e)).await,
+                    "Error sending SwapPipeline Err Result: {e}"
+                );
+            }
+        }
+    }
+
     async fn handle_get_flows(&self, reply_tx: Sender<Result<Vec<Flow>>>) {
         let flows = self.flows.values().cloned().collect();
         log_error!(
@@ -199,6 +293,17 @@ impl FlowSupervisor {
                     Msg::StartDeploy { flow, sender } => {
                         self.handle_start_deploy(*flow, sender).await;
                     }
+                    Msg::StopDeploy {
+                        id,
+                        timeout,
+                        sender,
+                    } => self.handle_stop_deploy(id, timeout, sender).await,
+                    Msg::SwapPipeline {
+                        id,
+                        alias,
+                        query,
+                        sender,
+                    } => self.handle_swap_pipeline(id, alias, &query, sender).await,
                     Msg::GetFlows(reply_tx) => self.handle_get_flows(reply_tx).await,
                     Msg::GetFlow(id, reply_tx) => self.handle_get_flow(id, reply_tx).await,
                     Msg::Stop => {
@@ -214,3 +319,23 @@ impl FlowSupervisor {
         (system_h, tx)
     }
 }
+
+/// gracefully take down a single flow: drain it first, so in-flight events are delivered, then stop it
+async fn drain_and_stop(flow: &Flow, timeout: Duration) -> Result<()> {
+    let alias = flow.alias();
+    info!("Draining Flow {alias} ...");
+    let (tx, rx) = bounded(1);
+    flow.drain(tx).await?;
+    if let Ok(res) = rx.recv().timeout(timeout).await {
+        log_error!(res?, "Error draining Flow {alias}: {e}");
+    } else {
+        warn!(
+            "Timeout draining Flow {alias} after {}ms",
+            timeout.as_millis()
+        );
+    }
+    info!("Stopping Flow {alias} ...");
+    let (tx, rx) = bounded(1);
+    flow.stop(tx).await?;
+    rx.recv().timeout(timeout).await??
+}
@@ -0,0 +1,419 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Collects the metrics sent by pipelines and connectors and renders their latest values
+//! in the Prometheus text exposition format.
+//!
+//! Every metrics event has the form
+//! `{"measurement": <name>, "tags": {..}, "fields": {..}, "timestamp": <ns>}`.
+//! Each field becomes a metric family named `tremor_<measurement>_<field>` with the tags as labels:
+//!
+//! * the `count` field of event counters becomes a counter named `tremor_<measurement>_total`
+//! * numeric fields become gauges
+//! * histogram fields, as emitted by `aggr::stats::hdr` and `aggr::stats::dds`, become summaries
+
+use async_broadcast::RecvError;
+use async_std::sync::{Arc, RwLock};
+use async_std::task;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use tremor_pipeline::{MetricsMsg, METRICS_CHANNEL};
+use tremor_value::prelude::*;
+
+const PREFIX: &str = "tremor_";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Counter,
+    Gauge,
+    Summary,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+            Kind::Summary => "summary",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Sample {
+    Value(f64),
+    Summary {
+        quantiles: Vec<(String, f64)>,
+        count: f64,
+        sum: Option<f64>,
+    },
+}
+
+#[derive(Debug)]
+struct Series {
+    /// the `flow` tag of the metric, if any
+    flow: Option<String>,
+    sample: Sample,
+}
+
+#[derive(Debug)]
+struct Family {
+    kind: Kind,
+    /// rendered labels to the latest sample
+    samples: BTreeMap<String, Series>,
+}
+
+/// The latest value of every metric received on the metrics channel
+#[derive(Debug, Default)]
+pub(crate) struct Registry {
+    families: BTreeMap<String, Family>,
+    /// stopped flows, metrics of those still queued on the metrics channel are ignored
+    stopped: BTreeSet<String>,
+}
+
+/// Shared handle to a `Registry` that is kept up to date by a collector task
+pub(crate) type Metrics = Arc<RwLock<Registry>>;
+
+/// Spawns a task subscribing to the metrics channel, recording everything into the returned registry
+pub(crate) fn spawn_collector() -> Metrics {
+    let metrics = Metrics::default();
+    let registry = metrics.clone();
+    let mut rx = METRICS_CHANNEL.rx();
+    task::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(MetricsMsg { payload, .. }) => {
+                    registry.write().await.record(payload.suffix().value());
+                }
+                // we are only interested in the latest values, so missing some is fine
+                Err(RecvError::Overflowed(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+        info!("Metrics collector stopped.");
+    });
+    metrics
+}
+
+/// replaces all characters not allowed in prometheus metric and label names with `_`
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if c.is_ascii_alphabetic() || c == '_' || (i > 0 && c.is_ascii_digit()) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+/// renders the tags of a metric as prometheus labels, sorted by name
+fn labels(tags: Option<&Value>) -> BTreeMap<String, String> {
+    tags.as_object()
+        .map(|tags| {
+            tags.iter()
+                .map(|(k, v)| {
+                    let v = v.as_str().map_or_else(|| v.encode(), ToString::to_string);
+                    (sanitize(k), escape(&v))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn render_labels(labels: &BTreeMap<String, String>) -> String {
+    let labels: Vec<String> = labels.iter().map(|(k, v)| format!("{k}=\"{v}\"")).collect();
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+fn render_value(value: f64) -> String {
+    if value.is_infinite() {
+        if value.is_sign_positive() {
+            "+Inf"
+        } else {
+            "-Inf"
+        }
+        .to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// extracts a summary from a histogram object, as emitted by `aggr::stats::hdr`
+fn summary(histogram: &Value) -> Option<Sample> {
+    let count = histogram.get("count").cast_f64()?;
+    let quantiles = histogram
+        .get_object("percentiles")?
+        .iter()
+        .filter_map(|(q, v)| Some((q.to_string(), v.cast_f64()?)))
+        .collect();
+    let sum = histogram
+        .get("sum")
+        .cast_f64()
+        .or_else(|| histogram.get("mean").cast_f64().map(|mean| mean * count));
+    Some(Sample::Summary {
+        quantiles,
+        count,
+        sum,
+    })
+}
+
+impl Registry {
+    /// records the fields of a single metrics event
+    pub(crate) fn record(&mut self, metric: &Value) {
+        let (measurement, fields) = if let (Some(m), Some(f)) =
+            (metric.get_str("measurement"), metric.get_object("fields"))
+        {
+            (sanitize(m), f)
+        } else {
+            return;
+        };
+        let labels = render_labels(&labels(metric.get("tags")));
+        let flow = metric.get("tags").get_str("flow").map(ToString::to_string);
+        if matches!(&flow, Some(flow) if self.stopped.contains(flow)) {
+            return;
+        }
+        for (field, value) in fields {
+            let (name, kind, sample) = if let Some(v) = value.cast_f64() {
+                if field.as_ref() == "count" {
+                    (
+                        format!("{PREFIX}{measurement}_total"),
+                        Kind::Counter,
+                        Sample::Value(v),
+                    )
+                } else {
+                    let name = format!("{PREFIX}{measurement}_{}", sanitize(field));
+                    (name, Kind::Gauge, Sample::Value(v))
+                }
+            } else if let Some(sample) = summary(value) {
+                let name = format!("{PREFIX}{measurement}_{}", sanitize(field));
+                (name, Kind::Summary, sample)
+            } else {
+                continue;
+            };
+            let family = self.families.entry(name).or_insert_with(|| Family {
+                kind,
+                samples: BTreeMap::new(),
+            });
+            // a metric name can only ever have a single type
+            if family.kind == kind {
+                let flow = flow.clone();
+                family
+                    .samples
+                    .insert(labels.clone(), Series { flow, sample });
+            }
+        }
+    }
+
+    /// records metrics of the flow `flow` again, e.g. once it is started after it has been stopped
+    pub(crate) fn add_flow(&mut self, flow: &str) {
+        self.stopped.remove(flow);
+    }
+
+    /// removes all metrics of the flow `flow` and ignores its metrics until it is added again,
+    /// e.g. once it is stopped
+    pub(crate) fn remove_flow(&mut self, flow: &str) {
+        self.stopped.insert(flow.to_string());
+        for family in self.families.values_mut() {
+            family
+                .samples
+                .retain(|_, series| series.flow.as_deref() != Some(flow));
+        }
+        self.families.retain(|_, family| !family.samples.is_empty());
+    }
+
+    /// renders all recorded metrics in the prometheus text format
+    pub(crate) fn render(&self) -> String {
+        let mut res = String::new();
+        for (name, family) in &self.families {
+            // writing into a string can't fail
+            let _ = writeln!(res, "# TYPE {name} {}", family.kind.as_str());
+            for (labels, Series { sample, .. }) in &family.samples {
+                match sample {
+                    Sample::Value(v) => {
+                        let _ = writeln!(res, "{name}{labels} {}", render_value(*v));
+                    }
+                    Sample::Summary {
+                        quantiles,
+                        count,
+                        sum,
+                    } => {
+                        for (q, v) in quantiles {
+                            let labels = with_label(labels, "quantile", q);
+                            let _ = writeln!(res, "{name}{labels} {}", render_value(*v));
+                        }
+                        if let Some(sum) = sum {
+                            let _ = writeln!(res, "{name}_sum{labels} {}", render_value(*sum));
+                        }
+                        let _ = writeln!(res, "{name}_count{labels} {}", render_value(*count));
+                    }
+                }
+            }
+        }
+        res
+    }
+}
+
+/// adds a label to already rendered labels
+fn with_label(labels: &str, name: &str, value: &str) -> String {
+    let label = format!("{name}=\"{}\"", escape(value));
+    match labels.strip_suffix('}') {
+        Some(labels) => format!("{labels},{label}}}"),
+        None => format!("{{{label}}}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tremor_value::literal;
+
+    #[test]
+    fn render_counters_and_gauges() {
+        let mut registry = Registry::default();
+        registry.record(&literal!({
+            "measurement": "connector_events",
+            "tags": {"flow": "main", "connector": "in", "port": "out"},
+            "fields": {"count": 42},
+            "timestamp": 1
+        }));
+        registry.record(&literal!({
+            "measurement": "connector_events",
+            "tags": {"flow": "main", "connector": "in", "port": "err"},
+            "fields": {"count": 1},
+            "timestamp": 1
+        }));
+        registry.record(&literal!({
+            "measurement": "kafka-producer stats",
+            "tags": {"connector": "k\"afka"},
+            "fields": {"queued_msgs": 3, "ignored": "string"},
+            "timestamp": 1
+        }));
+        // newer values replace older ones
+        registry.record(&literal!({
+            "measurement": "connector_events",
+            "tags": {"flow": "main", "connector": "in", "port": "out"},
+            "fields": {"count": 43},
+            "timestamp": 2
+        }));
+        // invalid metrics are ignored
+        registry.record(&literal!({"snot": "badger"}));
+
+        assert_eq!(
+            r#"# TYPE tremor_connector_events_total counter
+tremor_connector_events_total{connector="in",flow="main",port="err"} 1
+tremor_connector_events_total{connector="in",flow="main",port="out"} 43
+# TYPE tremor_kafka_producer_stats_queued_msgs gauge
+tremor_kafka_producer_stats_queued_msgs{connector="k\"afka"} 3
+"#,
+            registry.render()
+        );
+    }
+
+    #[test]
+    fn remove_flow() {
+        let mut registry = Registry::default();
+        for (flow, connector) in [("main", "in"), ("other", "in"), ("other", "out")] {
+            registry.record(&literal!({
+                "measurement": format!("{connector}_events"),
+                "tags": {"flow": flow, "connector": connector},
+                "fields": {"count": 1},
+                "timestamp": 1
+            }));
+        }
+        registry.record(&literal!({
+            "measurement": "in_events",
+            "tags": {"connector": "in"},
+            "fields": {"count": 2},
+            "timestamp": 1
+        }));
+        registry.remove_flow("other");
+        let other = literal!({
+            "measurement": "out_events",
+            "tags": {"flow": "other", "connector": "out"},
+            "fields": {"count": 2},
+            "timestamp": 2
+        });
+        // metrics of the stopped flow that were still in flight are ignored
+        registry.record(&other);
+        // families without any series left are removed as well
+        assert_eq!(
+            r#"# TYPE tremor_in_events_total counter
+tremor_in_events_total{connector="in"} 2
+tremor_in_events_total{connector="in",flow="main"} 1
+"#,
+            registry.render()
+        );
+        // until the flow is started again
+        registry.add_flow("other");
+        registry.record(&other);
+        assert_eq!(
+            r#"# TYPE tremor_in_events_total counter
+tremor_in_events_total{connector="in"} 2
+tremor_in_events_total{connector="in",flow="main"} 1
+# TYPE tremor_out_events_total counter
+tremor_out_events_total{connector="out",flow="other"} 2
+"#,
+            registry.render()
+        );
+    }
+
+    #[test]
+    fn render_histograms() {
+        let mut registry = Registry::default();
+        registry.record(&literal!({
+            "measurement": "latency",
+            "tags": {"pipeline": "main"},
+            "fields": {
+                "ms": {
+                    "count": 4,
+                    "min": 1,
+                    "max": 10,
+                    "mean": 2.5,
+                    "percentiles": {"0.5": 1, "0.99": 10}
+                }
+            },
+            "timestamp": 1
+        }));
+        assert_eq!(
+            r#"# TYPE tremor_latency_ms summary
+tremor_latency_ms{pipeline="main",quantile="0.5"} 1
+tremor_latency_ms{pipeline="main",quantile="0.99"} 10
+tremor_latency_ms_sum{pipeline="main"} 10
+tremor_latency_ms_count{pipeline="main"} 4
+"#,
+            registry.render()
+        );
+    }
+
+    #[test]
+    fn sanitize_names() {
+        assert_eq!("snot_badger", sanitize("snot-badger"));
+        assert_eq!("_1", sanitize("1"));
+        assert_eq!("a1", sanitize("a1"));
+    }
+}
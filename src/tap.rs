@@ -0,0 +1,236 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Live taps on pipeline output ports and connector outputs.
+//!
+//! A tap observes the events passing through a port without influencing them.
+//! Every observed event is optionally filtered by a tremor-script expression and sampled,
+//! the remaining ones are encoded as JSON objects of the form
+//! `{"port": <port>, "ingest_ns": <ns>, "value": <value>, "meta": <meta>}`
+//! and sent to the receiver returned by [`Tap::new`].
+//!
+//! Taps never apply backpressure: if the receiver can't keep up, events are skipped.
+//! A tap is removed once its receiver is dropped.
+
+use crate::errors::{ErrorKind, Result};
+use async_std::channel::{bounded, Receiver, Sender, TrySendError};
+use beef::Cow;
+use tremor_pipeline::Event;
+use tremor_script::{highlighter::Dumb, literal, prelude::*, Script, FN_REGISTRY};
+
+/// number of tapped events buffered for a receiver before events are skipped
+const TAP_QSIZE: usize = 64;
+
+/// A tap on a single port
+#[derive(Debug)]
+pub struct Tap {
+    /// emit every `sample`-th matching event
+    sample: u64,
+    /// number of events that matched the filter so far
+    matched: u64,
+    filter: Option<Script>,
+    state: Value<'static>,
+    tx: Sender<String>,
+}
+
+impl Tap {
+    /// Creates a new tap emitting every `sample`-th event for which the tremor-script
+    /// expression `filter` evaluates to `true`, and the receiver of the tapped events
+    ///
+    /// # Errors
+    /// if the filter is not a valid tremor-script expression
+    pub fn new(sample: u64, filter: Option<&str>) -> Result<(Self, Receiver<String>)> {
+        let filter = filter
+            .map(|src| -> Result<Script> {
+                let reg = FN_REGISTRY.read()?;
+                Script::parse(src, &reg).map_err(|e| {
+                    let err = Dumb::error_to_string(&e).unwrap_or_else(|_| e.to_string());
+                    ErrorKind::InvalidTapFilter(err).into()
+                })
+            })
+            .transpose()?;
+        let (tx, rx) = bounded(TAP_QSIZE);
+        let tap = Self {
+            sample: sample.max(1),
+            matched: 0,
+            filter,
+            state: Value::null(),
+            tx,
+        };
+        Ok((tap, rx))
+    }
+
+    /// is the receiver of this tap gone
+    fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    /// does the filter of this tap match the given value and meta
+    fn matches(&mut self, ingest_ns: u64, value: &Value, meta: &Value) -> bool {
+        if let Some(filter) = &self.filter {
+            let mut value = value.clone_static();
+            let mut meta = meta.clone_static();
+            match filter.run(
+                &EventContext::new(ingest_ns, None),
+                AggrType::Tick,
+                &mut value,
+                &mut self.state,
+                &mut meta,
+            ) {
+                Ok(Return::Emit { value, .. }) => value.as_bool().unwrap_or_default(),
+                Ok(Return::EmitEvent { .. } | Return::Drop) => false,
+                Err(e) => {
+                    debug!("Error running tap filter: {e}");
+                    false
+                }
+            }
+        } else {
+            true
+        }
+    }
+
+    /// observe an event passing the tapped `port`
+    fn observe(&mut self, port: &str, event: &Event) {
+        for (value, meta) in event.value_meta_iter() {
+            if !self.matches(event.ingest_ns, value, meta) {
+                continue;
+            }
+            self.matched += 1;
+            if (self.matched - 1) % self.sample != 0 {
+                continue;
+            }
+            let tapped = literal!({
+                "port": port.to_string(),
+                "ingest_ns": event.ingest_ns,
+                "value": value.clone_static(),
+                "meta": meta.clone_static(),
+            });
+            match self.tx.try_send(tapped.encode()) {
+                // a slow receiver only misses events
+                Ok(()) | Err(TrySendError::Full(_)) => (),
+                Err(TrySendError::Closed(_)) => return,
+            }
+        }
+    }
+}
+
+/// The taps attached to the ports of a pipeline or a source
+#[derive(Debug, Default)]
+pub(crate) struct Taps(Vec<(Cow<'static, str>, Tap)>);
+
+impl Taps {
+    /// attaches a tap to `port`, removing taps whose receiver is gone
+    pub(crate) fn add(&mut self, port: Cow<'static, str>, tap: Tap) {
+        self.0.retain(|(_, tap)| !tap.is_closed());
+        self.0.push((port, tap));
+    }
+
+    /// hands an event sent to `port` to all taps attached to it, removing taps whose receiver is gone
+    pub(crate) fn observe(&mut self, port: &str, event: &Event) {
+        if self.0.is_empty() {
+            return;
+        }
+        for (tapped_port, tap) in &mut self.0 {
+            if tapped_port.eq_ignore_ascii_case(port) {
+                tap.observe(port, event);
+            }
+        }
+        self.0.retain(|(_, tap)| !tap.is_closed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(value: Value<'static>) -> Event {
+        Event {
+            ingest_ns: 42,
+            data: (value, literal!({"snot": "badger"})).into(),
+            ..Event::default()
+        }
+    }
+
+    #[async_std::test]
+    async fn tap_filter_and_sample() -> Result<()> {
+        let (tap, rx) = Tap::new(2, Some("event.level == \"error\""))?;
+        let mut taps = Taps::default();
+        taps.add("out".into(), tap);
+
+        for i in 0..5_u64 {
+            taps.observe("out", &event(literal!({"level": "error", "i": i})));
+            taps.observe("out", &event(literal!({"level": "info", "i": i})));
+            taps.observe("err", &event(literal!({"level": "error", "i": i})));
+        }
+        let mut tapped = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            tapped.push(msg);
+        }
+        assert_eq!(
+            vec![
+                r#"{"port":"out","ingest_ns":42,"value":{"level":"error","i":0},"meta":{"snot":"badger"}}"#,
+                r#"{"port":"out","ingest_ns":42,"value":{"level":"error","i":2},"meta":{"snot":"badger"}}"#,
+                r#"{"port":"out","ingest_ns":42,"value":{"level":"error","i":4},"meta":{"snot":"badger"}}"#,
+            ],
+            tapped
+        );
+
+        // taps are removed once their receiver is gone
+        drop(rx);
+        taps.observe("out", &event(literal!({"level": "error"})));
+        assert!(taps.0.is_empty());
+
+        // closed receivers, e.g. of clients that went away, are removed as well, even if
+        // no event passes the tapped port anymore
+        let (tap, rx) = Tap::new(1, None)?;
+        taps.add("err".into(), tap);
+        rx.close();
+        let (tap, _rx) = Tap::new(1, None)?;
+        taps.add("out".into(), tap);
+        assert_eq!(1, taps.0.len());
+        Ok(())
+    }
+
+    #[test]
+    fn tap_invalid_filter() {
+        assert!(Tap::new(1, Some("event.")).is_err());
+        assert!(Tap::new(1, None).is_ok());
+    }
+
+    #[test]
+    fn tap_batched() -> Result<()> {
+        let (tap, rx) = Tap::new(1, None)?;
+        let mut taps = Taps::default();
+        taps.add("out".into(), tap);
+        let batch = Event {
+            is_batch: true,
+            data: (
+                literal!([{"data": {"value": 1, "meta": {}}}, {"data": {"value": 2, "meta": {}}}]),
+                Value::object(),
+            )
+                .into(),
+            ..Event::default()
+        };
+        taps.observe("OUT", &batch);
+        assert_eq!(
+            Ok(r#"{"port":"OUT","ingest_ns":0,"value":1,"meta":{}}"#.to_string()),
+            rx.try_recv().map_err(|_| ())
+        );
+        assert_eq!(
+            Ok(r#"{"port":"OUT","ingest_ns":0,"value":2,"meta":{}}"#.to_string()),
+            rx.try_recv().map_err(|_| ())
+        );
+        Ok(())
+    }
+}
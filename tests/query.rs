@@ -114,6 +114,8 @@ test_cases!(
     window_by_two_scripted,
     window_by_two,
     window_size_tilted,
+    window_sliding_size,
+    window_session,
     pp_win,
     pp_script,
     pp_operator,
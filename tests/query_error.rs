@@ -125,6 +125,7 @@ test_cases!(
     pp_embed_unrecognized_token4,
     pp_embed_unrecognized_token5,
     // INSERT
+    order_by_without_window,
     pipeline_stream_name_conflict,
     pipeline_out,
     pipeline_in,
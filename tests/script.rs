@@ -152,6 +152,7 @@ test_cases!(
     empty_array_pattern,
     const_in_const_lookup,
     // INSERT
+    contextual_keywords,
     drop,
     const_fn_tremor,
     const_string_interpolation,
@@ -205,4 +206,9 @@ test_cases!(
     heredoc_usefn_interpolation,
     heredoc_regression,
     path_defaulting,
+    datetime_tz,
+    crypto,
+    net,
+    id,
+    url_parse,
 );
@@ -24,11 +24,14 @@ use http_types::{
 };
 use serde::{Deserialize, Serialize};
 use tide::Response;
+use tide_websockets::WebSocket;
 use tremor_runtime::system::World;
 
 pub mod flow;
+pub mod metrics;
 pub mod prelude;
 pub mod status;
+pub mod tap;
 pub mod version;
 
 pub type Request = tide::Request<State>;
@@ -145,6 +148,17 @@ async fn handle_api_request<
 >(
     req: Request,
     handler_func: F,
+) -> tide::Result {
+    handle_api_request_with_timeout(req, handler_func, DEFAULT_API_TIMEOUT).await
+}
+
+async fn handle_api_request_with_timeout<
+    G: std::future::Future<Output = Result<tide::Response>>,
+    F: Fn(Request) -> G,
+>(
+    req: Request,
+    handler_func: F,
+    timeout: Duration,
 ) -> tide::Result {
     let resource_type = accept(&req);
     let path = req.url().path().to_string();
@@ -153,7 +167,7 @@ async fn handle_api_request<
     // Handle request. If any api error is returned, serialize it into a tide response
     // as well, respecting the requested resource type. (and if there's error during
     // this serialization, fall back to the error's conversion into tide response)
-    let r = match handler_func(req).timeout(DEFAULT_API_TIMEOUT).await {
+    let r = match handler_func(req).timeout(timeout).await {
         Err(e) => {
             error!("[API {method} {path}] Timeout");
             Err(e.into())
@@ -183,11 +197,25 @@ pub fn serve(host: String, world: &World) -> JoinHandle<Result<()>> {
         .get(|r| handle_api_request(r, status::get_runtime_status));
     v1_app
         .at("/flows")
-        .get(|r| handle_api_request(r, flow::list_flows));
+        .get(|r| handle_api_request(r, flow::list_flows))
+        .post(|r| handle_api_request(r, flow::create_flows));
     v1_app
         .at("/flows/:id")
         .get(|r| handle_api_request(r, flow::get_flow))
-        .patch(|r| handle_api_request(r, flow::patch_flow_status));
+        .patch(|r| handle_api_request(r, flow::patch_flow_status))
+        .delete(|r: Request| {
+            // draining and stopping the flow are given their own timeouts
+            let timeout =
+                flow::stop_timeout(&r).map_or(DEFAULT_API_TIMEOUT, |t| t * 2 + DEFAULT_API_TIMEOUT);
+            handle_api_request_with_timeout(r, flow::delete_flow, timeout)
+        });
+    v1_app
+        .at("/flows/:id/pipelines/:pipeline")
+        .get(|r| handle_api_request(r, flow::get_flow_pipeline))
+        .put(|r| handle_api_request(r, flow::put_flow_pipeline));
+    v1_app
+        .at("/flows/:id/pipelines/:pipeline/tap/:port")
+        .get(WebSocket::new(tap::tap_pipeline));
     v1_app
         .at("/flows/:id/connectors")
         .get(|r| handle_api_request(r, flow::get_flow_connectors));
@@ -195,9 +223,16 @@ pub fn serve(host: String, world: &World) -> JoinHandle<Result<()>> {
         .at("/flows/:id/connectors/:connector")
         .get(|r| handle_api_request(r, flow::get_flow_connector_status))
         .patch(|r| handle_api_request(r, flow::patch_flow_connector_status));
+    v1_app
+        .at("/flows/:id/connectors/:connector/tap/:port")
+        .get(WebSocket::new(tap::tap_connector));
 
-    let mut app = tide::Server::new();
+    let mut app = tide::Server::with_state(State {
+        world: world.clone(),
+    });
     app.at("/v1").nest(v1_app);
+    app.at("/metrics")
+        .get(|r| handle_api_request(r, metrics::get));
 
     // spawn API listener
     async_std::task::spawn(async move {
@@ -219,6 +254,7 @@ mod tests {
     use async_std::net::TcpListener;
     use http_types::Url;
     use simd_json::ValueAccess;
+    use tremor_pipeline::{GraphReport, MetricsMsg, METRICS_CHANNEL};
     use tremor_runtime::{
         errors::Result as RuntimeResult,
         instance::State as InstanceState,
@@ -240,6 +276,8 @@ mod tests {
         let config = WorldConfig {
             qsize: 16,
             debug_connectors: true,
+            metrics_endpoint: true,
+            plugin_dir: None,
         };
         let (world, world_handle) = World::start(config).await?;
 
@@ -541,6 +579,200 @@ mod tests {
             body
         );
 
+        // swap the query of a pipeline
+        let body = client
+            .put("/v1/flows/api_test/pipelines/main")
+            .body_string("select {\"swapped\": event} from in into out;".to_string())
+            .content_type(ResourceType::Trickle.as_str())
+            .await?
+            .body_json::<StatusReport>()
+            .await?;
+        assert_eq!("api_test".to_string(), body.alias);
+        assert_eq!(InstanceState::Running, body.status);
+
+        let mut res = client
+            .put("/v1/flows/api_test/pipelines/i_do_not_exist")
+            .body_string("select event from in into out;".to_string())
+            .await?;
+        assert_eq!(StatusCode::NotFound, res.status());
+        let _ = res.body_bytes().await?; // consume the body
+
+        let mut res = client
+            .put("/v1/flows/api_test/pipelines/main")
+            .body_string("snot badger".to_string())
+            .await?;
+        assert_eq!(StatusCode::BadRequest, res.status());
+        let _ = res.body_bytes().await?; // consume the body
+
+        // the connectors are still connected
+        let body = client
+            .get("/v1/flows/api_test/connectors/my_null")
+            .await?
+            .body_json::<StaticValue>()
+            .await?
+            .into_value();
+        assert_eq!(Some("connected"), body.get_str("connectivity"));
+
+        // introspect the graph of a pipeline
+        let body = client
+            .get("/v1/flows/api_test/pipelines/main")
+            .await?
+            .body_json::<GraphReport>()
+            .await?;
+        assert_eq!("main".to_string(), body.id);
+        assert_eq!(vec!["in".to_string()], body.inputs);
+        assert!(body.outputs.contains(&"out".to_string()));
+        assert!(body.nodes.iter().any(|node| node.kind == "select"));
+        assert!(!body.edges.is_empty());
+
+        let mut res = client
+            .get("/v1/flows/api_test/pipelines/main?format=dot")
+            .await?;
+        assert_eq!(StatusCode::Ok, res.status());
+        assert!(res.body_string().await?.starts_with("digraph"));
+
+        let mut res = client
+            .get("/v1/flows/api_test/pipelines/main?format=snot")
+            .await?;
+        assert_eq!(StatusCode::BadRequest, res.status());
+        let _ = res.body_bytes().await?; // consume the body
+
+        let mut res = client
+            .get("/v1/flows/api_test/pipelines/i_do_not_exist")
+            .await?;
+        assert_eq!(StatusCode::NotFound, res.status());
+        let _ = res.body_bytes().await?; // consume the body
+
+        // deploy another flow
+        let src = r#"
+        define flow api_test_2
+        flow
+            define pipeline main
+            pipeline
+                select event from in into out;
+            end;
+            create pipeline main;
+
+            define connector my_null from `null`;
+            create connector my_null;
+
+            connect /connector/my_null to /pipeline/main;
+            connect /pipeline/main to /connector/my_null;
+        end;
+        deploy flow api_test_2;
+        "#;
+        let body = client
+            .post("/v1/flows")
+            .body_string(src.to_string())
+            .content_type(ResourceType::Troy.as_str())
+            .await?
+            .body_json::<Vec<StatusReport>>()
+            .await?;
+        assert_eq!(1, body.len());
+        assert_eq!("api_test_2".to_string(), body[0].alias);
+        assert_eq!(1, body[0].connectors.len());
+
+        // deploying it again is a conflict
+        let mut res = client
+            .post("/v1/flows")
+            .body_string(src.to_string())
+            .await?;
+        assert_eq!(StatusCode::Conflict, res.status());
+        let _ = res.body_bytes().await?; // consume the body
+
+        // invalid troy
+        let mut res = client
+            .post("/v1/flows")
+            .body_string("snot badger".to_string())
+            .await?;
+        assert_eq!(StatusCode::BadRequest, res.status());
+        let _ = res.body_bytes().await?; // consume the body
+
+        // flows deployed together are rolled back if one of them fails to start
+        let rollback_src = r#"
+        define flow api_test_3
+        flow
+            define connector my_null from `null`;
+            create connector my_null;
+        end;
+        define flow api_test_4
+        flow
+            define connector my_snot from snot;
+            create connector my_snot;
+        end;
+        deploy flow api_test_3;
+        deploy flow api_test_4;
+        "#;
+        let mut res = client
+            .post("/v1/flows")
+            .body_string(rollback_src.to_string())
+            .await?;
+        assert!(!res.status().is_success());
+        let _ = res.body_bytes().await?; // consume the body
+        let mut res = client.get("/v1/flows/api_test_3").await?;
+        assert_eq!(StatusCode::NotFound, res.status());
+        let _ = res.body_bytes().await?; // consume the body
+
+        let body = client
+            .get("/v1/flows")
+            .await?
+            .body_json::<Vec<StatusReport>>()
+            .await?;
+        assert_eq!(2, body.len());
+
+        // undeploy it
+        let body = client
+            .delete("/v1/flows/api_test_2?timeout=1000")
+            .await?
+            .body_json::<StatusReport>()
+            .await?;
+        assert_eq!("api_test_2".to_string(), body.alias);
+
+        let mut res = client.get("/v1/flows/api_test_2").await?;
+        assert_eq!(StatusCode::NotFound, res.status());
+        let _ = res.body_bytes().await?; // consume the body
+
+        let mut res = client.delete("/v1/flows/api_test_2").await?;
+        assert_eq!(StatusCode::NotFound, res.status());
+        let _ = res.body_bytes().await?; // consume the body
+
+        let body = client
+            .get("/v1/flows")
+            .await?
+            .body_json::<Vec<StatusReport>>()
+            .await?;
+        assert_eq!(1, body.len());
+        assert_eq!("api_test".to_string(), body[0].alias);
+
+        // metrics are exposed in the prometheus text format
+        let metric = literal!({
+            "measurement": "connector_events",
+            "tags": {"flow": "api_test", "connector": "my_null", "port": "in"},
+            "fields": {"count": 42},
+            "timestamp": 1
+        });
+        METRICS_CHANNEL
+            .tx()
+            .try_broadcast(MetricsMsg::new((metric, literal!({})).into(), None))?;
+        let start = Instant::now();
+        let expected =
+            r#"tremor_connector_events_total{connector="my_null",flow="api_test",port="in"} 42"#;
+        loop {
+            let mut res = client.get("/metrics").await?;
+            assert_eq!(StatusCode::Ok, res.status());
+            assert_eq!(
+                Some("text/plain; version=0.0.4"),
+                res.header(headers::CONTENT_TYPE).map(|h| h.as_str())
+            );
+            let body = res.body_string().await?;
+            if body.contains(expected) {
+                assert!(body.contains("# TYPE tremor_connector_events_total counter"));
+                break;
+            } else if start.elapsed() > Duration::from_secs(2) {
+                assert!(false, "Timeout waiting for metrics: {body}");
+            }
+        }
+
         // cleanup
         world.stop(ShutdownMode::Graceful).await?;
         world_handle.cancel().await;
@@ -15,7 +15,34 @@
 //! Flow API
 
 use crate::api::prelude::*;
-use tremor_runtime::instance::State;
+use std::time::Duration;
+use tremor_runtime::{
+    instance::State,
+    system::{flow::StatusReport, World},
+};
+use tremor_script::{
+    deploy::Deploy,
+    highlighter::{Dumb as ToStringHighlighter, Highlighter},
+    FN_REGISTRY,
+};
+
+/// default timeout for draining and for stopping a flow removed via the API
+const FLOW_STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// query parameters for removing a flow
+#[derive(Deserialize)]
+struct StopQuery {
+    /// timeout in milliseconds for each of draining and stopping the flow
+    timeout: Option<u64>,
+}
+
+/// the timeout for each of draining and stopping the flow removed by `req`
+pub(crate) fn stop_timeout(req: &Request) -> Result<Duration> {
+    let query: StopQuery = req.query()?;
+    Ok(query
+        .timeout
+        .map_or(FLOW_STOP_TIMEOUT, Duration::from_millis))
+}
 
 pub(crate) async fn list_flows(req: Request) -> Result<Response> {
     let world = &req.state().world;
@@ -36,6 +63,82 @@ pub(crate) async fn get_flow(req: Request) -> Result<Response> {
     reply(&req, report, StatusCode::Ok)
 }
 
+/// parse the troy source and extract all flows it deploys
+fn parse_flows(src: &str) -> Result<Vec<tremor_script::ast::DeployFlow<'static>>> {
+    let aggr_reg = tremor_script::aggr_registry();
+    let fn_reg = FN_REGISTRY.read().map_err(|e| {
+        Error::new(
+            StatusCode::InternalServerError,
+            format!("Locking error: {e}"),
+        )
+    })?;
+    let deployable = Deploy::parse(src, &*fn_reg, &aggr_reg).map_err(|e| {
+        let mut h = ToStringHighlighter::new();
+        if h.format_error(&e).and_then(|_| h.finalize()).is_ok() {
+            Error::bad_request(h.to_string())
+        } else {
+            Error::bad_request(e.to_string())
+        }
+    })?;
+    Ok(deployable.iter_flows().cloned().collect())
+}
+
+pub(crate) async fn create_flows(mut req: Request) -> Result<Response> {
+    let src = req.body_string().await?;
+    let flows = parse_flows(&src)?;
+    if flows.is_empty() {
+        return Err(Error::bad_request("No flow deployed".into()));
+    }
+    let world = &req.state().world;
+    // fail before deploying anything if any of the flows is already deployed
+    for flow in &flows {
+        if world.get_flow(flow.instance_alias.clone()).await.is_ok() {
+            return Err(Error::new(
+                StatusCode::Conflict,
+                format!("Flow {} already deployed", flow.instance_alias),
+            ));
+        }
+    }
+    let mut started = Vec::with_capacity(flows.len());
+    let mut result = Vec::with_capacity(flows.len());
+    for flow in &flows {
+        if let Err(e) = start_flow(world, flow, &mut started, &mut result).await {
+            // deploy all flows or none of them
+            for flow_id in started {
+                if let Err(e) = world.stop_flow(flow_id.clone(), FLOW_STOP_TIMEOUT).await {
+                    warn!("[API POST /v1/flows] Error rolling back flow {flow_id}: {e}");
+                }
+            }
+            return Err(e);
+        }
+    }
+    reply(&req, result, StatusCode::Created)
+}
+
+/// starts `flow`, adding its id to `started` and its status to `result`
+async fn start_flow(
+    world: &World,
+    flow: &tremor_script::ast::DeployFlow<'static>,
+    started: &mut Vec<String>,
+    result: &mut Vec<StatusReport>,
+) -> Result<()> {
+    world.start_flow(flow).await?;
+    started.push(flow.instance_alias.clone());
+    let flow = world.get_flow(flow.instance_alias.clone()).await?;
+    result.push(flow.report_status().await?);
+    Ok(())
+}
+
+pub(crate) async fn delete_flow(req: Request) -> Result<Response> {
+    let world = &req.state().world;
+    let flow_id = req.param("id")?.to_string();
+    let flow = world.get_flow(flow_id.clone()).await?;
+    let timeout = stop_timeout(&req)?;
+    let report = flow.report_status().await?;
+    world.stop_flow(flow_id, timeout).await?;
+    reply(&req, report, StatusCode::Ok)
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct PatchStatus {
     pub(crate) status: State,
@@ -72,6 +175,46 @@ pub(crate) async fn patch_flow_status(mut req: Request) -> Result<Response> {
     reply(&req, report, StatusCode::Ok)
 }
 
+pub(crate) async fn put_flow_pipeline(mut req: Request) -> Result<Response> {
+    let src = req.body_string().await?;
+    let world = &req.state().world;
+    let flow_id = req.param("id")?.to_string();
+    let pipeline_id = req.param("pipeline")?.to_string();
+    world
+        .swap_pipeline(flow_id.clone(), pipeline_id, &src)
+        .await?;
+    let flow = world.get_flow(flow_id).await?;
+    let report = flow.report_status().await?;
+    reply(&req, report, StatusCode::Ok)
+}
+
+/// query parameters for getting a pipeline
+#[derive(Deserialize)]
+struct PipelineQuery {
+    /// `dot` to get the graph in the graphviz dot format
+    format: Option<String>,
+}
+
+pub(crate) async fn get_flow_pipeline(req: Request) -> Result<Response> {
+    let query: PipelineQuery = req.query()?;
+    let world = &req.state().world;
+    let flow_id = req.param("id")?.to_string();
+    let pipeline_id = req.param("pipeline")?.to_string();
+    let flow = world.get_flow(flow_id).await?;
+    let pipeline = flow.get_pipeline(pipeline_id).await?;
+    let report = pipeline.report_graph().await?;
+    match query.format.as_deref() {
+        None => reply(&req, report, StatusCode::Ok),
+        Some("dot") => Ok(Response::builder(StatusCode::Ok)
+            .header(headers::CONTENT_TYPE, "text/vnd.graphviz")
+            .body(report.dot)
+            .build()),
+        Some(other) => Err(Error::bad_request(format!(
+            "Unsupported pipeline format {other}, expected `dot`"
+        ))),
+    }
+}
+
 pub(crate) async fn get_flow_connectors(req: Request) -> Result<Response> {
     let world = &req.state().world;
     let flow_id = req.param("id")?.to_string();
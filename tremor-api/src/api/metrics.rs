@@ -0,0 +1,31 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::api::prelude::*;
+
+/// content type of the prometheus text exposition format
+const PROMETHEUS_TEXT: &str = "text/plain; version=0.0.4";
+
+pub(crate) async fn get(req: Request) -> Result<Response> {
+    let metrics = req.state().world.render_metrics().await.ok_or_else(|| {
+        Error::new(
+            StatusCode::NotFound,
+            "Metrics endpoint is disabled".to_string(),
+        )
+    })?;
+    Ok(Response::builder(StatusCode::Ok)
+        .header(headers::CONTENT_TYPE, PROMETHEUS_TEXT)
+        .body(metrics)
+        .build())
+}
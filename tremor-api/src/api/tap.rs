@@ -0,0 +1,117 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Live taps on pipeline and connector ports, streamed over WebSocket
+//!
+//! Every tapped event is sent as a JSON text message. If the tap can't be attached,
+//! a single error message of the form `{"code": <status>, "error": <msg>}` is sent
+//! before the connection is closed.
+
+use crate::api::prelude::*;
+use async_std::{channel::Receiver, prelude::*, task};
+use tide_websockets::{Message, WebSocketConnection};
+use tremor_runtime::tap::Tap;
+
+#[derive(Deserialize, Debug)]
+struct TapQuery {
+    /// only emit every n-th matching event
+    sample: Option<u64>,
+    /// tremor-script expression events need to evaluate to `true` for
+    filter: Option<String>,
+}
+
+fn new_tap(req: &Request) -> Result<(Tap, Receiver<String>)> {
+    let query: TapQuery = req.query()?;
+    Ok(Tap::new(
+        query.sample.unwrap_or(1),
+        query.filter.as_deref(),
+    )?)
+}
+
+async fn tap_pipeline_port(req: &Request) -> Result<Receiver<String>> {
+    let (tap, rx) = new_tap(req)?;
+    let world = &req.state().world;
+    let flow_id = req.param("id")?.to_string();
+    let pipeline_id = req.param("pipeline")?.to_string();
+    let flow = world.get_flow(flow_id).await?;
+    let pipeline = flow.get_pipeline(pipeline_id).await?;
+    pipeline.tap(req.param("port")?, tap).await?;
+    Ok(rx)
+}
+
+async fn tap_connector_port(req: &Request) -> Result<Receiver<String>> {
+    let (tap, rx) = new_tap(req)?;
+    let world = &req.state().world;
+    let flow_id = req.param("id")?.to_string();
+    let connector_id = req.param("connector")?.to_string();
+    let flow = world.get_flow(flow_id).await?;
+    let connector = flow.get_connector(connector_id).await?;
+    connector.tap(req.param("port")?, tap).await?;
+    Ok(rx)
+}
+
+pub(crate) async fn tap_pipeline(req: Request, conn: WebSocketConnection) -> tide::Result<()> {
+    let tapped = tap_pipeline_port(&req).await;
+    stream(&req, conn, tapped).await
+}
+
+pub(crate) async fn tap_connector(req: Request, conn: WebSocketConnection) -> tide::Result<()> {
+    let tapped = tap_connector_port(&req).await;
+    stream(&req, conn, tapped).await
+}
+
+/// sends the tapped events to the client until either the tapped pipeline or connector
+/// is stopped or the client goes away
+async fn stream(
+    req: &Request,
+    conn: WebSocketConnection,
+    tapped: Result<Receiver<String>>,
+) -> tide::Result<()> {
+    match tapped {
+        Ok(rx) => {
+            // the client never sends anything but a close frame, watch for it so the tap is
+            // removed even if no event passes it anymore
+            let client = {
+                let mut conn = conn.clone();
+                let rx = rx.clone();
+                task::spawn(async move {
+                    while let Some(Ok(msg)) = conn.next().await {
+                        if let Message::Close(_) = msg {
+                            break;
+                        }
+                    }
+                    // closing the receiver ends the stream below and removes the tap
+                    rx.close();
+                })
+            };
+            while let Ok(event) = rx.recv().await {
+                if conn.send_string(event).await.is_err() {
+                    // the client is gone, dropping the receiver removes the tap
+                    client.cancel().await;
+                    return Ok(());
+                }
+            }
+            // the watcher only finishes once the client is gone, nothing left to close then
+            if client.cancel().await.is_some() {
+                return Ok(());
+            }
+        }
+        Err(e) => {
+            error!("[API TAP {}] Error: {e}", req.url().path());
+            conn.send_string(simd_json::to_string(&e)?).await?;
+        }
+    }
+    conn.send(Message::Close(None)).await?;
+    Ok(())
+}
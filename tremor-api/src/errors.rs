@@ -136,6 +136,31 @@ impl From<TremorError> for Error {
             ErrorKind::FlowNotFound(id) => {
                 Error::new(StatusCode::NotFound, format!("Flow {id} not found"))
             }
+            ErrorKind::DuplicateFlow(id) => {
+                Error::new(StatusCode::Conflict, format!("Flow {id} already deployed"))
+            }
+            ErrorKind::FlowStopping(id) => {
+                Error::new(StatusCode::Conflict, format!("Flow {id} is still stopping"))
+            }
+            ErrorKind::DeployFlowError(id, err) => Error::new(
+                StatusCode::BadRequest,
+                format!("Error deploying Flow {id}: {err}"),
+            ),
+            ErrorKind::PipelineNotFound(flow_id, id) => Error::new(
+                StatusCode::NotFound,
+                format!("Pipeline {id} not found in Flow {flow_id}"),
+            ),
+            ErrorKind::PipelineSwapError(id, err) => Error::new(
+                StatusCode::BadRequest,
+                format!("Error swapping the query of Pipeline {id}: {err}"),
+            ),
+            ErrorKind::InvalidTap(id, port) => Error::new(
+                StatusCode::NotFound,
+                format!("{id} has no output port {port} to tap"),
+            ),
+            ErrorKind::InvalidTapFilter(err) => {
+                Error::new(StatusCode::BadRequest, format!("Invalid tap filter: {err}"))
+            }
             ErrorKind::ConnectorNotFound(flow_id, id) => Error::new(
                 StatusCode::NotFound,
                 format!("Connector {id} not found in Flow {flow_id}"),
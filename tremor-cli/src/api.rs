@@ -1,4 +1,4 @@
-// Copyright 2020-2021, The Tremor Team
+// Copyright 2020-2022, The Tremor Team
 //
 // Licensed under the Apache License, Version 2.0 (the "License");
 // you may not use this file except in compliance with the License.
@@ -13,317 +13,362 @@
 // limitations under the License.
 
 use crate::{
+    cli::{
+        Api, ApiCommand, ApiFormat, ConnectorCommand, FlowCommand, TapCommand, TapOptions,
+        TargetCommand,
+    },
     errors::{Error, Result},
-    util::load_trickle,
+    util::slurp_string,
 };
-use clap::{self, ArgMatches};
-use halfbrown::HashMap;
-use http_types::{headers, StatusCode};
-use tremor_script::prelude::*;
-
-use crate::util::{accept, content_type, load, save_config, ser, TremorApp};
-
-pub(crate) async fn run_cmd(mut app: TremorApp, cmd: &ArgMatches) -> Result<()> {
-    if let Some(matches) = cmd.subcommand_matches("version") {
-        conductor_version_cmd(&app, matches).await
-    } else if let Some(matches) = cmd.subcommand_matches("binding") {
-        conductor_binding_cmd(&app, matches).await
-    } else if let Some(matches) = cmd.subcommand_matches("pipeline") {
-        conductor_pipeline_cmd(&app, matches).await
-    } else if let Some(matches) = cmd.subcommand_matches("onramp") {
-        conductor_onramp_cmd(&app, matches).await
-    } else if let Some(matches) = cmd.subcommand_matches("offramp") {
-        conductor_offramp_cmd(&app, matches).await
-    } else if let Some(matches) = cmd.subcommand_matches("target") {
-        conductor_target_cmd(&mut app, matches)
-    } else {
-        Err(Error::from("Invalid command"))
+use async_tungstenite::{async_std::connect_async, tungstenite::Message};
+use futures::StreamExt;
+use http_types::headers;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tremor_value::prelude::*;
+use url::Url;
+
+/// the target used if none is given
+const DEFAULT_TARGET: &str = "default";
+/// the url used if no target is given and the `default` target is not configured
+const DEFAULT_URL: &str = "http://localhost:9898";
+
+impl ApiFormat {
+    fn mime(self) -> &'static str {
+        match self {
+            ApiFormat::Json => "application/json",
+            ApiFormat::Yaml => "application/yaml",
+        }
     }
 }
 
-/////////////////////////
-// API resource models //
-/////////////////////////
-
-#[derive(Deserialize, Debug, Serialize)]
-struct Version {
-    version: String,
+/// Named tremor servers the api commands can be sent to
+#[derive(Deserialize, Debug, Default, Serialize, PartialEq)]
+pub(crate) struct TargetConfig {
+    /// target id to the url of the API of the tremor server
+    #[serde(default)]
+    pub(crate) targets: BTreeMap<String, String>,
 }
 
-#[derive(Deserialize, Debug, Serialize)]
-struct Binding {
-    id: String,
-    description: String,
-    links: HashMap<String, Vec<String>>,
-}
+impl TargetConfig {
+    /// the path of the config file, `~/.tremor/config.yaml` unless given explicitly
+    fn path(config: Option<&str>) -> Result<PathBuf> {
+        if let Some(config) = config {
+            Ok(PathBuf::from(config))
+        } else {
+            let home = std::env::var_os("HOME")
+                .ok_or_else(|| Error::from("HOME not set, please provide a config file"))?;
+            Ok(PathBuf::from(home).join(".tremor").join("config.yaml"))
+        }
+    }
 
-/////////////////////////
-// API host targetting //
-/////////////////////////
+    fn load(path: &Path) -> Result<Self> {
+        if path.exists() {
+            Ok(serde_yaml::from_str(&slurp_string(path)?)?)
+        } else {
+            Ok(Self::default())
+        }
+    }
 
-fn conductor_target_cmd(app: &mut TremorApp, cmd: &ArgMatches) -> Result<()> {
-    if cmd.subcommand_matches("list").is_some() {
-        conductor_target_list_cmd(app);
-        Ok(())
-    } else if let Some(matches) = cmd.subcommand_matches("create") {
-        conductor_target_create_cmd(app, matches)
-    } else if let Some(matches) = cmd.subcommand_matches("delete") {
-        conductor_target_delete_cmd(app, matches)
-    } else {
-        println!("{}", simd_json::to_string(&app.config.instances)?);
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_yaml::to_string(self)?)?;
         Ok(())
     }
-}
-
-/////////////////////////////
-// API endpoint targetting //
-/////////////////////////////
-
-fn conductor_target_list_cmd(app: &TremorApp) {
-    println!(
-        "{:?}",
-        app.config
-            .instances
-            .keys()
-            .cloned()
-            .collect::<Vec<String>>()
-    );
-}
 
-fn conductor_target_create_cmd(app: &mut TremorApp, cmd: &ArgMatches) -> Result<()> {
-    let id = cmd.value_of("TARGET_ID").ok_or("TARGET_ID not provided")?;
-    let path_to_file = cmd.value_of("SOURCE").ok_or("SOURCE not provided")?;
-    let json = load(path_to_file)?;
-    let endpoints: Vec<String> = json
-        .as_array()
-        .ok_or_else(|| Error::from("Invalid Configuration"))?
-        .iter()
-        .filter_map(|v| v.as_str().map(String::from))
-        .collect();
-    app.config.instances.insert(id.to_string(), endpoints);
-    save_config(&app.config)
-}
-
-fn conductor_target_delete_cmd(app: &mut TremorApp, cmd: &ArgMatches) -> Result<()> {
-    let id = cmd.value_of("TARGET_ID").ok_or("TARGET_ID not provided")?;
-    app.config.instances.remove(&id.to_string());
-    save_config(&app.config)
-}
-
-//////////////////
-// API Version  //
-//////////////////
-
-async fn conductor_version_cmd(app: &TremorApp, cmd: &ArgMatches) -> Result<()> {
-    let endpoint = app.endpoint("version")?;
-    let mut response = surf::get(endpoint).await?;
-    let version: Version = response.body_json().await?;
-    println!(
-        "{}",
-        match cmd.value_of("format") {
-            Some("yaml") => serde_yaml::to_string(&version)?,
-            _ => simd_json::to_string(&version)?,
+    /// the base url of the API of `target`
+    fn url(&self, target: Option<&str>) -> Result<Url> {
+        let url = match target {
+            Some(target) => self
+                .targets
+                .get(target)
+                .ok_or_else(|| Error::from(format!("Unknown target {target}")))?,
+            None => self
+                .targets
+                .get(DEFAULT_TARGET)
+                .map_or(DEFAULT_URL, String::as_str),
+        };
+        let mut url = Url::parse(url)?;
+        // ensure we join paths onto the url and don't replace its last segment
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
         }
-    );
-    Ok(())
-}
-
-//////////////////////////////
-// API Pipeline subcommands //
-//////////////////////////////
-
-async fn conductor_pipeline_cmd(app: &TremorApp, cmd: &ArgMatches) -> Result<()> {
-    if cmd.subcommand_matches("list").is_some() {
-        conductor_list_cmd(app, "pipeline").await
-    } else if let Some(matches) = cmd.subcommand_matches("fetch") {
-        conductor_get_cmd(app, matches, "pipeline").await
-    } else if let Some(matches) = cmd.subcommand_matches("delete") {
-        conductor_delete_cmd(app, matches, "pipeline").await
-    } else if let Some(matches) = cmd.subcommand_matches("create") {
-        conductor_create_cmd_trickle(app, matches, "pipeline").await
-    } else if let Some(matches) = cmd.subcommand_matches("instance") {
-        conductor_instance_cmd(app, matches, "pipeline").await
-    } else {
-        Err("Invalid command".into())
+        Ok(url)
     }
 }
 
-/////////////////////////////
-// API Binding subcommands //
-/////////////////////////////
+/// Client for the API of a single tremor server
+struct Client {
+    base: Url,
+    format: ApiFormat,
+}
 
-async fn conductor_binding_cmd(app: &TremorApp, cmd: &ArgMatches) -> Result<()> {
-    if cmd.subcommand_matches("list").is_some() {
-        conductor_list_cmd(app, "binding").await
-    } else if let Some(matches) = cmd.subcommand_matches("fetch") {
-        conductor_get_cmd(app, matches, "binding").await
-    } else if let Some(matches) = cmd.subcommand_matches("delete") {
-        conductor_delete_cmd(app, matches, "binding").await
-    } else if let Some(matches) = cmd.subcommand_matches("create") {
-        conductor_create_cmd(app, matches, "binding").await
-    } else if let Some(matches) = cmd.subcommand_matches("instance") {
-        conductor_instance_cmd(app, matches, "binding").await
-    } else if let Some(matches) = cmd.subcommand_matches("activate") {
-        conductor_binding_activate_cmd(app, matches).await
-    } else if let Some(matches) = cmd.subcommand_matches("deactivate") {
-        conductor_binding_deactivate_cmd(app, matches).await
-    } else {
-        Err("Invalid command".into())
+impl Client {
+    async fn get(&self, path: &str) -> Result<()> {
+        let request = surf::get(self.base.join(path)?);
+        self.send(request).await
     }
-}
 
-async fn conductor_binding_activate_cmd(app: &TremorApp, cmd: &ArgMatches) -> Result<()> {
-    let a_id = cmd
-        .value_of("ARTEFACT_ID")
-        .ok_or("ARTEFACT_ID not provided")?;
-    let s_id = cmd
-        .value_of("INSTANCE_ID")
-        .ok_or("INSTANCE_ID not provided")?;
-    let endpoint = app.endpoint_id_instance("binding", a_id, s_id)?;
-    let path_to_file = cmd.value_of("SOURCE").ok_or("SOURCE not provided")?;
-    let json = load(path_to_file)?;
-    let ser = ser(app, &json)?;
-    let response = surf::post(&endpoint)
-        .header(headers::CONTENT_TYPE, content_type(app))
-        .header(headers::ACCEPT, accept(app))
-        .body(ser)
-        .await?;
-    handle_response(response).await
-}
+    async fn delete(&self, path: &str) -> Result<()> {
+        let request = surf::delete(self.base.join(path)?);
+        self.send(request).await
+    }
 
-async fn conductor_binding_deactivate_cmd(app: &TremorApp, cmd: &ArgMatches) -> Result<()> {
-    let a_id = cmd
-        .value_of("ARTEFACT_ID")
-        .ok_or("ARTEFACT_ID not provided")?;
-    let s_id = cmd
-        .value_of("INSTANCE_ID")
-        .ok_or("INSTANCE_ID not provided")?;
-    let endpoint = app.endpoint_id_instance("binding", a_id, s_id)?;
+    async fn post(&self, path: &str, content_type: &str, body: String) -> Result<()> {
+        let request = surf::post(self.base.join(path)?)
+            .header(headers::CONTENT_TYPE, content_type)
+            .body(body);
+        self.send(request).await
+    }
 
-    let response = surf::delete(&endpoint).await?;
-    handle_response(response).await
-}
+    async fn patch_status(&self, path: &str, status: &str) -> Result<()> {
+        let request = surf::patch(self.base.join(path)?)
+            .header(headers::CONTENT_TYPE, ApiFormat::Json.mime())
+            .body(format!(r#"{{"status":"{status}"}}"#));
+        self.send(request).await
+    }
 
-//////////////////////////
-// API Offramp commands //
-//////////////////////////
+    /// sends the request and prints the response body
+    async fn send(&self, request: surf::RequestBuilder) -> Result<()> {
+        let mut response = request.header(headers::ACCEPT, self.format.mime()).await?;
+        let body = response.body_string().await?;
+        let status = response.status();
+        if status.is_success() {
+            println!("{body}");
+            Ok(())
+        } else {
+            eprintln!("{body}");
+            Err(format!("{} {}", status, status.canonical_reason()).into())
+        }
+    }
 
-async fn conductor_offramp_cmd(app: &TremorApp, cmd: &ArgMatches) -> Result<()> {
-    if cmd.subcommand_matches("list").is_some() {
-        conductor_list_cmd(app, "offramp").await
-    } else if let Some(matches) = cmd.subcommand_matches("fetch") {
-        conductor_get_cmd(app, matches, "offramp").await
-    } else if let Some(matches) = cmd.subcommand_matches("delete") {
-        conductor_delete_cmd(app, matches, "offramp").await
-    } else if let Some(matches) = cmd.subcommand_matches("create") {
-        conductor_create_cmd(app, matches, "offramp").await
-    } else if let Some(matches) = cmd.subcommand_matches("instance") {
-        conductor_instance_cmd(app, matches, "offramp").await
-    } else {
-        Err("Invalid command".into())
+    /// connects to the tap websocket at `path` and prints every tapped event
+    async fn tap(&self, path: &str, options: &TapOptions) -> Result<()> {
+        let mut url = self.base.join(path)?;
+        let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+        url.set_scheme(scheme)
+            .map_err(|_| Error::from(format!("Can't tap {url}")))?;
+        url.query_pairs_mut()
+            .append_pair("sample", &options.sample.to_string());
+        if let Some(filter) = &options.filter {
+            url.query_pairs_mut().append_pair("filter", filter);
+        }
+        let (mut ws, _) = connect_async(url).await?;
+        while let Some(msg) = ws.next().await {
+            match msg? {
+                Message::Text(text) => {
+                    // tapped events never have a top level `code`, errors always do
+                    let mut bytes = text.clone().into_bytes();
+                    let value = tremor_value::parse_to_value(&mut bytes)?;
+                    if let (Some(code), Some(error)) =
+                        (value.get_u64("code"), value.get_str("error"))
+                    {
+                        return Err(format!("{code}: {error}").into());
+                    }
+                    println!("{text}");
+                }
+                Message::Close(_) => break,
+                _ => (),
+            }
+        }
+        Ok(())
     }
 }
 
-/////////////////////////
-// API Onramp commands //
-/////////////////////////
-
-async fn conductor_onramp_cmd(app: &TremorApp, cmd: &ArgMatches) -> Result<()> {
-    if cmd.subcommand_matches("list").is_some() {
-        conductor_list_cmd(app, "onramp").await
-    } else if let Some(matches) = cmd.subcommand_matches("fetch") {
-        conductor_get_cmd(app, matches, "onramp").await
-    } else if let Some(matches) = cmd.subcommand_matches("delete") {
-        conductor_delete_cmd(app, matches, "onramp").await
-    } else if let Some(matches) = cmd.subcommand_matches("create") {
-        conductor_create_cmd(app, matches, "onramp").await
-    } else if let Some(matches) = cmd.subcommand_matches("instance") {
-        conductor_instance_cmd(app, matches, "onramp").await
-    } else {
-        Err("Invalid command".into())
+impl Api {
+    pub(crate) async fn run(&self) -> Result<()> {
+        let path = TargetConfig::path(self.config.as_deref())?;
+        let mut config = TargetConfig::load(&path)?;
+        if let ApiCommand::Target { command } = &self.command {
+            return command.run(&mut config, &path);
+        }
+        let client = Client {
+            base: config.url(self.target.as_deref())?,
+            format: self.format,
+        };
+        match &self.command {
+            ApiCommand::Version => client.get("v1/version").await,
+            ApiCommand::Status => client.get("v1/status").await,
+            ApiCommand::Flow { command } => command.run(&client).await,
+            ApiCommand::Connector { command } => command.run(&client).await,
+            ApiCommand::Tap { command } => command.run(&client).await,
+            ApiCommand::Target { .. } => Ok(()),
+        }
     }
 }
 
-/////////////////
-// Shared code //
-/////////////////
-
-async fn conductor_get_cmd(app: &TremorApp, cmd: &ArgMatches, endpoint: &str) -> Result<()> {
-    let id = cmd
-        .value_of("ARTEFACT_ID")
-        .ok_or("ARTEFACT_ID not provided")?;
-    let endpoint = app.endpoint_id(endpoint, id)?;
-
-    let response = surf::get(&endpoint).await?;
-    handle_response(response).await
+impl TargetCommand {
+    fn run(&self, config: &mut TargetConfig, path: &Path) -> Result<()> {
+        match self {
+            TargetCommand::List => {
+                for (id, url) in &config.targets {
+                    println!("{id}: {url}");
+                }
+                Ok(())
+            }
+            TargetCommand::Create { id, url } => {
+                Url::parse(url)?;
+                config.targets.insert(id.clone(), url.clone());
+                config.save(path)
+            }
+            TargetCommand::Delete { id } => {
+                if config.targets.remove(id).is_none() {
+                    return Err(format!("Unknown target {id}").into());
+                }
+                config.save(path)
+            }
+        }
+    }
 }
 
-async fn conductor_list_cmd(app: &TremorApp, endpoint: &str) -> Result<()> {
-    let endpoint = app.endpoint(endpoint)?;
-    let response = surf::get(&endpoint).await?;
-    handle_response(response).await
+impl FlowCommand {
+    async fn run(&self, client: &Client) -> Result<()> {
+        match self {
+            FlowCommand::List => client.get("v1/flows").await,
+            FlowCommand::Get { id } => client.get(&format!("v1/flows/{id}")).await,
+            FlowCommand::Create { source } => {
+                let src = slurp_string(source)?;
+                client.post("v1/flows", "application/vnd.troy", src).await
+            }
+            FlowCommand::Delete { id } => client.delete(&format!("v1/flows/{id}")).await,
+            FlowCommand::Pause { id } => {
+                client
+                    .patch_status(&format!("v1/flows/{id}"), "paused")
+                    .await
+            }
+            FlowCommand::Resume { id } => {
+                client
+                    .patch_status(&format!("v1/flows/{id}"), "running")
+                    .await
+            }
+        }
+    }
 }
 
-async fn conductor_create_cmd(app: &TremorApp, cmd: &ArgMatches, endpoint: &str) -> Result<()> {
-    let endpoint = app.endpoint(endpoint)?;
-    let path_to_file = cmd.value_of("SOURCE").ok_or("SOURCE not provided")?;
-    let json = load(path_to_file)?;
-    let ser = ser(app, &json)?;
-    let response = surf::post(&endpoint)
-        .header(http_types::headers::CONTENT_TYPE, content_type(app))
-        .header("accept", accept(app))
-        .body(ser)
-        .await;
-    handle_response(response?).await
+impl ConnectorCommand {
+    async fn run(&self, client: &Client) -> Result<()> {
+        match self {
+            ConnectorCommand::List { flow } => {
+                client.get(&format!("v1/flows/{flow}/connectors")).await
+            }
+            ConnectorCommand::Get { flow, connector } => {
+                client
+                    .get(&format!("v1/flows/{flow}/connectors/{connector}"))
+                    .await
+            }
+            ConnectorCommand::Pause { flow, connector } => {
+                client
+                    .patch_status(&format!("v1/flows/{flow}/connectors/{connector}"), "paused")
+                    .await
+            }
+            ConnectorCommand::Resume { flow, connector } => {
+                client
+                    .patch_status(
+                        &format!("v1/flows/{flow}/connectors/{connector}"),
+                        "running",
+                    )
+                    .await
+            }
+        }
+    }
 }
 
-async fn conductor_create_cmd_trickle(
-    app: &TremorApp,
-    cmd: &ArgMatches,
-    endpoint: &str,
-) -> Result<()> {
-    let endpoint = app.endpoint(endpoint)?;
-    let path_to_file = cmd.value_of("SOURCE").ok_or("SOURCE not provided")?;
-    let ser = load_trickle(path_to_file)?;
-    let response = surf::post(&endpoint)
-        .header(http_types::headers::CONTENT_TYPE, "application/vnd.trickle")
-        .header("accept", accept(app))
-        .body(ser)
-        .await;
-    handle_response(response?).await
+impl TapCommand {
+    async fn run(&self, client: &Client) -> Result<()> {
+        match self {
+            TapCommand::Pipeline {
+                flow,
+                pipeline,
+                port,
+                options,
+            } => {
+                client
+                    .tap(
+                        &format!("v1/flows/{flow}/pipelines/{pipeline}/tap/{port}"),
+                        options,
+                    )
+                    .await
+            }
+            TapCommand::Connector {
+                flow,
+                connector,
+                port,
+                options,
+            } => {
+                client
+                    .tap(
+                        &format!("v1/flows/{flow}/connectors/{connector}/tap/{port}"),
+                        options,
+                    )
+                    .await
+            }
+        }
+    }
 }
 
-async fn conductor_delete_cmd(app: &TremorApp, cmd: &ArgMatches, endpoint: &str) -> Result<()> {
-    let id = cmd
-        .value_of("ARTEFACT_ID")
-        .ok_or("ARTEFACT_ID not provided")?;
-    let endpoint = app.endpoint_id(endpoint, id)?;
-    let response = surf::delete(&endpoint).await?;
-    handle_response(response).await
-}
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn target_urls() -> Result<()> {
+        let mut config = TargetConfig::default();
+        assert_eq!("http://localhost:9898/", config.url(None)?.as_str());
+        assert!(config.url(Some("snot")).is_err());
+
+        config
+            .targets
+            .insert("snot".to_string(), "http://snot:1234/badger".to_string());
+        config
+            .targets
+            .insert(DEFAULT_TARGET.to_string(), "https://tremor".to_string());
+        assert_eq!(
+            "http://snot:1234/badger/",
+            config.url(Some("snot"))?.as_str()
+        );
+        assert_eq!(
+            "http://snot:1234/badger/v1/flows",
+            config.url(Some("snot"))?.join("v1/flows")?.as_str()
+        );
+        assert_eq!("https://tremor/", config.url(None)?.as_str());
+        Ok(())
+    }
 
-async fn conductor_instance_cmd(app: &TremorApp, cmd: &ArgMatches, endpoint: &str) -> Result<()> {
-    let a_id = cmd
-        .value_of("ARTEFACT_ID")
-        .ok_or("ARTEFACT_ID not provided")?;
-    let s_id = cmd
-        .value_of("INSTANCE_ID")
-        .ok_or("INSTANCE_ID not provided")?;
-    let endpoint = app.endpoint_id_instance(endpoint, a_id, s_id)?;
-    let response = surf::get(&endpoint).await?;
-    handle_response(response).await
-}
+    #[test]
+    fn target_config_roundtrip() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "tremor-cli-api-{}-targets.yaml",
+            std::process::id()
+        ));
+        assert_eq!(TargetConfig::default(), TargetConfig::load(&path)?);
+
+        let mut config = TargetConfig::default();
+        TargetCommand::Create {
+            id: "snot".to_string(),
+            url: "http://snot:1234".to_string(),
+        }
+        .run(&mut config, &path)?;
+        assert_eq!(config, TargetConfig::load(&path)?);
+        assert!(TargetCommand::Create {
+            id: "badger".to_string(),
+            url: "not a url".to_string(),
+        }
+        .run(&mut config, &path)
+        .is_err());
 
-async fn handle_response(mut response: surf::Response) -> Result<()> {
-    let status = response.status();
-    match status {
-        StatusCode::Ok | StatusCode::Created => println!("{}", response.body_string().await?),
-        StatusCode::NotFound => eprintln!("Not found"),
-        StatusCode::Conflict => eprintln!("Conflict"),
-        _ => eprintln!(
-            "Unexpected response ( status: {} )",
-            status.canonical_reason()
-        ),
-    };
-    Ok(())
+        TargetCommand::Delete {
+            id: "snot".to_string(),
+        }
+        .run(&mut config, &path)?;
+        assert!(TargetCommand::Delete {
+            id: "snot".to_string(),
+        }
+        .run(&mut config, &path)
+        .is_err());
+        assert_eq!(TargetConfig::default(), TargetConfig::load(&path)?);
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
 }
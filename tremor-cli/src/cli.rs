@@ -237,9 +237,15 @@ pub(crate) struct ServerRun {
     /// Disable the API
     #[clap(short, long)]
     pub(crate) no_api: bool,
+    /// Disable the Prometheus metrics endpoint `/metrics` of the API
+    #[clap(long)]
+    pub(crate) no_metrics_endpoint: bool,
     /// Loads the debug connectors
     #[clap(short, long)]
     pub(crate) debug_connectors: bool,
+    /// Directory to load connector and function plugins, shared libraries built against `tremor-pdk`, from
+    #[clap(long)]
+    pub(crate) plugin_dir: Option<String>,
     /// The `host:port` to listen for the API
     #[clap(short, long, default_value = "0.0.0.0:9898")]
     pub(crate) api_host: String,
@@ -251,223 +257,186 @@ pub(crate) struct ServerRun {
     pub(crate) recursion_limit: u32,
 }
 
-// TODO: since the API will change this isn't translated yet
 #[derive(Parser, Debug)]
 pub(crate) struct Api {
-    /*
-- api:
-      args:
-        - FORMAT:
-            short: f
-            multiple_values: false
-            multiple_occurrences: false
-            about: Sets the output format
-            possible_values: [json, yaml]
-            takes_value: true
-        - CONFIG:
-            short: c
-            long: config
-            value_name: FILE
-            about: Sets a custom config file
-            takes_value: true
-      subcommands:
-        - version:
-            about: Get tremor version
-        - target:
-            about: Target one or many tremor server instances
-            subcommands:
-              - list:
-                  about: List registered targets
-              - create:
-                  about: Create a new API target
-                  args:
-                    - TARGET_ID:
-                        about: The unique target id for the targetted tremor servers
-                        required: true
-                        takes_value: true
-                    - SOURCE:
-                        about: JSON or YAML file request body
-                        required: true
-                        takes_value: true
-              - delete:
-                  about: Delete an existing API target
-                  args:
-                    - TARGET_ID:
-                        about: The unique target id for the targetted tremor servers
-                        required: true
-                        takes_value: true
-        - binding:
-            about: Query/update binding specification repository
-            subcommands:
-              - list:
-                  about: List registered binding specifications
-              - fetch:
-                  about: Fetch a binding by artefact id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the binding specification
-                        required: true
-                        takes_value: true
-              - delete:
-                  about: Delete a binding by artefact id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the binding specification
-                        required: true
-                        takes_value: true
-              - create:
-                  about: Create and register a binding specification
-                  args:
-                    - SOURCE:
-                        about: JSON or YAML file request body
-                        takes_value: true
-                        required: true
-              - instance:
-                  about: Fetch an binding instance by artefact id and instance id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the binding specification
-                        required: true
-                        takes_value: true
-                    - INSTANCE_ID:
-                        about: The unique instance id for the binding specification
-                        required: true
-                        takes_value: true
-              - activate:
-                  about: Activate a binding by artefact id and servant instance id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the binding specification
-                        required: true
-                        takes_value: true
-                    - INSTANCE_ID:
-                        about: The unique instance id for the binding specification
-                        required: true
-                        takes_value: true
-                    - SOURCE:
-                        about: JSON -r YAML file request body
-                        required: true
-                        takes_value: true
-              - deactivate:
-                  about: Activate a binding by artefact id and servant instance id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the binding specification
-                        required: true
-                        takes_value: true
-                    - INSTANCE_ID:
-                        about: The unique instance id for the binding specification
-                        required: true
-                        takes_value: true
-        - pipeline:
-            about: Query/update pipeline specification repository
-            subcommands:
-              - list:
-                  about: List registered pipeline specifications
-              - fetch:
-                  about: Fetch a pipeline by artefact id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the pipeline specification
-                        required: true
-                        takes_value: true
-              - delete:
-                  about: Delete a pipeline by artefact id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the pipeline specification
-                        required: true
-                        takes_value: true
-              - create:
-                  about: Create and register a pipeline specification
-                  args:
-                    - SOURCE:
-                        about: JSON or YAML file request body
-                        required: false
-                        takes_value: true
-              - instance:
-                  about: Fetch an pipeline instance by artefact id and instance id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the pipeline specification
-                        required: true
-                        takes_value: true
-                    - INSTANCE_ID:
-                        about: The unique instance id for the pipeline specification
-                        required: true
-                        takes_value: true
-        - onramp:
-            about: Query/update onramp specification repository
-            subcommands:
-              - list:
-                  about: List registered onramp specifications
-              - fetch:
-                  about: Fetch an onramp by artefact id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the onramp specification
-                        required: true
-                        takes_value: true
-              - delete:
-                  about: Delete an onramp by artefact id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the onramp specification
-                        required: true
-                        takes_value: true
-              - create:
-                  about: Create and register an onramp specification
-                  args:
-                    - SOURCE:
-                        about: JSON or YAML file request body
-                        required: false
-                        takes_value: true
-              - instance:
-                  about: Fetch an onramp instance by artefact id and instance id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the onramp specification
-                        required: true
-                        takes_value: true
-                    - INSTANCE_ID:
-                        about: The unique instance id for the onramp specification
-                        required: true
-                        takes_value: true
-        - offramp:
-            about: Query/update offramp specification repository
-            subcommands:
-              - list:
-                  about: List registered offramp specifications
-              - fetch:
-                  about: Fetch an offramp by artefact id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the offramp specification
-                        required: true
-                        takes_value: true
-              - delete:
-                  about: Delete an offramp by artefact id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the offramp specification
-                        required: true
-                        takes_value: true
-              - create:
-                  about: Create and register an offramp specification
-                  args:
-                    - SOURCE:
-                        about: JSON or YAML file request body
-                        required: false
-                        takes_value: true
-              - instance:
-                  about: Fetch an offramp instance by artefact id and instance id
-                  args:
-                    - ARTEFACT_ID:
-                        about: The unique artefact id for the offramp specification
-                        required: true
-                        takes_value: true
-                    - INSTANCE_ID:
-                        about: The unique instance id for the offramp specification
-                        required: true
-                        takes_value: true
-    */}
+    /// Sets the output format
+    #[clap(short, long, arg_enum, default_value_t)]
+    pub(crate) format: ApiFormat,
+    /// Sets a custom config file for targets, defaults to `~/.tremor/config.yaml`
+    #[clap(short, long)]
+    pub(crate) config: Option<String>,
+    /// The target to send requests to, defaults to the target `default` or `http://localhost:9898`
+    #[clap(short, long)]
+    pub(crate) target: Option<String>,
+    #[clap(subcommand)]
+    pub(crate) command: ApiCommand,
+}
+
+/// Output format of API responses
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ApiFormat {
+    /// JSON
+    Json,
+    /// YAML
+    Yaml,
+}
+
+impl ToString for ApiFormat {
+    fn to_string(&self) -> String {
+        match self {
+            ApiFormat::Json => "json".to_string(),
+            ApiFormat::Yaml => "yaml".to_string(),
+        }
+    }
+}
+
+impl Default for ApiFormat {
+    fn default() -> Self {
+        Self::Json
+    }
+}
+
+#[derive(Parser, Debug)]
+pub(crate) enum ApiCommand {
+    /// Get tremor version
+    Version,
+    /// Get the runtime status
+    Status,
+    /// Manage the tremor server instances to target
+    Target {
+        #[clap(subcommand)]
+        command: TargetCommand,
+    },
+    /// Query and manage flows
+    Flow {
+        #[clap(subcommand)]
+        command: FlowCommand,
+    },
+    /// Query and manage the connectors of a flow
+    Connector {
+        #[clap(subcommand)]
+        command: ConnectorCommand,
+    },
+    /// Print the events passing an output port of a pipeline or connector as JSON
+    Tap {
+        #[clap(subcommand)]
+        command: TapCommand,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub(crate) enum TargetCommand {
+    /// List registered targets
+    List,
+    /// Create or replace a target
+    Create {
+        /// The unique id of the target
+        id: String,
+        /// The url of the API of the tremor server, e.g. `http://localhost:9898`
+        url: String,
+    },
+    /// Delete a target
+    Delete {
+        /// The unique id of the target
+        id: String,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub(crate) enum FlowCommand {
+    /// List all flows
+    List,
+    /// Get a flow
+    Get {
+        /// The id of the flow
+        id: String,
+    },
+    /// Deploy all flows in a troy file
+    Create {
+        /// The troy file
+        source: String,
+    },
+    /// Drain and stop a flow
+    Delete {
+        /// The id of the flow
+        id: String,
+    },
+    /// Pause a flow and all its connectors
+    Pause {
+        /// The id of the flow
+        id: String,
+    },
+    /// Resume a flow and all its connectors
+    Resume {
+        /// The id of the flow
+        id: String,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub(crate) enum ConnectorCommand {
+    /// List all connectors of a flow
+    List {
+        /// The id of the flow
+        flow: String,
+    },
+    /// Get a connector
+    Get {
+        /// The id of the flow
+        flow: String,
+        /// The id of the connector within the flow
+        connector: String,
+    },
+    /// Pause a connector
+    Pause {
+        /// The id of the flow
+        flow: String,
+        /// The id of the connector within the flow
+        connector: String,
+    },
+    /// Resume a connector
+    Resume {
+        /// The id of the flow
+        flow: String,
+        /// The id of the connector within the flow
+        connector: String,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub(crate) enum TapCommand {
+    /// Tap an output port of a pipeline
+    Pipeline {
+        /// The id of the flow
+        flow: String,
+        /// The id of the pipeline within the flow
+        pipeline: String,
+        /// The output port to tap
+        #[clap(short, long, default_value = "out")]
+        port: String,
+        #[clap(flatten)]
+        options: TapOptions,
+    },
+    /// Tap an output port of a connector
+    Connector {
+        /// The id of the flow
+        flow: String,
+        /// The id of the connector within the flow
+        connector: String,
+        /// The output port to tap, `out` or `err`
+        #[clap(short, long, default_value = "out")]
+        port: String,
+        #[clap(flatten)]
+        options: TapOptions,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct TapOptions {
+    /// Only print every n-th matching event
+    #[clap(short, long, default_value = "1")]
+    pub(crate) sample: u64,
+    /// A tremor-script expression, only events it evaluates to `true` for are printed
+    #[clap(short, long)]
+    pub(crate) filter: Option<String>,
+}
@@ -56,6 +56,7 @@ error_chain! {
         Url(url::ParseError) #[doc = "Error while parsing a url"];
         Common(tremor_common::Error);
         ParseIntError(std::num::ParseIntError);
+        WebSocket(async_tungstenite::tungstenite::Error) #[doc = "Error on a websocket connection"];
     }
     errors {
         TestFailures(stats: crate::test::stats::Stats) {
@@ -38,7 +38,7 @@ use tremor_common::file;
 // use tremor_runtime::errors;
 
 mod alloc;
-// mod api;
+mod api;
 mod completions;
 mod debug;
 mod doc;
@@ -118,6 +118,6 @@ async fn run(cli: Cli) -> Result<()> {
         Command::Dbg(d) => d.run(),
         Command::Run(r) => r.run().await,
         Command::Doc(d) => d.run(),
-        Command::Api(_) => todo!(),
+        Command::Api(a) => a.run().await,
     }
 }
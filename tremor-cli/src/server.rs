@@ -24,6 +24,7 @@ use signal_hook::consts::signal::{SIGINT, SIGQUIT, SIGTERM};
 use signal_hook::low_level::signal_name;
 use signal_hook_async_std::Signals;
 use std::io::Write;
+use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use tremor_api as api;
 use tremor_common::file;
@@ -116,6 +117,8 @@ impl ServerRun {
         // TODO: Allow configuring this for offramps and pipelines
         let config = WorldConfig {
             debug_connectors: self.debug_connectors,
+            metrics_endpoint: !self.no_api && !self.no_metrics_endpoint,
+            plugin_dir: self.plugin_dir.as_ref().map(PathBuf::from),
             ..WorldConfig::default()
         };
 
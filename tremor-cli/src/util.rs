@@ -13,8 +13,6 @@
 // limitations under the License.
 
 use crate::errors::Result;
-use halfbrown::HashMap;
-use serde::Deserialize;
 use std::fs;
 use std::path::Path;
 use std::{ffi::OsStr, fmt};
@@ -32,11 +30,6 @@ pub(crate) fn slurp_string<P: AsRef<Path>>(file: P) -> Result<String> {
     Ok(data)
 }
 
-#[derive(Deserialize, Debug, Serialize)]
-pub(crate) struct TargetConfig {
-    pub(crate) instances: HashMap<String, Vec<String>>, // TODO TremorUrl
-}
-
 pub(crate) type PathVisitor = dyn Fn(Option<&Path>, &Path) -> Result<()>;
 
 pub(crate) fn visit_path_str(path: &str, visitor: &PathVisitor) -> Result<()> {
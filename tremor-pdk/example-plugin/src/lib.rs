@@ -0,0 +1,146 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Example connector plugin
+//!
+//! Provides the `echo` connector type: all data written to its sink is read back from its source.
+
+#![deny(
+    clippy::all,
+    clippy::unwrap_used,
+    clippy::unnecessary_unwrap,
+    clippy::pedantic
+)]
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use tremor_pdk::{
+    abi_stable::{
+        export_root_module,
+        prefix_type::PrefixTypeTrait,
+        sabi_extern_fn,
+        sabi_trait::TD_Opaque,
+        std_types::{RErr, RNone, ROk, ROption, RResult, RSlice, RSome, RStr, RString, RVec},
+    },
+    BoxedRawConnector, BoxedRawSink, BoxedRawSource, ConnectorPlugin, ConnectorPluginRef,
+    RawConnector, RawConnector_TO, RawSink, RawSink_TO, RawSource, RawSource_TO,
+};
+
+/// data written to the sink, waiting to be read by the source
+#[derive(Default)]
+struct Queue {
+    data: Mutex<VecDeque<Vec<u8>>>,
+    written: Condvar,
+}
+
+struct Echo {
+    queue: Arc<Queue>,
+    source: bool,
+    sink: bool,
+}
+
+impl RawConnector for Echo {
+    fn source(&mut self) -> ROption<BoxedRawSource> {
+        if std::mem::take(&mut self.source) {
+            let source = EchoSource {
+                queue: self.queue.clone(),
+            };
+            RSome(RawSource_TO::from_value(source, TD_Opaque))
+        } else {
+            RNone
+        }
+    }
+
+    fn sink(&mut self) -> ROption<BoxedRawSink> {
+        if std::mem::take(&mut self.sink) {
+            let sink = EchoSink {
+                queue: self.queue.clone(),
+            };
+            RSome(RawSink_TO::from_value(sink, TD_Opaque))
+        } else {
+            RNone
+        }
+    }
+
+    fn connect(&mut self) -> RResult<bool, RString> {
+        ROk(true)
+    }
+
+    fn stop(&mut self) -> RResult<(), RString> {
+        ROk(())
+    }
+}
+
+struct EchoSource {
+    queue: Arc<Queue>,
+}
+
+impl RawSource for EchoSource {
+    fn read(&mut self, timeout_ms: u64) -> RResult<ROption<RVec<u8>>, RString> {
+        let data = match self.queue.data.lock() {
+            Ok(data) => data,
+            Err(e) => return RErr(RString::from(e.to_string())),
+        };
+        match self.queue.written.wait_timeout_while(
+            data,
+            Duration::from_millis(timeout_ms),
+            |data| data.is_empty(),
+        ) {
+            Ok((mut data, _timeout)) => ROk(data.pop_front().map(RVec::from).into()),
+            Err(e) => RErr(RString::from(e.to_string())),
+        }
+    }
+}
+
+struct EchoSink {
+    queue: Arc<Queue>,
+}
+
+impl RawSink for EchoSink {
+    fn write(&mut self, data: RSlice<'_, u8>) -> RResult<(), RString> {
+        match self.queue.data.lock() {
+            Ok(mut queue) => {
+                queue.push_back(data.to_vec());
+                self.queue.written.notify_one();
+                ROk(())
+            }
+            Err(e) => RErr(RString::from(e.to_string())),
+        }
+    }
+}
+
+#[sabi_extern_fn]
+fn connector_type() -> RString {
+    RString::from("echo")
+}
+
+#[sabi_extern_fn]
+fn build(_alias: RStr, _config: RStr) -> RResult<BoxedRawConnector, RString> {
+    let echo = Echo {
+        queue: Arc::default(),
+        source: true,
+        sink: true,
+    };
+    ROk(RawConnector_TO::from_value(echo, TD_Opaque))
+}
+
+#[export_root_module]
+fn plugin() -> ConnectorPluginRef {
+    ConnectorPlugin {
+        connector_type,
+        build,
+    }
+    .leak_into_prefix()
+}
@@ -0,0 +1,163 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The plugin interface
+
+// for the items `abi_stable` generates alongside the plugin interface
+#![allow(
+    missing_docs,
+    clippy::expl_impl_clone_on_copy,
+    clippy::must_use_candidate,
+    clippy::used_underscore_binding
+)]
+
+use abi_stable::{
+    declare_root_module_statics,
+    library::RootModule,
+    package_version_strings, sabi_trait,
+    sabi_types::VersionStrings,
+    std_types::{RBox, ROption, RResult, RSlice, RStr, RString, RVec},
+    StableAbi,
+};
+use std::fmt;
+
+/// The source part of a plugin connector
+#[sabi_trait]
+pub trait RawSource: Send {
+    /// Reads the next chunk of data, waiting at most `timeout_ms` milliseconds.
+    /// Returns `RNone` if no data arrived in time.
+    ///
+    /// An error signals that the connection is lost and triggers a reconnect.
+    #[sabi(last_prefix_field)]
+    fn read(&mut self, timeout_ms: u64) -> RResult<ROption<RVec<u8>>, RString>;
+}
+
+/// A source part owned by the runtime
+pub type BoxedRawSource = RawSource_TO<'static, RBox<()>>;
+
+/// The sink part of a plugin connector
+#[sabi_trait]
+pub trait RawSink: Send {
+    /// Writes a chunk of serialized and postprocessed data.
+    ///
+    /// An error fails the event the data stems from.
+    #[sabi(last_prefix_field)]
+    fn write(&mut self, data: RSlice<'_, u8>) -> RResult<(), RString>;
+}
+
+/// A sink part owned by the runtime
+pub type BoxedRawSink = RawSink_TO<'static, RBox<()>>;
+
+/// A connector instance created by a plugin
+#[sabi_trait]
+pub trait RawConnector: Send {
+    /// Takes the source part of this connector, called once when the connector is created.
+    /// Returns `RNone` if the connector doesn't produce events.
+    fn source(&mut self) -> ROption<BoxedRawSource>;
+
+    /// Takes the sink part of this connector, called once when the connector is created.
+    /// Returns `RNone` if the connector doesn't consume events.
+    fn sink(&mut self) -> ROption<BoxedRawSink>;
+
+    /// Establishes the connection to the outside world, returning `true` on success.
+    ///
+    /// Called when the connector starts and whenever the connection was lost,
+    /// according to the `reconnect` config of the connector.
+    fn connect(&mut self) -> RResult<bool, RString>;
+
+    /// Closes the connection, called once when the connector is stopped
+    #[sabi(last_prefix_field)]
+    fn stop(&mut self) -> RResult<(), RString>;
+}
+
+/// A connector instance owned by the runtime
+pub type BoxedRawConnector = RawConnector_TO<'static, RBox<()>>;
+
+/// The root module of a connector plugin
+#[repr(C)]
+#[derive(StableAbi)]
+#[sabi(kind(Prefix(prefix_ref = "ConnectorPluginRef")))]
+#[sabi(missing_field(panic))]
+pub struct ConnectorPlugin {
+    /// The type of the connector, as used in `define connector ... from <type>`
+    pub connector_type: extern "C" fn() -> RString,
+
+    /// Creates a connector instance from its alias and its `config` encoded as JSON
+    #[sabi(last_prefix_field)]
+    pub build:
+        extern "C" fn(alias: RStr<'_>, config: RStr<'_>) -> RResult<BoxedRawConnector, RString>,
+}
+
+impl RootModule for ConnectorPluginRef {
+    declare_root_module_statics! {ConnectorPluginRef}
+
+    const BASE_NAME: &'static str = "tremor_connector_plugin";
+    const NAME: &'static str = "tremor connector plugin";
+    // plugins need to be built against a version of this crate compatible with the runtime
+    const VERSION_STRINGS: VersionStrings = package_version_strings!();
+}
+
+/// A tremor-script function provided by a function plugin
+#[repr(C)]
+#[derive(StableAbi, Clone)]
+pub struct PluginFunction {
+    /// The name of the function
+    pub name: RString,
+    /// The minimal number of arguments
+    pub min_arity: usize,
+    /// The maximal number of arguments
+    pub max_arity: usize,
+    /// Whether the function always returns the same result for the same arguments and has
+    /// no side effects. Calls to pure functions with constant arguments are evaluated once,
+    /// when the script is compiled.
+    pub pure: bool,
+    /// Invokes the function with its arguments encoded as a JSON array,
+    /// returning the JSON encoded result or an error message
+    pub invoke: extern "C" fn(args: RStr<'_>) -> RResult<RString, RString>,
+}
+
+// `Debug` can't be derived for the higher ranked `invoke`
+impl fmt::Debug for PluginFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginFunction")
+            .field("name", &self.name)
+            .field("min_arity", &self.min_arity)
+            .field("max_arity", &self.max_arity)
+            .field("pure", &self.pure)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The root module of a function plugin
+#[repr(C)]
+#[derive(StableAbi)]
+#[sabi(kind(Prefix(prefix_ref = "FunctionPluginRef")))]
+#[sabi(missing_field(panic))]
+pub struct FunctionPlugin {
+    /// The module the functions are registered in
+    pub module: extern "C" fn() -> RString,
+
+    /// The functions of this plugin
+    #[sabi(last_prefix_field)]
+    pub functions: extern "C" fn() -> RVec<PluginFunction>,
+}
+
+impl RootModule for FunctionPluginRef {
+    declare_root_module_statics! {FunctionPluginRef}
+
+    const BASE_NAME: &'static str = "tremor_function_plugin";
+    const NAME: &'static str = "tremor function plugin";
+    // plugins need to be built against a version of this crate compatible with the runtime
+    const VERSION_STRINGS: VersionStrings = package_version_strings!();
+}
@@ -0,0 +1,111 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tremor plugin development kit
+//!
+//! The stable ABI plugins, shipped as shared libraries, are built against.
+//! A plugin exports either a [`ConnectorPluginRef`] or a [`FunctionPluginRef`] as its root module.
+//! The runtime checks that the plugin was built against a compatible version of this crate and
+//! that all types have the expected layout before registering its connector type or functions.
+//!
+//! Plugins depend on `abi_stable` directly as well, `export_root_module` refers to it by its crate name.
+//! Entry points are defined with `#[sabi_extern_fn]`, which aborts the process if they panic
+//! instead of unwinding across the ABI boundary, which is undefined behaviour.
+//!
+//! # Connector plugins
+//!
+//! Connector plugins exchange raw bytes with the runtime, which applies the codecs and
+//! pre- and postprocessors configured for the connector. All calls are blocking, the runtime
+//! issues them from a thread pool.
+//!
+//! ```ignore
+//! use tremor_pdk::{
+//!     abi_stable::{
+//!         export_root_module, prefix_type::PrefixTypeTrait, sabi_extern_fn, sabi_trait::TD_Opaque,
+//!     },
+//!     BoxedRawConnector, ConnectorPlugin, ConnectorPluginRef, RawConnector, RawConnector_TO,
+//!     ...
+//! };
+//!
+//! #[sabi_extern_fn]
+//! fn connector_type() -> RString {
+//!     RString::from("my_protocol")
+//! }
+//!
+//! #[sabi_extern_fn]
+//! fn build(alias: RStr, config: RStr) -> RResult<BoxedRawConnector, RString> {
+//!     let connector = MyProtocol::new(alias.as_str(), config.as_str());
+//!     ROk(RawConnector_TO::from_value(connector, TD_Opaque))
+//! }
+//!
+//! #[export_root_module]
+//! fn plugin() -> ConnectorPluginRef {
+//!     ConnectorPlugin {
+//!         connector_type,
+//!         build,
+//!     }
+//!     .leak_into_prefix()
+//! }
+//! ```
+//!
+//! # Function plugins
+//!
+//! Function plugins add a module of tremor-script functions, callable as `core::<module>::<name>(...)`
+//! or wrapped in a tremor-script module via `intrinsic fn`. Arguments and results are exchanged
+//! as JSON, functions are invoked synchronously while the script runs.
+//!
+//! ```ignore
+//! #[sabi_extern_fn]
+//! fn module() -> RString {
+//!     RString::from("geoip")
+//! }
+//!
+//! #[sabi_extern_fn]
+//! fn country(args: RStr) -> RResult<RString, RString> {
+//!     // args is a JSON array of the arguments, e.g. `["1.2.3.4"]`
+//!     lookup(args.as_str()).map(RString::from).map_err(RString::from).into()
+//! }
+//!
+//! #[sabi_extern_fn]
+//! fn functions() -> RVec<PluginFunction> {
+//!     rvec![PluginFunction {
+//!         name: RString::from("country"),
+//!         min_arity: 1,
+//!         max_arity: 1,
+//!         pure: true,
+//!         invoke: country,
+//!     }]
+//! }
+//!
+//! #[export_root_module]
+//! fn plugin() -> FunctionPluginRef {
+//!     FunctionPlugin { module, functions }.leak_into_prefix()
+//! }
+//! ```
+
+#![deny(missing_docs)]
+#![recursion_limit = "1024"]
+#![deny(
+    clippy::all,
+    clippy::unwrap_used,
+    clippy::unnecessary_unwrap,
+    clippy::pedantic
+)]
+// the names of the trait objects are generated by `abi_stable`
+#![allow(non_camel_case_types)]
+
+pub use crate::abi::*;
+pub use abi_stable;
+
+mod abi;
@@ -0,0 +1,123 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loads the example plugin in `example-plugin` the way the runtime does
+
+use abi_stable::{
+    declare_root_module_statics,
+    library::{lib_header_from_path, LibHeader, LibraryError, RootModule},
+    sabi_types::VersionStrings,
+    std_types::{RNone, RSome, RString},
+    StableAbi,
+};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Once;
+use tremor_pdk::{ConnectorPluginRef, FunctionPluginRef};
+
+/// builds the example plugin, once for all tests, into a target dir of its own,
+/// as the one of this test is locked by cargo while it runs
+fn echo_plugin() -> PathBuf {
+    static BUILD: Once = Once::new();
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = manifest_dir.join("../target/plugins");
+    BUILD.call_once(|| {
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--package", "tremor-echo-plugin", "--target-dir"])
+            .arg(&target_dir)
+            .current_dir(manifest_dir)
+            .status()
+            .expect("Unable to run cargo");
+        assert!(status.success(), "Unable to build the example plugin");
+    });
+    target_dir.join("debug").join(format!(
+        "{}tremor_echo_plugin.{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_EXTENSION
+    ))
+}
+
+fn header() -> &'static LibHeader {
+    lib_header_from_path(&echo_plugin()).expect("Unable to load the example plugin")
+}
+
+/// the root module of a connector plugin built against a future `tremor-pdk`
+#[repr(C)]
+#[derive(StableAbi)]
+#[sabi(kind(Prefix(prefix_ref = "FutureConnectorPluginRef")))]
+#[sabi(missing_field(panic))]
+pub struct FutureConnectorPlugin {
+    #[sabi(last_prefix_field)]
+    pub connector_type: extern "C" fn() -> RString,
+}
+
+impl RootModule for FutureConnectorPluginRef {
+    declare_root_module_statics! {FutureConnectorPluginRef}
+
+    const BASE_NAME: &'static str = "tremor_connector_plugin";
+    const NAME: &'static str = "tremor connector plugin";
+    const VERSION_STRINGS: VersionStrings = VersionStrings::new("0.13.0");
+}
+
+#[test]
+fn version_check() {
+    let err = header()
+        .init_root_module::<FutureConnectorPluginRef>()
+        .err()
+        .expect("loaded a plugin of an incompatible version");
+    assert!(
+        matches!(err, LibraryError::IncompatibleVersionNumber { .. }),
+        "{err}"
+    );
+}
+
+#[test]
+fn layout_check() {
+    // a connector plugin is not a function plugin
+    let err = header()
+        .init_root_module::<FunctionPluginRef>()
+        .err()
+        .expect("loaded a connector plugin as a function plugin");
+    assert!(matches!(err, LibraryError::AbiInstability(_)), "{err}");
+}
+
+#[test]
+fn connector_round_trip() {
+    let plugin = header()
+        .init_root_module::<ConnectorPluginRef>()
+        .expect("Unable to load the connector plugin");
+    assert_eq!("echo", (plugin.connector_type())().as_str());
+
+    let mut connector = (plugin.build())("echo".into(), "null".into())
+        .into_result()
+        .expect("Unable to build the connector");
+    let mut source = connector.source().into_option().expect("no source");
+    let mut sink = connector.sink().into_option().expect("no sink");
+    // source and sink are only handed out once
+    assert!(connector.source().is_none());
+    assert!(connector.sink().is_none());
+    assert_eq!(Ok(true), connector.connect().into_result());
+
+    assert_eq!(RNone, source.read(10).into_result().expect("read failed"));
+    for data in [&b"snot"[..], &b"badger"[..]] {
+        sink.write(data.into()).into_result().expect("write failed");
+    }
+    for data in [&b"snot"[..], &b"badger"[..]] {
+        assert_eq!(
+            RSome(data.to_vec().into()),
+            source.read(10).into_result().expect("read failed")
+        );
+    }
+    assert_eq!(Ok(()), connector.stop().into_result());
+}
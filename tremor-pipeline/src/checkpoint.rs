@@ -0,0 +1,357 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persistence of operator state
+//!
+//! A pipeline takes a snapshot of the state of all of its operators at a regular
+//! interval. Such a snapshot only becomes a checkpoint, and is handed to a
+//! `StateBackend`, once every transactional event that left the pipeline before it
+//! was taken has been acknowledged downstream. Restoring a pipeline from its last
+//! checkpoint thus never loses the effects of an event a source was told to forget.
+
+use crate::errors::Result;
+use crate::{CbAction, Event, EventId};
+use lazy_static::lazy_static;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tremor_script::prelude::*;
+
+lazy_static! {
+    // sled holds an exclusive lock on its directory, so every database
+    // is opened once and shared between all pipelines of this process
+    static ref DATABASES: Mutex<HashMap<PathBuf, sled::Db>> = Mutex::new(HashMap::new());
+}
+
+/// A store for pipeline checkpoints
+pub trait StateBackend: Send + Sync + Debug {
+    /// Loads the last checkpoint stored for `pipeline`, if any
+    ///
+    /// # Errors
+    /// if the backend can not be read or the checkpoint is invalid
+    fn load(&self, pipeline: &str) -> Result<Option<Value<'static>>>;
+
+    /// Stores `checkpoint` as the latest checkpoint of `pipeline`
+    ///
+    /// # Errors
+    /// if the checkpoint can not be persisted
+    fn store(&self, pipeline: &str, checkpoint: &Value) -> Result<()>;
+
+    /// Removes the checkpoint stored for `pipeline`, if any
+    ///
+    /// # Errors
+    /// if the checkpoint can not be removed
+    fn remove(&self, pipeline: &str) -> Result<()>;
+}
+
+/// Keeps checkpoints in memory, they do not survive a restart of the process
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    checkpoints: Mutex<HashMap<String, Value<'static>>>,
+}
+
+impl StateBackend for MemoryBackend {
+    fn load(&self, pipeline: &str) -> Result<Option<Value<'static>>> {
+        Ok(self.checkpoints.lock()?.get(pipeline).cloned())
+    }
+
+    fn store(&self, pipeline: &str, checkpoint: &Value) -> Result<()> {
+        self.checkpoints
+            .lock()?
+            .insert(pipeline.to_string(), checkpoint.clone_static());
+        Ok(())
+    }
+
+    fn remove(&self, pipeline: &str) -> Result<()> {
+        self.checkpoints.lock()?.remove(pipeline);
+        Ok(())
+    }
+}
+
+/// Persists checkpoints to a sled database on disk
+#[derive(Debug, Clone)]
+pub struct SledBackend {
+    db: sled::Db,
+}
+
+impl SledBackend {
+    /// Opens, or creates, the database in `dir`
+    ///
+    /// # Errors
+    /// if the database can not be opened
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let mut dbs = DATABASES.lock()?;
+        let db = if let Some(db) = dbs.get(&dir) {
+            db.clone()
+        } else {
+            let db = sled::open(&dir)?;
+            dbs.insert(dir, db.clone());
+            db
+        };
+        Ok(Self { db })
+    }
+}
+
+impl StateBackend for SledBackend {
+    fn load(&self, pipeline: &str) -> Result<Option<Value<'static>>> {
+        if let Some(data) = self.db.get(pipeline)? {
+            let mut data = data.to_vec();
+            let checkpoint = tremor_value::parse_to_value(&mut data)?.into_static();
+            Ok(Some(checkpoint))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn store(&self, pipeline: &str, checkpoint: &Value) -> Result<()> {
+        self.db.insert(pipeline, checkpoint.encode().into_bytes())?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn remove(&self, pipeline: &str) -> Result<()> {
+        self.db.remove(pipeline)?;
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+/// A snapshot waiting for the events that left the pipeline before it was taken
+#[derive(Debug)]
+struct Pending {
+    snapshot: Value<'static>,
+    /// the pull ids, per source and stream, of the events sent before the snapshot
+    watermark: EventId,
+    /// how many deliveries of those events are not yet acknowledged
+    waiting: usize,
+}
+
+/// Tracks in flight events to decide when a snapshot becomes a checkpoint
+#[derive(Debug, Default)]
+pub(crate) struct Checkpoints {
+    /// the pull ids, per source and stream, of the transactional events in flight
+    sent: Option<EventId>,
+    /// the number of deliveries of transactional events that are not yet acknowledged
+    in_flight: usize,
+    /// snapshots waiting for acknowledgements, the oldest first
+    pending: VecDeque<Pending>,
+    /// a snapshot all events before it were acknowledged for
+    ready: Option<Value<'static>>,
+}
+
+impl Checkpoints {
+    /// Records a transactional event leaving the pipeline to `deliveries` destinations,
+    /// each of them acknowledges the event on its own
+    pub(crate) fn sent(&mut self, id: &EventId, deliveries: usize) {
+        if let Some(sent) = &mut self.sent {
+            sent.track(id);
+        } else {
+            self.sent = Some(id.clone());
+        }
+        self.in_flight += deliveries;
+    }
+
+    /// Stages a snapshot, it is ready once all events currently in flight are acknowledged.
+    ///
+    /// The oldest snapshot still waiting is kept until it resolves, so a steady stream of
+    /// events can't keep checkpoints from being stored. A newer snapshot replaces the latest one
+    /// queued behind it.
+    pub(crate) fn stage(&mut self, snapshot: Value<'static>) {
+        match &self.sent {
+            Some(sent) if self.in_flight > 0 => {
+                if self.pending.len() > 1 {
+                    self.pending.pop_back();
+                }
+                self.pending.push_back(Pending {
+                    snapshot,
+                    watermark: sent.clone(),
+                    waiting: self.in_flight,
+                });
+            }
+            _ => {
+                self.pending.clear();
+                self.ready = Some(snapshot);
+            }
+        }
+    }
+
+    /// Updates the in flight events with an insight travelling back through the pipeline
+    pub(crate) fn on_insight(&mut self, insight: &Event) {
+        let failed = match insight.cb {
+            CbAction::Ack => false,
+            CbAction::Fail => true,
+            _ => return,
+        };
+        if !self
+            .sent
+            .as_ref()
+            .map_or(false, |sent| sent.is_tracking(&insight.id))
+        {
+            return;
+        }
+        self.in_flight = self.in_flight.saturating_sub(1);
+        if failed {
+            // the events will be replayed, a checkpoint including
+            // their effects must never be stored
+            self.pending
+                .retain(|pending| !pending.watermark.is_tracking(&insight.id));
+        } else {
+            for pending in &mut self.pending {
+                if pending.watermark.is_tracking(&insight.id) {
+                    pending.waiting = pending.waiting.saturating_sub(1);
+                }
+            }
+            while let Some(pending) = self.pending.front() {
+                if pending.waiting > 0 {
+                    break;
+                }
+                self.ready = self.pending.pop_front().map(|pending| pending.snapshot);
+            }
+        }
+        if self.in_flight == 0 {
+            // nothing in flight, the watermarks can start over
+            self.sent = None;
+        }
+    }
+
+    /// Takes the latest checkpoint that is ready to be stored
+    pub(crate) fn take_ready(&mut self) -> Option<Value<'static>> {
+        self.ready.take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn insight(cb: CbAction, id: &EventId) -> Event {
+        Event {
+            cb,
+            id: id.clone(),
+            ..Event::default()
+        }
+    }
+
+    #[test]
+    fn stage_without_in_flight() {
+        let mut c = Checkpoints::default();
+        c.stage(literal!({"snot": 1}));
+        assert_eq!(Some(literal!({"snot": 1})), c.take_ready());
+        assert_eq!(None, c.take_ready());
+    }
+
+    #[test]
+    fn stage_waits_for_acks() {
+        let mut c = Checkpoints::default();
+        let id1 = EventId::from_id(1, 1, 1);
+        let id2 = EventId::from_id(1, 1, 2);
+        c.sent(&id1, 1);
+        c.sent(&id2, 1);
+        c.stage(literal!(1));
+        // events sent after the snapshot are not waited for
+        let id3 = EventId::from_id(1, 1, 3);
+        c.sent(&id3, 1);
+        c.on_insight(&insight(CbAction::Ack, &id1));
+        assert_eq!(None, c.take_ready());
+        c.on_insight(&insight(CbAction::Close, &id2));
+        assert_eq!(None, c.take_ready());
+        c.on_insight(&insight(CbAction::Ack, &id2));
+        assert_eq!(Some(literal!(1)), c.take_ready());
+
+        // a failed event drops the snapshot waiting for it
+        c.stage(literal!(2));
+        c.on_insight(&insight(CbAction::Fail, &id3));
+        assert_eq!(None, c.take_ready());
+        c.stage(literal!(3));
+        assert_eq!(Some(literal!(3)), c.take_ready());
+    }
+
+    #[test]
+    fn stage_waits_for_all_deliveries() {
+        let mut c = Checkpoints::default();
+        let id1 = EventId::from_id(1, 1, 1);
+        // the event is sent to two destinations, both acknowledge it
+        c.sent(&id1, 2);
+        c.stage(literal!(1));
+        c.on_insight(&insight(CbAction::Ack, &id1));
+        assert_eq!(None, c.take_ready());
+        c.on_insight(&insight(CbAction::Ack, &id1));
+        assert_eq!(Some(literal!(1)), c.take_ready());
+    }
+
+    #[test]
+    fn stage_keeps_oldest_pending() {
+        let mut c = Checkpoints::default();
+        let id1 = EventId::from_id(1, 1, 1);
+        let id2 = EventId::from_id(1, 1, 2);
+        let id3 = EventId::from_id(1, 2, 1);
+        c.sent(&id1, 1);
+        c.stage(literal!(1));
+        c.sent(&id2, 1);
+        c.stage(literal!(2));
+        c.sent(&id3, 1);
+        // replaces the latest snapshot, but not the oldest
+        c.stage(literal!(3));
+        // acks for events sent after the oldest snapshot don't resolve it
+        c.on_insight(&insight(CbAction::Ack, &id3));
+        assert_eq!(None, c.take_ready());
+        c.on_insight(&insight(CbAction::Ack, &id1));
+        assert_eq!(Some(literal!(1)), c.take_ready());
+        c.on_insight(&insight(CbAction::Ack, &id2));
+        assert_eq!(Some(literal!(3)), c.take_ready());
+
+        // a failed event only drops the snapshots taken after it was sent
+        c.sent(&id1, 1);
+        c.stage(literal!(4));
+        c.sent(&id2, 1);
+        c.stage(literal!(5));
+        c.on_insight(&insight(CbAction::Fail, &id2));
+        c.on_insight(&insight(CbAction::Ack, &id1));
+        assert_eq!(Some(literal!(4)), c.take_ready());
+        // unknown events are ignored
+        c.on_insight(&insight(CbAction::Ack, &id3));
+        c.stage(literal!(6));
+        assert_eq!(Some(literal!(6)), c.take_ready());
+    }
+
+    #[test]
+    fn memory_backend() -> Result<()> {
+        let b = MemoryBackend::default();
+        assert_eq!(None, b.load("p")?);
+        b.store("p", &literal!({"snot": "badger"}))?;
+        assert_eq!(Some(literal!({"snot": "badger"})), b.load("p")?);
+        b.remove("p")?;
+        assert_eq!(None, b.load("p")?);
+        Ok(())
+    }
+
+    #[test]
+    fn sled_backend() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let b = SledBackend::open(dir.path())?;
+        assert_eq!(None, b.load("flow::p")?);
+        b.store("flow::p", &literal!({"snot": ["badger", 1, 2.5, null]}))?;
+        // re-opening the same directory shares the database
+        let b = SledBackend::open(dir.path())?;
+        assert_eq!(
+            Some(literal!({"snot": ["badger", 1, 2.5, null]})),
+            b.load("flow::p")?
+        );
+        b.remove("flow::p")?;
+        assert_eq!(None, b.load("flow::p")?);
+        Ok(())
+    }
+}
@@ -56,6 +56,16 @@ impl Event {
         }
     }
 
+    /// create a shutdown signal event, operators emit what they still hold upon it
+    #[must_use]
+    pub fn signal_shutdown() -> Self {
+        Self {
+            ingest_ns: nanotime(),
+            kind: Some(SignalKind::Shutdown),
+            ..Self::default()
+        }
+    }
+
     /// create a drain signal event originating at the connector with the given `source_id`
     #[must_use]
     pub fn signal_drain(source_id: SourceId) -> Self {
@@ -12,9 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{fmt, fmt::Display};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    fmt::Display,
+};
 
 use crate::{
+    checkpoint::Checkpoints,
     common_cow,
     errors::Result,
     errors::{Error, ErrorKind},
@@ -26,7 +31,7 @@ use crate::{op::EventAndInsights, Event, NodeKind, Operator};
 use beef::Cow;
 use halfbrown::HashMap;
 use tremor_common::{ids::OperatorId, stry};
-use tremor_script::{ast::Helper, ast::Stmt, Value};
+use tremor_script::{ast::Helper, ast::Stmt, prelude::*, Value};
 
 /// Configuration for a node
 #[derive(Debug, Clone, Default)]
@@ -161,6 +166,14 @@ impl Operator for OperatorNode {
     fn skippable(&self) -> bool {
         self.op.skippable()
     }
+
+    fn snapshot(&self) -> Result<Option<Value<'static>>> {
+        self.op.snapshot()
+    }
+
+    fn restore(&mut self, snapshot: &Value) -> Result<()> {
+        self.op.restore(snapshot)
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -238,12 +251,62 @@ impl NodeMetrics {
     }
 }
 
+/// Event counts of a single node of a running graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeReport {
+    /// ID of the node
+    pub id: String,
+    /// kind of the node: `input`, `output`, `operator`, `select` or `script`
+    pub kind: String,
+    /// operator type
+    pub op_type: String,
+    /// number of events received per input port
+    pub inputs: BTreeMap<String, u64>,
+    /// number of events emitted per output port, whether the port is connected or not
+    pub outputs: BTreeMap<String, u64>,
+    /// number of events emitted to the `err` port
+    pub errors: u64,
+}
+
+/// A connection from the output port of one node to the input port of another
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EdgeReport {
+    /// ID of the sending node
+    pub from: String,
+    /// output port of the sending node
+    pub from_port: String,
+    /// ID of the receiving node
+    pub to: String,
+    /// input port of the receiving node
+    pub to_port: String,
+}
+
+/// A snapshot of the structure of a running graph and the events counted by each of its nodes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphReport {
+    /// ID of the graph
+    pub id: String,
+    /// input ports of the graph
+    pub inputs: Vec<String>,
+    /// output ports of the graph
+    pub outputs: Vec<String>,
+    /// all nodes of the graph
+    pub nodes: Vec<NodeReport>,
+    /// all connections between the nodes of the graph
+    pub edges: Vec<EdgeReport>,
+    /// the dot representation of the graph
+    #[serde(skip)]
+    pub dot: String,
+}
+
 /// An executable graph, this is the executable
 /// form of a pipeline
 #[derive(Debug)]
 pub struct ExecutableGraph {
     /// ID of the graph
     pub id: String,
+    /// ID of the flow this graph is deployed in, added to its metrics
+    pub flow_id: Option<String>,
     pub(crate) graph: Vec<OperatorNode>,
     pub(crate) state: State,
     pub(crate) inputs: HashMap<Cow<'static, str>, usize>,
@@ -259,6 +322,7 @@ pub struct ExecutableGraph {
     pub insights: Vec<(usize, Event)>,
     /// the dot representation of the graph
     pub dot: String,
+    pub(crate) checkpoints: Option<Checkpoints>,
 }
 
 /// The return of a graph execution
@@ -436,6 +500,9 @@ impl ExecutableGraph {
         {
             let mut tags = HashMap::with_capacity(8);
             tags.insert("pipeline".into(), common_cow(&self.id).into());
+            if let Some(flow_id) = &self.flow_id {
+                tags.insert("flow".into(), common_cow(flow_id).into());
+            }
             self.send_metrics("events", tags, event.ingest_ns).await;
             self.last_metrics = event.ingest_ns;
         }
@@ -576,8 +643,157 @@ impl ExecutableGraph {
                 skip_to = None;
             }
         }
+        if let Some(checkpoints) = self.checkpoints.as_mut() {
+            checkpoints.on_insight(&insight);
+        }
         insight
     }
+
+    /// The input ports of this graph
+    #[must_use]
+    pub fn input_ports(&self) -> BTreeSet<&str> {
+        self.inputs.keys().map(AsRef::as_ref).collect()
+    }
+
+    /// The output ports of this graph
+    #[must_use]
+    pub fn output_ports(&self) -> BTreeSet<&str> {
+        self.graph
+            .iter()
+            .filter_map(|node| match &node.kind {
+                NodeKind::Output(port) => Some(port.as_ref()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A snapshot of the nodes and edges of this graph with the events counted per node and port
+    #[must_use]
+    pub fn report(&self) -> GraphReport {
+        fn counts(counts: &HashMap<Cow<'static, str>, u64>) -> BTreeMap<String, u64> {
+            counts.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+        }
+        let nodes = self
+            .graph
+            .iter()
+            .zip(&self.metrics)
+            .map(|(node, metrics)| {
+                let outputs = counts(&metrics.outputs);
+                NodeReport {
+                    id: node.id.clone(),
+                    kind: match node.kind {
+                        NodeKind::Input => "input",
+                        NodeKind::Output(_) => "output",
+                        NodeKind::Operator => "operator",
+                        NodeKind::Select => "select",
+                        NodeKind::Script => "script",
+                    }
+                    .to_string(),
+                    op_type: node.op_type.clone(),
+                    inputs: counts(&metrics.inputs),
+                    errors: outputs.get("err").copied().unwrap_or_default(),
+                    outputs,
+                }
+            })
+            .collect();
+        let mut edges: Vec<EdgeReport> = self
+            .port_indexes
+            .iter()
+            .flat_map(|((from, from_port), tos)| {
+                tos.iter().filter_map(move |(to, to_port)| {
+                    Some(EdgeReport {
+                        from: self.graph.get(*from)?.id.clone(),
+                        from_port: from_port.to_string(),
+                        to: self.graph.get(*to)?.id.clone(),
+                        to_port: to_port.to_string(),
+                    })
+                })
+            })
+            .collect();
+        // the port index map is unordered
+        edges.sort();
+        GraphReport {
+            id: self.id.clone(),
+            inputs: self.input_ports().into_iter().map(String::from).collect(),
+            outputs: self.output_ports().into_iter().map(String::from).collect(),
+            nodes,
+            edges,
+            dot: self.dot.clone(),
+        }
+    }
+
+    /// Tracks the events leaving this graph so snapshots can be staged as checkpoints
+    pub fn enable_checkpoints(&mut self) {
+        self.checkpoints.get_or_insert_with(Checkpoints::default);
+    }
+
+    /// Records an event leaving this graph, it is delivered to `destinations`
+    /// destinations that each acknowledge it if it is transactional
+    pub fn sent(&mut self, event: &Event, destinations: usize) {
+        if let Some(checkpoints) = self.checkpoints.as_mut() {
+            if event.transactional {
+                checkpoints.sent(&event.id, destinations);
+            }
+        }
+    }
+
+    /// Takes a snapshot of the state of all nodes
+    ///
+    /// # Errors
+    /// if an operator fails to snapshot its state
+    pub fn snapshot(&self) -> Result<Value<'static>> {
+        let mut nodes = Value::object_with_capacity(self.graph.len());
+        for (node, state) in self.graph.iter().zip(self.state.ops.iter()) {
+            let op = stry!(node.snapshot());
+            if state.is_null() && op.is_none() {
+                continue;
+            }
+            let mut snapshot = Value::object_with_capacity(2);
+            snapshot.try_insert("state", state.clone_static());
+            if let Some(op) = op {
+                snapshot.try_insert("op", op);
+            }
+            nodes.try_insert(node.id.clone(), snapshot);
+        }
+        Ok(nodes)
+    }
+
+    /// Restores the state of all nodes from a snapshot taken with `snapshot`,
+    /// nodes that are no longer part of the graph are ignored
+    ///
+    /// # Errors
+    /// if an operator fails to restore its state
+    pub fn restore(&mut self, snapshot: &Value) -> Result<()> {
+        for (node, state) in self.graph.iter_mut().zip(self.state.ops.iter_mut()) {
+            if let Some(node_snapshot) = snapshot.get(node.id.as_str()) {
+                if let Some(s) = node_snapshot.get("state") {
+                    *state = s.clone_static();
+                }
+                if let Some(op) = node_snapshot.get("op") {
+                    stry!(node.restore(op));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes a snapshot and stages it as the next checkpoint, it becomes
+    /// available through `take_checkpoint` once all events sent before are acknowledged
+    ///
+    /// # Errors
+    /// if an operator fails to snapshot its state
+    pub fn checkpoint(&mut self) -> Result<()> {
+        let snapshot = stry!(self.snapshot());
+        self.checkpoints
+            .get_or_insert_with(Checkpoints::default)
+            .stage(snapshot);
+        Ok(())
+    }
+
+    /// Takes the latest checkpoint that is ready to be persisted
+    pub fn take_checkpoint(&mut self) -> Option<Value<'static>> {
+        self.checkpoints.as_mut().and_then(Checkpoints::take_ready)
+    }
     /// Enqueue a signal
     ///
     /// # Errors
@@ -618,7 +834,6 @@ mod test {
         GraphReturns, METRICS_CHANNEL,
     };
     use tremor_common::ids::Id;
-    use tremor_script::prelude::*;
     fn pass(uid: OperatorId, id: &'static str) -> OperatorNode {
         let config = NodeConfig::from_config(&"passthrough", None);
         OperatorNode {
@@ -819,6 +1034,7 @@ mod test {
         let mut rx = METRICS_CHANNEL.rx();
         let mut g = ExecutableGraph {
             id: "test".into(),
+            flow_id: None,
             graph,
             state,
             inputs,
@@ -833,6 +1049,7 @@ mod test {
             insights: vec![],
             dot: String::from(""),
             metrics_channel: METRICS_CHANNEL.tx(),
+            checkpoints: None,
         };
 
         // Test with one event
@@ -871,6 +1088,37 @@ mod test {
             metrics.push(m);
         }
         test_metrics(metrics, 3);
+
+        let report = g.report();
+        assert_eq!("test", report.id);
+        assert_eq!(vec!["in".to_string()], report.inputs);
+        assert_eq!(vec!["out".to_string()], report.outputs);
+        let kinds: Vec<_> = report.nodes.iter().map(|n| n.kind.as_str()).collect();
+        assert_eq!(vec!["input", "operator", "operator", "output"], kinds);
+        assert_eq!(
+            NodeReport {
+                id: "all-1".to_string(),
+                kind: "operator".to_string(),
+                op_type: "test".to_string(),
+                inputs: vec![("in".to_string(), 3)].into_iter().collect(),
+                outputs: vec![("out".to_string(), 3)].into_iter().collect(),
+                errors: 0,
+            },
+            report.nodes[1]
+        );
+        let edges: Vec<_> = report
+            .edges
+            .iter()
+            .map(|e| format!("{}/{} -> {}/{}", e.from, e.from_port, e.to, e.to_port))
+            .collect();
+        assert_eq!(
+            vec![
+                "all-1/out -> all-2/in",
+                "all-2/out -> out/in",
+                "in/out -> all-1/in"
+            ],
+            edges
+        );
     }
 
     #[async_std::test]
@@ -919,6 +1167,7 @@ mod test {
         };
         let mut g = ExecutableGraph {
             id: "test".into(),
+            flow_id: None,
             graph,
             state,
             inputs,
@@ -933,6 +1182,7 @@ mod test {
             insights: vec![],
             dot: String::from(""),
             metrics_channel: METRICS_CHANNEL.tx(),
+            checkpoints: None,
         };
         assert!(g.optimize().is_some());
         // Test with one event
@@ -141,6 +141,24 @@ pub trait Operator: std::fmt::Debug + Send + Sync {
     fn skippable(&self) -> bool {
         false
     }
+
+    /// Snapshots the internal state of the operator so it can be persisted
+    /// and restored after a restart, defaults to no state.
+    ///
+    /// # Errors
+    /// if the state can not be snapshotted
+    fn snapshot(&self) -> Result<Option<Value<'static>>> {
+        Ok(None)
+    }
+
+    /// Restores the internal state of the operator from a snapshot taken
+    /// with `snapshot`, defaults to a noop.
+    ///
+    /// # Errors
+    /// if the snapshot can not be restored
+    fn restore(&mut self, _snapshot: &Value) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Initialisable trait that can be turned from a `NodeConfig`
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{op::prelude::*, EventId, EventIdGenerator};
+use crate::{op::prelude::*, EventId, EventIdGenerator, SignalKind};
 use std::mem::swap;
 use tremor_script::prelude::*;
 
@@ -149,36 +149,30 @@ impl Operator for Batch {
         _state: &mut Value<'static>,
         signal: &mut Event,
     ) -> Result<EventAndInsights> {
-        Ok(self
-            .max_delay_ns
-            .map_or_else(EventAndInsights::default, |delay_ns| {
-                if signal.ingest_ns - self.first_ns > delay_ns {
-                    // We don't want to modify the original signal we clone it to
-                    // create a new event.
-
-                    if self.len > 0 {
-                        let mut data = empty();
-                        swap(&mut data, &mut self.data);
-
-                        self.len = 0; // reset len
-                        let mut event = Event {
-                            id: self.event_id_gen.next_id(),
-                            data,
-                            ingest_ns: self.first_ns,
-                            is_batch: true,
-                            transactional: self.is_transactional,
-                            ..Event::default()
-                        };
-                        self.is_transactional = false;
-                        swap(&mut self.batch_event_id, &mut event.id);
-                        EventAndInsights::from(event)
-                    } else {
-                        EventAndInsights::default()
-                    }
-                } else {
-                    EventAndInsights::default()
-                }
-            }))
+        // a shutdown flushes the batch, as nothing will be added to it anymore
+        let flush = signal.kind == Some(SignalKind::Shutdown)
+            || self.max_delay_ns.map_or(false, |delay_ns| {
+                signal.ingest_ns - self.first_ns > delay_ns
+            });
+        if flush && self.len > 0 {
+            let mut data = empty();
+            swap(&mut data, &mut self.data);
+
+            self.len = 0; // reset len
+            let mut event = Event {
+                id: self.event_id_gen.next_id(),
+                data,
+                ingest_ns: self.first_ns,
+                is_batch: true,
+                transactional: self.is_transactional,
+                ..Event::default()
+            };
+            self.is_transactional = false;
+            swap(&mut self.batch_event_id, &mut event.id);
+            Ok(EventAndInsights::from(event))
+        } else {
+            Ok(EventAndInsights::default())
+        }
     }
 }
 
@@ -405,6 +399,24 @@ mod test {
             .on_event(operator_id, "in", &mut state, event)
             .expect("failed to run piepeline");
         assert_eq!(r.len(), 0);
+
+        // a shutdown flushes the batch before the timeout
+        let mut signal = Event {
+            ingest_ns: 3,
+            kind: Some(SignalKind::Shutdown),
+            ..Event::default()
+        };
+        let mut r = op
+            .on_signal(operator_id, &mut state, &mut signal)
+            .expect("failed to run pipeline")
+            .events;
+        assert_eq!(r.len(), 1);
+        let (_, event) = r.pop().expect("empty resultset");
+        assert_eq!(2, event.value_iter().count());
+        let r = op
+            .on_signal(operator_id, &mut state, &mut signal)
+            .expect("failed to run pipeline");
+        assert!(r.events.is_empty());
     }
 
     #[test]
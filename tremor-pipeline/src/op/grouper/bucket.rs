@@ -69,7 +69,6 @@ use beef::Cow;
 use halfbrown::HashMap;
 use lru::LruCache;
 use tremor_script::prelude::*;
-use window::TimeWindow;
 
 const BUCKETING: Cow<'static, str> = Cow::const_str("bucketing");
 const CLASS: Cow<'static, str> = Cow::const_str("class");
@@ -112,8 +111,90 @@ impl Rate {
     }
 }
 
+/// Counts the events of a dimension within a sliding time range, split into a ring of slots
+#[derive(Debug, Clone, PartialEq)]
+struct RateWindow {
+    /// the maximum number of events within the time range
+    limit: u64,
+    /// the duration of a slot in nanoseconds
+    granularity: u64,
+    /// the event counts of the slots within the time range
+    counts: Vec<u64>,
+    /// the slot the latest event was counted in
+    head: u64,
+}
+
+impl RateWindow {
+    fn new(rate: &Rate) -> Self {
+        let windows = rate.windows.max(1);
+        Self {
+            limit: rate.rate,
+            granularity: (rate.time_range * 1_000_000 / windows as u64).max(1),
+            counts: vec![0; windows],
+            head: 0,
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)] // the slot index is below `counts.len()`
+    fn slot_mut(&mut self, slot: u64) -> Option<&mut u64> {
+        let len = self.counts.len() as u64;
+        self.counts.get_mut((slot % len) as usize)
+    }
+
+    /// counts an event ingested at `ingest_ns`, returns `false` if the rate is exceeded
+    fn inc_t(&mut self, ingest_ns: u64) -> bool {
+        let slot = ingest_ns / self.granularity;
+        if slot > self.head {
+            // clear the slots the time range moved past since the latest event
+            let passed = (slot - self.head).min(self.counts.len() as u64);
+            for s in (slot + 1 - passed)..=slot {
+                if let Some(count) = self.slot_mut(s) {
+                    *count = 0;
+                }
+            }
+            self.head = slot;
+        }
+        if self.counts.iter().sum::<u64>() < self.limit {
+            // late events are counted in the latest slot
+            let head = self.head;
+            if let Some(count) = self.slot_mut(head) {
+                *count += 1;
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn snapshot(&self) -> Value<'static> {
+        literal!({
+            "limit": self.limit,
+            "granularity": self.granularity,
+            "counts": self.counts.clone(),
+            "head": self.head,
+        })
+    }
+
+    fn restore(snapshot: &Value) -> Option<Self> {
+        let counts: Vec<u64> = snapshot
+            .get_array("counts")?
+            .iter()
+            .map(Value::as_u64)
+            .collect::<Option<_>>()?;
+        if counts.is_empty() {
+            return None;
+        }
+        Some(Self {
+            limit: snapshot.get_u64("limit")?,
+            granularity: snapshot.get_u64("granularity")?.max(1),
+            counts,
+            head: snapshot.get_u64("head")?,
+        })
+    }
+}
+
 pub struct Bucket {
-    cache: LruCache<String, TimeWindow>,
+    cache: LruCache<String, RateWindow>,
     pass: u64,
     overflow: u64,
 }
@@ -167,14 +248,7 @@ impl Operator for Grouper {
                     } else {
                         return Ok(vec![(ERR, event)].into());
                     };
-                    groups.cache.put(
-                        dimensions.clone(),
-                        TimeWindow::new(
-                            rate.windows,
-                            rate.time_range / (rate.windows as u64),
-                            rate.rate,
-                        ),
-                    );
+                    groups.cache.put(dimensions.clone(), RateWindow::new(&rate));
                     if let Some(g) = groups.cache.get_mut(&dimensions) {
                         g
                     } else {
@@ -184,7 +258,7 @@ impl Operator for Grouper {
                 }
                 Some(m) => m,
             };
-            if window.inc_t(event.ingest_ns).is_ok() {
+            if window.inc_t(event.ingest_ns) {
                 groups.pass += 1;
                 Ok(event.into())
             } else {
@@ -216,6 +290,49 @@ impl Operator for Grouper {
         }
         Ok(res)
     }
+
+    // The rate windows are bound to the ingest time of the events, so they
+    // are persisted as is and keep counting with events after a restore.
+    fn snapshot(&self) -> Result<Option<Value<'static>>> {
+        let mut buckets = Value::object_with_capacity(self.buckets.len());
+        for (class, b) in &self.buckets {
+            // the most recently used window first
+            let windows: Vec<Value<'static>> = b
+                .cache
+                .iter()
+                .map(|(dimensions, window)| literal!([dimensions.clone(), window.snapshot()]))
+                .collect();
+            buckets.try_insert(
+                class.clone(),
+                literal!({
+                    "cardinality": b.cache.cap(),
+                    "pass": b.pass,
+                    "overflow": b.overflow,
+                    "windows": windows,
+                }),
+            );
+        }
+        Ok(Some(buckets))
+    }
+
+    fn restore(&mut self, snapshot: &Value) -> Result<()> {
+        self.buckets.clear();
+        for (class, b) in snapshot.as_object().into_iter().flatten() {
+            let mut bucket = Bucket::new(b.get_usize("cardinality").unwrap_or(1000));
+            bucket.pass = b.get_u64("pass").unwrap_or_default();
+            bucket.overflow = b.get_u64("overflow").unwrap_or_default();
+            // the least recently used window is put first, so it is evicted first again
+            for window in b.get_array("windows").into_iter().flatten().rev() {
+                let dimensions = window.get_idx(0).and_then(Value::as_str);
+                let window = window.get_idx(1).and_then(RateWindow::restore);
+                if let (Some(dimensions), Some(window)) = (dimensions, window) {
+                    bucket.cache.put(dimensions.to_string(), window);
+                }
+            }
+            self.buckets.insert(class.to_string(), bucket);
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -309,5 +426,63 @@ mod test {
         assert_eq!(overflow["fields"]["count"], 1);
         assert_eq!(pass["tags"]["action"], "pass");
         assert_eq!(pass["fields"]["count"], 3);
+
+        let snapshot = op.snapshot().unwrap().unwrap();
+        let mut restored = Grouper {
+            buckets: HashMap::new(),
+            _id: "badger".into(),
+        };
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(
+            op.metrics(&HashMap::new(), 0).unwrap(),
+            restored.metrics(&HashMap::new(), 0).unwrap()
+        );
+
+        // the rate windows are restored, half a second later the rate is still exceeded
+        let event4 = Event {
+            id: (1, 1, 1).into(),
+            ingest_ns: 10_500_000_000,
+            data: (Value::from("snot"), literal!({"class": "test", "rate": 2})).into(),
+            ..Event::default()
+        };
+        let mut r = op
+            .on_event(operator_id, "in", &mut state, event4.clone())
+            .expect("could not run pipeline");
+        let (port, _) = r.events.pop().unwrap();
+        assert_eq!(port, "out");
+        let mut r = restored
+            .on_event(operator_id, "in", &mut state, event4.clone())
+            .expect("could not run pipeline");
+        let (port, _) = r.events.pop().unwrap();
+        assert_eq!(port, "out");
+        let mut r = restored
+            .on_event(operator_id, "in", &mut state, event4)
+            .expect("could not run pipeline");
+        let (port, _) = r.events.pop().unwrap();
+        assert_eq!(port, "overflow");
+    }
+
+    #[test]
+    fn rate_window() {
+        let mut window = RateWindow::new(&Rate {
+            rate: 2,
+            time_range: 1000,
+            windows: 10,
+        });
+        assert!(window.inc_t(1_000_000_000));
+        assert!(window.inc_t(1_400_000_000));
+        assert!(!window.inc_t(1_900_000_000));
+        // the first event left the time range
+        assert!(window.inc_t(2_050_000_000));
+        assert!(!window.inc_t(2_100_000_000));
+        // late events count towards the latest slot
+        assert!(!window.inc_t(0));
+        // all events left the time range
+        assert!(window.inc_t(10_000_000_000));
+        assert_eq!(
+            Some(window.clone()),
+            RateWindow::restore(&window.snapshot())
+        );
+        assert_eq!(None, RateWindow::restore(&literal!({"counts": []})));
     }
 }
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod join;
 pub mod operator;
 pub mod script;
 pub mod select;
@@ -0,0 +1,431 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Joins of the stream a select reads from with another stream or a table.
+//!
+//! Stream joins keep the events of both streams for the `interval` of the join window and
+//! join every event with the kept events of the other stream with the same key.
+//! Table joins keep the latest event per key of the joined stream, a `null` value removes
+//! the key, and join every event of the stream selected from with the entry for its key
+//! or `null` if there is none, events of the joined table are acked once they are applied.
+//! Both kinds of joins keep at most `max_groups` keys per stream. A stream join still joins
+//! an event with a new key beyond that, but sends it to `err` instead of keeping it.
+//!
+//! Joined events are records of the form `{"left": <value>, "right": <value>}`,
+//! the where clause, the target and the having clause of the select are evaluated on them.
+
+use super::select::run_guard;
+use crate::op::prelude::*;
+use crate::{EventId, EventIdGenerator, SignalKind};
+use std::collections::VecDeque;
+use tremor_script::{
+    ast::{self, JoinKind, SelectStmt},
+    interpreter::{Env, LocalStack},
+    prelude::*,
+    utils::sorted_serialize,
+    NO_AGGRS,
+};
+
+/// the port the joined stream is connected to
+pub const RIGHT: Cow<'static, str> = Cow::const_str("right");
+
+/// An event kept for joining
+#[derive(Debug)]
+struct Kept {
+    id: EventId,
+    ingest_ns: u64,
+    transactional: bool,
+    value: Value<'static>,
+    meta: Value<'static>,
+}
+
+type Side = HashMap<String, VecDeque<Kept>>;
+
+/// The key of an event kept by a stream join, in the order the events arrived
+#[derive(Debug)]
+struct Arrival {
+    ingest_ns: u64,
+    is_right: bool,
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct Join {
+    pub id: String,
+    pub(crate) select: ast::SelectStmt<'static>,
+    kind: JoinKind,
+    /// how long events of a stream join are kept
+    interval: u64,
+    max_groups: usize,
+    left: Side,
+    right: Side,
+    /// the events kept by a stream join, oldest first, so they are evicted without scanning all keys
+    arrivals: VecDeque<Arrival>,
+    event_id_gen: EventIdGenerator,
+    recursion_limit: u32,
+}
+
+impl Join {
+    /// Creates a join for a select statement, events of stream joins are kept for `interval` ns
+    pub fn from_stmt(
+        operator_uid: OperatorId,
+        id: String,
+        interval: u64,
+        max_groups: usize,
+        select: &ast::SelectStmt<'static>,
+    ) -> Result<Self> {
+        let kind = select
+            .stmt
+            .maybe_join
+            .as_ref()
+            .map(|join| join.kind)
+            .ok_or_else(|| ErrorKind::BadOpConfig("Select without join".to_string()))?;
+        Ok(Self {
+            id,
+            select: select.clone(),
+            kind,
+            interval,
+            max_groups,
+            left: HashMap::new(),
+            right: HashMap::new(),
+            arrivals: VecDeque::new(),
+            event_id_gen: EventIdGenerator::for_operator(operator_uid),
+            recursion_limit: tremor_script::recursion_limit(),
+        })
+    }
+
+    const fn opts() -> ExecOpts {
+        ExecOpts {
+            result_needed: true,
+            aggr: AggrType::Emit,
+        }
+    }
+
+    /// removes all events of stream joins kept for longer than the interval
+    fn evict(&mut self, ingest_ns: u64) {
+        let oldest = ingest_ns.saturating_sub(self.interval);
+        while let Some(arrival) = self.arrivals.front() {
+            if arrival.ingest_ns >= oldest {
+                break;
+            }
+            let side = if arrival.is_right {
+                &mut self.right
+            } else {
+                &mut self.left
+            };
+            // the events of a key are kept in the order they arrived
+            if let Some(kept) = side.get_mut(&arrival.key) {
+                kept.pop_front();
+                if kept.is_empty() {
+                    side.remove(&arrival.key);
+                }
+            }
+            self.arrivals.pop_front();
+        }
+    }
+}
+
+fn too_many_keys(max_groups: usize, key: &str) -> Error {
+    ErrorKind::BadOpConfig(format!(
+        "Maxmimum amount of keys reached ({max_groups}). Ignoring key {key}"
+    ))
+    .into()
+}
+
+/// the event sent to `err` for the `kept` copy of `event` that failed with `error`
+fn error_event(event: &Event, kept: Kept, error: &Error) -> Event {
+    Event {
+        id: event.id.clone(),
+        ingest_ns: event.ingest_ns,
+        origin_uri: event.origin_uri.clone(),
+        op_meta: event.op_meta.clone(),
+        data: (
+            literal!({
+                "error": error.to_string(),
+                "event": kept.value,
+            }),
+            kept.meta,
+        )
+            .into(),
+        transactional: event.transactional,
+        ..Event::default()
+    }
+}
+
+/// evaluates the where clause, target and having clause of a join select on a joined event
+fn joined(
+    stmt: &ast::Select,
+    env: &Env,
+    local_stack: &LocalStack,
+    event: &Event,
+    (left, right): (&Value, &Value),
+    other: Option<&Kept>,
+) -> Result<Option<Event>> {
+    let opts = Join::opts();
+    let value = literal!({
+        "left": left.clone_static(),
+        "right": right.clone_static(),
+    });
+    let meta = event.data.suffix().meta();
+    if !run_guard(
+        stmt,
+        &stmt.maybe_where,
+        opts,
+        env,
+        &value,
+        meta,
+        local_stack,
+    )? {
+        return Ok(None);
+    }
+    let value = stmt
+        .target
+        .run(opts, env, &value, &NULL, meta, local_stack)?
+        .into_owned();
+    if !run_guard(
+        stmt,
+        &stmt.maybe_having,
+        opts,
+        env,
+        &value,
+        meta,
+        local_stack,
+    )? {
+        return Ok(None);
+    }
+    let mut id = event.id.clone();
+    let mut transactional = event.transactional;
+    if let Some(other) = other {
+        id.track(&other.id);
+        transactional |= other.transactional;
+    }
+    Ok(Some(Event {
+        id,
+        ingest_ns: event.ingest_ns,
+        origin_uri: event.origin_uri.clone(),
+        op_meta: event.op_meta.clone(),
+        data: (value.into_static(), meta.clone_static()).into(),
+        transactional,
+        ..Event::default()
+    }))
+}
+
+impl Operator for Join {
+    #[allow(clippy::too_many_lines)]
+    fn on_event(
+        &mut self,
+        _uid: OperatorId,
+        port: &str,
+        _state: &mut Value<'static>,
+        event: Event,
+    ) -> Result<EventAndInsights> {
+        self.evict(event.ingest_ns);
+        let Self {
+            select,
+            kind,
+            max_groups,
+            left,
+            right,
+            arrivals,
+            recursion_limit,
+            ..
+        } = self;
+        let SelectStmt {
+            stmt,
+            consts,
+            locals,
+            ..
+        } = select;
+        let join = stmt
+            .maybe_join
+            .as_ref()
+            .ok_or_else(|| ErrorKind::BadOpConfig("Select without join".to_string()))?;
+
+        let is_right = port.eq_ignore_ascii_case(&RIGHT);
+        let ctx = EventContext::new(event.ingest_ns, event.origin_uri.as_ref());
+        let local_stack = LocalStack::with_size(*locals);
+        let env = Env {
+            context: &ctx,
+            consts: consts.run(),
+            aggrs: &NO_AGGRS,
+            recursion_limit: *recursion_limit,
+        };
+        let (value, meta) = event.data.parts();
+        let key_expr = if is_right {
+            &join.right_key
+        } else {
+            &join.left_key
+        };
+        let key = key_expr.run(Self::opts(), &env, value, &NULL, meta, &local_stack)?;
+        let key = sorted_serialize(&key)?;
+        let kept = Kept {
+            id: event.id.clone(),
+            ingest_ns: event.ingest_ns,
+            transactional: event.transactional,
+            value: value.clone_static(),
+            meta: meta.clone_static(),
+        };
+
+        let mut res = EventAndInsights::default();
+        match (*kind, is_right) {
+            // the joined stream updates the table, its events are acked once applied
+            (JoinKind::Table, true) => {
+                if value.is_null() {
+                    right.remove(&key);
+                } else if !right.contains_key(&key) && right.len() >= *max_groups {
+                    return Err(too_many_keys(*max_groups, &key));
+                } else {
+                    right.insert(key, VecDeque::from([kept]));
+                }
+                if event.transactional {
+                    res.insights.push(event.insight_ack());
+                }
+            }
+            (JoinKind::Table, false) => {
+                let null = Value::null();
+                let right_value = right
+                    .get(&key)
+                    .and_then(VecDeque::back)
+                    .map_or(&null, |kept| &kept.value);
+                // the table entry is acked already, so it isn't tracked by the joined event
+                if let Some(event) =
+                    joined(stmt, &env, &local_stack, &event, (value, right_value), None)?
+                {
+                    res.events.push((OUT, event));
+                }
+            }
+            (JoinKind::Stream, _) => {
+                let (this, other) = if is_right {
+                    (right, left)
+                } else {
+                    (left, right)
+                };
+                for kept_other in other.get(&key).into_iter().flatten() {
+                    let values = if is_right {
+                        (&kept_other.value, value)
+                    } else {
+                        (value, &kept_other.value)
+                    };
+                    if let Some(event) =
+                        joined(stmt, &env, &local_stack, &event, values, Some(kept_other))?
+                    {
+                        res.events.push((OUT, event));
+                    }
+                }
+                if let Some(kept_for_key) = this.get_mut(&key) {
+                    kept_for_key.push_back(kept);
+                } else if this.len() >= *max_groups {
+                    // the event is joined nonetheless, it is only not kept to join later events
+                    let error = too_many_keys(*max_groups, &key);
+                    res.events.push((ERR, error_event(&event, kept, &error)));
+                    return Ok(res);
+                } else {
+                    this.insert(key.clone(), VecDeque::from([kept]));
+                }
+                arrivals.push_back(Arrival {
+                    ingest_ns: event.ingest_ns,
+                    is_right,
+                    key,
+                });
+            }
+        }
+        Ok(res)
+    }
+
+    fn handles_signal(&self) -> bool {
+        self.kind == JoinKind::Stream
+    }
+
+    fn on_signal(
+        &mut self,
+        _uid: OperatorId,
+        _state: &mut Value<'static>,
+        signal: &mut Event,
+    ) -> Result<EventAndInsights> {
+        if signal.kind == Some(SignalKind::Tick) {
+            self.evict(signal.ingest_ns);
+        }
+        Ok(EventAndInsights::default())
+    }
+
+    fn snapshot(&self) -> Result<Option<Value<'static>>> {
+        Ok(Some(literal!({
+            "left": snapshot_side(&self.left),
+            "right": snapshot_side(&self.right),
+        })))
+    }
+
+    fn restore(&mut self, snapshot: &Value) -> Result<()> {
+        self.left = restore_side(snapshot.get("left"), &mut self.event_id_gen);
+        self.right = restore_side(snapshot.get("right"), &mut self.event_id_gen);
+        self.arrivals.clear();
+        if self.kind == JoinKind::Stream {
+            let mut arrivals = Vec::new();
+            for (is_right, side) in [(false, &self.left), (true, &self.right)] {
+                for (key, kept) in side {
+                    arrivals.extend(kept.iter().map(|k| Arrival {
+                        ingest_ns: k.ingest_ns,
+                        is_right,
+                        key: key.clone(),
+                    }));
+                }
+            }
+            // stable, so the events of a key stay in the order they arrived
+            arrivals.sort_by_key(|a| a.ingest_ns);
+            self.arrivals = arrivals.into();
+        }
+        Ok(())
+    }
+}
+
+fn snapshot_side(side: &Side) -> Value<'static> {
+    let mut snapshot = Value::object_with_capacity(side.len());
+    for (key, kept) in side {
+        let kept: Vec<_> = kept
+            .iter()
+            .map(|k| {
+                literal!({
+                    "ingest_ns": k.ingest_ns,
+                    "transactional": k.transactional,
+                    "value": k.value.clone(),
+                    "meta": k.meta.clone(),
+                })
+            })
+            .collect();
+        snapshot.try_insert(key.clone(), kept);
+    }
+    snapshot
+}
+
+fn restore_side(snapshot: Option<&Value>, event_id_gen: &mut EventIdGenerator) -> Side {
+    let mut side = HashMap::new();
+    for (key, kept) in snapshot.and_then(Value::as_object).into_iter().flatten() {
+        let kept = kept
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|k| Kept {
+                id: event_id_gen.next_id(),
+                ingest_ns: k.get_u64("ingest_ns").unwrap_or_default(),
+                transactional: k.get_bool("transactional").unwrap_or_default(),
+                value: k.get("value").map_or_else(Value::null, Value::clone_static),
+                meta: k
+                    .get("meta")
+                    .map_or_else(Value::object, Value::clone_static),
+            })
+            .collect();
+        side.insert(key.to_string(), kept);
+    }
+    side
+}
@@ -17,7 +17,7 @@
 #[cfg(test)]
 mod test;
 
-use std::mem;
+use std::{collections::BTreeMap, mem};
 
 use super::window::{self, Group, Window};
 use crate::op::prelude::trickle::window::{GroupWindow, SelectCtx, Trait};
@@ -29,7 +29,7 @@ use tremor_common::stry;
 
 use tremor_script::{
     self,
-    ast::{self, ImutExpr, RunConsts, SelectStmt},
+    ast::{self, Consts, ImutExpr, OrderDirection, RunConsts, SelectStmt},
     errors::{error_generic, Result as TSResult},
     interpreter::{Env, LocalStack},
     prelude::*,
@@ -47,6 +47,14 @@ pub struct Select {
     recursion_limit: u32,
     dflt_group: Group,
     max_groups: usize,
+    /// The watermark for event time windows, no events with an event time
+    /// before it are expected anymore
+    watermark: Option<u64>,
+    /// The ingest time the watermark last advanced at
+    watermark_ns: Option<u64>,
+    /// The groups by the time their first window closes at, so only the
+    /// groups due are visited on ticks and watermark advances
+    closing: BTreeMap<u64, Vec<String>>,
 }
 
 impl Select {
@@ -69,6 +77,7 @@ impl Select {
         let dflt_group = Group {
             value: Value::const_null(),
             windows: GroupWindow::from_windows(aggregates, &EventId::default(), windows_itr),
+            closes_at: None,
         };
         let windows_itr = windows.iter();
         let max_groups = windows_itr
@@ -84,6 +93,9 @@ impl Select {
             recursion_limit: tremor_script::recursion_limit(),
             dflt_group,
             max_groups,
+            watermark: None,
+            watermark_ns: None,
+            closing: BTreeMap::new(),
         }
     }
     const fn opts() -> ExecOpts {
@@ -155,6 +167,338 @@ fn env<'run, 'script>(
     }
 }
 
+/// Emits the first window of a group and propagates its data along the
+/// following tilt frames, the event id of the window needs to be moved into
+/// the context before.
+///
+/// # Returns
+///
+/// true  - If no window in the group holds on to any data
+///         and the entire group can be safely removed.
+/// false - If at least one window holds on to some data
+///         and this group can **not** be removed.
+fn emit_first_window(
+    w: &mut GroupWindow,
+    group: &Value<'static>,
+    consts: &Consts<'static>,
+    ctx: &mut SelectCtx,
+    data: &ValueAndMeta,
+    events: &mut Vec<(Cow<'static, str>, Event)>,
+) -> TSResult<bool> {
+    // sliding windows emit the data of all their panes
+    let panes = w.close_pane(ctx)?;
+    let mut run = consts.run();
+    run.group = group;
+    run.window = &w.name;
+    if let Some(aggrs) = panes.as_ref().or_else(|| w.holds_data.then(|| &w.aggrs)) {
+        let mut env = env(ctx.ctx, run, ctx.recursion_limit);
+        env.aggrs = aggrs;
+        if let Some(port_and_event) = execute_select_and_having(ctx, &env, data)? {
+            events.push(port_and_event);
+        };
+    }
+    let mut can_remove = true;
+    if let Some(next) = &mut w.next {
+        can_remove = next.on_event(
+            ctx,
+            run,
+            data,
+            events,
+            Some((w.holds_data, &w.aggrs)),
+            can_remove,
+        )?;
+    }
+    // re-initialize aggr state for new window
+    w.reset();
+    w.promote_pending();
+    // previous or pending panes still holding data keep the group alive
+    Ok(can_remove && !w.holds_panes() && !w.holds_data)
+}
+
+/// Updates the time the first window of a group closes at in the index of closing groups
+fn reindex(closing: &mut BTreeMap<u64, Vec<String>>, group_str: &str, group: &mut Group) {
+    let closes_at = group.window_closes_at();
+    if closes_at != group.closes_at {
+        unindex(closing, group_str, group);
+        if let Some(closes_at) = closes_at {
+            closing
+                .entry(closes_at)
+                .or_default()
+                .push(group_str.to_string());
+        }
+        group.closes_at = closes_at;
+    }
+}
+
+/// Removes a group from the index of closing groups
+fn unindex(closing: &mut BTreeMap<u64, Vec<String>>, group_str: &str, group: &Group) {
+    if let Some(closes_at) = group.closes_at {
+        if let Some(group_strs) = closing.get_mut(&closes_at) {
+            group_strs.retain(|g| g != group_str);
+            if group_strs.is_empty() {
+                closing.remove(&closes_at);
+            }
+        }
+    }
+}
+
+/// Emits the first window of the groups `group_strs` that were taken out of the index of
+/// closing groups as they close at the same time, the events of these windows are ordered and
+/// limited together. A `now` of `None` flushes the windows.
+#[allow(clippy::too_many_arguments)]
+fn emit_windows(
+    select: &ast::Select<'static>,
+    consts: &Consts<'static>,
+    groups: &mut HashMap<String, Group>,
+    closing: &mut BTreeMap<u64, Vec<String>>,
+    event_id_gen: &mut EventIdGenerator,
+    local_stack: &LocalStack,
+    ctx: &EventContext,
+    ingest_ns: u64,
+    recursion_limit: u32,
+    group_strs: &[String],
+    now: Option<u64>,
+    res: &mut EventAndInsights,
+) -> TSResult<()> {
+    let data: ValueAndMeta = (Value::const_null(), Value::object()).into();
+    let op_meta = OpMeta::default();
+    let mut emitted = Vec::with_capacity(group_strs.len());
+    for group_str in group_strs {
+        let g = if let Some(g) = groups.get_mut(group_str) {
+            g
+        } else {
+            continue;
+        };
+        // the group was taken out of the index, it is indexed again by its next window
+        g.closes_at = None;
+        let mut can_remove = false;
+        if let Some(w) = &mut g.windows {
+            let emit = match now {
+                None => true,
+                Some(watermark) if w.window.is_event_time() => {
+                    w.window.on_watermark(watermark).emit
+                }
+                Some(now) => w.window.on_tick(now).emit,
+            };
+            if !emit {
+                w.evict_panes();
+                reindex(closing, group_str, g);
+                continue;
+            }
+            // move the event id of the window into the outgoing event
+            let mut outgoing_event_id = event_id_gen.next_id();
+            mem::swap(&mut outgoing_event_id, &mut w.id);
+
+            let mut sel_ctx = SelectCtx {
+                select,
+                local_stack,
+                opts: Select::opts(),
+                ctx,
+                event_id: outgoing_event_id,
+                event_id_gen,
+                ingest_ns,
+                op_meta: &op_meta,
+                origin_uri: &None,
+                transactional: w.transactional,
+                recursion_limit,
+                late: false,
+                watermark: None,
+            };
+            can_remove = emit_first_window(w, &g.value, consts, &mut sel_ctx, &data, &mut emitted)?;
+        }
+        if can_remove {
+            groups.remove(group_str);
+        } else {
+            reindex(closing, group_str, g);
+        }
+    }
+    order_and_limit_window(
+        select,
+        consts,
+        local_stack,
+        ctx,
+        recursion_limit,
+        &mut emitted,
+        &mut res.insights,
+    )?;
+    res.events.append(&mut emitted);
+    Ok(())
+}
+
+/// Emits the windows of all groups closing at or before `now`, only the groups due are visited.
+///
+/// Event time windows are aligned, `now` is the watermark and the windows ending at the same
+/// time are emitted together in the order they end. Windows on processing time close at `now`,
+/// so all windows due are emitted together.
+#[allow(clippy::too_many_arguments)]
+fn emit_due_windows(
+    select: &ast::Select<'static>,
+    consts: &Consts<'static>,
+    groups: &mut HashMap<String, Group>,
+    closing: &mut BTreeMap<u64, Vec<String>>,
+    event_id_gen: &mut EventIdGenerator,
+    local_stack: &LocalStack,
+    ctx: &EventContext,
+    ingest_ns: u64,
+    recursion_limit: u32,
+    event_time: bool,
+    now: u64,
+    res: &mut EventAndInsights,
+) -> TSResult<()> {
+    // event time windows of a group can close one after the other, so we
+    // take the next windows closing from the index until none is due anymore
+    while let Some(closes_at) = closing.keys().next().copied().filter(|at| *at <= now) {
+        let mut group_strs = closing.remove(&closes_at).unwrap_or_default();
+        if !event_time {
+            while let Some(closes_at) = closing.keys().next().copied().filter(|at| *at <= now) {
+                group_strs.append(&mut closing.remove(&closes_at).unwrap_or_default());
+            }
+        }
+        emit_windows(
+            select,
+            consts,
+            groups,
+            closing,
+            event_id_gen,
+            local_stack,
+            ctx,
+            ingest_ns,
+            recursion_limit,
+            &group_strs,
+            Some(now),
+            res,
+        )?;
+    }
+    Ok(())
+}
+
+/// Emits the first window of all groups, event time windows ending at the same time are
+/// emitted together in the order they end, all other windows are emitted together
+#[allow(clippy::too_many_arguments)]
+fn flush_windows(
+    select: &ast::Select<'static>,
+    consts: &Consts<'static>,
+    groups: &mut HashMap<String, Group>,
+    closing: &mut BTreeMap<u64, Vec<String>>,
+    event_id_gen: &mut EventIdGenerator,
+    local_stack: &LocalStack,
+    ctx: &EventContext,
+    ingest_ns: u64,
+    recursion_limit: u32,
+    event_time: bool,
+    res: &mut EventAndInsights,
+) -> TSResult<()> {
+    let mut all: Vec<(Option<u64>, String)> = groups
+        .iter()
+        .map(|(group_str, g)| (g.closes_at.filter(|_| event_time), group_str.clone()))
+        .collect();
+    all.sort();
+    closing.clear();
+    let mut batch = Vec::new();
+    let mut batch_closes_at = None;
+    for (closes_at, group_str) in all {
+        if closes_at != batch_closes_at && !batch.is_empty() {
+            emit_windows(
+                select,
+                consts,
+                groups,
+                closing,
+                event_id_gen,
+                local_stack,
+                ctx,
+                ingest_ns,
+                recursion_limit,
+                &batch,
+                None,
+                res,
+            )?;
+            batch.clear();
+        }
+        batch_closes_at = closes_at;
+        batch.push(group_str);
+    }
+    emit_windows(
+        select,
+        consts,
+        groups,
+        closing,
+        event_id_gen,
+        local_stack,
+        ctx,
+        ingest_ns,
+        recursion_limit,
+        &batch,
+        None,
+        res,
+    )
+}
+
+/// Orders and limits the events emitted by windows closing at the same time
+fn order_and_limit_window(
+    select: &ast::Select<'static>,
+    consts: &Consts<'static>,
+    local_stack: &LocalStack,
+    ctx: &EventContext,
+    recursion_limit: u32,
+    events: &mut Vec<(Cow<'static, str>, Event)>,
+    insights: &mut Vec<Event>,
+) -> TSResult<()> {
+    let null = Value::const_null();
+    let mut run = consts.run();
+    run.group = &null;
+    run.window = &null;
+    let env = env(ctx, run, recursion_limit);
+    order_and_limit(select, Select::opts(), &env, local_stack, events, insights)
+}
+
+/// Orders the events emitted at once by the `order by` clause of the statement
+/// and only keeps the first `limit` of them, events sent to other ports than `out` are kept as they are.
+///
+/// Dropped transactional events are acked via `insights`,
+/// as they carry the ids of all events of their windows.
+fn order_and_limit(
+    select: &ast::Select,
+    opts: ExecOpts,
+    env: &Env,
+    local_stack: &LocalStack,
+    events: &mut Vec<(Cow<'static, str>, Event)>,
+    insights: &mut Vec<Event>,
+) -> TSResult<()> {
+    if select.maybe_order_by.is_none() && select.maybe_limit.is_none() {
+        return Ok(());
+    }
+    let (mut out, other): (Vec<_>, Vec<_>) = events.drain(..).partition(|(port, _)| *port == OUT);
+    if let Some(order_by) = &select.maybe_order_by {
+        let mut keyed = Vec::with_capacity(out.len());
+        for (port, event) in out {
+            let key = {
+                let (value, meta) = event.data.parts();
+                let key = order_by
+                    .expr
+                    .run(opts, env, value, &NULL, meta, local_stack)?;
+                key.into_owned().into_static()
+            };
+            keyed.push((key, (port, event)));
+        }
+        // the sort is stable, so events with the same key keep the order they were emitted in
+        match order_by.direction {
+            OrderDirection::Asc => keyed.sort_by(|(a, _), (b, _)| a.cmp(b)),
+            OrderDirection::Desc => keyed.sort_by(|(a, _), (b, _)| b.cmp(a)),
+        }
+        out = keyed.into_iter().map(|(_, event)| event).collect();
+    }
+    if let Some(limit) = select.maybe_limit {
+        for (_, event) in out.drain(limit.min(out.len())..) {
+            if event.transactional {
+                insights.push(event.insight_ack());
+            }
+        }
+    }
+    events.extend(out);
+    events.extend(other);
+    Ok(())
+}
+
 #[derive(Debug)]
 /// Simple enum to decide what we return
 enum Res {
@@ -177,6 +521,7 @@ impl Res {
 impl Operator for Select {
     // Note: we don't use state in this function as select does not allow mutation
     // so the state can never be changed.
+    #[allow(clippy::too_many_lines)]
     fn on_event(
         &mut self,
         _uid: OperatorId,
@@ -192,6 +537,9 @@ impl Operator for Select {
             recursion_limit,
             dflt_group,
             max_groups,
+            watermark,
+            watermark_ns,
+            closing,
             ..
         } = self;
         let Event {
@@ -255,7 +603,34 @@ impl Operator for Select {
 
             // Usually one or two windows emit, this is the common case so we don't pre-allocate
             // for the entire window depth
-            let mut events = Vec::with_capacity(group_values.len() * 2);
+            let mut res = EventAndInsights {
+                events: Vec::with_capacity(group_values.len() * 2),
+                ..EventAndInsights::default()
+            };
+            let mut late = false;
+            let mut new_watermark = None;
+
+            // windows closing by now are emitted before the event opens new ones, so all windows
+            // closing at the same time are ordered and limited together
+            let event_time = windows
+                .first()
+                .map_or(false, |w| w.window_impl.is_event_time());
+            if !event_time && (select.maybe_order_by.is_some() || select.maybe_limit.is_some()) {
+                stry!(emit_due_windows(
+                    select,
+                    consts,
+                    groups,
+                    closing,
+                    event_id_gen,
+                    &locals,
+                    &ctx,
+                    ingest_ns,
+                    *recursion_limit,
+                    false,
+                    ingest_ns,
+                    &mut res
+                ));
+            }
 
             // with the `each` grouping an event could be in more then one group, so we
             // iterate over all groups we found
@@ -264,7 +639,8 @@ impl Operator for Select {
 
                 ctx.cardinality = groups.len();
 
-                let sel_ctx = SelectCtx {
+                let mut emitted = Vec::new();
+                let mut sel_ctx = SelectCtx {
                     select,
                     local_stack: &locals,
                     opts,
@@ -276,16 +652,27 @@ impl Operator for Select {
                     origin_uri,
                     transactional,
                     recursion_limit: *recursion_limit,
+                    late: false,
+                    watermark: None,
                 };
 
                 // see if we know the group already, we use the `entry` here so we don't
                 // need to add / remove from the groups unenessessarily
                 match groups.entry(group_str) {
                     Entry::Occupied(mut o) => {
+                        // groups not due on the last watermark advance still need to know it
+                        // to detect late events
+                        if let (Some(w), Some(watermark)) = (&mut o.get_mut().windows, *watermark) {
+                            w.window.on_watermark(watermark);
+                        }
                         // If we found a group execute it, and remove it if it is not longer
                         // needed
-                        if stry!(o.get_mut().on_event(sel_ctx, consts, event, &mut events)) {
+                        if stry!(o.get_mut().on_event(&mut sel_ctx, consts, event, &mut emitted)) {
+                            unindex(closing, o.key(), o.get());
                             o.remove();
+                        } else {
+                            let group_str = o.key().clone();
+                            reindex(closing, &group_str, o.get_mut());
                         }
                     }
                     Entry::Vacant(v) => {
@@ -294,7 +681,7 @@ impl Operator for Select {
                         dflt_group.value = group_value;
                         dflt_group.value.try_push(v.key().to_string());
                         // execute it
-                        if !stry!(dflt_group.on_event(sel_ctx, consts, event, &mut events)) {
+                        if !stry!(dflt_group.on_event(&mut sel_ctx, consts, event, &mut emitted)) {
                             // if we can't delete it check if we're having too many groups,
                             // if so, error.
                             if ctx.cardinality >= *max_groups {
@@ -307,13 +694,69 @@ impl Operator for Select {
                             // otherwise we clone the default group (this is a cost we got to pay)
                             // and reset it . If we didn't clone here we'd need to allocate a new
                             // group for every event we haven't seen yet
-                            v.insert(dflt_group.clone());
+                            let group_str = v.key().clone();
+                            reindex(closing, &group_str, v.insert(dflt_group.clone()));
                             dflt_group.reset();
                         }
                     }
                 }
+                late |= sel_ctx.late;
+                new_watermark = new_watermark.max(sel_ctx.watermark);
+                stry!(order_and_limit_window(
+                    select,
+                    consts,
+                    &locals,
+                    &ctx,
+                    *recursion_limit,
+                    &mut emitted,
+                    &mut res.insights
+                ));
+                res.events.append(&mut emitted);
+            }
+
+            // events for already emitted event time windows are passed on unchanged
+            if late {
+                let (data, meta) = event.parts();
+                res.events.push((
+                    LATE,
+                    Event {
+                        id: id.clone(),
+                        ingest_ns,
+                        origin_uri: origin_uri.clone(),
+                        op_meta: op_meta.clone(),
+                        data: (data.clone_static(), meta.clone_static()).into(),
+                        transactional,
+                        ..Event::default()
+                    },
+                ));
+            }
+
+            // if the watermark advanced we emit all event time windows ending before it
+            if let Some(new_watermark) =
+                new_watermark.filter(|new| watermark.map_or(true, |current| *new > current))
+            {
+                *watermark = Some(new_watermark);
+                *watermark_ns = Some(ingest_ns);
+                // new groups need to know the watermark to detect late events
+                if let Some(w) = &mut dflt_group.windows {
+                    w.window.on_watermark(new_watermark);
+                }
+                stry!(emit_due_windows(
+                    select,
+                    consts,
+                    groups,
+                    closing,
+                    event_id_gen,
+                    &locals,
+                    &ctx,
+                    ingest_ns,
+                    *recursion_limit,
+                    true,
+                    new_watermark,
+                    &mut res
+                ));
             }
-            Ok(Res::Data(events.into()))
+            Ok(Res::Data(res))
         })?;
 
         Ok(res.into_insights(event))
@@ -325,26 +768,34 @@ impl Operator for Select {
         _state: &mut Value<'static>,
         signal: &mut Event,
     ) -> Result<EventAndInsights> {
-        // we only react on ticks and when we have windows
+        // we only react on ticks and shutdowns and when we have windows
         let Self {
             select,
             windows,
             event_id_gen,
             groups,
             recursion_limit,
+            dflt_group,
+            watermark,
+            watermark_ns,
+            closing,
             ..
         } = self;
         let recursion_limit = *recursion_limit;
 
+        // a shutdown emits all windows, as no event will close them anymore
+        let flush = signal.kind == Some(SignalKind::Shutdown);
         // if it isn't a tick or we do not have any windows, or have no
         // recorded groups, we can just return
-        if signal.kind != Some(SignalKind::Tick) || windows.is_empty() || groups.is_empty() {
+        if (signal.kind != Some(SignalKind::Tick) && !flush)
+            || windows.is_empty()
+            || groups.is_empty()
+        {
             return Ok(EventAndInsights::default());
         }
 
         let ingest_ns = signal.ingest_ns;
 
-        let opts = Self::opts();
         let SelectStmt {
             stmt: select,
             consts,
@@ -353,8 +804,6 @@ impl Operator for Select {
         } = select;
         let mut res = EventAndInsights::default();
 
-        let data: ValueAndMeta = (Value::const_null(), Value::object()).into();
-        let op_meta = OpMeta::default();
         let local_stack = tremor_script::interpreter::LocalStack::with_size(*locals);
 
         consts.window = Value::const_null();
@@ -364,69 +813,66 @@ impl Operator for Select {
         let mut ctx = EventContext::new(ingest_ns, None);
         ctx.cardinality = groups.len();
 
-        let mut to_remove = vec![];
-        for (group_str, g) in groups.iter_mut() {
-            if let Some(w) = &mut g.windows {
-                let mut outgoing_event_id = event_id_gen.next_id();
-                mem::swap(&mut w.id, &mut outgoing_event_id);
-
-                let mut run = consts.run();
-                run.group = &g.value;
-                run.window = &w.name;
-                let window_event = w.window.on_tick(ingest_ns);
-                let mut can_remove = window_event.emit;
-
-                if window_event.emit {
-                    // push
-                    let mut env = env(&ctx, run, recursion_limit);
-                    env.aggrs = &w.aggrs;
-
-                    let mut outgoing_event_id = event_id_gen.next_id();
-
-                    mem::swap(&mut outgoing_event_id, &mut w.id);
-
-                    let mut ctx = SelectCtx {
+        let event_time = windows
+            .first()
+            .map_or(false, |w| w.window_impl.is_event_time());
+        if flush {
+            flush_windows(
+                select,
+                consts,
+                groups,
+                closing,
+                event_id_gen,
+                &local_stack,
+                &ctx,
+                ingest_ns,
+                recursion_limit,
+                event_time,
+                &mut res,
+            )?;
+        } else if event_time {
+            // an idle stream doesn't hold back event time windows, on ticks the watermark
+            // advances by the time passed since it last advanced
+            if let Some(current) = *watermark {
+                let since = *watermark_ns.get_or_insert(ingest_ns);
+                let new_watermark = current.saturating_add(ingest_ns.saturating_sub(since));
+                if new_watermark > current {
+                    *watermark = Some(new_watermark);
+                    *watermark_ns = Some(ingest_ns);
+                    if let Some(w) = &mut dflt_group.windows {
+                        w.window.on_watermark(new_watermark);
+                    }
+                    emit_due_windows(
                         select,
-                        local_stack: &local_stack,
-                        opts,
-                        ctx: &ctx,
-                        event_id: outgoing_event_id,
+                        consts,
+                        groups,
+                        closing,
                         event_id_gen,
+                        &local_stack,
+                        &ctx,
                         ingest_ns,
-                        op_meta: &op_meta,
-                        origin_uri: &None,
-                        transactional: w.transactional,
                         recursion_limit,
-                    };
-                    if w.holds_data {
-                        if let Some(port_and_event) =
-                            super::select::execute_select_and_having(&ctx, &env, &data)?
-                        {
-                            res.events.push(port_and_event);
-                        };
-                    }
-                    // re-initialize aggr state for new window
-                    // reset transactional state for outgoing events
-
-                    if let Some(next) = &mut w.next {
-                        can_remove = next.on_event(
-                            &mut ctx,
-                            run,
-                            &data,
-                            &mut res.events,
-                            Some((w.holds_data, &w.aggrs)),
-                            can_remove,
-                        )?;
-                    }
-                    w.reset();
-                }
-                if can_remove {
-                    to_remove.push(group_str.clone());
+                        true,
+                        new_watermark,
+                        &mut res,
+                    )?;
                 }
             }
-        }
-        for g in to_remove {
-            groups.remove(&g);
+        } else {
+            emit_due_windows(
+                select,
+                consts,
+                groups,
+                closing,
+                event_id_gen,
+                &local_stack,
+                &ctx,
+                ingest_ns,
+                recursion_limit,
+                false,
+                ingest_ns,
+                &mut res,
+            )?;
         }
         Ok(res)
     }
@@ -434,9 +880,49 @@ impl Operator for Select {
     fn handles_signal(&self) -> bool {
         true
     }
+
+    fn snapshot(&self) -> Result<Option<Value<'static>>> {
+        if self.windows.is_empty() {
+            return Ok(None);
+        }
+        let mut groups = Value::object_with_capacity(self.groups.len());
+        for (group_str, group) in &self.groups {
+            groups.try_insert(group_str.clone(), group.snapshot()?);
+        }
+        Ok(Some(literal!({
+            "watermark": self.watermark,
+            "groups": groups,
+        })))
+    }
+
+    fn restore(&mut self, snapshot: &Value) -> Result<()> {
+        let Self {
+            groups,
+            dflt_group,
+            event_id_gen,
+            watermark,
+            closing,
+            ..
+        } = self;
+        groups.clear();
+        closing.clear();
+        *watermark = snapshot.get_u64("watermark");
+        // new groups need to know the watermark of event time windows
+        if let (Some(w), Some(watermark)) = (&mut dflt_group.windows, *watermark) {
+            w.window.on_watermark(watermark);
+        }
+        for (group_str, group_snapshot) in snapshot.get_object("groups").into_iter().flatten() {
+            let mut group = dflt_group.clone();
+            group.reset();
+            group.restore(group_snapshot, event_id_gen)?;
+            reindex(closing, group_str, &mut group);
+            groups.insert(group_str.to_string(), group);
+        }
+        Ok(())
+    }
 }
 
-fn run_guard(
+pub(crate) fn run_guard(
     select: &ast::Select,
     guard: &Option<ImutExpr>,
     opts: ExecOpts,
@@ -17,7 +17,7 @@
 #![allow(clippy::float_cmp)]
 use crate::op::prelude::trickle::window::{Actions, Trait};
 use crate::query::window_defn_to_impl;
-use crate::EventId;
+use crate::{CbAction, EventId};
 
 use super::*;
 
@@ -68,6 +68,9 @@ fn test_stmt(target: ast::ImutExpr) -> ast::Select {
         windows: vec![],
         maybe_group_by: None,
         maybe_having: None,
+        maybe_join: None,
+        maybe_order_by: None,
+        maybe_limit: None,
     }
 }
 
@@ -304,6 +307,60 @@ fn select_single_win_with_script_on_signal() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn select_flushes_windows_on_shutdown() -> Result<()> {
+    let mut select = select_stmt_from_query(
+        r#"
+        define window window1 from tumbling
+        with
+            size = 3
+        end;
+        select aggr::win::collect_flattened(event) from in[window1] group by event.g into out;
+        "#,
+    )?;
+    let uid = OperatorId::new(42);
+    let mut state = Value::null();
+    for (pull_id, g) in [(1, "a"), (2, "b"), (3, "a")] {
+        let event = Event {
+            id: (1, 1, pull_id).into(),
+            ingest_ns: pull_id,
+            data: literal!({ "g": g }).into(),
+            ..Event::default()
+        };
+        let eis = select.on_event(uid, "IN", &mut state, event)?;
+        assert_eq!(0, eis.events.len());
+    }
+    // a tick doesn't close count based windows
+    let eis = select.on_signal(uid, &mut state, &mut test_tick(4))?;
+    assert_eq!(0, eis.events.len());
+
+    let mut shutdown = Event {
+        id: EventId::from_id(1, 1, 5),
+        kind: Some(SignalKind::Shutdown),
+        ingest_ns: 5,
+        ..Event::default()
+    };
+    let eis = select.on_signal(uid, &mut state, &mut shutdown)?;
+    assert!(eis.insights.is_empty());
+    let mut emitted = eis
+        .events
+        .iter()
+        .map(|(_, event)| sorted_serialize(event.data.parts().0))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    emitted.sort();
+    assert_eq!(
+        vec![
+            r#"[{"g":"a"},{"g":"a"}]"#.to_string(),
+            r#"[{"g":"b"}]"#.to_string()
+        ],
+        emitted
+    );
+    // and nothing is left to emit
+    let eis = select.on_signal(uid, &mut state, &mut shutdown)?;
+    assert_eq!(0, eis.events.len());
+    Ok(())
+}
+
 #[test]
 fn select_single_win_on_signal() -> Result<()> {
     let mut select = select_stmt_from_query(
@@ -354,6 +411,104 @@ fn select_single_win_on_signal() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn select_sliding_win_on_signal() -> Result<()> {
+    let mut select = select_stmt_from_query(
+        r#"
+        define window window1 from sliding
+        with
+            interval = 4,
+            slide = 2
+        end;
+        select aggr::win::collect_flattened(event.v) from in[window1] group by event.g into out;
+        "#,
+    )?;
+    let uid = OperatorId::new(42);
+    let mut state = Value::null();
+    let event = |ingest_ns: u64, v: u64| Event {
+        id: (1, 1, ingest_ns).into(),
+        ingest_ns,
+        data: literal!({
+           "g": "group",
+           "v": v
+        })
+        .into(),
+        ..Event::default()
+    };
+
+    let mut eis = select.on_event(uid, "in", &mut state, event(2, 1))?;
+    assert_eq!(0, eis.events.len());
+    eis = select.on_event(uid, "in", &mut state, event(3, 2))?;
+    assert_eq!(0, eis.events.len());
+
+    // first slide
+    eis = select.on_signal(uid, &mut state, &mut test_tick(4))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("[1,2]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+
+    eis = select.on_event(uid, "in", &mut state, event(5, 3))?;
+    assert_eq!(0, eis.events.len());
+
+    // the window now spans both panes
+    eis = select.on_signal(uid, &mut state, &mut test_tick(6))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("[1,2,3]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+    assert_eq!(1, select.groups.len());
+
+    // the first pane slid out of the window
+    eis = select.on_signal(uid, &mut state, &mut test_tick(8))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("[3]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+
+    // all panes are empty now so the group got removed
+    assert!(select.groups.is_empty());
+    eis = select.on_signal(uid, &mut state, &mut test_tick(10))?;
+    assert_eq!(0, eis.events.len());
+    Ok(())
+}
+
+#[test]
+fn select_sliding_win_after_gap() -> Result<()> {
+    let mut select = select_stmt_from_query(
+        r#"
+        define window window1 from sliding
+        with
+            interval = 4,
+            slide = 2
+        end;
+        select aggr::win::collect_flattened(event.v) from in[window1] group by event.g into out;
+        "#,
+    )?;
+    let uid = OperatorId::new(42);
+    let mut state = Value::null();
+    let event = |ingest_ns: u64, v: u64| Event {
+        id: (1, 1, ingest_ns).into(),
+        ingest_ns,
+        data: literal!({
+           "g": "group",
+           "v": v
+        })
+        .into(),
+        ..Event::default()
+    };
+
+    let mut eis = select.on_event(uid, "in", &mut state, event(2, 1))?;
+    assert_eq!(0, eis.events.len());
+    eis = select.on_event(uid, "in", &mut state, event(5, 2))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("[1]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+
+    // after a gap longer than the interval the first pane slid out of the window
+    eis = select.on_event(uid, "in", &mut state, event(20, 3))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("[2]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+
+    eis = select.on_event(uid, "in", &mut state, event(30, 4))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("[3]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+    Ok(())
+}
+
 #[test]
 fn select_multiple_wins_on_signal() -> Result<()> {
     let mut select = select_stmt_from_query(
@@ -464,6 +619,47 @@ fn test_transactional_single_window() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_transactional_limit() -> Result<()> {
+    let mut op = select_stmt_from_query(
+        r#"
+            define window w2 from tumbling
+            with
+              interval = 2
+            end;
+            select {"group": group[0], "count": aggr::stats::count()}
+            from in[w2]
+            group by event.group
+            into out
+            order by event.group desc
+            limit 1;
+        "#,
+    )?;
+    let uid = OperatorId::new(0);
+    let mut state = Value::null();
+    let event1 = test_event_tx(0, true, 0);
+    let id1 = event1.id.clone();
+    let res = op.on_event(uid, "in", &mut state, event1)?;
+    assert!(res.events.is_empty());
+    let event2 = test_event_tx(1, true, 1);
+    let id2 = event2.id.clone();
+    let res = op.on_event(uid, "in", &mut state, event2)?;
+    assert!(res.events.is_empty());
+
+    let mut res = op.on_signal(uid, &mut state, &mut test_tick(103))?;
+    assert_eq!(1, res.events.len());
+    let (_, event) = res.events.pop().unwrap();
+    assert_eq!(&literal!({"group": 1, "count": 1}), event.data.parts().0);
+    assert_eq!(true, event.id.is_tracking(&id2));
+    // the window left out by the limit is acked, so its events are acked at their source
+    assert_eq!(1, res.insights.len());
+    let insight = &res.insights[0];
+    assert_eq!(CbAction::Ack, insight.cb);
+    assert_eq!(true, insight.id.is_tracking(&id1));
+    assert_eq!(false, insight.id.is_tracking(&id2));
+    Ok(())
+}
+
 #[test]
 fn test_transactional_multiple_windows() -> Result<()> {
     let mut op = select_stmt_from_query(
@@ -914,6 +1110,274 @@ fn tumbling_window_on_time_emit_empty_windows() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn session_window_on_time() -> Result<()> {
+    let mut window = window::SessionOnTime::from_stmt(100, window::Impl::DEFAULT_MAX_GROUPS, None);
+    let vm = ValueAndMeta::default();
+    // no session, nothing to close
+    assert_eq!(Actions::all_false(), window.on_tick(0));
+    // the first event opens the session
+    assert_eq!(Actions::all_false(), window.on_event(&vm, 10, &None)?);
+    assert_eq!(Actions::all_false(), window.on_event(&vm, 109, &None)?);
+    assert_eq!(Actions::all_false(), window.on_tick(200));
+    // an out of order event doesn't move the session backwards
+    assert_eq!(Actions::all_false(), window.on_event(&vm, 50, &None)?);
+    assert_eq!(Actions::all_false(), window.on_tick(208));
+    // the gap expired
+    assert_eq!(
+        Actions {
+            include: false,
+            emit: true
+        },
+        window.on_tick(209)
+    );
+    assert_eq!(Actions::all_false(), window.on_tick(400));
+    // a new session
+    assert_eq!(Actions::all_false(), window.on_event(&vm, 500, &None)?);
+    // an event after the gap closes the session and starts the next one
+    assert_eq!(
+        Actions {
+            include: false,
+            emit: true
+        },
+        window.on_event(&vm, 600, &None)?
+    );
+    Ok(())
+}
+
+#[test]
+fn select_session_win_on_signal() -> Result<()> {
+    let mut select = select_stmt_from_query(
+        r#"
+        define window sess from session
+        with
+            gap = 10
+        end;
+        select aggr::win::collect_flattened(event.v) from in[sess] group by event.g into out;
+        "#,
+    )?;
+    let uid = OperatorId::new(42);
+    let mut state = Value::null();
+    let event = |ingest_ns: u64, g: &'static str, v: u64| Event {
+        id: (1, 1, ingest_ns).into(),
+        ingest_ns,
+        data: literal!({
+           "g": g,
+           "v": v
+        })
+        .into(),
+        ..Event::default()
+    };
+
+    let mut eis = select.on_event(uid, "in", &mut state, event(1, "a", 1))?;
+    assert_eq!(0, eis.events.len());
+    eis = select.on_event(uid, "in", &mut state, event(5, "b", 2))?;
+    assert_eq!(0, eis.events.len());
+    eis = select.on_event(uid, "in", &mut state, event(8, "a", 3))?;
+    assert_eq!(0, eis.events.len());
+
+    // the session of `b` is still active while `a` was active recently
+    eis = select.on_signal(uid, &mut state, &mut test_tick(12))?;
+    assert_eq!(0, eis.events.len());
+
+    // only the session of `b` expired
+    eis = select.on_signal(uid, &mut state, &mut test_tick(15))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("[2]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+    assert_eq!(1, select.groups.len());
+
+    // now the session of `a` expired as well
+    eis = select.on_signal(uid, &mut state, &mut test_tick(18))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("[1,3]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+    assert!(select.groups.is_empty());
+    Ok(())
+}
+
+#[test]
+fn select_session_win_with_script_on_signal() -> Result<()> {
+    let mut select = select_stmt_from_query(
+        r#"
+        define window sess from session
+        with
+            gap = 10
+        script
+            event.time
+        end;
+        select aggr::win::collect_flattened(event.v) from in[sess] group by event.g into out;
+        "#,
+    )?;
+    let uid = OperatorId::new(42);
+    let mut state = Value::null();
+    let event = |ingest_ns: u64, time: u64, v: u64| Event {
+        id: (1, 1, ingest_ns).into(),
+        ingest_ns,
+        data: literal!({
+           "g": "group",
+           "time": time,
+           "v": v
+        })
+        .into(),
+        ..Event::default()
+    };
+
+    let mut eis = select.on_event(uid, "in", &mut state, event(1, 1000, 1))?;
+    assert_eq!(0, eis.events.len());
+    eis = select.on_event(uid, "in", &mut state, event(5, 1004, 2))?;
+    assert_eq!(0, eis.events.len());
+
+    // the session advanced to 1011 in event time
+    eis = select.on_signal(uid, &mut state, &mut test_tick(12))?;
+    assert_eq!(0, eis.events.len());
+
+    // the session is idle for the gap since its last event
+    eis = select.on_signal(uid, &mut state, &mut test_tick(15))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("[1,2]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+    assert!(select.groups.is_empty());
+    Ok(())
+}
+
+#[test]
+fn tumbling_window_on_event_time() -> Result<()> {
+    let mut window =
+        window::TumblingOnEventTime::from_stmt(10, 5, window::Impl::DEFAULT_MAX_GROUPS, None);
+    let vm = ValueAndMeta::default();
+    // events are never emitted as they arrive, the placement tells where they belong to
+    let none = Actions::all_false();
+    // the first event opens the window [10, 20)
+    assert_eq!(none, window.on_event(&vm, 12, &None)?);
+    assert_eq!(window::Placement::Current, window.placement());
+    assert_eq!(Some(7), window.watermark());
+    // events are never emitted on ticks
+    assert_eq!(Actions::all_false(), window.on_tick(100));
+    // an event for a later window
+    assert_eq!(none, window.on_event(&vm, 25, &None)?);
+    assert_eq!(window::Placement::Ahead(30), window.placement());
+    assert_eq!(Some(20), window.watermark());
+    // an event for an earlier window that was not yet emitted
+    assert_eq!(none, window.on_event(&vm, 3, &None)?);
+    assert_eq!(window::Placement::Earlier(20), window.placement());
+    assert_eq!(Some(20), window.watermark());
+    // the watermark closes [0, 10) and [10, 20)
+    let emit = Actions {
+        include: false,
+        emit: true,
+    };
+    assert_eq!(emit, window.on_watermark(20));
+    // after an emit the first pending window becomes the current one
+    window.promote(20);
+    assert_eq!(emit, window.on_watermark(20));
+    window.promote(30);
+    assert_eq!(Actions::all_false(), window.on_watermark(20));
+    // events for emitted windows are late
+    assert_eq!(none, window.on_event(&vm, 15, &None)?);
+    assert_eq!(window::Placement::Late, window.placement());
+    assert_eq!(none, window.on_event(&vm, 20, &None)?);
+    assert_eq!(window::Placement::Current, window.placement());
+    Ok(())
+}
+
+#[test]
+fn select_event_time_win_on_watermark() -> Result<()> {
+    let mut select = select_stmt_from_query(
+        r#"
+        define window ev from tumbling
+        with
+            interval = 10,
+            allowed_lateness = 5
+        end;
+        select aggr::win::collect_flattened(event.v) from in[ev] into out;
+        "#,
+    )?;
+    let uid = OperatorId::new(42);
+    let mut state = Value::null();
+    let event = |ingest_ns: u64, v: u64| Event {
+        id: (1, 1, ingest_ns).into(),
+        ingest_ns,
+        data: literal!({ "v": v }).into(),
+        ..Event::default()
+    };
+
+    let mut eis = select.on_event(uid, "in", &mut state, event(12, 1))?;
+    assert_eq!(0, eis.events.len());
+    // out of order, but within the allowed lateness
+    eis = select.on_event(uid, "in", &mut state, event(8, 2))?;
+    assert_eq!(0, eis.events.len());
+    // a tick advances the watermark by the time passed, not far enough to close a window
+    eis = select.on_signal(uid, &mut state, &mut test_tick(14))?;
+    assert_eq!(0, eis.events.len());
+
+    // the watermark passes 10 and closes the first window
+    eis = select.on_event(uid, "in", &mut state, event(16, 3))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("out", eis.events[0].0);
+    assert_eq!("[2]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+
+    // the window of this event was already emitted
+    eis = select.on_event(uid, "in", &mut state, event(4, 4))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("late", eis.events[0].0);
+    assert_eq!(
+        r#"{"v":4}"#,
+        sorted_serialize(eis.events[0].1.data.parts().0)?
+    );
+
+    // the watermark passes 20 and closes the second window
+    eis = select.on_event(uid, "in", &mut state, event(30, 5))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("out", eis.events[0].0);
+    assert_eq!("[1,3]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+    assert_eq!(1, select.groups.len());
+    Ok(())
+}
+
+#[test]
+fn select_event_time_win_far_ahead_and_on_tick() -> Result<()> {
+    let mut select = select_stmt_from_query(
+        r#"
+        define window ev from tumbling
+        with
+            interval = 10,
+            allowed_lateness = 5
+        script
+            event.time
+        end;
+        select aggr::win::collect_flattened(event.v) from in[ev] into out;
+        "#,
+    )?;
+    let uid = OperatorId::new(42);
+    let mut state = Value::null();
+    let event = |ingest_ns: u64, time: u64, v: u64| Event {
+        id: (1, 1, ingest_ns).into(),
+        ingest_ns,
+        data: literal!({ "time": time, "v": v }).into(),
+        ..Event::default()
+    };
+
+    let mut eis = select.on_event(uid, "in", &mut state, event(12, 12, 1))?;
+    assert_eq!(0, eis.events.len());
+    // the windows in between an event far ahead are never opened
+    eis = select.on_event(uid, "in", &mut state, event(14, 1_000_012, 2))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("[1]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+    let group = select
+        .groups
+        .values()
+        .next()
+        .and_then(|g| g.windows.as_ref());
+    assert_eq!(Some(0), group.map(|w| w.pending.len()));
+
+    // without further events ticks advance the watermark
+    eis = select.on_signal(uid, &mut state, &mut test_tick(20))?;
+    assert_eq!(0, eis.events.len());
+    eis = select.on_signal(uid, &mut state, &mut test_tick(30))?;
+    assert_eq!(1, eis.events.len());
+    assert_eq!("[2]", sorted_serialize(eis.events[0].1.data.parts().0)?);
+    assert!(select.groups.is_empty());
+    Ok(())
+}
+
 #[test]
 fn no_window_emit() -> Result<()> {
     let mut window = window::No::default();
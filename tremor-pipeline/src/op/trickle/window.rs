@@ -14,7 +14,7 @@
 
 use crate::{Event, EventId, EventIdGenerator, OpMeta};
 use beef::Cow;
-use std::borrow::Cow as SCow;
+use std::{borrow::Cow as SCow, collections::BTreeMap};
 use tremor_common::stry;
 use tremor_script::{
     self,
@@ -39,6 +39,10 @@ pub(crate) struct SelectCtx<'run, 'script, 'local> {
     pub(crate) origin_uri: &'run Option<EventOriginUri>,
     pub(crate) transactional: bool,
     pub(crate) recursion_limit: u32,
+    /// Set if the event belongs to a window that was already emitted
+    pub(crate) late: bool,
+    /// The watermark derived from the event time seen by the first window
+    pub(crate) watermark: Option<u64>,
 }
 
 /// A singular tilt frame (window) inside a group
@@ -63,6 +67,26 @@ pub struct GroupWindow {
     pub(crate) next: Option<Box<GroupWindow>>,
     /// If the window holds any data
     pub(crate) holds_data: bool,
+    /// The previous panes of a sliding window, keyed by their start
+    pub(crate) panes: BTreeMap<u64, Pane>,
+    /// The windows following the current one that are still open
+    /// for an event time window, keyed by their end
+    pub(crate) pending: BTreeMap<u64, Pane>,
+}
+
+/// A closed slide of a sliding window, kept around until it
+/// slides out of the window, or a pending window of an event
+/// time window
+#[derive(Clone, Debug)]
+pub struct Pane {
+    /// The aggregates of this pane
+    pub(crate) aggrs: Aggregates<'static>,
+    /// The event id(s) of all events that are tracked in this pane
+    pub(crate) id: EventId,
+    /// If the data of the pane is considered transactional
+    pub(crate) transactional: bool,
+    /// If the pane holds any data
+    pub(crate) holds_data: bool,
 }
 
 impl GroupWindow {
@@ -84,6 +108,8 @@ impl GroupWindow {
                 transactional: false,
                 next: GroupWindow::from_windows(aggrs, id, iter),
                 holds_data: false,
+                panes: BTreeMap::new(),
+                pending: BTreeMap::new(),
             })
         })
     }
@@ -96,6 +122,161 @@ impl GroupWindow {
         self.holds_data = false;
     }
 
+    /// If any of the previous panes of a sliding window or the pending
+    /// windows of an event time window hold data
+    pub(crate) fn holds_panes(&self) -> bool {
+        self.panes
+            .values()
+            .chain(self.pending.values())
+            .any(|p| p.holds_data)
+    }
+
+    /// If this window or any of the following tilt frames hold data
+    fn holds_any_data(&self) -> bool {
+        self.holds_data
+            || self.holds_panes()
+            || self
+                .next
+                .as_ref()
+                .map_or(false, |next| next.holds_any_data())
+    }
+
+    /// Creates an empty pane for this window
+    fn empty_pane(&self, id: EventId) -> Pane {
+        let mut aggrs = self.aggrs.clone();
+        for aggr in &mut aggrs {
+            aggr.invocable.init();
+        }
+        Pane {
+            aggrs,
+            id,
+            transactional: false,
+            holds_data: false,
+        }
+    }
+
+    /// Makes the first pending window of an event time window the
+    /// current one, this is called after the current window was emitted
+    /// and reset
+    pub(crate) fn promote_pending(&mut self) {
+        let first = self.pending.keys().next().copied();
+        if let Some((end, pane)) = first.and_then(|end| self.pending.remove_entry(&end)) {
+            self.aggrs = pane.aggrs;
+            self.id = pane.id;
+            self.transactional = pane.transactional;
+            self.holds_data = pane.holds_data;
+            self.window.promote(end);
+        }
+    }
+
+    /// Accumulates data into the pending window ending at `end`, only
+    /// windows holding data are kept pending
+    fn accumulate_pending(
+        &mut self,
+        ctx: &mut SelectCtx,
+        consts: RunConsts,
+        data: &ValueAndMeta,
+        end: u64,
+    ) -> Result<()> {
+        let mut pane = if let Some(pane) = self.pending.remove(&end) {
+            pane
+        } else {
+            self.empty_pane(ctx.event_id_gen.next_id())
+        };
+        pane.id.track(&ctx.event_id);
+        pane.transactional |= ctx.transactional;
+        pane.holds_data = true;
+        stry!(accumulate_into(
+            &mut pane.aggrs,
+            &self.name,
+            ctx,
+            consts,
+            data
+        ));
+        self.pending.insert(end, pane);
+        Ok(())
+    }
+
+    /// Opens an earlier window before the current one of an event time
+    /// window, the current window ending at `current` becomes pending
+    fn open_earlier(&mut self, ctx: &mut SelectCtx, current: u64) {
+        let mut id = ctx.event_id_gen.next_id();
+        std::mem::swap(&mut id, &mut self.id);
+        self.pending.insert(
+            current,
+            Pane {
+                aggrs: self.aggrs.clone(),
+                id,
+                transactional: self.transactional,
+                holds_data: self.holds_data,
+            },
+        );
+        self.reset();
+    }
+
+    /// Closes the current pane of a sliding window, retiring it into the
+    /// previous panes, tracking their event ids in the context and merging
+    /// the aggregates of all panes the window spans.
+    ///
+    /// # Returns
+    ///
+    /// The merged aggregates of a sliding window holding data, `None`
+    /// for tumbling windows or sliding windows without data
+    pub(crate) fn close_pane(
+        &mut self,
+        ctx: &mut SelectCtx,
+    ) -> Result<Option<Aggregates<'static>>> {
+        let slide = if let Some(slide) = self.window.slide() {
+            slide
+        } else {
+            return Ok(None);
+        };
+        // panes that slid out of the window, e.g. during a gap without events, don't
+        // take part in this emit, the pane closed now always does
+        self.panes = self.panes.split_off(&slide.oldest(slide.current));
+        self.panes.insert(
+            slide.closed,
+            Pane {
+                aggrs: self.aggrs.clone(),
+                id: ctx.event_id.clone(),
+                transactional: self.transactional,
+                holds_data: self.holds_data,
+            },
+        );
+        let merged = if self.holds_panes() {
+            // merge all panes, from the oldest to the newest, into a fresh
+            // set of aggregates
+            let mut aggrs = self.aggrs.clone();
+            for aggr in &mut aggrs {
+                aggr.invocable.init();
+            }
+            for pane in self.panes.values().filter(|p| p.holds_data) {
+                ctx.event_id.track(&pane.id);
+                ctx.transactional |= pane.transactional;
+                for (this, pane) in aggrs.iter_mut().zip(pane.aggrs.iter()) {
+                    stry!(this.invocable.merge_pane(&pane.invocable).map_err(|e| {
+                        let r: Option<&Registry> = None;
+                        e.into_err(pane, pane, r)
+                    }));
+                }
+            }
+            Some(aggrs)
+        } else {
+            None
+        };
+        self.evict_panes();
+        Ok(merged)
+    }
+
+    /// Drops the previous panes of a sliding window that won't be part of
+    /// any later emit
+    pub(crate) fn evict_panes(&mut self) {
+        if let Some(slide) = self.window.slide() {
+            let oldest = slide.oldest(slide.current.saturating_add(slide.step));
+            self.panes = self.panes.split_off(&oldest);
+        }
+    }
+
     /// Accumultes data into the window
     pub(crate) fn accumulate(
         &mut self,
@@ -110,56 +291,7 @@ impl GroupWindow {
         self.transactional |= ctx.transactional;
         self.holds_data = true;
 
-        // Ensure the `window` constant is set propery
-        let mut consts = consts;
-        consts.window = &self.name;
-
-        // create an execution environment for the accumulation
-        // note: we set aggrs to no_aggrs sice nested aggregation
-        // is not supported and the `env` is used to evaluate
-        // the function arguments for the aggregates not the
-        // aggregates themsefls
-        let env = Env {
-            context: ctx.ctx,
-            consts,
-            aggrs: &NO_AGGRS,
-            recursion_limit: ctx.recursion_limit,
-        };
-
-        let (event_data, event_meta) = data.parts();
-        let SelectCtx { opts, .. } = ctx;
-        for aggr in &mut self.aggrs {
-            let invocable = &mut aggr.invocable;
-            // We need two arrays to handle the we know the lenght so
-            // we pre-allocate. We need this to minimize copying and allocations
-            // the functions take a refference to a value and since we
-            // might get owned data back in the `Cow` we don't know for
-            // sure if we can reference it without keeping ownership.
-
-            // the first one is the computed data in `Cow`s
-            let mut argv: Vec<SCow<Value>> = Vec::with_capacity(aggr.args.len());
-            // the second vector are refernces to the first vector
-            let mut argv1: Vec<&Value> = Vec::with_capacity(aggr.args.len());
-
-            // evaluate the arguments
-            for arg in &aggr.args {
-                let result =
-                    stry!(arg.run(*opts, &env, event_data, &NULL, event_meta, ctx.local_stack));
-                argv.push(result);
-            }
-
-            // collect references to them
-            for arg in &argv {
-                argv1.push(arg);
-            }
-            // now execute the fnctions
-            stry!(invocable.accumulate(argv1.as_slice()).map_err(|e| {
-                // TODO nice error
-                let r: Option<&Registry> = None;
-                e.into_err(aggr, aggr, r)
-            }));
-        }
-        Ok(())
+        accumulate_into(&mut self.aggrs, &self.name, ctx, consts, data)
     }
 
     /// Merge data from the privious tilt frame / window into this one
@@ -201,6 +333,27 @@ impl GroupWindow {
     ) -> Result<bool> {
         // determin what to do with the event
         let window_event = stry!(self.window.on_event(data, ctx.ingest_ns, ctx.origin_uri));
+        if !window_event.emit {
+            self.evict_panes();
+        }
+
+        // event time windows can place an event outside of the current window
+        if prev.is_none() {
+            ctx.watermark = self.window.watermark();
+            match self.window.placement() {
+                Placement::Current => (),
+                Placement::Late => {
+                    // the window of the event was already emitted
+                    ctx.late = true;
+                    return Ok(can_remove && !self.holds_any_data());
+                }
+                Placement::Ahead(end) => {
+                    stry!(self.accumulate_pending(ctx, consts, data, end));
+                    return Ok(false);
+                }
+                Placement::Earlier(current) => self.open_earlier(ctx, current),
+            }
+        }
 
         // if it should be included in the current window include it
         if window_event.include {
@@ -233,13 +386,18 @@ impl GroupWindow {
 
             // Set the window name for emission
 
-            if self.holds_data {
+            // sliding windows emit the data of all their panes
+            let panes = stry!(self.close_pane(ctx));
+            if let Some(aggrs) = panes
+                .as_ref()
+                .or_else(|| self.holds_data.then(|| &self.aggrs))
+            {
                 let mut consts = consts;
                 consts.window = &self.name;
                 let env = Env {
                     context: ctx.ctx,
                     consts,
-                    aggrs: &self.aggrs,
+                    aggrs,
                     recursion_limit: ctx.recursion_limit,
                 };
 
@@ -268,7 +426,8 @@ impl GroupWindow {
         if window_event.include {
             // if include is set we recorded the event earlier, meaning that
             // from the point of view of this window we could remove the group
-            Ok(can_remove)
+            // unless previous panes are still holding on to data
+            Ok(can_remove && !self.holds_panes())
         } else {
             // The event wasn't recorded earlier so we need to record it now
             // either by merging the pervious aggregates or accumulating the
@@ -286,6 +445,181 @@ impl GroupWindow {
     }
 }
 
+impl GroupWindow {
+    /// Snapshots the state of this window and all following tilt frames
+    pub(crate) fn snapshot(&self) -> Result<Value<'static>> {
+        let aggrs = stry!(snapshot_aggrs(&self.aggrs));
+        let panes: Result<Vec<Value<'static>>> = self.panes.iter().map(Pane::snapshot).collect();
+        let pending: Result<Vec<Value<'static>>> =
+            self.pending.iter().map(Pane::snapshot).collect();
+        let next = if let Some(next) = &self.next {
+            stry!(next.snapshot())
+        } else {
+            Value::null()
+        };
+        Ok(literal!({
+            "window": self.window.snapshot(),
+            "aggrs": aggrs,
+            "transactional": self.transactional,
+            "holds_data": self.holds_data,
+            "panes": stry!(panes),
+            "pending": stry!(pending),
+            "next": next,
+        }))
+    }
+
+    /// Restores the state of this window and all following tilt frames,
+    /// the windows get new event ids as the ones they tracked are gone
+    pub(crate) fn restore(
+        &mut self,
+        snapshot: &Value,
+        event_id_gen: &mut EventIdGenerator,
+    ) -> Result<()> {
+        self.window.restore(snapshot.get("window").unwrap_or(&NULL));
+        stry!(restore_aggrs(
+            &mut self.aggrs,
+            snapshot.get("aggrs").unwrap_or(&NULL)
+        ));
+        self.id = event_id_gen.next_id();
+        self.transactional = snapshot.get_bool("transactional").unwrap_or_default();
+        self.holds_data = snapshot.get_bool("holds_data").unwrap_or_default();
+        self.panes = stry!(self.restore_panes(snapshot.get("panes"), event_id_gen));
+        self.pending = stry!(self.restore_panes(snapshot.get("pending"), event_id_gen));
+        if let Some(next) = &mut self.next {
+            if let Some(snapshot) = snapshot.get("next").filter(|s| !s.is_null()) {
+                stry!(next.restore(snapshot, event_id_gen));
+            }
+        }
+        Ok(())
+    }
+
+    fn restore_panes(
+        &self,
+        snapshot: Option<&Value>,
+        event_id_gen: &mut EventIdGenerator,
+    ) -> Result<BTreeMap<u64, Pane>> {
+        let mut panes = BTreeMap::new();
+        for snapshot in snapshot.and_then(Value::as_array).into_iter().flatten() {
+            let key = snapshot
+                .get_idx(0)
+                .and_then(Value::as_u64)
+                .unwrap_or_default();
+            let snapshot = snapshot.get_idx(1).unwrap_or(&NULL);
+            let mut pane = self.empty_pane(event_id_gen.next_id());
+            stry!(restore_aggrs(
+                &mut pane.aggrs,
+                snapshot.get("aggrs").unwrap_or(&NULL)
+            ));
+            pane.transactional = snapshot.get_bool("transactional").unwrap_or_default();
+            pane.holds_data = snapshot.get_bool("holds_data").unwrap_or_default();
+            panes.insert(key, pane);
+        }
+        Ok(panes)
+    }
+}
+
+impl Pane {
+    /// Snapshots a pane along with the time it is kept by
+    fn snapshot((key, pane): (&u64, &Self)) -> Result<Value<'static>> {
+        let aggrs = stry!(snapshot_aggrs(&pane.aggrs));
+        Ok(Value::from(vec![
+            Value::from(*key),
+            literal!({
+                "aggrs": aggrs,
+                "transactional": pane.transactional,
+                "holds_data": pane.holds_data,
+            }),
+        ]))
+    }
+}
+
+/// Snapshots the state of a set of aggregates
+fn snapshot_aggrs(aggrs: &AggrSlice<'static>) -> Result<Value<'static>> {
+    let mut snapshots = Vec::with_capacity(aggrs.len());
+    for aggr in aggrs {
+        snapshots.push(stry!(aggr.invocable.snapshot().map_err(|e| {
+            let r: Option<&Registry> = None;
+            e.into_err(aggr, aggr, r)
+        })));
+    }
+    Ok(Value::from(snapshots))
+}
+
+/// Restores the state of a set of aggregates from a snapshot
+fn restore_aggrs(aggrs: &mut Aggregates<'static>, snapshot: &Value) -> Result<()> {
+    let snapshots = snapshot
+        .as_array()
+        .filter(|snapshots| snapshots.len() == aggrs.len())
+        .ok_or("Invalid snapshot of the aggregates of a window")?;
+    for (aggr, snapshot) in aggrs.iter_mut().zip(snapshots) {
+        stry!(aggr.invocable.restore(snapshot).map_err(|e| {
+            let r: Option<&Registry> = None;
+            e.into_err(aggr, aggr, r)
+        }));
+    }
+    Ok(())
+}
+
+/// Accumultes data into a set of aggregates of the window `name`
+fn accumulate_into(
+    aggrs: &mut Aggregates<'static>,
+    name: &Value<'static>,
+    ctx: &SelectCtx,
+    consts: RunConsts,
+    data: &ValueAndMeta,
+) -> Result<()> {
+    // Ensure the `window` constant is set propery
+    let mut consts = consts;
+    consts.window = name;
+
+    // create an execution environment for the accumulation
+    // note: we set aggrs to no_aggrs sice nested aggregation
+    // is not supported and the `env` is used to evaluate
+    // the function arguments for the aggregates not the
+    // aggregates themsefls
+    let env = Env {
+        context: ctx.ctx,
+        consts,
+        aggrs: &NO_AGGRS,
+        recursion_limit: ctx.recursion_limit,
+    };
+
+    let (event_data, event_meta) = data.parts();
+    let SelectCtx { opts, .. } = ctx;
+    for aggr in aggrs {
+        let invocable = &mut aggr.invocable;
+        // We need two arrays to handle the we know the lenght so
+        // we pre-allocate. We need this to minimize copying and allocations
+        // the functions take a refference to a value and since we
+        // might get owned data back in the `Cow` we don't know for
+        // sure if we can reference it without keeping ownership.
+
+        // the first one is the computed data in `Cow`s
+        let mut argv: Vec<SCow<Value>> = Vec::with_capacity(aggr.args.len());
+        // the second vector are refernces to the first vector
+        let mut argv1: Vec<&Value> = Vec::with_capacity(aggr.args.len());
+
+        // evaluate the arguments
+        for arg in &aggr.args {
+            let result =
+                stry!(arg.run(*opts, &env, event_data, &NULL, event_meta, ctx.local_stack));
+            argv.push(result);
+        }
+
+        // collect references to them
+        for arg in &argv {
+            argv1.push(arg);
+        }
+        // now execute the fnctions
+        stry!(invocable.accumulate(argv1.as_slice()).map_err(|e| {
+            // TODO nice error
+            let r: Option<&Registry> = None;
+            e.into_err(aggr, aggr, r)
+        }));
+    }
+    Ok(())
+}
+
 /// A group wiht a number of none or more tilt frames
 #[derive(Clone, Debug)]
 pub struct Group {
@@ -293,6 +627,8 @@ pub struct Group {
     pub(crate) value: Value<'static>,
     /// the first window in the group (or none)
     pub(crate) windows: Option<Box<GroupWindow>>,
+    /// the time the group is indexed to close at by the select
+    pub(crate) closes_at: Option<u64>,
 }
 
 impl Group {
@@ -301,14 +637,53 @@ impl Group {
     /// the data but also sets to windo into a state of 'never
     /// having seen an element'.
     pub(crate) fn reset(&mut self) {
+        self.closes_at = None;
         let mut w = &mut self.windows;
         while let Some(g) = w {
             g.reset();
+            g.panes.clear();
+            g.pending.clear();
             g.window.reset();
             w = &mut g.next;
         }
     }
 
+    /// The time the first window of the group closes at
+    pub(crate) fn window_closes_at(&self) -> Option<u64> {
+        self.windows.as_ref().and_then(|w| w.window.closes_at())
+    }
+
+    /// Snapshots the state of the group
+    pub(crate) fn snapshot(&self) -> Result<Value<'static>> {
+        let windows = if let Some(windows) = &self.windows {
+            stry!(windows.snapshot())
+        } else {
+            Value::null()
+        };
+        Ok(literal!({
+            "value": self.value.clone(),
+            "windows": windows,
+        }))
+    }
+
+    /// Restores the state of the group from a snapshot
+    pub(crate) fn restore(
+        &mut self,
+        snapshot: &Value,
+        event_id_gen: &mut EventIdGenerator,
+    ) -> Result<()> {
+        self.value = snapshot
+            .get("value")
+            .map(Value::clone_static)
+            .unwrap_or_default();
+        if let Some(windows) = &mut self.windows {
+            if let Some(snapshot) = snapshot.get("windows").filter(|s| !s.is_null()) {
+                stry!(windows.restore(snapshot, event_id_gen));
+            }
+        }
+        Ok(())
+    }
+
     /// The group receives an event we propagate it through
     /// the different windows.
     /// # Returns
@@ -319,7 +694,7 @@ impl Group {
     ///         and this group can **not** be removed.
     pub(crate) fn on_event(
         &mut self,
-        mut ctx: SelectCtx,
+        ctx: &mut SelectCtx,
         consts: &mut Consts,
         data: &ValueAndMeta,
         events: &mut Vec<(Cow<'static, str>, Event)>,
@@ -330,7 +705,7 @@ impl Group {
         if let Some(first) = &mut self.windows {
             // If we have windows trigger `on_event` fo the first of them
             // with the assumption that this can be removed.
-            first.on_event(&mut ctx, run, data, events, None, true)
+            first.on_event(ctx, run, data, events, None, true)
         } else {
             // If we have no windows just execute the select statement
             // and mark this group as removable
@@ -340,7 +715,7 @@ impl Group {
                 aggrs: &NO_AGGRS,
                 recursion_limit: ctx.recursion_limit,
             };
-            if let Some(port_and_event) = stry!(execute_select_and_having(&ctx, &env, data)) {
+            if let Some(port_and_event) = stry!(execute_select_and_having(ctx, &env, data)) {
                 events.push(port_and_event);
             };
             Ok(true)
@@ -366,6 +741,81 @@ pub trait Trait: std::fmt::Debug {
     /// decreasing this value will guard against runwaway memory growth
     /// when faced with unexpected huge cardinalities for grouping dimensions
     fn max_groups(&self) -> usize;
+    /// the panes of a sliding window, `None` for windows that don't slide
+    fn slide(&self) -> Option<Slide> {
+        None
+    }
+    /// handle an advanced watermark, no event with an event time before the
+    /// `watermark` is expected anymore, so windows ending before it can be emitted
+    fn on_watermark(&mut self, _watermark: u64) -> Actions {
+        Actions::all_false()
+    }
+    /// the watermark derived from the event times seen by this window
+    fn watermark(&self) -> Option<u64> {
+        None
+    }
+    /// the pending window ending at `end` became the current one of an
+    /// event time window
+    fn promote(&mut self, _end: u64) {}
+    /// the time the current window closes at, in ns for windows closed by ticks
+    /// and in event time for event time windows, `None` if it isn't known
+    fn closes_at(&self) -> Option<u64> {
+        None
+    }
+    /// the window the last event passed to `on_event` belongs to
+    fn placement(&self) -> Placement {
+        Placement::Current
+    }
+    /// the state of the window so it can be persisted
+    fn snapshot(&self) -> Value<'static> {
+        Value::null()
+    }
+    /// restores the state of the window from a snapshot
+    fn restore(&mut self, _snapshot: &Value) {}
+}
+
+/// The panes of a sliding window, positions are in ns for windows on time
+/// and in panes for windows on number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slide {
+    /// The start of the current pane
+    pub current: u64,
+    /// The start of the pane closed by the last emit
+    pub closed: u64,
+    /// How far the window reaches back
+    pub length: u64,
+    /// How far the window slides with every emit, this is how long a pane lasts
+    pub step: u64,
+}
+
+impl Slide {
+    /// The start of the oldest pane that is part of a window ending at `end`
+    fn oldest(&self, end: u64) -> u64 {
+        end.saturating_add(1)
+            .saturating_sub(self.length.saturating_add(self.step))
+    }
+}
+
+/// Where an event belongs to in relation to the current window of a group
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Placement {
+    /// The event belongs to the current window
+    Current,
+    /// The event belongs to a later window than the current one, ending
+    /// at the given time
+    Ahead(u64),
+    /// The event belongs to an earlier window than the current one, which
+    /// was not yet opened, the current window ending at the given time
+    /// becomes pending
+    Earlier(u64),
+    /// The window of the event was already emitted
+    Late,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self::Current
+    }
 }
 
 #[derive(Debug)]
@@ -381,9 +831,14 @@ impl Window {
 }
 
 #[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Impl {
     TumblingCountBased(TumblingOnNumber),
     TumblingTimeBased(TumblingOnTime),
+    SlidingCountBased(SlidingOnNumber),
+    SlidingTimeBased(SlidingOnTime),
+    SessionTimeBased(SessionOnTime),
+    TumblingEventTimeBased(TumblingOnEventTime),
 }
 
 impl Impl {
@@ -395,8 +850,17 @@ impl Impl {
         match self {
             Self::TumblingTimeBased(w) => w.reset(),
             Self::TumblingCountBased(w) => w.reset(),
+            Self::SlidingTimeBased(w) => w.reset(),
+            Self::SlidingCountBased(w) => w.reset(),
+            Self::SessionTimeBased(w) => w.reset(),
+            Self::TumblingEventTimeBased(w) => w.reset(),
         }
     }
+
+    /// If the window is driven by the watermark of the select
+    pub(crate) fn is_event_time(&self) -> bool {
+        matches!(self, Self::TumblingEventTimeBased(_))
+    }
 }
 
 impl Trait for Impl {
@@ -409,6 +873,10 @@ impl Trait for Impl {
         match self {
             Self::TumblingTimeBased(w) => w.on_event(data, ingest_ns, origin_uri),
             Self::TumblingCountBased(w) => w.on_event(data, ingest_ns, origin_uri),
+            Self::SlidingTimeBased(w) => w.on_event(data, ingest_ns, origin_uri),
+            Self::SlidingCountBased(w) => w.on_event(data, ingest_ns, origin_uri),
+            Self::SessionTimeBased(w) => w.on_event(data, ingest_ns, origin_uri),
+            Self::TumblingEventTimeBased(w) => w.on_event(data, ingest_ns, origin_uri),
         }
     }
 
@@ -416,6 +884,10 @@ impl Trait for Impl {
         match self {
             Self::TumblingTimeBased(w) => w.on_tick(ns),
             Self::TumblingCountBased(w) => w.on_tick(ns),
+            Self::SlidingTimeBased(w) => w.on_tick(ns),
+            Self::SlidingCountBased(w) => w.on_tick(ns),
+            Self::SessionTimeBased(w) => w.on_tick(ns),
+            Self::TumblingEventTimeBased(w) => w.on_tick(ns),
         }
     }
 
@@ -423,6 +895,98 @@ impl Trait for Impl {
         match self {
             Self::TumblingTimeBased(w) => w.max_groups(),
             Self::TumblingCountBased(w) => w.max_groups(),
+            Self::SlidingTimeBased(w) => w.max_groups(),
+            Self::SlidingCountBased(w) => w.max_groups(),
+            Self::SessionTimeBased(w) => w.max_groups(),
+            Self::TumblingEventTimeBased(w) => w.max_groups(),
+        }
+    }
+
+    fn slide(&self) -> Option<Slide> {
+        match self {
+            Self::TumblingTimeBased(w) => w.slide(),
+            Self::TumblingCountBased(w) => w.slide(),
+            Self::SlidingTimeBased(w) => w.slide(),
+            Self::SlidingCountBased(w) => w.slide(),
+            Self::SessionTimeBased(w) => w.slide(),
+            Self::TumblingEventTimeBased(w) => w.slide(),
+        }
+    }
+
+    fn on_watermark(&mut self, watermark: u64) -> Actions {
+        match self {
+            Self::TumblingTimeBased(w) => w.on_watermark(watermark),
+            Self::TumblingCountBased(w) => w.on_watermark(watermark),
+            Self::SlidingTimeBased(w) => w.on_watermark(watermark),
+            Self::SlidingCountBased(w) => w.on_watermark(watermark),
+            Self::SessionTimeBased(w) => w.on_watermark(watermark),
+            Self::TumblingEventTimeBased(w) => w.on_watermark(watermark),
+        }
+    }
+
+    fn watermark(&self) -> Option<u64> {
+        match self {
+            Self::TumblingTimeBased(w) => w.watermark(),
+            Self::TumblingCountBased(w) => w.watermark(),
+            Self::SlidingTimeBased(w) => w.watermark(),
+            Self::SlidingCountBased(w) => w.watermark(),
+            Self::SessionTimeBased(w) => w.watermark(),
+            Self::TumblingEventTimeBased(w) => w.watermark(),
+        }
+    }
+
+    fn promote(&mut self, end: u64) {
+        match self {
+            Self::TumblingTimeBased(w) => w.promote(end),
+            Self::TumblingCountBased(w) => w.promote(end),
+            Self::SlidingTimeBased(w) => w.promote(end),
+            Self::SlidingCountBased(w) => w.promote(end),
+            Self::SessionTimeBased(w) => w.promote(end),
+            Self::TumblingEventTimeBased(w) => w.promote(end),
+        }
+    }
+
+    fn closes_at(&self) -> Option<u64> {
+        match self {
+            Self::TumblingTimeBased(w) => w.closes_at(),
+            Self::TumblingCountBased(w) => w.closes_at(),
+            Self::SlidingTimeBased(w) => w.closes_at(),
+            Self::SlidingCountBased(w) => w.closes_at(),
+            Self::SessionTimeBased(w) => w.closes_at(),
+            Self::TumblingEventTimeBased(w) => w.closes_at(),
+        }
+    }
+
+    fn placement(&self) -> Placement {
+        match self {
+            Self::TumblingTimeBased(w) => w.placement(),
+            Self::TumblingCountBased(w) => w.placement(),
+            Self::SlidingTimeBased(w) => w.placement(),
+            Self::SlidingCountBased(w) => w.placement(),
+            Self::SessionTimeBased(w) => w.placement(),
+            Self::TumblingEventTimeBased(w) => w.placement(),
+        }
+    }
+
+    fn snapshot(&self) -> Value<'static> {
+        match self {
+            Self::TumblingTimeBased(w) => w.snapshot(),
+            Self::TumblingCountBased(w) => w.snapshot(),
+            Self::SlidingTimeBased(w) => w.snapshot(),
+            Self::SlidingCountBased(w) => w.snapshot(),
+            Self::SessionTimeBased(w) => w.snapshot(),
+            Self::TumblingEventTimeBased(w) => w.snapshot(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: &Value) {
+        match self {
+            Self::TumblingTimeBased(w) => w.restore(snapshot),
+            Self::TumblingCountBased(w) => w.restore(snapshot),
+            Self::SlidingTimeBased(w) => w.restore(snapshot),
+            Self::SlidingCountBased(w) => w.restore(snapshot),
+            Self::SessionTimeBased(w) => w.restore(snapshot),
+            Self::TumblingEventTimeBased(w) => w.restore(snapshot),
         }
     }
 }
@@ -437,6 +1001,26 @@ impl From<TumblingOnTime> for Impl {
         Self::TumblingTimeBased(w)
     }
 }
+impl From<SlidingOnNumber> for Impl {
+    fn from(w: SlidingOnNumber) -> Self {
+        Self::SlidingCountBased(w)
+    }
+}
+impl From<SlidingOnTime> for Impl {
+    fn from(w: SlidingOnTime) -> Self {
+        Self::SlidingTimeBased(w)
+    }
+}
+impl From<SessionOnTime> for Impl {
+    fn from(w: SessionOnTime) -> Self {
+        Self::SessionTimeBased(w)
+    }
+}
+impl From<TumblingOnEventTime> for Impl {
+    fn from(w: TumblingOnEventTime) -> Self {
+        Self::TumblingEventTimeBased(w)
+    }
+}
 
 #[derive(Debug, PartialEq, Default)]
 pub struct Actions {
@@ -475,6 +1059,36 @@ impl Trait for No {
     }
 }
 
+/// Determines the time of an event for time based windows, this is either
+/// the value returned by the window script or the `ingest_ns` of the event
+fn event_time(
+    script: Option<&WindowDefinition<'static>>,
+    data: &ValueAndMeta,
+    ingest_ns: u64,
+    origin_uri: &Option<EventOriginUri>,
+) -> Result<u64> {
+    script
+        .and_then(|script| script.script.as_ref())
+        .map(|script| {
+            let context = EventContext::new(ingest_ns, origin_uri.as_ref());
+            let (unwind_event, event_meta) = data.parts();
+            let value = stry!(script.run_imut(
+                &context,
+                AggrType::Emit,
+                unwind_event,   // event
+                &Value::null(), // state for the window
+                event_meta,     // $
+            ));
+            let data = match value {
+                Return::Emit { value, .. } => value.as_u64(),
+                Return::EmitEvent { .. } => unwind_event.as_u64(),
+                Return::Drop { .. } => None,
+            };
+            data.ok_or_else(|| "Data based window didn't provide a valid value".into())
+        })
+        .unwrap_or(Ok(ingest_ns))
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct TumblingOnTime {
     pub(crate) next_window: Option<u64>,
@@ -521,6 +1135,12 @@ impl TumblingOnTime {
 }
 
 impl Trait for TumblingOnTime {
+    fn snapshot(&self) -> Value<'static> {
+        Value::from(self.next_window)
+    }
+    fn restore(&mut self, snapshot: &Value) {
+        self.next_window = snapshot.as_u64();
+    }
     fn max_groups(&self) -> usize {
         self.max_groups
     }
@@ -530,28 +1150,12 @@ impl Trait for TumblingOnTime {
         ingest_ns: u64,
         origin_uri: &Option<EventOriginUri>,
     ) -> Result<Actions> {
-        let time = stry!(self
-            .script
-            .as_ref()
-            .and_then(|script| script.script.as_ref())
-            .map(|script| {
-                let context = EventContext::new(ingest_ns, origin_uri.as_ref());
-                let (unwind_event, event_meta) = data.parts();
-                let value = stry!(script.run_imut(
-                    &context,
-                    AggrType::Emit,
-                    unwind_event,   // event
-                    &Value::null(), // state for the window
-                    event_meta,     // $
-                ));
-                let data = match value {
-                    Return::Emit { value, .. } => value.as_u64(),
-                    Return::EmitEvent { .. } => unwind_event.as_u64(),
-                    Return::Drop { .. } => None,
-                };
-                data.ok_or_else(|| "Data based window didn't provide a valid value".into())
-            })
-            .unwrap_or(Ok(ingest_ns)));
+        let time = stry!(event_time(
+            self.script.as_ref(),
+            data,
+            ingest_ns,
+            origin_uri
+        ));
         Ok(self.get_window_event(time))
     }
 
@@ -563,6 +1167,11 @@ impl Trait for TumblingOnTime {
             Actions::all_false()
         }
     }
+
+    fn closes_at(&self) -> Option<u64> {
+        // windows with a custom timestamp are not closed by ticks
+        self.next_window.filter(|_| self.script.is_none())
+    }
 }
 
 #[derive(Default, Debug, Clone)]
@@ -595,6 +1204,19 @@ impl TumblingOnNumber {
     }
 }
 impl Trait for TumblingOnNumber {
+    fn snapshot(&self) -> Value<'static> {
+        Value::from(vec![self.count, self.next_eviction])
+    }
+    fn restore(&mut self, snapshot: &Value) {
+        self.count = snapshot
+            .get_idx(0)
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        self.next_eviction = snapshot
+            .get_idx(1)
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+    }
     fn max_groups(&self) -> usize {
         self.max_groups
     }
@@ -639,3 +1261,437 @@ impl Trait for TumblingOnNumber {
         }
     }
 }
+
+/// A time based sliding window, it emits every `slide` ns the data of the
+/// last `interval` ns.
+///
+/// The window is split up in panes of `slide` ns that are tracked like
+/// a tumbling window, the aggregates of the panes are merged on emit.
+/// Panes are kept by the time they started, so panes that slid out of the
+/// window during a gap without events are not merged anymore.
+#[derive(Default, Debug, Clone)]
+pub struct SlidingOnTime {
+    /// The tumbling window tracking the current pane
+    pub(crate) pane: TumblingOnTime,
+    /// How long the window lasts (how many ns we aggregate)
+    pub(crate) interval: u64,
+    /// The start of the current pane
+    pub(crate) start: u64,
+    /// The start of the pane closed by the last emit
+    pub(crate) closed: u64,
+}
+
+impl SlidingOnTime {
+    pub(crate) fn reset(&mut self) {
+        self.pane.reset();
+        self.start = 0;
+        self.closed = 0;
+    }
+
+    /// Creates a new sliding window, `interval` is expected to be a
+    /// multiple of `slide`
+    pub fn from_stmt(
+        interval: u64,
+        slide: u64,
+        max_groups: usize,
+        script: Option<&WindowDefinition<'static>>,
+    ) -> Self {
+        Self {
+            pane: TumblingOnTime::from_stmt(slide, max_groups, script),
+            interval,
+            start: 0,
+            closed: 0,
+        }
+    }
+
+    fn get_window_event(&mut self, time: u64) -> Actions {
+        let opens = self.pane.next_window.is_none();
+        let actions = self.pane.get_window_event(time);
+        if actions.emit {
+            self.closed = self.start;
+        }
+        if actions.emit || opens {
+            self.start = time;
+        }
+        actions
+    }
+}
+
+impl Trait for SlidingOnTime {
+    fn snapshot(&self) -> Value<'static> {
+        Value::from(vec![self.pane.snapshot(), Value::from(self.start)])
+    }
+    fn restore(&mut self, snapshot: &Value) {
+        self.pane.restore(snapshot.get_idx(0).unwrap_or(&NULL));
+        self.start = snapshot
+            .get_idx(1)
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+    }
+    fn max_groups(&self) -> usize {
+        self.pane.max_groups()
+    }
+    fn slide(&self) -> Option<Slide> {
+        Some(Slide {
+            current: self.start,
+            closed: self.closed,
+            length: self.interval,
+            step: self.pane.interval,
+        })
+    }
+    fn on_event(
+        &mut self,
+        data: &ValueAndMeta,
+        ingest_ns: u64,
+        origin_uri: &Option<EventOriginUri>,
+    ) -> Result<Actions> {
+        let time = stry!(event_time(
+            self.pane.script.as_ref(),
+            data,
+            ingest_ns,
+            origin_uri
+        ));
+        Ok(self.get_window_event(time))
+    }
+    fn on_tick(&mut self, ns: u64) -> Actions {
+        if self.pane.script.is_none() {
+            self.get_window_event(ns)
+        } else {
+            // we basically ignore ticks when we have a script with a custom timestamp
+            Actions::all_false()
+        }
+    }
+    fn closes_at(&self) -> Option<u64> {
+        self.pane.closes_at()
+    }
+}
+
+/// A count based sliding window, it emits every `slide` events the data of
+/// the last `size` events.
+///
+/// The window is split up in panes of `slide` events that are tracked like
+/// a tumbling window, the aggregates of the panes are merged on emit.
+#[derive(Default, Debug, Clone)]
+pub struct SlidingOnNumber {
+    /// The tumbling window tracking the current pane
+    pub(crate) pane: TumblingOnNumber,
+    /// The number of panes the window spans
+    pub(crate) panes: u64,
+    /// The number of the current pane
+    pub(crate) current: u64,
+}
+
+impl SlidingOnNumber {
+    pub(crate) fn reset(&mut self) {
+        self.pane.reset();
+        self.current = 0;
+    }
+
+    /// Creates a new sliding window, `size` is expected to be a
+    /// multiple of `slide`
+    pub fn from_stmt(
+        size: u64,
+        slide: u64,
+        max_groups: usize,
+        script: Option<&WindowDefinition<'static>>,
+    ) -> Self {
+        Self {
+            pane: TumblingOnNumber::from_stmt(slide, max_groups, script),
+            panes: size / slide.max(1),
+            current: 0,
+        }
+    }
+}
+
+impl Trait for SlidingOnNumber {
+    fn snapshot(&self) -> Value<'static> {
+        Value::from(vec![self.pane.snapshot(), Value::from(self.current)])
+    }
+    fn restore(&mut self, snapshot: &Value) {
+        self.pane.restore(snapshot.get_idx(0).unwrap_or(&NULL));
+        self.current = snapshot
+            .get_idx(1)
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+    }
+    fn max_groups(&self) -> usize {
+        self.pane.max_groups()
+    }
+    fn slide(&self) -> Option<Slide> {
+        Some(Slide {
+            current: self.current,
+            closed: self.current.saturating_sub(1),
+            length: self.panes,
+            step: 1,
+        })
+    }
+    fn on_event(
+        &mut self,
+        data: &ValueAndMeta,
+        ingest_ns: u64,
+        origin_uri: &Option<EventOriginUri>,
+    ) -> Result<Actions> {
+        let actions = stry!(self.pane.on_event(data, ingest_ns, origin_uri));
+        if actions.emit {
+            self.current += 1;
+        }
+        Ok(actions)
+    }
+}
+
+/// A time based session window, a session lasts as long as events arrive
+/// within `gap` ns of each other and is closed after `gap` ns of inactivity.
+///
+/// As every group holds its own window this tracks inactivity per group.
+/// With a script providing the time of events, the event time of a session
+/// advances with the processing time passed since its last event on ticks,
+/// so idle sessions are closed as well.
+#[derive(Default, Debug, Clone)]
+pub struct SessionOnTime {
+    /// The time the last event of the current session was seen
+    pub(crate) last_seen: Option<u64>,
+    /// The ingest time of the last event of the current session
+    pub(crate) seen_at: u64,
+    pub(crate) max_groups: usize,
+    /// The inactivity gap (in ns) after which a session is closed
+    pub(crate) gap: u64,
+    pub(crate) script: Option<WindowDefinition<'static>>,
+}
+
+impl SessionOnTime {
+    pub(crate) fn reset(&mut self) {
+        self.last_seen = None;
+    }
+
+    pub fn from_stmt(
+        gap: u64,
+        max_groups: usize,
+        script: Option<&WindowDefinition<'static>>,
+    ) -> Self {
+        let script = script.cloned();
+        Self {
+            last_seen: None,
+            seen_at: 0,
+            max_groups,
+            gap,
+            script,
+        }
+    }
+
+    /// The event time of the session at the processing time `ns`
+    fn event_time_at(&self, last_seen: u64, ns: u64) -> u64 {
+        last_seen.saturating_add(ns.saturating_sub(self.seen_at))
+    }
+
+    fn get_window_event(&mut self, time: u64) -> Actions {
+        match self.last_seen {
+            Some(last_seen) if last_seen + self.gap <= time => {
+                self.last_seen = Some(time);
+                Actions {
+                    include: false, // event is beyond the session gap, it starts the next session
+                    emit: true,
+                }
+            }
+            Some(last_seen) => {
+                // out of order events never move the session backwards
+                self.last_seen = Some(last_seen.max(time));
+                Actions::all_false()
+            }
+            None => {
+                self.last_seen = Some(time);
+                Actions::all_false()
+            }
+        }
+    }
+}
+
+impl Trait for SessionOnTime {
+    fn snapshot(&self) -> Value<'static> {
+        Value::from(vec![Value::from(self.last_seen), Value::from(self.seen_at)])
+    }
+    fn restore(&mut self, snapshot: &Value) {
+        self.last_seen = snapshot.get_idx(0).and_then(Value::as_u64);
+        self.seen_at = snapshot
+            .get_idx(1)
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+    }
+    fn max_groups(&self) -> usize {
+        self.max_groups
+    }
+    fn on_event(
+        &mut self,
+        data: &ValueAndMeta,
+        ingest_ns: u64,
+        origin_uri: &Option<EventOriginUri>,
+    ) -> Result<Actions> {
+        let time = stry!(event_time(
+            self.script.as_ref(),
+            data,
+            ingest_ns,
+            origin_uri
+        ));
+        self.seen_at = self.seen_at.max(ingest_ns);
+        Ok(self.get_window_event(time))
+    }
+
+    fn on_tick(&mut self, ns: u64) -> Actions {
+        match self.last_seen {
+            Some(last_seen) if last_seen + self.gap <= self.event_time_at(last_seen, ns) => {
+                // the session expired, the next event starts a new one
+                self.last_seen = None;
+                Actions {
+                    include: false,
+                    emit: true,
+                }
+            }
+            _ => Actions::all_false(),
+        }
+    }
+
+    fn closes_at(&self) -> Option<u64> {
+        // the session expires once the gap passed since its last event was seen
+        self.last_seen.map(|_| self.seen_at.saturating_add(self.gap))
+    }
+}
+
+/// An event time based tumbling window, windows are aligned to multiples of
+/// `interval` on the event time axis and are emitted once the watermark of the
+/// select passes their end.
+///
+/// The watermark trails the highest event time seen by `allowed_lateness` ns,
+/// so events may arrive out of order as long as they are not later than that.
+/// Events for windows that were already emitted are considered late.
+#[derive(Default, Debug, Clone)]
+pub struct TumblingOnEventTime {
+    /// The end of the current window
+    pub(crate) next_window: Option<u64>,
+    pub(crate) max_groups: usize,
+    /// How long a window lasts (how many ns we accumulate)
+    pub(crate) interval: u64,
+    /// How far (in ns) events may lag behind the highest event time seen
+    pub(crate) allowed_lateness: u64,
+    /// The highest event time seen by this window
+    pub(crate) max_event_time: Option<u64>,
+    /// The current watermark of the select
+    pub(crate) watermark: Option<u64>,
+    /// Where the last event belongs to
+    pub(crate) placement: Placement,
+    pub(crate) script: Option<WindowDefinition<'static>>,
+}
+
+impl TumblingOnEventTime {
+    pub(crate) fn reset(&mut self) {
+        // the watermark is not reset as it is shared by all groups
+        self.next_window = None;
+        self.placement = Placement::Current;
+    }
+
+    pub fn from_stmt(
+        interval: u64,
+        allowed_lateness: u64,
+        max_groups: usize,
+        script: Option<&WindowDefinition<'static>>,
+    ) -> Self {
+        let script = script.cloned();
+        Self {
+            max_groups,
+            interval,
+            allowed_lateness,
+            script,
+            ..Self::default()
+        }
+    }
+
+    /// The end of the window an event with the given time belongs to
+    fn window_end(&self, time: u64) -> u64 {
+        (time - time % self.interval).saturating_add(self.interval)
+    }
+
+    fn place(&mut self, time: u64) -> Placement {
+        let end = self.window_end(time);
+        if self.watermark.map_or(false, |watermark| end <= watermark) {
+            return Placement::Late;
+        }
+        self.max_event_time = Some(self.max_event_time.map_or(time, |max| max.max(time)));
+        match self.next_window {
+            None => {
+                self.next_window = Some(end);
+                Placement::Current
+            }
+            Some(current) if end > current => Placement::Ahead(end),
+            Some(current) if end < current => {
+                self.next_window = Some(end);
+                Placement::Earlier(current)
+            }
+            Some(_) => Placement::Current,
+        }
+    }
+}
+
+impl Trait for TumblingOnEventTime {
+    fn snapshot(&self) -> Value<'static> {
+        Value::from(vec![
+            Value::from(self.next_window),
+            Value::from(self.max_event_time),
+            Value::from(self.watermark),
+        ])
+    }
+    fn restore(&mut self, snapshot: &Value) {
+        self.next_window = snapshot.get_idx(0).and_then(Value::as_u64);
+        self.max_event_time = snapshot.get_idx(1).and_then(Value::as_u64);
+        self.watermark = snapshot.get_idx(2).and_then(Value::as_u64);
+    }
+    fn max_groups(&self) -> usize {
+        self.max_groups
+    }
+    fn on_event(
+        &mut self,
+        data: &ValueAndMeta,
+        ingest_ns: u64,
+        origin_uri: &Option<EventOriginUri>,
+    ) -> Result<Actions> {
+        let time = stry!(event_time(
+            self.script.as_ref(),
+            data,
+            ingest_ns,
+            origin_uri
+        ));
+        self.placement = self.place(time);
+        // events are recorded as they arrive, so their group is kept until the
+        // watermark passes their window and it is emitted
+        Ok(Actions::all_false())
+    }
+
+    fn on_watermark(&mut self, watermark: u64) -> Actions {
+        self.watermark = Some(self.watermark.map_or(watermark, |w| w.max(watermark)));
+        match self.next_window {
+            Some(end) if end <= watermark => {
+                // the next window is the first pending one, if any
+                self.next_window = None;
+                Actions {
+                    include: false,
+                    emit: true,
+                }
+            }
+            _ => Actions::all_false(),
+        }
+    }
+
+    fn watermark(&self) -> Option<u64> {
+        self.max_event_time
+            .map(|time| time.saturating_sub(self.allowed_lateness))
+    }
+
+    fn promote(&mut self, end: u64) {
+        self.next_window = Some(end);
+    }
+
+    fn closes_at(&self) -> Option<u64> {
+        self.next_window
+    }
+
+    fn placement(&self) -> Placement {
+        self.placement
+    }
+}
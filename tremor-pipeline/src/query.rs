@@ -18,8 +18,14 @@ use crate::{
     op::{
         self,
         identity::PassthroughFactory,
-        prelude::{IN, OUT},
-        trickle::{operator::TrickleOperator, select::Select, simple_select::SimpleSelect, window},
+        prelude::{IN, LATE, OUT},
+        trickle::{
+            join::{Join, RIGHT},
+            operator::TrickleOperator,
+            select::Select,
+            simple_select::SimpleSelect,
+            window,
+        },
     },
     ConfigGraph, Connection, ExecPortIndexMap, ExecutableGraph, NodeConfig, NodeKind, NodeMetrics,
     Operator, OperatorNode, State, METRICS_CHANNEL,
@@ -42,8 +48,8 @@ use tremor_script::{
         self,
         visitors::{ArgsRewriter, ConstFolder},
         walkers::QueryWalker,
-        Helper, Ident, OperatorDefinition, PipelineCreate, PipelineDefinition, ScriptDefinition,
-        SelectType, Stmt, WindowDefinition, WindowKind,
+        Helper, Ident, JoinKind, OperatorDefinition, PipelineCreate, PipelineDefinition,
+        ScriptDefinition, SelectType, Stmt, WindowDefinition, WindowKind,
     },
     errors::{
         err_generic, not_defined_err, query_node_duplicate_name_err,
@@ -97,27 +103,74 @@ fn resolve_output_port(port: &(Ident, Ident)) -> OutputPort {
     }
 }
 
+/// resolves the stream a select reads from, adding an input node for named ports of `in`
+fn resolve_input_stream(
+    from: &(Ident, Ident),
+    pipe_graph: &mut ConfigGraph,
+    nodes_by_name: &mut HashMap<Cow<'static, str>, NodeIndex>,
+) -> OutputPort {
+    let mut from = resolve_output_port(from);
+    if from.id == "in" && from.port != "out" {
+        let name: Cow<'static, str> = format!("in/{}", from.port).into();
+        from.id = name.clone();
+        // the input node passes events on to its `out` port
+        from.port = OUT;
+        if !nodes_by_name.contains_key(&name) {
+            let id = pipe_graph.add_node(NodeConfig {
+                id: name.to_string(),
+                kind: NodeKind::Input,
+                op_type: "passthrough".to_string(),
+                ..NodeConfig::default()
+            });
+            nodes_by_name.insert(name, id);
+        }
+    }
+    from
+}
+
 pub(crate) fn window_defn_to_impl(d: &WindowDefinition<'static>) -> Result<window::Impl> {
-    use op::trickle::window::{TumblingOnNumber, TumblingOnTime};
+    use op::trickle::window::{
+        SessionOnTime, SlidingOnNumber, SlidingOnTime, TumblingOnEventTime, TumblingOnNumber,
+        TumblingOnTime,
+    };
+    let script = if d.script.is_some() { Some(d) } else { None };
+    let with = d.params.render()?;
+    let max_groups = with
+        .get(WindowDefinition::MAX_GROUPS)
+        .and_then(Value::as_usize)
+        .unwrap_or(window::Impl::DEFAULT_MAX_GROUPS);
+    let interval = with.get(WindowDefinition::INTERVAL).and_then(Value::as_u64);
+    let size = with.get(WindowDefinition::SIZE).and_then(Value::as_u64);
+
     match &d.kind {
-        WindowKind::Sliding => Err("Sliding windows are not yet implemented".into()),
-        WindowKind::Tumbling => {
-            let script = if d.script.is_some() { Some(d) } else { None };
-            let with = d.params.render()?;
-            let max_groups = with
-                .get(WindowDefinition::MAX_GROUPS)
-                .and_then(Value::as_usize)
-                .unwrap_or(window::Impl::DEFAULT_MAX_GROUPS);
-
-            match (
-                with.get(WindowDefinition::INTERVAL).and_then(Value::as_u64),
-                with.get(WindowDefinition::SIZE).and_then(Value::as_u64),
-            ) {
-                (Some(interval), None) => Ok(window::Impl::from(TumblingOnTime::from_stmt(
-                    interval, max_groups, script,
+        WindowKind::Sliding => {
+            let slide = with
+                .get(WindowDefinition::SLIDE)
+                .and_then(Value::as_u64)
+                .ok_or_else(|| {
+                    Error::from("Bad window configuration, sliding windows require a `slide`.")
+                })?;
+            let check_slide = |extent: u64| {
+                if slide == 0 || slide > extent || extent % slide != 0 {
+                    Err(Error::from(
+                        "Bad window configuration, `slide` needs to be a divisor of the window extent.",
+                    ))
+                } else {
+                    Ok(extent)
+                }
+            };
+            match (interval, size) {
+                (Some(interval), None) => Ok(window::Impl::from(SlidingOnTime::from_stmt(
+                    check_slide(interval)?,
+                    slide,
+                    max_groups,
+                    script,
                 ))),
-                (None, Some(size)) => Ok(window::Impl::from(TumblingOnNumber::from_stmt(
-                    size, max_groups, script,
+                (None, Some(size)) => Ok(window::Impl::from(SlidingOnNumber::from_stmt(
+                    check_slide(size)?,
+                    slide,
+                    max_groups,
+                    script,
                 ))),
                 (Some(_), Some(_)) => Err(Error::from(
                     "Bad window configuration, only one of `size` or `interval` is allowed.",
@@ -127,8 +180,57 @@ pub(crate) fn window_defn_to_impl(d: &WindowDefinition<'static>) -> Result<windo
                 )),
             }
         }
+        WindowKind::Session => match (
+            with.get(WindowDefinition::GAP).and_then(Value::as_u64),
+            interval,
+            size,
+        ) {
+            (Some(gap), None, None) if gap > 0 => Ok(window::Impl::from(SessionOnTime::from_stmt(
+                gap, max_groups, script,
+            ))),
+            (Some(_), None, None) => Err(Error::from(
+                "Bad window configuration, the session `gap` needs to be greater than 0.",
+            )),
+            (_, Some(_), _) | (_, _, Some(_)) => Err(Error::from(
+                "Bad window configuration, session windows only support a `gap`.",
+            )),
+            (None, None, None) => Err(Error::from(
+                "Bad window configuration, session windows require a `gap`.",
+            )),
+        },
+        WindowKind::Tumbling => match (
+            interval,
+            size,
+            with.get(WindowDefinition::ALLOWED_LATENESS)
+                .and_then(Value::as_u64),
+        ) {
+            (Some(interval), None, Some(lateness)) if interval > 0 => Ok(window::Impl::from(
+                TumblingOnEventTime::from_stmt(interval, lateness, max_groups, script),
+            )),
+            (Some(_), None, Some(_)) => Err(Error::from(
+                "Bad window configuration, the `interval` needs to be greater than 0.",
+            )),
+            (None, Some(_), Some(_)) => Err(Error::from(
+                "Bad window configuration, `allowed_lateness` requires an `interval`.",
+            )),
+            (Some(interval), None, None) => Ok(window::Impl::from(TumblingOnTime::from_stmt(
+                interval, max_groups, script,
+            ))),
+            (None, Some(size), None) => Ok(window::Impl::from(TumblingOnNumber::from_stmt(
+                size, max_groups, script,
+            ))),
+            (Some(_), Some(_), _) => Err(Error::from(
+                "Bad window configuration, only one of `size` or `interval` is allowed.",
+            )),
+            (None, None, _) => Err(Error::from(
+                "Bad window configuration, either `size` or `interval` is required.",
+            )),
+        },
     }
 }
+/// Default interval in which checkpoints of the operator state are taken: 10s
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 10_000_000_000;
+
 /// A Tremor Query
 #[derive(Clone, Debug)]
 pub struct Query(pub tremor_script::query::Query);
@@ -139,6 +241,32 @@ impl Query {
         self.0.query.config.get("id").and_then(ValueAccess::as_str)
     }
 
+    /// Fetches the directory operator state is persisted in, if it was provided
+    pub fn state_dir(&self) -> Option<&str> {
+        self.0
+            .query
+            .config
+            .get("state_dir")
+            .and_then(ValueAccess::as_str)
+    }
+
+    /// Fetches the interval in nanoseconds in which checkpoints of the operator state
+    /// are taken, defaults to `DEFAULT_CHECKPOINT_INTERVAL`
+    ///
+    /// # Errors
+    /// if `checkpoint_interval_s` is too large to be expressed in nanoseconds
+    pub fn checkpoint_interval(&self) -> Result<u64> {
+        self.0
+            .query
+            .config
+            .get("checkpoint_interval_s")
+            .and_then(ValueAccess::as_u64)
+            .map_or(Ok(DEFAULT_CHECKPOINT_INTERVAL), |s| {
+                s.checked_mul(1_000_000_000)
+                    .ok_or_else(|| format!("checkpoint_interval_s {s} is too large").into())
+            })
+    }
+
     /// Parse a query
     ///
     /// # Errors
@@ -226,6 +354,13 @@ impl Query {
                         let name = into_name(&g.prefix, port.as_str());
                         node.id = name.into();
                     }
+                    if let Some(join) = &mut select.stmt.maybe_join {
+                        let (node, port) = &mut join.from;
+                        if let Some(g) = included_graphs.get(node.as_str()) {
+                            let name = into_name(&g.prefix, port.as_str());
+                            node.id = name.into();
+                        }
+                    }
                     let (node, port) = &mut select.stmt.into;
                     if let Some(g) = included_graphs.get(node.as_str()) {
                         let name = from_name(&g.prefix, port.as_str());
@@ -234,14 +369,17 @@ impl Query {
 
                     let s: &ast::Select<'_> = &select.stmt;
 
-                    if !nodes_by_name.contains_key(&s.from.0.id) {
-                        return Err(query_stream_not_defined_err(
-                            s,
-                            &s.from.0,
-                            s.from.0.to_string(),
-                            s.from.1.to_string(),
-                        )
-                        .into());
+                    let joined = s.maybe_join.as_ref().map(|join| &join.from);
+                    for from in iter::once(&s.from).chain(joined) {
+                        if !nodes_by_name.contains_key(&from.0.id) {
+                            return Err(query_stream_not_defined_err(
+                                s,
+                                &from.0,
+                                from.0.to_string(),
+                                from.1.to_string(),
+                            )
+                            .into());
+                        }
                     }
                     let e = select.stmt.extent();
                     let mut h = Dumb::new();
@@ -263,20 +401,7 @@ impl Query {
                         mid: Box::new(s.meta().clone()),
                     };
                     select_num += 1;
-                    let mut from = resolve_output_port(&s.from);
-                    if from.id == "in" && from.port != "out" {
-                        let name: Cow<'static, str> = format!("in/{}", from.port).into();
-                        from.id = name.clone();
-                        if !nodes_by_name.contains_key(&name) {
-                            let id = pipe_graph.add_node(NodeConfig {
-                                id: name.to_string(),
-                                kind: NodeKind::Input,
-                                op_type: "passthrough".to_string(),
-                                ..NodeConfig::default()
-                            });
-                            nodes_by_name.insert(name.clone(), id);
-                        }
-                    }
+                    let from = resolve_input_stream(&s.from, &mut pipe_graph, &mut nodes_by_name);
                     let mut into = resolve_input_port(&s.into);
                     if into.id == "out" && into.port != "in" {
                         let name: Cow<'static, str> = format!("out/{}", into.port).into();
@@ -296,11 +421,65 @@ impl Query {
                     links.entry(from).or_default().push(select_in.clone());
                     links.entry(select_out).or_default().push(into);
 
+                    // the joined stream is connected to the `right` port
+                    if let Some(join) = &s.maybe_join {
+                        let from =
+                            resolve_input_stream(&join.from, &mut pipe_graph, &mut nodes_by_name);
+                        let select_right = InputPort {
+                            port: RIGHT,
+                            ..select_in.clone()
+                        };
+                        links.entry(from).or_default().push(select_right);
+                    }
+
+                    // selects over event time windows send late events to `out/late`
+                    let mut has_late_port = false;
+                    if let Some(w) = s.windows.first() {
+                        if let Some(defn) = helper.get::<WindowDefinition>(&w.id)? {
+                            has_late_port =
+                                defn.kind == WindowKind::Tumbling
+                                    && defn.params.with.0.iter().any(|(k, _)| {
+                                        k.as_str() == WindowDefinition::ALLOWED_LATENESS
+                                    });
+                        }
+                    }
+                    if has_late_port {
+                        let name: Cow<'static, str> = format!("out/{}", LATE).into();
+                        if !nodes_by_name.contains_key(&name) {
+                            let id = pipe_graph.add_node(NodeConfig {
+                                id: name.to_string(),
+                                label: Some(name.to_string()),
+                                kind: NodeKind::Output(LATE),
+                                op_type: "passthrough".to_string(),
+                                ..NodeConfig::default()
+                            });
+                            nodes_by_name.insert(name.clone(), id);
+                        }
+                        let select_late = OutputPort {
+                            id: select_in.id.clone(),
+                            port: LATE,
+                            had_port: false,
+                            mid: Box::new(s.meta().clone()),
+                        };
+                        let late = InputPort {
+                            id: name,
+                            port: IN,
+                            had_port: false,
+                            mid: Box::new(s.meta().clone()),
+                        };
+                        links.entry(select_late).or_default().push(late);
+                    }
+
                     let node = NodeConfig {
                         id: select_in.id.to_string(),
                         label,
                         kind: NodeKind::Select,
-                        op_type: "trickle::select".to_string(),
+                        op_type: if s.maybe_join.is_some() {
+                            "trickle::join"
+                        } else {
+                            "trickle::select"
+                        }
+                        .to_string(),
                         stmt: Some(stmt.clone()),
                         ..NodeConfig::default()
                     };
@@ -649,6 +828,7 @@ impl Query {
                     .collect(),
                 stack: Vec::with_capacity(graph.len()),
                 id: pipeline_id.to_string(), // TODO make configurable
+                flow_id: None,
                 last_metrics: 0,
                 state: State::new(iter::repeat(Value::null()).take(graph.len()).collect()),
                 graph,
@@ -660,6 +840,7 @@ impl Query {
                 insights: Vec::new(),
                 dot: format!("{}", dot),
                 metrics_channel: METRICS_CHANNEL.tx(),
+                checkpoints: None,
             })
         }
     }
@@ -742,17 +923,107 @@ fn select(
                         })
                 })
                 .collect();
+            let windows = windows?;
+            // event time windows are driven by the watermark of the select so
+            // they can only be the first window in a tilt frame
+            if windows.iter().skip(1).any(|(_, w)| w.is_event_time()) {
+                return Err(Error::from(ErrorKind::BadOpConfig(
+                    "Event time windows are only supported as the first window of a select"
+                        .to_string(),
+                )));
+            }
 
             Ok(Box::new(Select::from_stmt(
                 operator_uid,
                 config.id.clone(),
-                windows?,
+                windows,
                 node,
             )))
         }
+        SelectType::Join => join(operator_uid, config, node, helper),
     }
 }
 
+/// creates a join, stream joins keep events for the `interval` of their tumbling window,
+/// table joins can only bound the number of keys they keep with the `max_groups` of their window
+fn join(
+    operator_uid: OperatorId,
+    config: &NodeConfig,
+    node: &ast::SelectStmt<'static>,
+    helper: &Helper<'static, '_>,
+) -> Result<Box<dyn Operator>> {
+    let bad_config = |msg: &str| Error::from(ErrorKind::BadOpConfig(msg.to_string()));
+    let window_params = |id: &ast::NodeId| -> Result<(WindowDefinition<'static>, Value<'static>)> {
+        let mut defn = helper.get::<WindowDefinition>(id)?.ok_or_else(|| {
+            Error::from(ErrorKind::BadOpConfig(format!(
+                "Unknown window: {} available",
+                id,
+            )))
+        })?;
+        ConstFolder::new(helper).walk_window_defn(&mut defn)?;
+        let with = defn.params.render()?;
+        Ok((defn, with))
+    };
+    let kind = node.stmt.maybe_join.as_ref().map(|join| join.kind);
+    let (interval, max_groups) = match (kind, node.stmt.windows.as_slice()) {
+        (Some(JoinKind::Table), []) => (0, window::Impl::DEFAULT_MAX_GROUPS),
+        (Some(JoinKind::Table), [w]) => {
+            let (defn, with) = window_params(&w.id)?;
+            let only_max_groups = with.as_object().map_or(false, |o| {
+                o.keys().all(|k| &**k == WindowDefinition::MAX_GROUPS)
+            });
+            match with
+                .get(WindowDefinition::MAX_GROUPS)
+                .and_then(Value::as_usize)
+            {
+                Some(max_groups) if only_max_groups && defn.script.is_none() => (0, max_groups),
+                _ => {
+                    return Err(bad_config(
+                        "Table joins only support a window with `max_groups`",
+                    ))
+                }
+            }
+        }
+        (Some(JoinKind::Table), _) => {
+            return Err(bad_config("Table joins support at most one window"));
+        }
+        (Some(JoinKind::Stream), [w]) => {
+            let (defn, with) = window_params(&w.id)?;
+            let interval = with.get(WindowDefinition::INTERVAL).and_then(Value::as_u64);
+            let size_or_lateness = with.contains_key(WindowDefinition::SIZE)
+                || with.contains_key(WindowDefinition::ALLOWED_LATENESS);
+            match (&defn.kind, interval) {
+                (WindowKind::Tumbling, Some(interval))
+                    if interval > 0 && !size_or_lateness && defn.script.is_none() =>
+                {
+                    (
+                        interval,
+                        with.get(WindowDefinition::MAX_GROUPS)
+                            .and_then(Value::as_usize)
+                            .unwrap_or(window::Impl::DEFAULT_MAX_GROUPS),
+                    )
+                }
+                _ => {
+                    return Err(bad_config(
+                        "Stream joins require a tumbling window with an `interval`",
+                    ))
+                }
+            }
+        }
+        (Some(JoinKind::Stream), _) => {
+            return Err(bad_config("Stream joins require exactly one window"));
+        }
+        (None, _) => return Err(bad_config("Select without join")),
+    };
+    Ok(Box::new(Join::from_stmt(
+        operator_uid,
+        config.id.clone(),
+        interval,
+        max_groups,
+        node,
+    )?))
+}
+
 fn operator(
     operator_uid: OperatorId,
     node: &ast::OperatorDefinition<'static>,
@@ -798,10 +1069,11 @@ pub(crate) fn supported_operators(
 #[cfg(test)]
 mod test {
     use tremor_common::ids::Id;
+    use tremor_script::literal;
 
     use super::*;
     #[test]
-    fn query() {
+    fn query() -> Result<()> {
         let aggr_reg = tremor_script::aggr_registry();
 
         let src = "select event from in into out;";
@@ -814,6 +1086,498 @@ mod test {
         let query =
             Query::parse(src, &*tremor_script::FN_REGISTRY.read().unwrap(), &aggr_reg).unwrap();
         assert_eq!(query.id().unwrap(), "test");
+        assert!(query.state_dir().is_none());
+        assert_eq!(DEFAULT_CHECKPOINT_INTERVAL, query.checkpoint_interval()?);
+
+        let src = "#!config state_dir = \"/tmp/tremor\"\n#!config checkpoint_interval_s = 2\nselect event from in into out;";
+        let query =
+            Query::parse(src, &*tremor_script::FN_REGISTRY.read().unwrap(), &aggr_reg).unwrap();
+        assert_eq!(Some("/tmp/tremor"), query.state_dir());
+        assert_eq!(2_000_000_000, query.checkpoint_interval()?);
+
+        let src = "#!config checkpoint_interval_s = 20000000000\nselect event from in into out;";
+        let query =
+            Query::parse(src, &*tremor_script::FN_REGISTRY.read().unwrap(), &aggr_reg).unwrap();
+        assert!(query.checkpoint_interval().is_err());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn snapshot_restore() -> Result<()> {
+        let aggr_reg = tremor_script::aggr_registry();
+        let src = r#"
+        define window three from tumbling
+        with
+          size = 3
+        end;
+        select aggr::stats::sum(event) from in[three] into out;
+        "#;
+        let q = Query::parse(src, &*tremor_script::FN_REGISTRY.read()?, &aggr_reg)?;
+        let event = |n: u64| crate::Event {
+            ingest_ns: n,
+            data: Value::from(n).into(),
+            ..crate::Event::default()
+        };
+
+        let mut idgen = OperatorIdGen::new();
+        let mut g = q.to_pipe(&mut idgen)?;
+        let mut returns = crate::GraphReturns::default();
+        g.enqueue("in", event(1), &mut returns).await?;
+        g.enqueue("in", event(2), &mut returns).await?;
+        assert!(returns.output.is_empty());
+        let snapshot = g.snapshot()?;
+
+        // a fresh pipeline continues the window where the snapshot left off
+        let mut restored = q.to_pipe(&mut idgen)?;
+        restored.restore(&snapshot)?;
+        restored.enqueue("in", event(3), &mut returns).await?;
+        assert_eq!(1, returns.output.len());
+        let (port, e) = returns.output.pop().ok_or("no output")?;
+        assert_eq!(OUT, port);
+        assert_eq!(&Value::from(6.0), e.data.suffix().value());
+        Ok(())
+    }
+
+    fn values(returns: &mut crate::GraphReturns) -> Vec<Value<'static>> {
+        returns
+            .output
+            .drain(..)
+            .map(|(_, e)| e.data.suffix().value().clone_static())
+            .collect()
+    }
+
+    #[async_std::test]
+    async fn join_streams() -> Result<()> {
+        let aggr_reg = tremor_script::aggr_registry();
+        let src = r#"
+        define window ten_ns from tumbling
+        with
+          interval = 10
+        end;
+        select {"id": event.left.id, "name": event.right.name}
+        from in[ten_ns] by event.id join in/users by event.id
+        into out;
+        "#;
+        let q = Query::parse(src, &*tremor_script::FN_REGISTRY.read()?, &aggr_reg)?;
+        let event = |ingest_ns: u64, value: Value<'static>| crate::Event {
+            ingest_ns,
+            data: value.into(),
+            ..crate::Event::default()
+        };
+
+        let mut idgen = OperatorIdGen::new();
+        let mut g = q.to_pipe(&mut idgen)?;
+        let mut returns = crate::GraphReturns::default();
+        g.enqueue("in", event(1, literal!({"id": 1})), &mut returns)
+            .await?;
+        assert!(returns.output.is_empty());
+        g.enqueue(
+            "in/users",
+            event(2, literal!({"id": 1, "name": "snot"})),
+            &mut returns,
+        )
+        .await?;
+        g.enqueue("in", event(3, literal!({"id": 1})), &mut returns)
+            .await?;
+        g.enqueue("in", event(4, literal!({"id": 2})), &mut returns)
+            .await?;
+        assert_eq!(
+            vec![
+                literal!({"id": 1, "name": "snot"}),
+                literal!({"id": 1, "name": "snot"})
+            ],
+            values(&mut returns)
+        );
+        // events older than the interval are no longer joined
+        g.enqueue("in", event(20, literal!({"id": 1})), &mut returns)
+            .await?;
+        assert!(returns.output.is_empty());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn join_streams_max_groups() -> Result<()> {
+        let aggr_reg = tremor_script::aggr_registry();
+        let src = r#"
+        define window one_key from tumbling
+        with
+          interval = 10,
+          max_groups = 1
+        end;
+        select {"id": event.left.id, "name": event.right.name}
+        from in[one_key] by event.id join in/users by event.id
+        into out;
+        "#;
+        let q = Query::parse(src, &*tremor_script::FN_REGISTRY.read()?, &aggr_reg)?;
+        let event = |ingest_ns: u64, value: Value<'static>| crate::Event {
+            ingest_ns,
+            data: value.into(),
+            transactional: true,
+            ..crate::Event::default()
+        };
+
+        let mut idgen = OperatorIdGen::new();
+        let mut g = q.to_pipe(&mut idgen)?;
+        let mut returns = crate::GraphReturns::default();
+        g.enqueue("in", event(1, literal!({"id": 1})), &mut returns)
+            .await?;
+        g.enqueue(
+            "in/users",
+            event(2, literal!({"id": 2, "name": "snot"})),
+            &mut returns,
+        )
+        .await?;
+        assert!(returns.output.is_empty());
+        // a second key of the users is joined, but not kept
+        g.enqueue(
+            "in/users",
+            event(3, literal!({"id": 1, "name": "badger"})),
+            &mut returns,
+        )
+        .await?;
+        assert_eq!(
+            vec![literal!({"id": 1, "name": "badger"})],
+            values(&mut returns)
+        );
+        let errors: Vec<_> = returns
+            .dead_ends
+            .drain(..)
+            .map(|e| e.data.suffix().value().clone_static())
+            .collect();
+        assert_eq!(1, errors.len());
+        assert_eq!(
+            Some(&literal!({"id": 1, "name": "badger"})),
+            errors[0].get("event")
+        );
+        g.enqueue("in", event(4, literal!({"id": 1})), &mut returns)
+            .await?;
+        assert!(returns.output.is_empty());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn join_table() -> Result<()> {
+        let aggr_reg = tremor_script::aggr_registry();
+        let src = r#"
+        select {"id": event.left.id, "user": event.right}
+        from in by event.id join table in/users by event.id
+        where event.left.id != 3
+        into out;
+        "#;
+        let q = Query::parse(src, &*tremor_script::FN_REGISTRY.read()?, &aggr_reg)?;
+        let event = |value: Value<'static>| crate::Event {
+            data: value.into(),
+            transactional: true,
+            ..crate::Event::default()
+        };
+
+        let mut idgen = OperatorIdGen::new();
+        let mut g = q.to_pipe(&mut idgen)?;
+        let mut returns = crate::GraphReturns::default();
+        g.enqueue(
+            "in/users",
+            event(literal!({"id": 1, "name": "snot"})),
+            &mut returns,
+        )
+        .await?;
+        g.enqueue(
+            "in/users",
+            event(literal!({"id": 1, "name": "badger"})),
+            &mut returns,
+        )
+        .await?;
+        assert!(returns.output.is_empty());
+        // the events of the table are acked once they are applied
+        assert_eq!(2, g.insights.len());
+        assert!(g
+            .insights
+            .iter()
+            .all(|(_, insight)| insight.cb == crate::CbAction::Ack));
+        let mut joined = Vec::new();
+        for id in 1..=3 {
+            g.enqueue("in", event(literal!({ "id": id })), &mut returns)
+                .await?;
+            joined.extend(values(&mut returns));
+        }
+        assert_eq!(
+            vec![
+                literal!({"id": 1, "user": {"id": 1, "name": "badger"}}),
+                literal!({"id": 2, "user": null})
+            ],
+            joined
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn join_table_max_groups() -> Result<()> {
+        let aggr_reg = tremor_script::aggr_registry();
+        let src = r#"
+        define window two_keys from tumbling
+        with
+          max_groups = 2
+        end;
+        select {"id": event.left.id, "user": event.right}
+        from in[two_keys] by event.id join table in/users by event.id
+        into out;
+        "#;
+        let q = Query::parse(src, &*tremor_script::FN_REGISTRY.read()?, &aggr_reg)?;
+        let event = |value: Value<'static>| crate::Event {
+            data: value.into(),
+            ..crate::Event::default()
+        };
+
+        let mut idgen = OperatorIdGen::new();
+        let mut g = q.to_pipe(&mut idgen)?;
+        let mut returns = crate::GraphReturns::default();
+        for id in 1..=2 {
+            g.enqueue(
+                "in/users",
+                event(literal!({"id": id, "name": "snot"})),
+                &mut returns,
+            )
+            .await?;
+        }
+        // updating a kept key is fine, a third key is not
+        g.enqueue(
+            "in/users",
+            event(literal!({"id": 2, "name": "badger"})),
+            &mut returns,
+        )
+        .await?;
+        assert!(g
+            .enqueue(
+                "in/users",
+                event(literal!({"id": 3, "name": "snot"})),
+                &mut returns,
+            )
+            .await
+            .is_err());
+        let mut joined = Vec::new();
+        for id in 2..=3 {
+            g.enqueue("in", event(literal!({ "id": id })), &mut returns)
+                .await?;
+            joined.extend(values(&mut returns));
+        }
+        assert_eq!(
+            vec![
+                literal!({"id": 2, "user": {"id": 2, "name": "badger"}}),
+                literal!({"id": 3, "user": null})
+            ],
+            joined
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn join_requires_valid_windows() -> Result<()> {
+        let aggr_reg = tremor_script::aggr_registry();
+        let mut idgen = OperatorIdGen::new();
+        let stream = r#"
+        define window three from tumbling
+        with
+          size = 3
+        end;
+        select event from in[three] by event.id join in/other by event.id into out;
+        "#;
+        let table = r#"
+        define window ten_ns from tumbling
+        with
+          interval = 10
+        end;
+        select event from in[ten_ns] by event.id join table in/other by event.id into out;
+        "#;
+        let table_script = r#"
+        define window keys from tumbling
+        with
+          max_groups = 10
+        script
+          event.time
+        end;
+        select event from in[keys] by event.id join table in/other by event.id into out;
+        "#;
+        for src in [stream, table, table_script] {
+            let q = Query::parse(src, &*tremor_script::FN_REGISTRY.read()?, &aggr_reg)?;
+            assert!(q.to_pipe(&mut idgen).is_err());
+        }
+        let grouped = "select event from in by event.id join table in/other by event.id group by event.id into out;";
+        assert!(Query::parse(grouped, &*tremor_script::FN_REGISTRY.read()?, &aggr_reg).is_err());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn order_by_and_limit() -> Result<()> {
+        let aggr_reg = tremor_script::aggr_registry();
+        let src = r#"
+        define window ten_ns from tumbling
+        with
+          interval = 10,
+          allowed_lateness = 0
+        end;
+        select {"ip": group[0], "bytes": aggr::stats::sum(event.bytes)}
+        from in[ten_ns]
+        group by set(event.ip)
+        into out
+        order by event.bytes desc
+        limit 2;
+        "#;
+        let q = Query::parse(src, &*tremor_script::FN_REGISTRY.read()?, &aggr_reg)?;
+        let event = |ingest_ns: u64, ip: &'static str, bytes: u64| crate::Event {
+            ingest_ns,
+            data: literal!({"ip": ip, "bytes": bytes}).into(),
+            ..crate::Event::default()
+        };
+
+        let mut idgen = OperatorIdGen::new();
+        let mut g = q.to_pipe(&mut idgen)?;
+        let mut returns = crate::GraphReturns::default();
+        for (ingest_ns, ip, bytes) in [(1, "a", 5), (2, "b", 3), (3, "c", 1), (4, "b", 4)] {
+            g.enqueue("in", event(ingest_ns, ip, bytes), &mut returns)
+                .await?;
+        }
+        assert!(returns.output.is_empty());
+        // advancing the watermark emits the windows of all groups at once
+        g.enqueue("in", event(10, "c", 1), &mut returns).await?;
+        assert_eq!(
+            vec![
+                literal!({"ip": "b", "bytes": 7.0}),
+                literal!({"ip": "a", "bytes": 5.0})
+            ],
+            values(&mut returns)
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn order_by_and_limit_per_window() -> Result<()> {
+        let aggr_reg = tremor_script::aggr_registry();
+        let src = r#"
+        define window ten_ns from tumbling
+        with
+          interval = 10,
+          allowed_lateness = 20
+        end;
+        select {"ip": group[0], "bytes": aggr::stats::sum(event.bytes)}
+        from in[ten_ns]
+        group by set(event.ip)
+        into out
+        order by event.bytes desc
+        limit 2;
+        "#;
+        let q = Query::parse(src, &*tremor_script::FN_REGISTRY.read()?, &aggr_reg)?;
+        let event = |ingest_ns: u64, ip: &'static str, bytes: u64| crate::Event {
+            ingest_ns,
+            data: literal!({"ip": ip, "bytes": bytes}).into(),
+            ..crate::Event::default()
+        };
+
+        let mut idgen = OperatorIdGen::new();
+        let mut g = q.to_pipe(&mut idgen)?;
+        let mut returns = crate::GraphReturns::default();
+        for (ingest_ns, ip, bytes) in [
+            (1, "a", 5),
+            (2, "b", 3),
+            (13, "a", 1),
+            (14, "c", 2),
+            (15, "b", 4),
+        ] {
+            g.enqueue("in", event(ingest_ns, ip, bytes), &mut returns)
+                .await?;
+        }
+        assert!(returns.output.is_empty());
+        // the windows closed by the same watermark advance are ordered and limited window by window
+        g.enqueue("in", event(45, "a", 0), &mut returns).await?;
+        assert_eq!(
+            vec![
+                literal!({"ip": "a", "bytes": 5.0}),
+                literal!({"ip": "b", "bytes": 3.0}),
+                literal!({"ip": "b", "bytes": 4.0}),
+                literal!({"ip": "c", "bytes": 2.0})
+            ],
+            values(&mut returns)
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn order_by_and_limit_processing_time() -> Result<()> {
+        let aggr_reg = tremor_script::aggr_registry();
+        let src = r#"
+        define window ten_ns from tumbling
+        with
+          interval = 10
+        end;
+        select {"ip": group[0], "bytes": aggr::stats::sum(event.bytes)}
+        from in[ten_ns]
+        group by set(event.ip)
+        into out
+        order by event.bytes desc
+        limit 2;
+        "#;
+        let q = Query::parse(src, &*tremor_script::FN_REGISTRY.read()?, &aggr_reg)?;
+        let event = |ingest_ns: u64, ip: &'static str, bytes: u64| crate::Event {
+            ingest_ns,
+            data: literal!({"ip": ip, "bytes": bytes}).into(),
+            ..crate::Event::default()
+        };
+
+        let mut idgen = OperatorIdGen::new();
+        let mut g = q.to_pipe(&mut idgen)?;
+        let mut returns = crate::GraphReturns::default();
+        for (ingest_ns, ip, bytes) in [(1, "a", 5), (2, "b", 3), (3, "c", 1), (4, "b", 4)] {
+            g.enqueue("in", event(ingest_ns, ip, bytes), &mut returns)
+                .await?;
+        }
+        assert!(returns.output.is_empty());
+        // an event closes the windows of all groups that ended before it,
+        // not only the one of its own group
+        g.enqueue("in", event(13, "c", 1), &mut returns).await?;
+        assert_eq!(
+            vec![
+                literal!({"ip": "b", "bytes": 7.0}),
+                literal!({"ip": "a", "bytes": 5.0})
+            ],
+            values(&mut returns)
+        );
+        // so do ticks
+        g.enqueue("in", event(14, "a", 2), &mut returns).await?;
+        assert!(returns.output.is_empty());
+        let tick = crate::Event {
+            ingest_ns: 24,
+            kind: Some(crate::SignalKind::Tick),
+            ..crate::Event::default()
+        };
+        g.enqueue_signal(tick, &mut returns)?;
+        assert_eq!(
+            vec![
+                literal!({"ip": "a", "bytes": 2.0}),
+                literal!({"ip": "c", "bytes": 1.0})
+            ],
+            values(&mut returns)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn order_by_and_limit_errors() -> Result<()> {
+        let aggr_reg = tremor_script::aggr_registry();
+        let window = r#"
+        define window ten_ns from tumbling
+        with
+          interval = 10
+        end;
+        "#;
+        for select in [
+            "select event from in into out order by event.x;",
+            "select event from in into out limit 10;",
+            "select aggr::win::last(event) from in[ten_ns] into out order by aggr::stats::sum(event.x);",
+            "select aggr::win::last(event) from in[ten_ns] into out limit 0;",
+        ] {
+            let src = format!("{window}{select}");
+            let query = Query::parse(&src, &*tremor_script::FN_REGISTRY.read()?, &aggr_reg);
+            assert!(query.is_err(), "{select}");
+        }
+        Ok(())
     }
 
     #[test]
@@ -110,6 +110,8 @@ impl<'script> ConnectorDefinition<'script> {
     pub const CODEC: &'static str = "codec";
     /// param name for connector config
     pub const CONFIG: &'static str = "config";
+    /// param name for the dead letter behaviour of sources
+    pub const DEAD_LETTER: &'static str = "dead_letter";
     /// param name for preprocessors
     pub const PREPROCESSORS: &'static str = "preprocessors";
     /// param name for postprocessors
@@ -119,9 +121,10 @@ impl<'script> ConnectorDefinition<'script> {
     /// param name for reconnct configuration
     pub const RECONNECT: &'static str = "reconnect";
 
-    const AVAILABLE_PARAMS: [&'static str; 6] = [
+    const AVAILABLE_PARAMS: [&'static str; 7] = [
         Self::CODEC,
         Self::CONFIG,
+        Self::DEAD_LETTER,
         Self::METRICS_INTERVAL_S,
         Self::POSTPROCESSORS,
         Self::PREPROCESSORS,
@@ -124,13 +124,17 @@ pub enum SelectType {
     Simple,
     /// This is a full fledged select statement
     Normal,
+    /// This select statement joins two streams or a stream and a table
+    Join,
 }
 
 impl SelectStmt<'_> {
     /// Determine how complex a select statement is
     #[must_use]
     pub fn complexity(&self) -> SelectType {
-        if matches!(
+        if self.stmt.maybe_join.is_some() {
+            SelectType::Join
+        } else if matches!(
             &self.stmt.target,
             ImutExpr::Path(Path::Event(EventPath {
                 segments, ..
@@ -326,6 +330,8 @@ pub enum WindowKind {
     Sliding,
     /// we're forced to make this pub because of lalrpop
     Tumbling,
+    /// we're forced to make this pub because of lalrpop
+    Session,
 }
 
 /// A window definition
@@ -353,6 +359,12 @@ impl<'script> WindowDefinition<'script> {
     pub const INTERVAL: &'static str = "interval";
     /// `size` setting
     pub const SIZE: &'static str = "size";
+    /// `slide` setting
+    pub const SLIDE: &'static str = "slide";
+    /// `gap` setting
+    pub const GAP: &'static str = "gap";
+    /// `allowed_lateness` setting
+    pub const ALLOWED_LATENESS: &'static str = "allowed_lateness";
 }
 
 /// A select statement
@@ -374,9 +386,61 @@ pub struct Select<'script> {
     pub maybe_group_by: Option<GroupBy<'script>>,
     /// Window
     pub windows: Vec<WindowName>,
+    /// Join with another stream or table
+    pub maybe_join: Option<Join<'script>>,
+    /// Order of the events emitted at once
+    pub maybe_order_by: Option<OrderBy<'script>>,
+    /// Maximum number of events emitted at once
+    pub maybe_limit: Option<usize>,
 }
 impl_expr!(Select);
 
+/// we're forced to make this pub because of lalrpop
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum OrderDirection {
+    /// Smallest value first
+    Asc,
+    /// Largest value first
+    Desc,
+}
+
+/// An order by clause
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct OrderBy<'script> {
+    /// metadata id
+    pub(crate) mid: Box<NodeMeta>,
+    /// The expression to order by, evaluated on the emitted events
+    pub expr: ImutExpr<'script>,
+    /// The direction to order in
+    pub direction: OrderDirection,
+}
+impl_expr!(OrderBy);
+
+/// we're forced to make this pub because of lalrpop
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum JoinKind {
+    /// Joins with the events of another stream within a window
+    Stream,
+    /// Looks up the latest event per key of another stream
+    Table,
+}
+
+/// A join clause
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Join<'script> {
+    /// metadata id
+    pub(crate) mid: Box<NodeMeta>,
+    /// The kind of join
+    pub kind: JoinKind,
+    /// The joined stream
+    pub from: (Ident<'script>, Ident<'script>),
+    /// The key of the events of the stream selected from
+    pub left_key: ImutExpr<'script>,
+    /// The key of the events of the joined stream
+    pub right_key: ImutExpr<'script>,
+}
+impl_expr!(Join);
+
 /// A group by clause
 #[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum GroupBy<'script> {
@@ -22,10 +22,10 @@ use super::{
     ArgsExprs, CreationalWith, DefinitionalArgs, DefinitionalArgsWith, WithExprs,
 };
 use super::{
-    error_generic, error_no_locals, BaseExpr, GroupBy, HashMap, Helper, OperatorCreate,
-    OperatorDefinition, OperatorKind, PipelineCreate, PipelineDefinition, Query, Result,
-    ScriptCreate, ScriptDefinition, Select, SelectStmt, Serialize, Stmt, StreamStmt, Upable,
-    WindowDefinition, WindowKind,
+    error_generic, error_no_locals, BaseExpr, GroupBy, HashMap, Helper, Join, JoinKind,
+    OperatorCreate, OperatorDefinition, OperatorKind, OrderBy, OrderDirection, PipelineCreate,
+    PipelineDefinition, Query, Result, ScriptCreate, ScriptDefinition, Select, SelectStmt,
+    Serialize, Stmt, StreamStmt, Upable, WindowDefinition, WindowKind,
 };
 use crate::{ast::NodeMeta, impl_expr};
 use crate::{
@@ -147,6 +147,16 @@ impl<'script> Upable<'script> for StmtRaw<'script> {
                 helper.swap(&mut aggregates, &mut locals);
                 let stmt: Select<'script> = stmt.up(helper)?;
                 helper.swap(&mut aggregates, &mut locals);
+                if let (Some(join), Some(aggr)) = (&stmt.maybe_join, aggregates.first()) {
+                    return error_generic(
+                        &stmt,
+                        join,
+                        &format!(
+                            "Aggregate functions like {}::{} can't be used in joins",
+                            aggr.module, aggr.fun
+                        ),
+                    );
+                }
 
                 Ok(Some(Stmt::SelectStmt(SelectStmt {
                     stmt: Box::new(stmt),
@@ -471,12 +481,16 @@ pub struct SelectRaw<'script> {
     pub(crate) maybe_having: Option<ImutExprRaw<'script>>,
     pub(crate) maybe_group_by: Option<GroupByRaw<'script>>,
     pub(crate) windows: Option<Vec<WindowName>>,
+    pub(crate) maybe_join: Option<JoinRaw<'script>>,
+    pub(crate) maybe_order_by: Option<OrderByRaw<'script>>,
+    pub(crate) maybe_limit: Option<u64>,
     pub(crate) mid: Box<NodeMeta>,
 }
 impl_expr!(SelectRaw);
 
 impl<'script> Upable<'script> for SelectRaw<'script> {
     type Target = Select<'script>;
+    #[allow(clippy::too_many_lines)]
     fn up<'registry>(self, helper: &mut Helper<'script, 'registry>) -> Result<Self::Target> {
         let mut target = self.target.up(helper)?;
 
@@ -514,8 +528,53 @@ impl<'script> Upable<'script> for SelectRaw<'script> {
         } else {
             vec![]
         };
+        let maybe_join = self.maybe_join.up(helper)?;
+        if helper.has_locals() {
+            if let Some(definitely) = maybe_join {
+                return error_no_locals(&self.mid.range, &definitely);
+            }
+        };
+        if let (Some(join), Some(group_by)) = (&maybe_join, &maybe_group_by) {
+            return error_generic(join, group_by, &"Joins can't be grouped");
+        }
+
+        let aggregates = helper.aggregates.len();
+        let maybe_order_by = self.maybe_order_by.up(helper)?;
+        if let Some(order_by) = &maybe_order_by {
+            if helper.has_locals() {
+                return error_no_locals(&self.mid.range, order_by);
+            }
+            // the order is determined on the emitted events, after all aggregates are evaluated
+            if helper.aggregates.len() > aggregates {
+                return error_generic(
+                    order_by,
+                    &order_by.expr,
+                    &"Aggregate functions can't be used in `order by`, order by the selected value instead",
+                );
+            }
+        }
+
         let windows: Vec<_> = self.windows.unwrap_or_default().into_iter().collect();
-        if !windows.is_empty() {
+        // events are ordered and limited per emission of a single window
+        if (maybe_order_by.is_some() || self.maybe_limit.is_some())
+            && (windows.len() != 1 || maybe_join.is_some())
+        {
+            let msg = "`order by` and `limit` are only supported in selects with a single window and without joins";
+            return if let Some(order_by) = &maybe_order_by {
+                error_generic(&self.mid.range, order_by, &msg)
+            } else {
+                error_generic(&self.mid.range, &self.mid.range, &msg)
+            };
+        }
+        if self.maybe_limit == Some(0) {
+            return error_generic(
+                &self.mid.range,
+                &self.mid.range,
+                &"`limit` needs to be greater than 0",
+            );
+        }
+        // the windows of a join only limit which events are joined
+        if !windows.is_empty() && maybe_join.is_none() {
             // if we have windows we need to forbid free event references in the target if they are not
             // inside an aggregate function or can be rewritten to a group reference
             TargetEventRef::new(group_by_expressions).rewrite_target(&mut target)?;
@@ -546,6 +605,61 @@ impl<'script> Upable<'script> for SelectRaw<'script> {
             maybe_having,
             maybe_group_by,
             windows,
+            maybe_join,
+            maybe_order_by,
+            maybe_limit: self
+                .maybe_limit
+                .map(|limit| usize::try_from(limit).unwrap_or(usize::MAX)),
+        })
+    }
+}
+
+/// we're forced to make this pub because of lalrpop
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct OrderByRaw<'script> {
+    pub(crate) mid: Box<NodeMeta>,
+    pub(crate) expr: ImutExprRaw<'script>,
+    pub(crate) direction: OrderDirection,
+}
+impl_expr!(OrderByRaw);
+
+impl<'script> Upable<'script> for OrderByRaw<'script> {
+    type Target = OrderBy<'script>;
+    fn up<'registry>(self, helper: &mut Helper<'script, 'registry>) -> Result<Self::Target> {
+        Ok(OrderBy {
+            mid: self.mid,
+            expr: self.expr.up(helper)?,
+            direction: self.direction,
+        })
+    }
+}
+
+/// we're forced to make this pub because of lalrpop
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct JoinRaw<'script> {
+    pub(crate) mid: Box<NodeMeta>,
+    pub(crate) kind: JoinKind,
+    pub(crate) from: (IdentRaw<'script>, Option<IdentRaw<'script>>),
+    pub(crate) left_key: ImutExprRaw<'script>,
+    pub(crate) right_key: ImutExprRaw<'script>,
+}
+impl_expr!(JoinRaw);
+
+impl<'script> Upable<'script> for JoinRaw<'script> {
+    type Target = Join<'script>;
+    fn up<'registry>(self, helper: &mut Helper<'script, 'registry>) -> Result<Self::Target> {
+        let (stream, port) = self.from;
+        let port = port.unwrap_or_else(|| {
+            let mut port = stream.clone();
+            port.id = Cow::from("out");
+            port
+        });
+        Ok(Join {
+            mid: self.mid,
+            kind: self.kind,
+            from: (stream.up(helper)?, port.up(helper)?),
+            left_key: self.left_key.up(helper)?,
+            right_key: self.right_key.up(helper)?,
         })
     }
 }
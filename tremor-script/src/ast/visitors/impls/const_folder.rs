@@ -14,7 +14,7 @@
 
 use super::super::prelude::*;
 use crate::{
-    ast::{base_expr::Ranged, binary::extend_bytes_from_value, NodeMeta},
+    ast::{base_expr::Ranged, binary::extend_bytes_from_value, Invocable, NodeMeta},
     errors::{
         err_invalid_unary, err_need_int, error_array_out_of_bound, error_bad_key,
         error_decreasing_range, error_generic, error_need_arr, error_need_obj,
@@ -44,6 +44,29 @@ fn fake_path(mid: &NodeMeta) -> Path {
         mid: Box::new(mid.clone()),
     })
 }
+/// lets intrinsic functions precompile the arguments that are constant
+fn specialize(mut i: Invoke) -> Invoke {
+    if let Invocable::Intrinsic(f) = &i.invocable {
+        let args: Vec<Option<&Value>> = i
+            .args
+            .iter()
+            .map(|a| match a {
+                ImutExpr::Literal(Literal { value, .. }) => Some(value),
+                _ => None,
+            })
+            .collect();
+        let specialized = if args.iter().any(Option::is_some) {
+            f.specialize(&args)
+        } else {
+            None
+        };
+        if let Some(specialized) = specialized {
+            i.invocable = Invocable::Intrinsic(specialized);
+        }
+    }
+    i
+}
+
 impl<'run, 'script: 'run> DeployWalker<'script> for ConstFolder<'run, 'script> {}
 impl<'run, 'script: 'run> QueryWalker<'script> for ConstFolder<'run, 'script> {}
 impl<'run, 'script: 'run> ExprWalker<'script> for ConstFolder<'run, 'script> {}
@@ -204,6 +227,10 @@ impl<'run, 'script: 'run> ImutExprVisitor<'script> for ConstFolder<'run, 'script
                     .map_err(|e| e.into_err(&ex, &ex, Some(self.helper.reg)))?;
                 ImutExpr::literal(i.mid, v)
             }
+            ImutExpr::Invoke(i) => ImutExpr::Invoke(specialize(i)),
+            ImutExpr::Invoke1(i) => ImutExpr::Invoke1(specialize(i)),
+            ImutExpr::Invoke2(i) => ImutExpr::Invoke2(specialize(i)),
+            ImutExpr::Invoke3(i) => ImutExpr::Invoke3(specialize(i)),
             e @ (ImutExpr::Path(_)
             | ImutExpr::String(_)
             | ImutExpr::Patch(_)
@@ -212,10 +239,6 @@ impl<'run, 'script: 'run> ImutExprVisitor<'script> for ConstFolder<'run, 'script
             | ImutExpr::Merge(_)
             | ImutExpr::Local { .. }
             | ImutExpr::Present { .. }
-            | ImutExpr::Invoke(_)
-            | ImutExpr::Invoke1(_)
-            | ImutExpr::Invoke2(_)
-            | ImutExpr::Invoke3(_)
             | ImutExpr::Literal(_)
             | ImutExpr::InvokeAggr(_)
             | ImutExpr::Recur(_)) => e,
@@ -81,6 +81,13 @@ pub trait Walker<'script>: ExprWalker<'script> + QueryVisitor<'script> {
         for w in &mut select.windows {
             self.walk_window_name(w)?;
         }
+        if let Some(j) = select.maybe_join.as_mut() {
+            ImutExprWalker::walk_expr(self, &mut j.left_key)?;
+            ImutExprWalker::walk_expr(self, &mut j.right_key)?;
+        };
+        if let Some(o) = select.maybe_order_by.as_mut() {
+            ImutExprWalker::walk_expr(self, &mut o.expr)?;
+        };
 
         self.leave_select(select)
     }
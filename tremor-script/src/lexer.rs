@@ -116,14 +116,20 @@ pub(crate) fn ident_to_token(ident: &str) -> Token {
         "from" => Token::From,
         "where" => Token::Where,
         "with" => Token::With,
-        // "order" => Token::Order,
+        "order" => Token::Order,
+        "asc" => Token::Asc,
+        "desc" => Token::Desc,
+        "limit" => Token::Limit,
         "group" => Token::Group,
         "by" => Token::By,
+        "join" => Token::Join,
+        "table" => Token::Table,
         "having" => Token::Having,
         "into" => Token::Into,
         "create" => Token::Create,
         "tumbling" => Token::Tumbling,
         "sliding" => Token::Sliding,
+        "session" => Token::Session,
         "window" => Token::Window,
         "stream" => Token::Stream,
         "operator" => Token::Operator,
@@ -354,11 +360,21 @@ pub enum Token<'input> {
     /// The `with` keyword
     With,
     /// The `order` keyword
-    // Order,
+    Order,
+    /// The `asc` keyword
+    Asc,
+    /// The `desc` keyword
+    Desc,
+    /// The `limit` keyword
+    Limit,
     /// the `group` keyword
     Group,
     /// The `by` keyword
     By,
+    /// The `join` keyword
+    Join,
+    /// The `table` keyword
+    Table,
     /// The `having` keyword
     Having,
     /// The `into` keyword
@@ -369,6 +385,8 @@ pub enum Token<'input> {
     Tumbling,
     /// The `sliding` keyword
     Sliding,
+    /// The `session` keyword
+    Session,
     /// The `window` keyword
     Window,
     /// The `stream` keyword
@@ -421,6 +439,7 @@ impl<'input> Token<'input> {
             *self,
             Token::Absent
                 | Token::Args
+                | Token::Asc
                 | Token::By
                 | Token::Case
                 | Token::Const
@@ -428,6 +447,7 @@ impl<'input> Token<'input> {
                 | Token::Create
                 | Token::Default
                 | Token::Define
+                | Token::Desc
                 | Token::Drop
                 | Token::Each
                 | Token::Emit
@@ -442,14 +462,16 @@ impl<'input> Token<'input> {
                 | Token::Insert
                 | Token::Into
                 | Token::Intrinsic
+                | Token::Join
                 | Token::Let
+                | Token::Limit
                 | Token::Match
                 | Token::Merge
                 | Token::Module
                 | Token::Move
                 | Token::Of
                 | Token::Operator
-                // | Token::Order
+                | Token::Order
                 | Token::Patch
                 | Token::Present
                 | Token::Script
@@ -458,8 +480,10 @@ impl<'input> Token<'input> {
                 | Token::Use
                 | Token::As
                 | Token::Sliding
+                | Token::Session
                 | Token::State
                 | Token::Stream
+                | Token::Table
                 | Token::Tumbling
                 | Token::Update
                 | Token::Upsert
@@ -724,14 +748,20 @@ impl<'input> fmt::Display for Token<'input> {
             Token::From => write!(f, "from"),
             Token::Where => write!(f, "where"),
             Token::With => write!(f, "with"),
-            // Token::Order => write!(f, "order"),
+            Token::Order => write!(f, "order"),
+            Token::Asc => write!(f, "asc"),
+            Token::Desc => write!(f, "desc"),
+            Token::Limit => write!(f, "limit"),
             Token::Group => write!(f, "group"),
             Token::By => write!(f, "by"),
+            Token::Join => write!(f, "join"),
+            Token::Table => write!(f, "table"),
             Token::Having => write!(f, "having"),
             Token::Into => write!(f, "into"),
             Token::Create => write!(f, "create"),
             Token::Tumbling => write!(f, "tumbling"),
             Token::Sliding => write!(f, "sliding"),
+            Token::Session => write!(f, "session"),
             Token::Window => write!(f, "window"),
             Token::Stream => write!(f, "stream"),
             Token::Operator => write!(f, "operator"),
@@ -1075,6 +1105,28 @@ impl<'input> Lexer<'input> {
         spanned(start, end, token)
     }
 
+    /// handle dollar, the contextual keywords of a select are names of metadata
+    /// right after it, so `$limit` isn't mistaken for the `limit` clause
+    fn dollar(&mut self, start: Location) -> TokenSpan<'input> {
+        if let Some((id_start, _)) = self.lookahead().filter(|(_, ch)| is_ident_start(*ch)) {
+            self.bump();
+            let mut id = self.id(id_start);
+            let keyword = match id.value {
+                Token::Join => Some("join"),
+                Token::Order => Some("order"),
+                Token::Asc => Some("asc"),
+                Token::Desc => Some("desc"),
+                Token::Limit => Some("limit"),
+                _ => None,
+            };
+            if let Some(name) = keyword {
+                id.value = Token::Ident(name.into(), false);
+            }
+            self.stored_tokens.push_back(id);
+        }
+        spanned(start, start + '$', Token::Dollar)
+    }
+
     fn next_index(&mut self) -> Result<Location> {
         let (loc, _) = self.must_bump()?;
         Ok(loc)
@@ -1932,7 +1984,7 @@ impl<'input> Iterator for Lexer<'input> {
             // '...' =>  Some(Ok(spanned2(start, self.next_index(), Token::DotDotDot))),
             // ".." =>  Some(Ok(spanned2(start, self.next_index(), Token::DotDot))),
             ',' => Some(Ok(spanned(start, start + ch, Token::Comma))),
-            '$' => Some(Ok(spanned(start, start + ch, Token::Dollar))),
+            '$' => Some(Ok(self.dollar(start))),
             '.' => Some(Ok(spanned(start, start + ch, Token::Dot))),
             //                        '?' => Some(Ok(spanned2(start, start, Token::Question))),
             '_' => Some(Ok(spanned(start, start + ch, Token::DontCare))),
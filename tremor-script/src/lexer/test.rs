@@ -131,6 +131,16 @@ fn paths() -> Result<()> {
         "  ~ " => Token::Dollar,
         "   ~~~~~~ " => Token::Ident("borp".into(), true),
     };
+    lex_ok! {
+        "  $limit ",
+        "  ~ " => Token::Dollar,
+        "   ~~~~~ " => Token::Ident("limit".into(), false),
+    };
+    lex_ok! {
+        "  $ limit ",
+        "  ~ " => Token::Dollar,
+        "    ~~~~~ " => Token::Limit,
+    };
     Ok(())
 }
 
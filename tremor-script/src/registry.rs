@@ -58,6 +58,27 @@ pub trait TremorAggrFn: DowncastSync + Sync + Send {
     /// # Errors
     /// if the two functions can not be merged
     fn merge(&mut self, src: &dyn TremorAggrFn) -> FResult<()>;
+    /// Merges the state of a later pane of the same (sliding) window into this
+    /// this requires `&self` and `&src` to be of the same type.
+    ///
+    /// Unlike `merge` both states belong to the same window level, by default
+    /// this is the same as `merge`.
+    ///
+    /// # Errors
+    /// if the two functions can not be merged
+    fn merge_pane(&mut self, src: &dyn TremorAggrFn) -> FResult<()> {
+        self.merge(src)
+    }
+    /// Snapshots the state of the function so it can be persisted,
+    /// returns `None` if the function does not support snapshots
+    fn snapshot(&self) -> Option<Value<'static>> {
+        None
+    }
+    /// Restores the state of the function from a snapshot taken with
+    /// `snapshot`, returns `None` if the snapshot does not match the function
+    fn restore(&mut self, _snapshot: &Value) -> Option<()> {
+        None
+    }
     /// allows cloning the functions without implementing
     /// `Clone` to avoid rust complaining
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn>;
@@ -96,6 +117,14 @@ pub trait TremorFn: Sync + Send {
     fn is_const(&self) -> bool {
         false
     }
+    /// returns a version of this function specialized for the arguments
+    /// that are constant, e.g. with precompiled patterns. `args` holds
+    /// `None` for every argument that is only known at runtime.
+    ///
+    /// The specialized function is still invoked with all arguments.
+    fn specialize(&self, _args: &[Option<&Value>]) -> Option<Box<dyn TremorFn>> {
+        None
+    }
 }
 /// The result of a function
 pub type FResult<T> = std::result::Result<T, FunctionError>;
@@ -343,6 +372,17 @@ impl TremorFnWrapper {
     pub fn is_const(&self) -> bool {
         self.fun.is_const()
     }
+
+    /// Returns a version of the function specialized for its constant arguments, if the
+    /// function supports it
+    #[must_use]
+    pub fn specialize(&self, args: &[Option<&Value>]) -> Option<Self> {
+        self.fun.specialize(args).map(|fun| Self {
+            module: self.module.clone(),
+            name: self.name.clone(),
+            fun,
+        })
+    }
 }
 
 impl Clone for TremorFnWrapper {
@@ -774,6 +814,42 @@ impl TremorAggrFnWrapper {
         use std::borrow::Borrow;
         self.fun.merge(src.fun.borrow())
     }
+
+    /// Merges the state of a later pane of the same window into this
+    /// this requires `&self` and `&src` to be of the same type.
+    ///
+    /// # Errors
+    /// if the merge function fails
+    pub fn merge_pane(&mut self, src: &Self) -> FResult<()> {
+        use std::borrow::Borrow;
+        self.fun.merge_pane(src.fun.borrow())
+    }
+
+    /// Snapshots the state of the function
+    ///
+    /// # Errors
+    /// if the function does not support snapshots
+    pub fn snapshot(&self) -> FResult<Value<'static>> {
+        self.fun
+            .snapshot()
+            .ok_or_else(|| FunctionError::RuntimeError {
+                mfa: mfa(&self.module, &self.name, *self.arity().start()),
+                error: "The function does not support snapshots".to_string(),
+            })
+    }
+
+    /// Restores the state of the function from a snapshot
+    ///
+    /// # Errors
+    /// if the snapshot does not match the function
+    pub fn restore(&mut self, snapshot: &Value) -> FResult<()> {
+        self.fun
+            .restore(snapshot)
+            .ok_or_else(|| FunctionError::RuntimeError {
+                mfa: mfa(&self.module, &self.name, *self.arity().start()),
+                error: "Invalid snapshot".to_string(),
+            })
+    }
 }
 
 // #[cfg_attr(coverage, no_coverage)]
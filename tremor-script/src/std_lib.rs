@@ -23,12 +23,16 @@ mod array;
 mod base64;
 mod binary;
 mod chash;
+mod crypto;
 mod datetime;
 mod dummy;
 mod float;
+mod hex;
+mod id;
 mod integer;
 mod json;
 mod math;
+mod net;
 mod origin;
 mod path;
 mod random;
@@ -50,12 +54,16 @@ pub fn load(registry: &mut Registry) {
     base64::load(registry);
     binary::load(registry);
     chash::load(registry);
+    crypto::load(registry);
     datetime::load(registry);
     dummy::load(registry);
     float::load(registry);
+    hex::load(registry);
+    id::load(registry);
     integer::load(registry);
     json::load(registry);
     math::load(registry);
+    net::load(registry);
     origin::load(registry);
     random::load(registry);
     range::load(registry);
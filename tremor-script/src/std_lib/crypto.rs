@@ -0,0 +1,205 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::prelude::*;
+use crate::registry::Registry;
+use crate::tremor_const_fn;
+use hmac::{digest::KeyInit, Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::io::Cursor;
+
+/// the bytes to hash, strings are hashed as UTF-8
+fn data<'value>(value: &'value Value) -> Option<&'value [u8]> {
+    value
+        .as_str()
+        .map(str::as_bytes)
+        .or_else(|| value.as_bytes())
+}
+
+macro_rules! digest_fn {
+    ($name:ident, $digest:expr) => {
+        tremor_const_fn! (crypto|$name(_context, _data) {
+            data(_data)
+                .map(|data| Value::Bytes($digest(data).into()))
+                .ok_or_else(|| FunctionError::BadType{ mfa: this_mfa() })
+        })
+    };
+}
+
+fn mac<M: Mac + KeyInit>(key: &[u8], data: &[u8]) -> Result<M, String> {
+    let mut mac = <M as KeyInit>::new_from_slice(key).map_err(|e| e.to_string())?;
+    mac.update(data);
+    Ok(mac)
+}
+
+fn unknown_algorithm(algorithm: &str) -> String {
+    format!(
+        "Unknown HMAC algorithm {}, supported are sha1, sha256 and sha512",
+        algorithm
+    )
+}
+
+pub fn _hmac(algorithm: &str, key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+    match algorithm {
+        "sha1" => Ok(mac::<Hmac<Sha1>>(key, data)?
+            .finalize()
+            .into_bytes()
+            .to_vec()),
+        "sha256" => Ok(mac::<Hmac<Sha256>>(key, data)?
+            .finalize()
+            .into_bytes()
+            .to_vec()),
+        "sha512" => Ok(mac::<Hmac<Sha512>>(key, data)?
+            .finalize()
+            .into_bytes()
+            .to_vec()),
+        other => Err(unknown_algorithm(other)),
+    }
+}
+
+/// verifies a signature in constant time
+pub fn _hmac_verify(
+    algorithm: &str,
+    key: &[u8],
+    data: &[u8],
+    signature: &[u8],
+) -> Result<bool, String> {
+    match algorithm {
+        "sha1" => Ok(mac::<Hmac<Sha1>>(key, data)?
+            .verify_slice(signature)
+            .is_ok()),
+        "sha256" => Ok(mac::<Hmac<Sha256>>(key, data)?
+            .verify_slice(signature)
+            .is_ok()),
+        "sha512" => Ok(mac::<Hmac<Sha512>>(key, data)?
+            .verify_slice(signature)
+            .is_ok()),
+        other => Err(unknown_algorithm(other)),
+    }
+}
+
+pub fn load(registry: &mut Registry) {
+    registry
+        .insert(digest_fn!(md5, |data| ::md5::compute(data).0.to_vec()))
+        .insert(digest_fn!(sha1, |data| Sha1::digest(data).to_vec()))
+        .insert(digest_fn!(sha256, |data| Sha256::digest(data).to_vec()))
+        .insert(digest_fn!(sha512, |data| Sha512::digest(data).to_vec()))
+        .insert(tremor_const_fn! (crypto|xxhash64(_context, _data, _seed) {
+            if let (Some(data), Some(seed)) = (data(_data), _seed.as_u64()) {
+                Ok(Value::from(xxhash_rust::xxh64::xxh64(data, seed)))
+            } else {
+                Err(FunctionError::BadType{ mfa: this_mfa() })
+            }
+        }))
+        .insert(tremor_const_fn! (crypto|murmur3_32(_context, _data, _seed) {
+            if let (Some(data), Some(seed)) = (data(_data), _seed.as_u32()) {
+                murmur3::murmur3_32(&mut Cursor::new(data), seed).map(Value::from).map_err(to_runtime_error)
+            } else {
+                Err(FunctionError::BadType{ mfa: this_mfa() })
+            }
+        }))
+        .insert(tremor_const_fn! (crypto|hmac(_context, _algorithm, _key, _data) {
+            if let (Some(algorithm), Some(key), Some(data)) = (_algorithm.as_str(), data(_key), data(_data)) {
+                _hmac(algorithm, key, data).map(|mac| Value::Bytes(mac.into())).map_err(to_runtime_error)
+            } else {
+                Err(FunctionError::BadType{ mfa: this_mfa() })
+            }
+        }))
+        .insert(tremor_const_fn! (crypto|hmac_verify(_context, _algorithm, _key, _data, _signature) {
+            if let (Some(algorithm), Some(key), Some(data), Some(signature)) =
+                (_algorithm.as_str(), data(_key), data(_data), _signature.as_bytes())
+            {
+                _hmac_verify(algorithm, key, data, signature).map(Value::from).map_err(to_runtime_error)
+            } else {
+                Err(FunctionError::BadType{ mfa: this_mfa() })
+            }
+        }));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::registry::fun;
+    use crate::Value;
+
+    fn bytes(s: &str) -> Value<'static> {
+        Value::Bytes(hex::decode(s).unwrap_or_default().into())
+    }
+
+    #[test]
+    fn digests() {
+        let v = Value::from("abc");
+        let f = fun("crypto", "md5");
+        assert_val!(f(&[&v]), bytes("900150983cd24fb0d6963f7d28e17f72"));
+        let f = fun("crypto", "sha1");
+        assert_val!(f(&[&v]), bytes("a9993e364706816aba3e25717850c26c9cd0d89d"));
+        let f = fun("crypto", "sha256");
+        assert_val!(
+            f(&[&v]),
+            bytes("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        // binaries are hashed as they are
+        let b = Value::Bytes("abc".as_bytes().into());
+        assert_val!(
+            f(&[&b]),
+            bytes("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        let f = fun("crypto", "sha512");
+        assert_val!(
+            f(&[&v]),
+            bytes("ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f")
+        );
+        assert!(f(&[&Value::from(42)]).is_err());
+    }
+
+    #[test]
+    fn non_cryptographic() {
+        let f = fun("crypto", "xxhash64");
+        assert_val!(
+            f(&[&Value::from("abc"), &Value::from(0)]),
+            4_952_883_123_889_572_249_u64
+        );
+        assert_val!(
+            f(&[&Value::from("snot"), &Value::from(42)]),
+            7_590_099_351_491_190_029_u64
+        );
+        let f = fun("crypto", "murmur3_32");
+        assert_val!(
+            f(&[&Value::from("hello"), &Value::from(0)]),
+            613_153_351_u32
+        );
+        assert_val!(
+            f(&[&Value::from("snot"), &Value::from(42)]),
+            3_100_365_455_u32
+        );
+    }
+
+    #[test]
+    fn hmac() {
+        let key = Value::from("key");
+        let data = Value::from("The quick brown fox jumps over the lazy dog");
+        let signature = bytes("f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8");
+        let f = fun("crypto", "hmac");
+        assert_val!(f(&[&Value::from("sha256"), &key, &data]), signature.clone());
+        assert!(f(&[&Value::from("sha3"), &key, &data]).is_err());
+
+        let f = fun("crypto", "hmac_verify");
+        assert_val!(f(&[&Value::from("sha256"), &key, &data, &signature]), true);
+        let other = Value::from("the quick brown fox jumps over the lazy dog");
+        assert_val!(
+            f(&[&Value::from("sha256"), &key, &other, &signature]),
+            false
+        );
+    }
+}
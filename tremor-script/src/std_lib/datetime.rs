@@ -23,7 +23,11 @@ use crate::datetime::{_parse, has_tz};
 use crate::prelude::*;
 use crate::registry::Registry;
 use crate::{tremor_const_fn, tremor_fn};
-use chrono::{offset::Utc, DateTime, Datelike, NaiveDateTime, SubsecRound, Timelike};
+use chrono::{
+    offset::Utc, DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Offset, SubsecRound,
+    TimeZone, Timelike,
+};
+use chrono_tz::Tz;
 
 macro_rules! time_fn {
     ($name:ident, $fn:ident) => {
@@ -40,6 +44,31 @@ macro_rules! time_fn_32 {
     };
 }
 
+macro_rules! time_tz_fn {
+    ($name:ident, $fn:ident) => {
+        tremor_const_fn! (datetime|$name(_context, _value, _tz) {
+            if let (Some(value), Some(tz)) = (_value.as_u64(), _tz.as_str()) {
+                let tz = to_tz(tz).map_err(to_runtime_error)?;
+                Ok(Value::from($fn(value, tz)))
+            } else {
+                Err(FunctionError::BadType{ mfa: this_mfa() })
+            }
+        })
+    };
+}
+macro_rules! start_of_tz_fn {
+    ($name:ident, $fn:ident) => {
+        tremor_const_fn! (datetime|$name(_context, _value, _tz) {
+            if let (Some(value), Some(tz)) = (_value.as_u64(), _tz.as_str()) {
+                let tz = to_tz(tz).map_err(to_runtime_error)?;
+                $fn(value, tz).map(Value::from).ok_or_else(|| to_runtime_error("The start is before 1970-01-01"))
+            } else {
+                Err(FunctionError::BadType{ mfa: this_mfa() })
+            }
+        })
+    };
+}
+
 pub fn load(registry: &mut Registry) {
     registry
         .insert(
@@ -59,6 +88,23 @@ pub fn load(registry: &mut Registry) {
                 Err(FunctionError::BadType{ mfa: this_mfa() })
             }
         }))
+        .insert(tremor_const_fn!(datetime|format_tz(_context, _datetime, _fmt, _tz) {
+            if let (Some(datetime), Some(fmt), Some(tz)) = (_datetime.as_u64(), _fmt.as_str(), _tz.as_str()) {
+                let tz = to_tz(tz).map_err(to_runtime_error)?;
+                Ok(Value::from(_format_tz(datetime, fmt, tz)))
+            } else {
+                Err(FunctionError::BadType{ mfa: this_mfa() })
+            }
+        }))
+        .insert(time_tz_fn!(year_tz, _year_tz))
+        .insert(time_tz_fn!(month_tz, _month_tz))
+        .insert(time_tz_fn!(day_tz, _day_tz))
+        .insert(time_tz_fn!(hour_tz, _hour_tz))
+        .insert(time_tz_fn!(minute_tz, _minute_tz))
+        .insert(time_tz_fn!(offset_tz, _offset_tz))
+        .insert(start_of_tz_fn!(start_of_day_tz, _start_of_day_tz))
+        .insert(start_of_tz_fn!(start_of_week_tz, _start_of_week_tz))
+        .insert(start_of_tz_fn!(start_of_month_tz, _start_of_month_tz))
         .insert(time_fn!(year, _year))
         .insert(time_fn!(month, _month))
         .insert(time_fn!(day, _day))
@@ -147,6 +193,79 @@ pub fn _subsecond(value: u64) -> u32 {
     to_naive_datetime(value).nanosecond()
 }
 
+fn to_tz(tz: &str) -> Result<Tz, String> {
+    tz.parse().map_err(|_| {
+        format!(
+            "Unknown timezone {}, expected an IANA name like Europe/Berlin",
+            tz
+        )
+    })
+}
+
+fn to_datetime_tz(value: u64, tz: Tz) -> DateTime<Tz> {
+    tz.from_utc_datetime(&to_naive_datetime(value))
+}
+
+pub fn _format_tz(value: u64, fmt: &str, tz: Tz) -> String {
+    format!("{}", to_datetime_tz(value, tz).format(fmt))
+}
+
+pub fn _minute_tz(value: u64, tz: Tz) -> u8 {
+    to_datetime_tz(value, tz).minute() as u8
+}
+
+pub fn _hour_tz(value: u64, tz: Tz) -> u8 {
+    to_datetime_tz(value, tz).hour() as u8
+}
+
+pub fn _day_tz(value: u64, tz: Tz) -> u8 {
+    to_datetime_tz(value, tz).day() as u8
+}
+
+pub fn _month_tz(value: u64, tz: Tz) -> u8 {
+    to_datetime_tz(value, tz).month() as u8
+}
+
+pub fn _year_tz(value: u64, tz: Tz) -> u32 {
+    to_datetime_tz(value, tz).year() as u32
+}
+
+/// The offset of the local time in the timezone to UTC in seconds
+pub fn _offset_tz(value: u64, tz: Tz) -> i32 {
+    to_datetime_tz(value, tz).offset().fix().local_minus_utc()
+}
+
+/// The first instant of a local date in a timezone. If a DST transition skips
+/// midnight, the day starts with the transition.
+fn start_of_date(date: NaiveDate, tz: Tz) -> Option<u64> {
+    let midnight = date.and_hms(0, 0, 0);
+    let start = tz.from_local_datetime(&midnight).earliest().or_else(|| {
+        // midnight in the offset before the transition is the instant of the transition
+        let before = tz
+            .from_local_datetime(&(midnight - Duration::hours(6)))
+            .earliest()?;
+        let utc = midnight - Duration::seconds(i64::from(before.offset().fix().local_minus_utc()));
+        Some(tz.from_utc_datetime(&utc))
+    })?;
+    u64::try_from(start.timestamp_nanos()).ok()
+}
+
+pub fn _start_of_day_tz(value: u64, tz: Tz) -> Option<u64> {
+    start_of_date(to_datetime_tz(value, tz).date().naive_local(), tz)
+}
+
+/// weeks start on monday
+pub fn _start_of_week_tz(value: u64, tz: Tz) -> Option<u64> {
+    let date = to_datetime_tz(value, tz).date().naive_local();
+    let days = date.weekday().num_days_from_monday();
+    start_of_date(date - Duration::days(i64::from(days)), tz)
+}
+
+pub fn _start_of_month_tz(value: u64, tz: Tz) -> Option<u64> {
+    let date = to_datetime_tz(value, tz).date().naive_local();
+    start_of_date(date.with_day(1)?, tz)
+}
+
 fn to_naive_datetime(value: u64) -> NaiveDateTime {
     NaiveDateTime::from_timestamp(
         (value / 1_000_000_000) as i64,
@@ -385,6 +504,65 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn format_tz() -> Result<(), String> {
+        let berlin = to_tz("Europe/Berlin")?;
+        assert_eq!(
+            "2019-06-04 15:43:02 +02:00",
+            _format_tz(1_559_655_782_123_456_789, "%Y-%m-%d %H:%M:%S %:z", berlin)
+        );
+        assert_eq!(
+            "2019-01-16 00:30:00 CET",
+            _format_tz(1_547_595_000_000_000_000, "%Y-%m-%d %H:%M:%S %Z", berlin)
+        );
+        assert!(to_tz("Mars/Olympus_Mons").is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn fields_tz() -> Result<(), String> {
+        let berlin = to_tz("Europe/Berlin")?;
+        // 2019-01-15T23:30:00Z is already the 16th in Berlin
+        let winter = 1_547_595_000_000_000_000;
+        assert_eq!(_year_tz(winter, berlin), 2019);
+        assert_eq!(_month_tz(winter, berlin), 1);
+        assert_eq!(_day_tz(winter, berlin), 16);
+        assert_eq!(_hour_tz(winter, berlin), 0);
+        assert_eq!(_minute_tz(winter, berlin), 30);
+        assert_eq!(_offset_tz(winter, berlin), 3600);
+        assert_eq!(_hour_tz(1_559_655_782_123_456_789, berlin), 15);
+        assert_eq!(_offset_tz(1_559_655_782_123_456_789, berlin), 7200);
+        Ok(())
+    }
+
+    #[test]
+    pub fn start_of_tz() -> Result<(), String> {
+        let berlin = to_tz("Europe/Berlin")?;
+        // 2022-03-27, a sunday, only has 23 hours in Berlin
+        let dst_day = 1_648_382_400_000_000_000;
+        assert_eq!(
+            _start_of_day_tz(dst_day, berlin),
+            Some(1_648_335_600_000_000_000)
+        );
+        assert_eq!(
+            _start_of_week_tz(dst_day, berlin),
+            Some(1_647_817_200_000_000_000)
+        );
+        assert_eq!(
+            _start_of_month_tz(dst_day, berlin),
+            Some(1_646_089_200_000_000_000)
+        );
+        // on 2018-11-04 Sao Paulo skipped from midnight to 1am
+        let sao_paulo = to_tz("America/Sao_Paulo")?;
+        assert_eq!(
+            _start_of_day_tz(1_541_332_800_000_000_000, sao_paulo),
+            Some(1_541_300_400_000_000_000)
+        );
+        // the start of the day is before the epoch
+        assert_eq!(_start_of_day_tz(0, berlin), None);
+        Ok(())
+    }
+
     #[test]
     pub fn without_subseconds() {
         let input = 1_559_655_782_123_456_789_u64;
@@ -0,0 +1,197 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::prelude::*;
+use crate::registry::Registry;
+use crate::{tremor_const_fn, tremor_fn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tremor_common::time::nanotime;
+use uuid::Uuid;
+
+/// Crockford's base32 alphabet used by ULIDs
+const ULID_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// The epoch of snowflake ids, 2010-11-04T01:42:54.657Z as used by Twitter
+const SNOWFLAKE_EPOCH_MS: u64 = 1_288_834_974_657;
+const SNOWFLAKE_WORKER_BITS: u64 = 10;
+const SNOWFLAKE_SEQUENCE_BITS: u64 = 12;
+
+/// the millisecond and sequence number of the last snowflake id, as `ms << 12 | sequence`
+static SNOWFLAKE_STATE: AtomicU64 = AtomicU64::new(0);
+
+fn now_ms() -> u64 {
+    nanotime() / 1_000_000
+}
+
+/// a version 7 UUID: 48 bits of unix time in milliseconds followed by random bits
+fn uuid_v7(ms: u64, random: [u8; 16]) -> Uuid {
+    let mut bytes = random;
+    bytes[..6].copy_from_slice(&ms.to_be_bytes()[2..]);
+    bytes[6] = 0x70 | (bytes[6] & 0x0f);
+    bytes[8] = 0x80 | (bytes[8] & 0x3f);
+    Uuid::from_bytes(bytes)
+}
+
+/// a ULID: 48 bits of unix time in milliseconds followed by 80 random bits, base32 encoded
+// every character is masked to 5 bits
+#[allow(clippy::cast_possible_truncation)]
+fn ulid(ms: u64, random: [u8; 10]) -> String {
+    let mut value = u128::from(ms & 0xffff_ffff_ffff) << 80;
+    for (i, b) in random.iter().enumerate() {
+        value |= u128::from(*b) << (72 - 8 * i);
+    }
+    (0..26)
+        .rev()
+        .map(|i| char::from(ULID_ALPHABET[((value >> (5 * i)) & 0x1f) as usize]))
+        .collect()
+}
+
+/// the next snowflake id of a worker: 41 bits of milliseconds since the snowflake epoch,
+/// 10 bits of worker id and a 12 bit sequence number.
+///
+/// If the sequence of a millisecond is exhausted, ids of the next millisecond are handed out,
+/// so ids of this process are always increasing.
+fn snowflake(worker: u64, ms: u64) -> u64 {
+    let max_sequence = (1 << SNOWFLAKE_SEQUENCE_BITS) - 1;
+    let mut last = SNOWFLAKE_STATE.load(Ordering::Acquire);
+    loop {
+        let (last_ms, sequence) = (last >> SNOWFLAKE_SEQUENCE_BITS, last & max_sequence);
+        let next = if ms > last_ms {
+            ms << SNOWFLAKE_SEQUENCE_BITS
+        } else if sequence < max_sequence {
+            last + 1
+        } else {
+            (last_ms + 1) << SNOWFLAKE_SEQUENCE_BITS
+        };
+        match SNOWFLAKE_STATE.compare_exchange_weak(last, next, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => {
+                let (ms, sequence) = (next >> SNOWFLAKE_SEQUENCE_BITS, next & max_sequence);
+                let ms = ms.saturating_sub(SNOWFLAKE_EPOCH_MS) & ((1 << 41) - 1);
+                return ms << (SNOWFLAKE_WORKER_BITS + SNOWFLAKE_SEQUENCE_BITS)
+                    | worker << SNOWFLAKE_SEQUENCE_BITS
+                    | sequence;
+            }
+            Err(actual) => last = actual,
+        }
+    }
+}
+
+fn namespace(namespace: &str) -> Result<Uuid, String> {
+    match namespace {
+        "dns" => Ok(Uuid::NAMESPACE_DNS),
+        "url" => Ok(Uuid::NAMESPACE_URL),
+        "oid" => Ok(Uuid::NAMESPACE_OID),
+        "x500" => Ok(Uuid::NAMESPACE_X500),
+        other => Uuid::parse_str(other).map_err(|e| format!("Invalid namespace {}: {}", other, e)),
+    }
+}
+
+pub fn load(registry: &mut Registry) {
+    // the generating functions are not const, each call has to produce a new id
+    registry
+        .insert(tremor_fn! (id|uuid_v4(_context) {
+            Ok(Value::from(Uuid::new_v4().to_string()))
+        }))
+        .insert(tremor_fn! (id|uuid_v7(_context) {
+            Ok(Value::from(uuid_v7(now_ms(), rand::random()).to_string()))
+        }))
+        .insert(tremor_fn! (id|ulid(_context) {
+            Ok(Value::from(ulid(now_ms(), rand::random())))
+        }))
+        .insert(tremor_fn! (id|snowflake(_context, _worker) {
+            if let Some(worker) = _worker.as_u64().filter(|w| *w < 1 << SNOWFLAKE_WORKER_BITS) {
+                Ok(Value::from(snowflake(worker, now_ms())))
+            } else {
+                Err(to_runtime_error("The worker id needs to be an integer between 0 and 1023"))
+            }
+        }))
+        .insert(
+            tremor_const_fn! (id|uuid_v5(_context, _namespace: String, _name: String) {
+                let namespace = namespace(_namespace).map_err(to_runtime_error)?;
+                Ok(Value::from(Uuid::new_v5(&namespace, _name.as_bytes()).to_string()))
+            }),
+        );
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::registry::fun;
+
+    #[test]
+    fn uuid_v4() {
+        let f = fun("id", "uuid_v4");
+        let a = f(&[]).expect("uuid");
+        let b = f(&[]).expect("uuid");
+        assert_ne!(a, b);
+        let a = uuid::Uuid::parse_str(a.as_str().unwrap_or_default()).expect("valid uuid");
+        assert_eq!(4, a.get_version_num());
+    }
+
+    #[test]
+    fn uuid_v7() {
+        let u = super::uuid_v7(0x0123_4567_89ab, [0xff; 16]);
+        assert_eq!("01234567-89ab-7fff-bfff-ffffffffffff", u.to_string());
+        assert_eq!(7, u.get_version_num());
+        assert_eq!(uuid::Variant::RFC4122, u.get_variant());
+
+        let f = fun("id", "uuid_v7");
+        let a = f(&[]).expect("uuid");
+        let b = f(&[]).expect("uuid");
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn uuid_v5() {
+        let f = fun("id", "uuid_v5");
+        let ns = Value::from("dns");
+        let name = Value::from("tremor.rs");
+        let expected = uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_DNS, b"tremor.rs").to_string();
+        assert_val!(f(&[&ns, &name]), expected.clone());
+        let ns = Value::from("6ba7b810-9dad-11d1-80b4-00c04fd430c8");
+        assert_val!(f(&[&ns, &name]), expected);
+        let ns = Value::from("snot");
+        assert!(f(&[&ns, &name]).is_err());
+    }
+
+    #[test]
+    fn ulid() {
+        assert_eq!("00000000000000000000000000", super::ulid(0, [0; 10]));
+        assert_eq!(
+            "7ZZZZZZZZZZZZZZZZZZZZZZZZZ",
+            super::ulid(0xffff_ffff_ffff, [0xff; 10])
+        );
+        assert_eq!("01ARZ3NDEK", &super::ulid(1_469_922_850_259, [0; 10])[..10]);
+
+        let f = fun("id", "ulid");
+        let a = f(&[]).expect("ulid");
+        assert_eq!(Some(26), a.as_str().map(str::len));
+    }
+
+    #[test]
+    fn snowflake() {
+        let ms = super::SNOWFLAKE_EPOCH_MS + 42;
+        let a = super::snowflake(7, ms);
+        let b = super::snowflake(7, ms);
+        assert!(b > a);
+        assert_eq!(7, (a >> 12) & 0x3ff);
+
+        let f = fun("id", "snowflake");
+        let worker = Value::from(1);
+        assert!(f(&[&worker]).is_ok());
+        let worker = Value::from(1024);
+        assert!(f(&[&worker]).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+// Copyright 2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::prelude::*;
+use crate::registry::{
+    mfa, to_runtime_error, FResult, FunctionError, Registry, TremorFn, TremorFnWrapper,
+};
+use crate::tremor_const_fn;
+use crate::EventContext;
+use crate::Value;
+use cidr_utils::{cidr::IpCidr, utils::IpCidrCombiner};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use std::sync::Arc;
+
+fn parse_ip(s: &str) -> Result<IpAddr, String> {
+    IpAddr::from_str(s).map_err(|_| format!("Invalid IP address: {}", s))
+}
+
+/// A network in CIDR notation, a plain address is a network with all bits set
+#[derive(Clone, Copy, Debug)]
+struct Net {
+    addr: IpAddr,
+    bits: u8,
+}
+
+impl Net {
+    fn parse(s: &str) -> Result<Self, String> {
+        let (addr, bits) = s.split_once('/').unwrap_or((s, ""));
+        let addr = parse_ip(addr)?;
+        let bits = if bits.is_empty() {
+            max_bits(addr)
+        } else {
+            u8::from_str(bits)
+                .ok()
+                .filter(|bits| *bits <= max_bits(addr))
+                .ok_or_else(|| format!("Invalid CIDR: {}", s))?
+        };
+        Ok(Self { addr, bits })
+    }
+
+    /// the first address of the network
+    fn network(self) -> IpAddr {
+        mask(self.addr, self.bits, false)
+    }
+
+    /// the last address of the network
+    fn broadcast(self) -> IpAddr {
+        mask(self.addr, self.bits, true)
+    }
+}
+
+fn max_bits(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// keeps the first `bits` bits of the address and sets all other bits to `host`
+fn mask(addr: IpAddr, bits: u8, host: bool) -> IpAddr {
+    match addr {
+        IpAddr::V4(addr) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(bits)).unwrap_or(0);
+            let addr = u32::from(addr) & mask;
+            IpAddr::V4(Ipv4Addr::from(if host { addr | !mask } else { addr }))
+        }
+        IpAddr::V6(addr) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(bits)).unwrap_or(0);
+            let addr = u128::from(addr) & mask;
+            IpAddr::V6(Ipv6Addr::from(if host { addr | !mask } else { addr }))
+        }
+    }
+}
+
+fn is_private(addr: IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(addr) => addr.is_private(),
+        // unique local addresses, fc00::/7
+        IpAddr::V6(addr) => addr.segments()[0] & 0xfe00 == 0xfc00,
+    }
+}
+
+/// Matches addresses against a list of networks
+struct Matcher(IpCidrCombiner);
+
+impl Matcher {
+    /// `cidrs` is either a single network or a list of networks
+    fn new(cidrs: &Value) -> Result<Self, String> {
+        let cidrs: Vec<&str> = if let Some(cidr) = cidrs.as_str() {
+            vec![cidr]
+        } else if let Some(cidrs) = cidrs.as_array() {
+            cidrs
+                .iter()
+                .map(|cidr| {
+                    cidr.as_str()
+                        .ok_or_else(|| format!("Expected a CIDR string but got: {}", cidr.encode()))
+                })
+                .collect::<Result<_, _>>()?
+        } else {
+            return Err("Expected a CIDR string or a list of them".to_string());
+        };
+        let mut combiner = IpCidrCombiner::new();
+        for cidr in cidrs {
+            let net = Net::parse(cidr)?;
+            let cidr = IpCidr::from_str(&format!("{}/{}", net.network(), net.bits))
+                .map_err(|_| format!("Invalid CIDR: {}", cidr))?;
+            combiner.push(cidr);
+        }
+        Ok(Self(combiner))
+    }
+
+    fn contains(&self, addr: IpAddr) -> bool {
+        self.0.contains(addr)
+    }
+}
+
+/// `net::in_cidr`, a constant list of networks is compiled into a matcher once
+#[derive(Clone, Default)]
+struct InCidr {
+    matcher: Option<Arc<Matcher>>,
+}
+
+impl TremorFn for InCidr {
+    fn invoke<'event, 'c>(
+        &self,
+        _ctx: &'c EventContext,
+        args: &[&Value<'event>],
+    ) -> FResult<Value<'event>> {
+        let this_mfa = || mfa("net", "in_cidr", 2);
+        let (addr, cidrs) = match args {
+            [addr, cidrs] => (addr, cidrs),
+            _ => {
+                return Err(FunctionError::BadArity {
+                    mfa: this_mfa(),
+                    calling_a: args.len(),
+                })
+            }
+        };
+        let addr = addr
+            .as_str()
+            .ok_or_else(|| FunctionError::BadType { mfa: this_mfa() })?;
+        let addr = parse_ip(addr).map_err(|e| to_runtime_error(this_mfa(), e))?;
+        let contained = if let Some(matcher) = &self.matcher {
+            matcher.contains(addr)
+        } else {
+            Matcher::new(cidrs)
+                .map_err(|e| to_runtime_error(this_mfa(), e))?
+                .contains(addr)
+        };
+        Ok(Value::from(contained))
+    }
+
+    fn boxed_clone(&self) -> Box<dyn TremorFn> {
+        Box::new(self.clone())
+    }
+    fn arity(&self) -> std::ops::RangeInclusive<usize> {
+        2..=2
+    }
+    fn is_const(&self) -> bool {
+        true
+    }
+    fn specialize(&self, args: &[Option<&Value>]) -> Option<Box<dyn TremorFn>> {
+        // invalid networks are reported when the function is invoked
+        let matcher = Matcher::new(args.get(1).copied().flatten()?).ok()?;
+        Some(Box::new(Self {
+            matcher: Some(Arc::new(matcher)),
+        }))
+    }
+}
+
+pub fn load(registry: &mut Registry) {
+    registry
+        .insert(tremor_const_fn! (net|is_ipv4(_context, _input: String) {
+            Ok(Value::from(Ipv4Addr::from_str(_input).is_ok()))
+        }))
+        .insert(tremor_const_fn! (net|is_ipv6(_context, _input: String) {
+            Ok(Value::from(Ipv6Addr::from_str(_input).is_ok()))
+        }))
+        .insert(tremor_const_fn! (net|parse(_context, _input: String) {
+            match parse_ip(_input).map_err(to_runtime_error)? {
+                IpAddr::V4(addr) => Ok(literal!({
+                    "version": 4,
+                    "address": addr.to_string(),
+                    "octets": addr.octets().to_vec(),
+                })),
+                IpAddr::V6(addr) => Ok(literal!({
+                    "version": 6,
+                    "address": addr.to_string(),
+                    "segments": addr.segments().to_vec(),
+                })),
+            }
+        }))
+        .insert(tremor_const_fn! (net|is_private(_context, _input: String) {
+            parse_ip(_input).map(|addr| Value::from(is_private(addr))).map_err(to_runtime_error)
+        }))
+        .insert(tremor_const_fn! (net|is_loopback(_context, _input: String) {
+            parse_ip(_input).map(|addr| Value::from(addr.is_loopback())).map_err(to_runtime_error)
+        }))
+        .insert(tremor_const_fn! (net|network(_context, _input: String) {
+            Net::parse(_input).map(|net| Value::from(net.network().to_string())).map_err(to_runtime_error)
+        }))
+        .insert(tremor_const_fn! (net|broadcast(_context, _input: String) {
+            Net::parse(_input).map(|net| Value::from(net.broadcast().to_string())).map_err(to_runtime_error)
+        }))
+        .insert(tremor_const_fn! (net|anonymize(_context, _input, _bits) {
+            if let (Some(input), Some(bits)) = (_input.as_str(), _bits.as_u8()) {
+                let addr = parse_ip(input).map_err(to_runtime_error)?;
+                if bits > max_bits(addr) {
+                    return Err(to_runtime_error(format!("Can't keep {} bits of {}", bits, addr)));
+                }
+                Ok(Value::from(mask(addr, bits, false).to_string()))
+            } else {
+                Err(FunctionError::BadType{ mfa: this_mfa() })
+            }
+        }))
+        .insert(TremorFnWrapper::new(
+            "net".to_string(),
+            "in_cidr".to_string(),
+            Box::new(InCidr::default()),
+        ));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::registry::fun;
+    use crate::Value;
+    use tremor_value::literal;
+
+    #[test]
+    fn parse() {
+        let f = fun("net", "parse");
+        let v = Value::from("10.0.0.1");
+        assert_val!(
+            f(&[&v]),
+            literal!({"version": 4, "address": "10.0.0.1", "octets": [10, 0, 0, 1]})
+        );
+        let v = Value::from("2001:DB8:0:0:0:0:0:1");
+        assert_val!(
+            f(&[&v]),
+            literal!({"version": 6, "address": "2001:db8::1", "segments": [8193, 3512, 0, 0, 0, 0, 0, 1]})
+        );
+        let v = Value::from("10.0.0.256");
+        assert!(f(&[&v]).is_err());
+
+        let f = fun("net", "is_ipv4");
+        let v = Value::from("127.0.0.1");
+        assert_val!(f(&[&v]), true);
+        let v = Value::from("::1");
+        assert_val!(f(&[&v]), false);
+        let f = fun("net", "is_ipv6");
+        assert_val!(f(&[&v]), true);
+    }
+
+    #[test]
+    fn classify() {
+        let f = fun("net", "is_private");
+        for (addr, private) in [
+            ("10.1.2.3", true),
+            ("172.16.0.1", true),
+            ("192.168.10.1", true),
+            ("192.169.10.1", false),
+            ("8.8.8.8", false),
+            ("fd12:3456::1", true),
+            ("2001:db8::1", false),
+        ] {
+            let v = Value::from(addr);
+            assert_val!(f(&[&v]), private);
+        }
+        let f = fun("net", "is_loopback");
+        for (addr, loopback) in [("127.0.0.1", true), ("::1", true), ("10.0.0.1", false)] {
+            let v = Value::from(addr);
+            assert_val!(f(&[&v]), loopback);
+        }
+    }
+
+    #[test]
+    fn network_and_broadcast() {
+        let network = fun("net", "network");
+        let broadcast = fun("net", "broadcast");
+        let v = Value::from("192.168.1.77/26");
+        assert_val!(network(&[&v]), "192.168.1.64");
+        assert_val!(broadcast(&[&v]), "192.168.1.127");
+        let v = Value::from("0.0.0.0/0");
+        assert_val!(broadcast(&[&v]), "255.255.255.255");
+        let v = Value::from("2001:db8:1:2::1/48");
+        assert_val!(network(&[&v]), "2001:db8:1::");
+        assert_val!(broadcast(&[&v]), "2001:db8:1:ffff:ffff:ffff:ffff:ffff");
+        let v = Value::from("10.0.0.1/33");
+        assert!(network(&[&v]).is_err());
+    }
+
+    #[test]
+    fn anonymize() {
+        let f = fun("net", "anonymize");
+        let v = Value::from("192.168.1.77");
+        let bits = Value::from(24);
+        assert_val!(f(&[&v, &bits]), "192.168.1.0");
+        let v = Value::from("2001:db8:1:2:3:4:5:6");
+        let bits = Value::from(48);
+        assert_val!(f(&[&v, &bits]), "2001:db8:1::");
+        let bits = Value::from(129);
+        assert!(f(&[&v, &bits]).is_err());
+    }
+
+    #[test]
+    fn in_cidr() {
+        let f = fun("net", "in_cidr");
+        let cidrs = literal!(["10.0.0.0/8", "192.168.1.0/24", "fd00::/8"]);
+        for (addr, contained) in [
+            ("10.20.30.40", true),
+            ("192.168.1.1", true),
+            ("192.168.2.1", false),
+            ("fd00::1", true),
+            ("2001:db8::1", false),
+        ] {
+            let v = Value::from(addr);
+            assert_val!(f(&[&v, &cidrs]), contained);
+        }
+        let v = Value::from("10.0.0.1");
+        let cidr = Value::from("10.0.0.0/30");
+        assert_val!(f(&[&v, &cidr]), true);
+        let bad = literal!(["10.0.0.0/8", 42]);
+        assert!(f(&[&v, &bad]).is_err());
+        let bad = literal!(["snot"]);
+        assert!(f(&[&v, &bad]).is_err());
+    }
+
+    #[test]
+    fn in_cidr_specialized() {
+        let f = crate::registry::registry()
+            .find("net", "in_cidr")
+            .expect("could not find function")
+            .clone();
+        let cidrs = literal!(["10.0.0.0/8"]);
+        assert!(f.specialize(&[None, Some(&literal!(["snot"]))]).is_none());
+        let f = f
+            .specialize(&[None, Some(&cidrs)])
+            .expect("in_cidr is specialized");
+        let ctx = crate::EventContext::new(0, None);
+        let v = Value::from("10.0.0.1");
+        assert_eq!(Ok(Value::from(true)), f.invoke(&ctx, &[&v, &cidrs]));
+        let v = Value::from("11.0.0.1");
+        assert_eq!(Ok(Value::from(false)), f.invoke(&ctx, &[&v, &cidrs]));
+    }
+}
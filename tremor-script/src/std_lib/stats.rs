@@ -18,6 +18,7 @@ use crate::registry::{
     mfa, Aggr as AggrRegistry, FResult, FunctionError, TremorAggrFn, TremorAggrFnWrapper,
 };
 use crate::Value;
+use hdrhistogram::serialization::{Deserializer, Serializer, V2Serializer};
 use hdrhistogram::Histogram;
 use sketches_ddsketch::{Config as DDSketchConfig, DDSketch};
 use std::cmp::max;
@@ -65,6 +66,14 @@ impl TremorAggrFn for Count {
         Ok(())
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        Some(Value::from(self.0))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        self.0 = snapshot.as_i64()?;
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -105,6 +114,14 @@ impl TremorAggrFn for Sum {
         Ok(())
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        Some(Value::from(self.0))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        self.0 = snapshot.cast_f64()?;
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -151,6 +168,16 @@ impl TremorAggrFn for Mean {
         Ok(())
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        Some(Value::from(vec![Value::from(self.0), Value::from(self.1)]))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        let s = snapshot.as_array()?;
+        self.0 = s.first()?.as_i64()?;
+        self.1 = s.get(1)?.cast_f64()?;
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -195,6 +222,18 @@ impl TremorAggrFn for Min {
         Ok(())
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        Some(Value::from(self.0))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        self.0 = if snapshot.is_null() {
+            None
+        } else {
+            Some(snapshot.cast_f64()?)
+        };
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -238,6 +277,18 @@ impl TremorAggrFn for Max {
         Ok(())
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        Some(Value::from(self.0))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        self.0 = if snapshot.is_null() {
+            None
+        } else {
+            Some(snapshot.cast_f64()?)
+        };
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -291,14 +342,38 @@ impl TremorAggrFn for Var {
     }
     fn merge(&mut self, src: &dyn TremorAggrFn) -> FResult<()> {
         if let Some(other) = src.downcast_ref::<Self>() {
-            self.n += other.n;
-            self.k += other.k;
-            self.ex += other.ex;
-            self.ex2 += other.ex2;
+            if self.n == 0 {
+                *self = other.clone();
+            } else if other.n > 0 {
+                // the sums of `other` are shifted by `other.k` so we rebase
+                // them onto our shift `self.k` before adding them up
+                let d = other.k - self.k;
+                let n = other.n as f64;
+                self.n += other.n;
+                self.ex += other.ex + n * d;
+                self.ex2 += other.ex2 + 2.0 * d * other.ex + n * d * d;
+            }
         }
         Ok(())
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        Some(Value::from(vec![
+            Value::from(self.n),
+            Value::from(self.k),
+            Value::from(self.ex),
+            Value::from(self.ex2),
+        ]))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        let s = snapshot.as_array()?;
+        self.n = s.first()?.as_u64()?;
+        self.k = s.get(1)?.cast_f64()?;
+        self.ex = s.get(2)?.cast_f64()?;
+        self.ex2 = s.get(3)?.cast_f64()?;
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -332,6 +407,13 @@ impl TremorAggrFn for Stdev {
         Ok(())
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        self.0.snapshot()
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        self.0.restore(snapshot)
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -340,6 +422,25 @@ impl TremorAggrFn for Stdev {
     }
 }
 
+/// snapshots the percentiles of `dds` and `hdr` by their names, which they are parsed from
+fn snapshot_percentiles(percentiles: &[(String, f64)]) -> Value<'static> {
+    percentiles
+        .iter()
+        .map(|(name, _)| Value::from(name.clone()))
+        .collect()
+}
+
+fn restore_percentiles(snapshot: &Value) -> Option<Vec<(String, f64)>> {
+    snapshot
+        .as_array()?
+        .iter()
+        .map(|name| {
+            let name = name.as_str()?;
+            Some((name.to_string(), name.parse().ok()?))
+        })
+        .collect()
+}
+
 #[derive(Clone)]
 struct Dds {
     sketch: Option<DDSketch>,
@@ -520,6 +621,37 @@ impl TremorAggrFn for Dds {
         self.cache.clear();
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        // the bounds of an empty sketch are infinite and can't be encoded,
+        // it is equivalent to no sketch at all
+        let sketch = match &self.sketch {
+            Some(sketch) if sketch.count() > 0 => tremor_value::to_value(sketch).ok()?,
+            _ => Value::null(),
+        };
+        Some(literal!({
+            "percentiles": snapshot_percentiles(&self.percentiles),
+            "percentiles_set": self.percentiles_set,
+            "cache": self.cache.clone(),
+            "sketch": sketch,
+        }))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        self.percentiles = restore_percentiles(snapshot.get("percentiles")?)?;
+        self.percentiles_set = snapshot.get_bool("percentiles_set")?;
+        self.cache = snapshot
+            .get_array("cache")?
+            .iter()
+            .map(Value::cast_f64)
+            .collect::<Option<_>>()?;
+        let sketch = snapshot.get("sketch")?;
+        self.sketch = if sketch.is_null() {
+            None
+        } else {
+            Some(tremor_value::structurize(sketch.clone()).ok()?)
+        };
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -711,6 +843,46 @@ impl TremorAggrFn for Hdr {
         self.cache.clear();
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        let histo = if let Some(histo) = &self.histo {
+            let mut buf = Vec::new();
+            V2Serializer::new().serialize(histo, &mut buf).ok()?;
+            Value::from(base64::encode(buf))
+        } else {
+            Value::null()
+        };
+        Some(literal!({
+            "percentiles": snapshot_percentiles(&self.percentiles),
+            "percentiles_set": self.percentiles_set,
+            "high_bound": self.high_bound,
+            "cache": self.cache.clone(),
+            "histo": histo,
+        }))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        self.percentiles = restore_percentiles(snapshot.get("percentiles")?)?;
+        self.percentiles_set = snapshot.get_bool("percentiles_set")?;
+        self.high_bound = snapshot.get_u64("high_bound")?;
+        self.cache = snapshot
+            .get_array("cache")?
+            .iter()
+            .map(Value::as_u64)
+            .collect::<Option<_>>()?;
+        let histo = snapshot.get("histo")?;
+        self.histo = if histo.is_null() {
+            None
+        } else {
+            let encoded = base64::decode(histo.as_str()?).ok()?;
+            let mut histo: Histogram<u64> = Deserializer::new()
+                .deserialize(&mut encoded.as_slice())
+                .ok()?;
+            // auto resizing is not part of the serialized histogram
+            histo.auto(true);
+            Some(histo)
+        };
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -941,6 +1113,102 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn variance_merge_different_shift() -> Result<()> {
+        let mut a = Var::default();
+        a.init();
+        a.accumulate(&[&Value::from(2)])?;
+        a.accumulate(&[&Value::from(4)])?;
+
+        let mut b = Var::default();
+        b.init();
+        b.accumulate(&[&Value::from(19)])?;
+        b.accumulate(&[&Value::from(9)])?;
+
+        a.merge(&b)?;
+        let r = a.emit()?.cast_f64().expect("screw it");
+        assert!(approx_eq!(f64, r, 173.0 / 3.0));
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_restore() -> Result<()> {
+        let mut a = Var::default();
+        a.init();
+        a.accumulate(&[&Value::from(2)])?;
+        a.accumulate(&[&Value::from(4)])?;
+        a.accumulate(&[&Value::from(19)])?;
+
+        let mut b = Var::default();
+        assert_eq!(Some(()), b.restore(&a.snapshot().expect("screw it")));
+        assert_eq!(a.emit()?, b.emit()?);
+
+        let mut min = Min::default();
+        assert_eq!(Some(()), min.restore(&Value::null()));
+        assert_eq!(None, min.0);
+        assert_eq!(Some(()), min.restore(&Value::from(3.0)));
+        assert_eq!(Some(3.0), min.0);
+        assert_eq!(None, min.restore(&Value::from("snot")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_restore_histograms() -> Result<()> {
+        let percentiles = literal!(["0.5", "0.9"]);
+        let mut hdr = Hdr::default();
+        let mut dds = Dds::default();
+        // values still in the caches
+        for v in 1..=10 {
+            hdr.accumulate(&[&Value::from(v), &percentiles])?;
+            dds.accumulate(&[&Value::from(v), &percentiles])?;
+        }
+        let mut restored_hdr = Hdr::default();
+        assert_eq!(
+            Some(()),
+            restored_hdr.restore(&hdr.snapshot().expect("screw it"))
+        );
+        assert_eq!(hdr.emit()?, restored_hdr.emit()?);
+        let mut restored_dds = Dds::default();
+        assert_eq!(
+            Some(()),
+            restored_dds.restore(&dds.snapshot().expect("screw it"))
+        );
+        assert_eq!(dds.emit()?, restored_dds.emit()?);
+
+        // values in the histogram and the sketch
+        hdr.init();
+        dds.init();
+        for v in 0..(HIST_MAX_CACHE_SIZE as u64 + 10) {
+            hdr.accumulate(&[&Value::from(v), &percentiles])?;
+            dds.accumulate(&[&Value::from(v), &percentiles])?;
+        }
+        assert!(hdr.histo.is_some());
+        assert!(dds.sketch.is_some());
+        let mut restored_hdr = Hdr::default();
+        assert_eq!(
+            Some(()),
+            restored_hdr.restore(&hdr.snapshot().expect("screw it"))
+        );
+        // the restored histogram keeps resizing
+        hdr.accumulate(&[&Value::from(u64::MAX / 4)])?;
+        restored_hdr.accumulate(&[&Value::from(u64::MAX / 4)])?;
+        assert_eq!(hdr.emit()?, restored_hdr.emit()?);
+        let mut restored_dds = Dds::default();
+        assert_eq!(
+            Some(()),
+            restored_dds.restore(&dds.snapshot().expect("screw it"))
+        );
+        assert_eq!(dds.emit()?, restored_dds.emit()?);
+
+        assert_eq!(None, Hdr::default().restore(&Value::from("snot")));
+        assert_eq!(
+            None,
+            Dds::default().restore(&literal!({"sketch": "badger"}))
+        );
+        Ok(())
+    }
+
     #[test]
     fn stdev() -> Result<()> {
         let mut a = Stdev::default();
@@ -49,6 +49,15 @@ impl TremorAggrFn for First {
         Ok(())
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        Some(Value::from(self.0.iter().cloned().collect::<Vec<_>>()))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        // the value is wrapped in an array to tell `null` apart from no value
+        self.0 = snapshot.as_array()?.first().map(Value::clone_static);
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -86,6 +95,15 @@ impl TremorAggrFn for Last {
         Ok(())
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        Some(Value::from(self.0.iter().cloned().collect::<Vec<_>>()))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        // the value is wrapped in an array to tell `null` apart from no value
+        self.0 = snapshot.as_array()?.first().map(Value::clone_static);
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -124,6 +142,18 @@ impl TremorAggrFn for CollectFlattened {
         Ok(())
     }
 
+    fn snapshot(&self) -> Option<Value<'static>> {
+        Some(Value::from(self.0.clone()))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        self.0 = snapshot
+            .as_array()?
+            .iter()
+            .map(Value::clone_static)
+            .collect();
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -167,6 +197,26 @@ impl TremorAggrFn for CollectNested {
         Ok(())
     }
 
+    fn merge_pane(&mut self, src: &dyn TremorAggrFn) -> FResult<()> {
+        if let Some(other) = src.downcast_ref::<Self>() {
+            // panes belong to the same window so we do not nest them
+            self.0.append(&mut other.0.clone());
+        }
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Option<Value<'static>> {
+        Some(Value::from(self.0.clone()))
+    }
+    fn restore(&mut self, snapshot: &Value) -> Option<()> {
+        self.0 = snapshot
+            .as_array()?
+            .iter()
+            .map(Value::clone_static)
+            .collect();
+        Some(())
+    }
+
     fn boxed_clone(&self) -> Box<dyn TremorAggrFn> {
         Box::new(self.clone())
     }
@@ -237,6 +287,20 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn first_snapshot() -> Result<()> {
+        let mut a = First::default();
+        let mut b = First::default();
+        // no value and a `null` value are different states
+        assert_eq!(Some(()), b.restore(&a.snapshot().unwrap()));
+        assert_eq!(None, b.0);
+        a.accumulate(&[&Value::null()])?;
+        assert_eq!(Some(()), b.restore(&a.snapshot().unwrap()));
+        assert_eq!(Some(Value::null()), b.0);
+        assert_eq!(None, b.restore(&Value::null()));
+        Ok(())
+    }
+
     #[test]
     fn collect() -> Result<()> {
         let mut a = CollectFlattened::default();